@@ -2,16 +2,134 @@
  * Titan AI - Native Indexer TypeScript Definitions
  */
 
+export const enum HashAlgorithm {
+  Sha256 = 0,
+  Blake3 = 1
+}
+
+/** Where each chunk after the first starts relative to the one before */
+export const enum OverlapStrategy {
+  /** Repeat the last `overlap` lines */
+  FixedLines = 0,
+  /** Repeat whole trailing lines of up to `overlapTokens` estimated tokens */
+  FixedTokens = 1,
+  /**
+   * Repeat from the last natural boundary in the second half of the chunk,
+   * so a declaration near the end appears whole in the next
+   */
+  SemanticBoundary = 2
+}
+
 export interface CodeChunk {
+  /** Content-addressed id, see `generateStableChunkId` */
   id: string;
   filePath: string;
   content: string;
   startLine: number;
   endLine: number;
+  /** Byte offset of the first character, inclusive */
+  startByte: number;
+  /** Byte offset just past the last character, exclusive */
+  endByte: number;
+  /**
+   * Number of branching constructs, a rough stand-in for McCabe
+   * cyclomatic complexity rather than an exact value
+   */
+  complexity: number;
+  /** Estimated LLM tokens in `content` */
+  tokenCount: number;
+  /**
+   * Number of file header lines prepended to `content`; `startLine` and
+   * `endLine` still refer to the chunk's own lines
+   */
+  headerLines: number;
   chunkType: string;
   language: string;
   symbols: string[];
   hash: string;
+  /**
+   * `startLine` before `chunkCodeOverlapDedup` trimmed the lines shared
+   * with the previous chunk
+   */
+  originalStartLine?: number;
+  /**
+   * Version and dialect pragmas near the top of the file, such as
+   * `"use client"` or `#![feature(...)]`, comma-separated
+   */
+  languageVersion?: string;
+}
+
+export interface ChunkChange {
+  old: CodeChunk;
+  new: CodeChunk;
+}
+
+export interface ChunkDiff {
+  added: CodeChunk[];
+  modified: ChunkChange[];
+  deleted: CodeChunk[];
+}
+
+export interface ChunkContext {
+  /** Chunks before the target, nearest first */
+  precedingChunks: CodeChunk[];
+  /** Chunks after the target, nearest first */
+  followingChunks: CodeChunk[];
+}
+
+export interface CoverageReport {
+  /** Non-empty lines in the file */
+  totalLines: number;
+  coveredLines: number;
+  /** 1-based inclusive `[start, end]` line ranges no chunk covers */
+  uncoveredRanges: Array<[number, number]>;
+  coveragePercent: number;
+}
+
+export interface AllocatedChunk {
+  chunk: CodeChunk;
+  allocatedTokens: number;
+  /** Chunk content trimmed to the allocation, at a line end if possible */
+  truncatedContent: string;
+}
+
+export interface ChunkLintResult {
+  chunkId: string;
+  severity: 'error' | 'warning';
+  message: string;
+}
+
+export interface DuplicateGroup {
+  representativeChunkId: string;
+  duplicateIds: string[];
+  /**
+   * `filePath:startLine` of the representative; exact copies share its id,
+   * so this is what tells them apart
+   */
+  representativeLocation: string;
+  /** `filePath:startLine` of each duplicate, in `duplicateIds` order */
+  duplicateLocations: string[];
+  /**
+   * Lowest similarity between the representative and a duplicate, 1 for
+   * identical code
+   */
+  similarity: number;
+}
+
+export interface CallEdge {
+  caller: string;
+  callee: string;
+}
+
+export interface CallGraph {
+  edges: CallEdge[];
+  /** Functions and methods no other one refers to */
+  roots: string[];
+}
+
+export interface SymbolParameter {
+  name: string;
+  typeAnnotation?: string;
 }
 
 export interface Symbol {
@@ -20,8 +138,193 @@ export interface Symbol {
   filePath: string;
   startLine: number;
   endLine: number;
+  /** 0-based byte offset of the start within its line, as in Tree-sitter */
+  startColumn: number;
+  /** 0-based byte offset of the end within its line, as in Tree-sitter */
+  endColumn: number;
+  /** 0-based start column counted in Unicode characters */
+  startColumnChar: number;
   signature?: string;
+  parameters: SymbolParameter[];
   exported: boolean;
+  parentName?: string;
+  parentKind?: string;
+  /** Uses of the symbol's name in the same file, when requested */
+  references?: SymbolRef[];
+  /** Names of generic type parameters, such as `T` in `fn f<T: Clone>()` */
+  genericParams: string[];
+  /**
+   * Type signature declared apart from the definition, such as Haskell's
+   * `area :: Shape -> Double`, with whitespace collapsed
+   */
+  signatureAnnotation?: string;
+}
+
+export interface SymbolRef {
+  filePath: string;
+  /** 1-based line */
+  line: number;
+  /** 0-based byte offset within the line */
+  column: number;
+}
+
+export interface Import {
+  source: string;
+  specifiers: string[];
+  isTypeOnly: boolean;
+  /** One of `import`, `re-export` or `dynamic` */
+  kind: string;
+  startLine: number;
+}
+
+export interface CodeComment {
+  content: string;
+  /** One of `line`, `block` or `doc` */
+  kind: string;
+  startLine: number;
+  endLine: number;
+  associatedSymbol?: string;
+  /** Tags of a JSDoc or PHPDoc comment */
+  docBlock?: DocBlock;
+}
+
+export interface DocBlock {
+  params: DocParam[];
+  returns?: DocReturn;
+  author?: string;
+}
+
+export interface DocParam {
+  /** As written, so PHP names keep their `$` */
+  name: string;
+  typeHint?: string;
+}
+
+export interface DocReturn {
+  typeHint?: string;
+}
+
+export interface FileInput {
+  filePath: string;
+  content: string;
+  /** Inferred from the file extension or shebang when omitted or `auto` */
+  language?: string;
+}
+
+export interface ParseResult {
+  filePath: string;
+  chunks: CodeChunk[];
+  /** Only filled by `parseFileWithOptions` with `extractSymbols` */
+  symbols: Symbol[];
+  /** Only filled by `parseFileWithOptions` with `extractImports` */
+  imports: Import[];
+  /** Only filled by `parseFileWithOptions` with `extractComments` */
+  comments: CodeComment[];
+  /** Set when the file could not be parsed; `chunks` is then empty */
+  error?: string;
+}
+
+export interface ParseFileResult {
+  chunks: CodeChunk[];
+  symbols: Symbol[];
+  syntaxErrors: SyntaxError[];
+}
+
+export interface ParseProfile {
+  /** Whole call, including grammar setup and node counting */
+  totalMs: number;
+  treeConstructionMs: number;
+  chunkExtractionMs: number;
+  symbolExtractionMs: number;
+  /** Named and anonymous nodes of the syntax tree by kind */
+  nodeCountByKind: Record<string, number>;
+}
+
+export interface SyntaxError {
+  message: string;
+  /** 1-based line */
+  startLine: number;
+  /** 0-based byte offset within the line */
+  startColumn: number;
+}
+
+export interface SymbolsResult {
+  filePath: string;
+  symbols: Symbol[];
+  /** Set when the file could not be parsed; `symbols` is then empty */
+  error?: string;
+}
+
+export interface DependencyEdge {
+  from: string;
+  to: string;
+  /** One of `import`, `re-export` or `dynamic` */
+  kind: string;
+}
+
+export interface DependencyGraph {
+  edges: DependencyEdge[];
+}
+
+export interface IndexSnapshot {
+  chunks: CodeChunk[];
+  symbols: Symbol[];
+}
+
+export interface LanguageInfo {
+  name: string;
+  aliases: string[];
+  /** Tree-sitter language (ABI) version of the linked grammar */
+  grammarVersion: string;
+  astChunking: boolean;
+  supportedSymbolKinds: string[];
+}
+
+export interface ParseOptions {
+  /** Also return string literals as `string_literal` chunks */
+  extractStrings?: boolean;
+  /** Return declaration chunks, defaults to true */
+  extractChunks?: boolean;
+  /** Return symbols, as from `extractSymbols` */
+  extractSymbols?: boolean;
+  /** Return imports, as from `extractImports` */
+  extractImports?: boolean;
+  /** Return comments, as from `extractComments` */
+  extractComments?: boolean;
+  /** Return empty results for files `isTestFile` takes for tests */
+  excludeTestFiles?: boolean;
+}
+
+export interface ChunkOptions {
+  maxChunkSize: number;
+  /** Lines repeated between chunks with `OverlapStrategy.FixedLines` */
+  overlap: number;
+  /** Emit classes up to this many lines as a single `class` chunk */
+  keepClassIntactUnderLines?: number;
+  /** Defaults to `Sha256` */
+  hashAlgorithm?: HashAlgorithm;
+  /**
+   * Count tokens exactly with the GPT-4 tokenizer (requires the native
+   * module to be built with the `tiktoken` feature) instead of the
+   * character heuristic
+   */
+  estimateTokens?: boolean;
+  /**
+   * Prefix each chunk with the file's imports and top-level declarations
+   * that precede it
+   */
+  prependHeader?: boolean;
+  /** Most header lines to prepend, defaults to 10 */
+  headerMaxLines?: number;
+  /**
+   * Size chunks in estimated tokens instead of lines, so long lines count
+   * for more; `maxChunkSize` is then unused
+   */
+  maxTokens?: number;
+  /** Estimated tokens repeated with `OverlapStrategy.FixedTokens`, defaults to 0 */
+  overlapTokens?: number;
+  /** Defaults to `SemanticBoundary`, which suits embedding best */
+  chunkOverlapStrategy?: OverlapStrategy;
 }
 
 export interface MerkleNode {
@@ -29,23 +332,325 @@ export interface MerkleNode {
   path: string;
   isFile: boolean;
   children: string[];
+  /**
+   * Caller-defined attributes such as size or mtime; never hashed, so they
+   * do not affect roots or diffs
+   */
+  metadata?: Record<string, string>;
+}
+
+export interface TreeStats {
+  /** Files and empty directories */
+  leafCount: number;
+  /** Hashing levels from the deepest leaf to the root */
+  depth: number;
+  totalNodes: number;
+  /**
+   * `depth` over that of a balanced binary tree of the same leaves; well
+   * above 1 means deeply nested directories
+   */
+  balanceFactor: number;
 }
 
 export interface SyncDiff {
   added: string[];
   modified: string[];
   deleted: string[];
+  renamed: RenamedFile[];
+  totalChanged: number;
+  totalFiles: number;
+  changeRatio: number;
+}
+
+export interface RenamedFile {
+  oldPath: string;
+  newPath: string;
+}
+
+export interface TreeEdit {
+  startByte: number;
+  oldEndByte: number;
+  newEndByte: number;
+  startRow: number;
+  startColumn: number;
+  oldEndRow: number;
+  oldEndColumn: number;
+  newEndRow: number;
+  newEndColumn: number;
 }
 
 /**
  * Parse a file and extract code chunks
+ *
+ * Like the other parsing functions, throws with `code` `"InvalidArg"` for an
+ * unsupported language and `"GenericFailure"` when parsing fails.
+ */
+export function parseFile(
+  filePath: string,
+  content: string,
+  language: string,
+  options?: ParseOptions
+): CodeChunk[];
+
+/**
+ * Chunk a Markdown document into sections and code examples
+ *
+ * Each heading gives a `"section"` chunk running to the next heading of the
+ * same or a higher level, with the heading text as its symbol. Each fenced
+ * code block gives a `"code_example"` chunk of just the code, whose
+ * `language` comes from the fence info string (`"text"` if there is none).
+ */
+export function parseMarkdown(filePath: string, content: string): CodeChunk[];
+
+/**
+ * Parse a file once and extract chunks, symbols, imports and comments
+ *
+ * Each requested kind is collected by its own walk over the shared tree.
+ */
+export function parseFileWithOptions(
+  filePath: string,
+  content: string,
+  language: string,
+  options: ParseOptions
+): ParseResult;
+
+/**
+ * Parse a file into chunks and symbols, also reporting its syntax errors
+ *
+ * Parsing recovers from errors, so the valid parts of a broken file are
+ * still chunked. Each error is reported once, at its outermost node.
+ */
+export function parseFileWithErrors(filePath: string, content: string, language: string): ParseFileResult;
+
+/**
+ * Time the phases of parsing a file (tree construction, chunk extraction,
+ * symbol extraction) and count its syntax nodes by kind. For diagnosing
+ * slow files; timings vary from run to run.
+ */
+export function profileParseFile(filePath: string, content: string, language: string): ParseProfile;
+
+/**
+ * Parse a file on the libuv thread pool
+ */
+export function parseFileAsync(filePath: string, content: string, language: string): Promise<CodeChunk[]>;
+
+/**
+ * Parse many files in parallel, reporting failures per file
+ */
+export function parseFilesBatch(files: FileInput[]): ParseResult[];
+
+/**
+ * Group chunks by `filePath`, keeping their order within each file. The
+ * result is a plain object; use `new Map(Object.entries(groups))` for a
+ * `Map`, e.g.
+ * `groupChunksByFile(parseFilesBatch(files).flatMap(r => r.chunks))`.
+ */
+export function groupChunksByFile(chunks: CodeChunk[]): Record<string, CodeChunk[]>;
+
+/**
+ * Group symbols by `filePath`, keeping their order within each file
+ */
+export function groupSymbolsByFile(symbols: Symbol[]): Record<string, Symbol[]>;
+
+/**
+ * Group symbols by `kind`, keeping their order within each kind
+ */
+export function groupSymbolsByKind(symbols: Symbol[]): Record<string, Symbol[]>;
+
+/**
+ * Parse tree kept in memory between edits of a file. Tree-sitter trees
+ * have no byte format, so the host keeps this handle instead.
+ */
+export class ParseTree {
+  constructor(content: string, language: string);
+  /** Language the tree was parsed with */
+  readonly language: string;
+}
+
+/**
+ * Re-parse an edited file from its previous tree. The edits describe how
+ * the tree's content became `newContent`; only the changed subtrees are
+ * rebuilt, and `tree` then holds the new tree for the next edit.
+ */
+export function parseFileIncremental(
+  filePath: string,
+  tree: ParseTree,
+  newContent: string,
+  edits: TreeEdit[]
+): CodeChunk[];
+
+/**
+ * Parse a file reusing a held tree. Unchanged content is chunked straight
+ * from the tree; changed content is re-parsed incrementally over the span
+ * that differs, and `tree` then holds the new tree.
+ */
+export function parseFileFromTree(filePath: string, content: string, tree: ParseTree): CodeChunk[];
+
+/**
+ * Extract symbols from a file, optionally with same-file references
+ */
+export function extractSymbols(
+  filePath: string,
+  content: string,
+  language: string,
+  includeReferences?: boolean
+): Symbol[];
+
+/**
+ * Top-level keys of a YAML file, one `"key"` symbol per dot-path.
+ * Documents separated by `---` are all read.
+ *
+ * Keys under `jobs` (GitHub Actions), `services` (Compose), `scripts`,
+ * `dependencies` and `devDependencies` are listed as well, e.g.
+ * `jobs.build`. Each signature is `<path>: <type>` with a JSON type name.
+ */
+export function extractYamlKeys(content: string): Symbol[];
+
+/**
+ * Top-level keys of a JSON file, in the same form as `extractYamlKeys`
+ */
+export function extractJsonKeys(content: string): Symbol[];
+
+/**
+ * Symbols for the `FROM`, `RUN`, `COPY`, `ENV`, `EXPOSE`, `ENTRYPOINT` and
+ * `CMD` instructions of a Dockerfile
+ *
+ * Each has `kind: "instruction"`, the upper-case keyword as `name` and the
+ * instruction text, with continued lines joined, as `signature`.
+ * Instructions after `FROM ... AS name` have that `"stage"` as parent.
+ */
+export function parseDockerfile(filePath: string, content: string): Symbol[];
+
+/**
+ * Extract symbols on the libuv thread pool
+ */
+export function extractSymbolsAsync(filePath: string, content: string, language: string): Promise<Symbol[]>;
+
+/**
+ * Extract symbols from many files in parallel, reporting failures per file
+ */
+export function extractSymbolsBatch(files: FileInput[]): SymbolsResult[];
+
+/**
+ * Find the innermost symbol at a 1-based line and column
+ */
+export function getSymbolAtPosition(
+  content: string,
+  language: string,
+  line: number,
+  column: number
+): Symbol | null;
+
+/**
+ * Extract import statements from a file
+ */
+export function extractImports(filePath: string, content: string, language: string): Import[];
+
+/**
+ * Dump the syntax tree as pretty-printed JSON for debugging. Each node has
+ * `kind`, `start_position`, `end_position`, `is_named` and `children`; leaf
+ * nodes also carry `text`.
+ */
+export function getAstJson(content: string, language: string): string;
+
+/**
+ * Extract module-level variables and constants from a file
+ */
+export function extractVariables(filePath: string, content: string, language: string): Symbol[];
+
+/**
+ * Extract comments and docstrings from a file
+ */
+export function extractComments(filePath: string, content: string, language: string): CodeComment[];
+
+/**
+ * Resolve imports across a set of files into a dependency graph
+ */
+export function extractDependencies(files: FileInput[]): DependencyGraph;
+
+/**
+ * Serialise chunks and symbols as a JSON snapshot with `schema_version`,
+ * `created_at` (Unix seconds), `chunks` and `symbols`
+ */
+export function exportIndexToJson(chunks: CodeChunk[], symbols: Symbol[]): string;
+
+/**
+ * Restore chunks and symbols from a JSON snapshot; throws on a missing or
+ * unsupported `schema_version`
+ */
+export function importIndexFromJson(json: string): IndexSnapshot;
+
+/**
+ * Serialise chunks and symbols as a compact MessagePack snapshot
+ */
+export function exportIndexToMsgpack(chunks: CodeChunk[], symbols: Symbol[]): Buffer;
+
+/**
+ * Restore chunks and symbols from a MessagePack snapshot; throws on an
+ * unsupported `schema_version`
  */
-export function parseFile(filePath: string, content: string, language: string): CodeChunk[];
+export function importIndexFromMsgpack(data: Buffer): IndexSnapshot;
 
 /**
- * Extract symbols from a file
+ * Serialise chunks as an Apache Arrow IPC file with one column per chunk
+ * field (snake_case names), e.g. for `pyarrow.ipc.open_file` in a Python
+ * embedding service
  */
-export function extractSymbols(filePath: string, content: string, language: string): Symbol[];
+export function serializeChunksToArrow(chunks: CodeChunk[]): Buffer;
+
+/**
+ * Read chunks from an Arrow IPC file written by `serializeChunksToArrow`;
+ * throws if a column is missing or has the wrong type
+ */
+export function deserializeChunksFromArrow(data: Buffer): CodeChunk[];
+
+/**
+ * Reusable parser that keeps its Tree-sitter state between calls
+ */
+export class NativeParser {
+  constructor(language: string);
+  /** Language the parser is configured for */
+  readonly language: string;
+  /** Switch the parser to another language */
+  setLanguage(language: string): void;
+  /** Parse a file and extract code chunks */
+  parse(filePath: string, content: string): CodeChunk[];
+  /** Extract symbols from source content */
+  extractSymbols(content: string): Symbol[];
+}
+
+/**
+ * Symbol lookup by name over an in-memory inverted index
+ */
+export class CodeSearch {
+  constructor(symbols: Symbol[]);
+  /** Restore an index written by `toJson` */
+  static fromJson(json: string): CodeSearch;
+  /** Serialise the index so it can be persisted with the chunk index */
+  toJson(): string;
+  /** Number of indexed symbols */
+  readonly size: number;
+  /**
+   * Symbols whose name, or a camelCase/snake_case word of it, matches
+   * `name` ignoring case; exact matches first
+   */
+  findSymbol(name: string): Symbol[];
+  /** Symbols with names most similar to `query` by trigram overlap */
+  fuzzyFindSymbol(query: string, maxResults: number): Symbol[];
+}
+
+/**
+ * Canonical lower-case, space-separated form of a camelCase, PascalCase or
+ * snake_case symbol name: `calculateTotal` and `calculate_total` are both
+ * `"calculate total"`
+ */
+export function normalizeSymbolName(name: string, language: string): string;
+
+/**
+ * Similarity from 0 to 1 of two symbol names once normalised, by trigram
+ * overlap; names made of the same words score 1
+ */
+export function symbolNameSimilarity(a: string, b: string): number;
 
 /**
  * Build a Merkle tree from file hashes
@@ -53,15 +658,111 @@ export function extractSymbols(filePath: string, content: string, language: stri
 export function buildMerkleTree(files: MerkleNode[]): string;
 
 /**
- * Compute diff between two Merkle trees
+ * Build the same Merkle tree with each level hashed in parallel; faster
+ * than `buildMerkleTree` for many thousands of files
+ */
+export function buildMerkleTreeParallel(files: MerkleNode[]): string;
+
+/**
+ * Describe the shape of the Merkle tree built from file hashes
  */
-export function computeMerkleDiff(oldRoot: string, newFiles: MerkleNode[]): SyncDiff;
+export function getTreeStats(files: MerkleNode[]): TreeStats;
+
+/**
+ * Hashing levels from the deepest leaf of the Merkle tree to its root
+ */
+export function getTreeDepth(files: MerkleNode[]): number;
+
+/**
+ * Build a Merkle tree on the libuv thread pool
+ */
+export function buildMerkleTreeAsync(files: MerkleNode[]): Promise<string>;
+
+/**
+ * Generate a Merkle proof for a file
+ *
+ * Only top-level files have proofs; files inside a directory are rejected.
+ */
+export function generateProof(files: MerkleNode[], path: string): string[];
+
+/**
+ * Verify a Merkle proof against a known root
+ */
+export function verifyProof(leafHash: string, proof: string[], root: string, index: number): boolean;
+
+/**
+ * Compute diff between two Merkle trees. With `oldFiles`, directories
+ * whose hash is unchanged are skipped without visiting their files;
+ * without them every new file is reported as modified once the roots differ.
+ */
+export function computeMerkleDiff(oldRoot: string, newFiles: MerkleNode[], oldFiles?: MerkleNode[]): SyncDiff;
+
+/**
+ * Compute a Merkle diff on the libuv thread pool
+ */
+export function computeMerkleDiffAsync(oldRoot: string, newFiles: MerkleNode[], oldFiles?: MerkleNode[]): Promise<SyncDiff>;
+
+/**
+ * Compute diff between two full file sets
+ */
+export function computeIncrementalDiff(oldFiles: MerkleNode[], newFiles: MerkleNode[]): SyncDiff;
+
+/**
+ * Merkle state persisted to disk between sessions
+ */
+export class MerkleStore {
+  constructor(dbPath: string);
+  /** Diff against the stored file set and replace it with `files` */
+  update(files: MerkleNode[]): SyncDiff;
+  /** Root hash over the stored files in path order */
+  getRoot(): string;
+  /** Write the stored file set to `dbPath` */
+  save(): void;
+  /** Replace the stored file set with the contents of `dbPath` */
+  load(): void;
+}
+
+/**
+ * Watches a directory and reports incremental diffs as files change
+ */
+export class FileWatcher {
+  constructor(rootDir: string);
+  /**
+   * Start watching, calling `callback` with a `SyncDiff` once each burst of
+   * changes has been quiet for 50 ms
+   */
+  start(callback: (diff: SyncDiff) => void): void;
+  /** Stop watching; does nothing if not started */
+  stop(): void;
+}
 
 /**
  * Hash file content
  */
 export function hashContent(content: string): string;
 
+/**
+ * SHA-256 hash many contents in parallel, one hash per input in order
+ */
+export function hashContentsBatch(contents: string[]): string[];
+
+/**
+ * Hash file content with BLAKE3
+ */
+export function hashContentBlake3(content: string): string;
+
+/**
+ * Hash file content with the given algorithm
+ */
+export function hashContentWithAlgorithm(content: string, algorithm: HashAlgorithm): string;
+
+/**
+ * Compute the stable, content-addressed id of a chunk, as used for
+ * `CodeChunk.id`: `language:symbol:hash`, or `language:hash` without a
+ * symbol
+ */
+export function generateStableChunkId(content: string, symbolName: string | null | undefined, language: string): string;
+
 /**
  * Chunk code into semantic blocks
  */
@@ -69,13 +770,197 @@ export function chunkCode(
   content: string,
   language: string,
   maxChunkSize: number,
-  overlap: number
+  overlap: number,
+  hashAlgorithm?: HashAlgorithm
+): CodeChunk[];
+
+/**
+ * Chunk code with extended options
+ */
+export function chunkCodeWithOptions(content: string, language: string, options: ChunkOptions): CodeChunk[];
+
+/**
+ * Chunk code with file-level context prepended to each chunk
+ */
+export function chunkCodeWithHeader(content: string, language: string, options: ChunkOptions): CodeChunk[];
+
+/**
+ * Chunks of one file, computed on a background thread as they are read
+ *
+ * Chunking runs at most 16 chunks ahead of the reader and pauses until they
+ * are read. Wrap it in a Node `Readable` with `chunkCodeStreaming` from
+ * `stream.js`.
+ */
+export class ChunkStream {
+  constructor(content: string, language: string, options: ChunkOptions);
+  /**
+   * The next chunk on the libuv thread pool, `null` once all have been read
+   * or the stream was cancelled
+   */
+  next(): Promise<CodeChunk | null>;
+  /** Stop chunking; a pending `next` resolves to `null` */
+  cancel(): void;
+}
+
+/**
+ * Chunk code along AST declarations, using line scanning for languages
+ * without a grammar
+ */
+export function chunkCodeWithAst(
+  filePath: string,
+  content: string,
+  language: string,
+  options: ChunkOptions
 ): CodeChunk[];
 
 /**
- * Get supported languages
+ * Compare two chunk lists to find function-level changes
+ */
+export function diffChunks(oldChunks: CodeChunk[], newChunks: CodeChunk[]): ChunkDiff;
+
+/**
+ * Get up to `window` chunks either side of a chunk in the same file
+ */
+export function getChunkContext(chunk: CodeChunk, allChunks: CodeChunk[], window: number): ChunkContext;
+
+/**
+ * Check that chunks' line ranges cover every non-empty line of a file
+ *
+ * Uncovered ranges bridge empty lines between uncovered ones; a file with
+ * no non-empty lines is 100 % covered.
+ */
+export function validateChunkCoverage(content: string, chunks: CodeChunk[]): CoverageReport;
+
+/**
+ * Jaccard similarity of two files' chunk hash sets, 0 when neither has
+ * chunks
+ */
+export function computeFileSimilarity(a: CodeChunk[], b: CodeChunk[]): number;
+
+/**
+ * MinHash approximation of `computeFileSimilarity`; error shrinks with
+ * `1 / sqrt(numHashes)`
+ */
+export function computeFileSimilarityMinhash(a: CodeChunk[], b: CodeChunk[], numHashes: number): number;
+
+/**
+ * Split a chunk into pieces that each fit a token budget
+ */
+export function splitChunkToFitTokenBudget(chunk: CodeChunk, maxTokens: number): CodeChunk[];
+
+/**
+ * Share a token budget between chunks
+ *
+ * `strategy` is `"equal"`, `"proportional"` (to each chunk's token count)
+ * or `"priority"`, which weights chunks by `priorities`.
+ */
+export function allocateTokenBudget(
+  chunks: CodeChunk[],
+  totalBudget: number,
+  strategy: 'equal' | 'proportional' | 'priority',
+  priorities?: number[]
+): AllocatedChunk[];
+
+/**
+ * Check chunks for overlaps, copies within a file, sizes embedding models
+ * handle badly and declarations without symbols
+ *
+ * Chunks over 4096 tokens are errors; overlapping line ranges, repeated
+ * hashes in a file, chunks under 5 tokens and non-`"chunk"` chunks without
+ * symbols are warnings.
+ */
+export function lintChunks(chunks: CodeChunk[]): ChunkLintResult[];
+
+/**
+ * Find copy-pasted code. Chunks with equal hashes are grouped with
+ * similarity 1; with a `threshold` below 1, other chunks also join a group
+ * when the SimHash of their token 3-grams and symbols agrees with it on at
+ * least that share of bits. A `threshold` of 1 returns only exact
+ * duplicates.
+ */
+export function detectCodeDuplication(chunks: CodeChunk[], threshold: number): DuplicateGroup[];
+
+/**
+ * Find which functions and methods call which others within each file.
+ * Each function's chunk is scanned for identifiers naming another function
+ * or method of its file, so references such as callbacks count as calls.
+ * Edges between files are not found.
+ */
+export function computeCallGraph(symbols: Symbol[], chunks: CodeChunk[]): CallGraph;
+
+/**
+ * Merge consecutive chunks under `minSize` tokens from the same file;
+ * only chunks with no lines between them are merged
+ */
+export function mergeSmallChunks(chunks: CodeChunk[], minSize: number): CodeChunk[];
+
+/**
+ * Trim the lines each chunk repeats from the chunks before it in its file,
+ * as chunking with `overlap` produces, so they are embedded once. Every
+ * chunk gets `originalStartLine`; trimmed chunks get a new `startLine`,
+ * `startByte`, `content`, `tokenCount` and SHA-256 `hash` but keep their
+ * id. Chunks wholly inside earlier ones are dropped.
+ */
+export function chunkCodeOverlapDedup(chunks: CodeChunk[]): CodeChunk[];
+
+/**
+ * Re-hash chunks by their whitespace-normalized content: trailing
+ * whitespace trimmed, indentation tabs expanded to 4-column stops, and the
+ * indentation shared by all lines and blank lines at either end removed. Only the SHA-256 `hash`
+ * changes; `content` and `id` are kept.
+ */
+export function normalizeWhitespaceInChunks(chunks: CodeChunk[]): CodeChunk[];
+
+/**
+ * Chunk code on the libuv thread pool
+ */
+export function chunkCodeAsync(
+  content: string,
+  language: string,
+  maxChunkSize: number,
+  overlap: number,
+  hashAlgorithm?: HashAlgorithm
+): Promise<CodeChunk[]>;
+
+/**
+ * Detect a file's language from its extension, shebang line or, failing
+ * those, its content. Functions taking a `language` also accept `"auto"`
+ * to detect it.
+ */
+export function detectLanguage(filePath: string, contentPrefix?: string): string | null;
+
+/**
+ * Detect a language from content alone: a shebang, or signatures such as
+ * `package main` (Go), `#[derive(` (Rust), `'use strict'` (JavaScript)
+ * and JSX markup (`jsx`/`tsx`). Returns null unless the confidence, from
+ * 0 to 1, reaches `minConfidence` (default 0.7).
+ */
+export function detectLanguageFromContent(content: string, minConfidence?: number): string | null;
+
+/**
+ * Guess whether a file holds tests: its path has a `test`, `tests`,
+ * `spec`, `specs` or `__tests__` component or name part (`a.test.ts`,
+ * `test_a.py`, `a_test.go`), or the first 200 bytes of content hold
+ * `describe(`/`it(`/`test(`, `#[test]`, `def test_` or `func Test` as
+ * fits the language
+ */
+export function isTestFile(filePath: string, contentPrefix: string, language: string): boolean;
+
+/**
+ * Get supported languages with grammar metadata
+ */
+export function getSupportedLanguages(): LanguageInfo[];
+
+/**
+ * Every node kind Tree-sitter produces for a representative snippet of the
+ * language, sorted; for debugging language support
+ */
+export function getSupportedNodeKinds(language: string): string[];
+
+/**
+ * Get supported language names
  */
-export function getSupportedLanguages(): string[];
+export function getSupportedLanguageNames(): string[];
 
 /**
  * Get version info
@@ -0,0 +1,209 @@
+//! Chunks as Apache Arrow IPC files, for handing to Python tooling
+//!
+//! One column per `CodeChunk` field under its Rust (snake_case) name:
+//! strings are `Utf8`, numbers `UInt32`, both nullable for the optional
+//! `original_start_line` and `language_version`, and `symbols` a
+//! `List<Utf8>`. A file holds one record batch and can be opened directly
+//! with `pyarrow.ipc.open_file`.
+
+use crate::CodeChunk;
+use anyhow::{anyhow, Result};
+use arrow2::array::{
+    Array, ListArray, MutableListArray, MutableUtf8Array, TryExtend, UInt32Array, Utf8Array,
+};
+use arrow2::chunk::Chunk;
+use arrow2::datatypes::{DataType, Field, Schema};
+use arrow2::io::ipc::read::{read_file_metadata, FileReader};
+use arrow2::io::ipc::write::{FileWriter, WriteOptions};
+use std::io::Cursor;
+
+/// Columns of optional fields, which may be null or missing
+const OPTIONAL_COLUMNS: &[&str] = &["original_start_line", "language_version"];
+
+/// Write chunks as an in-memory Arrow IPC file
+pub fn chunks_to_ipc(chunks: &[CodeChunk]) -> Result<Vec<u8>> {
+    let text = |field: fn(&CodeChunk) -> &str| -> Box<dyn Array> {
+        Utf8Array::<i32>::from_iter_values(chunks.iter().map(field)).boxed()
+    };
+    let number = |field: fn(&CodeChunk) -> u32| -> Box<dyn Array> {
+        UInt32Array::from_vec(chunks.iter().map(field).collect()).boxed()
+    };
+
+    let mut symbols = MutableListArray::<i32, MutableUtf8Array<i32>>::new();
+    symbols.try_extend(chunks.iter().map(|c| Some(c.symbols.iter().map(Some))))?;
+    let symbols: ListArray<i32> = symbols.into();
+
+    let columns: Vec<(&str, Box<dyn Array>)> = vec![
+        ("id", text(|c| &c.id)),
+        ("file_path", text(|c| &c.file_path)),
+        ("content", text(|c| &c.content)),
+        ("start_line", number(|c| c.start_line)),
+        ("end_line", number(|c| c.end_line)),
+        ("start_byte", number(|c| c.start_byte)),
+        ("end_byte", number(|c| c.end_byte)),
+        ("complexity", number(|c| c.complexity)),
+        ("token_count", number(|c| c.token_count)),
+        ("header_lines", number(|c| c.header_lines)),
+        (
+            "original_start_line",
+            UInt32Array::from(
+                chunks
+                    .iter()
+                    .map(|c| c.original_start_line)
+                    .collect::<Vec<_>>(),
+            )
+            .boxed(),
+        ),
+        ("chunk_type", text(|c| &c.chunk_type)),
+        ("language", text(|c| &c.language)),
+        ("symbols", symbols.boxed()),
+        ("hash", text(|c| &c.hash)),
+        (
+            "language_version",
+            Utf8Array::<i32>::from(
+                chunks
+                    .iter()
+                    .map(|c| c.language_version.as_deref())
+                    .collect::<Vec<_>>(),
+            )
+            .boxed(),
+        ),
+    ];
+
+    let schema = Schema::from(
+        columns
+            .iter()
+            .map(|(name, array)| {
+                Field::new(
+                    *name,
+                    array.data_type().clone(),
+                    OPTIONAL_COLUMNS.contains(name),
+                )
+            })
+            .collect::<Vec<_>>(),
+    );
+    let batch = Chunk::try_new(columns.into_iter().map(|(_, array)| array).collect())?;
+
+    let mut writer =
+        FileWriter::try_new(Vec::new(), schema, None, WriteOptions { compression: None })?;
+    writer.write(&batch, None)?;
+    writer.finish()?;
+    Ok(writer.into_inner())
+}
+
+/// Read chunks from an Arrow IPC file written by `chunks_to_ipc`
+///
+/// Columns are found by name, so files with extra columns or another
+/// column order are accepted. The `OPTIONAL_COLUMNS` may be left out.
+pub fn chunks_from_ipc(data: &[u8]) -> Result<Vec<CodeChunk>> {
+    let mut cursor = Cursor::new(data);
+    let metadata = read_file_metadata(&mut cursor)?;
+    let names: Vec<String> = metadata
+        .schema
+        .fields
+        .iter()
+        .map(|f| f.name.clone())
+        .collect();
+    let mut chunks = Vec::new();
+
+    for batch in FileReader::new(cursor, metadata, None, None) {
+        let batch = batch?;
+        let column = |name: &str| {
+            names
+                .iter()
+                .position(|n| n == name)
+                .map(|i| &batch.arrays()[i])
+                .ok_or_else(|| anyhow!("Arrow file has no {} column", name))
+        };
+        let text = |name: &str| -> Result<&Utf8Array<i32>> {
+            column(name)?
+                .as_any()
+                .downcast_ref()
+                .ok_or_else(|| anyhow!("Arrow column {} is not Utf8", name))
+        };
+        let number = |name: &str| -> Result<&UInt32Array> {
+            column(name)?
+                .as_any()
+                .downcast_ref()
+                .ok_or_else(|| anyhow!("Arrow column {} is not UInt32", name))
+        };
+
+        let (id, file_path, content) = (text("id")?, text("file_path")?, text("content")?);
+        let (start_line, end_line) = (number("start_line")?, number("end_line")?);
+        let (start_byte, end_byte) = (number("start_byte")?, number("end_byte")?);
+        let (complexity, token_count, header_lines) = (
+            number("complexity")?,
+            number("token_count")?,
+            number("header_lines")?,
+        );
+        let original_start_line = match names.iter().any(|n| n == "original_start_line") {
+            true => Some(number("original_start_line")?),
+            false => None,
+        };
+        let (chunk_type, language, hash) = (text("chunk_type")?, text("language")?, text("hash")?);
+        let language_version = match names.iter().any(|n| n == "language_version") {
+            true => Some(text("language_version")?),
+            false => None,
+        };
+        let symbols: &ListArray<i32> = column("symbols")?
+            .as_any()
+            .downcast_ref()
+            .filter(|list: &&ListArray<i32>| list.values().data_type() == &DataType::Utf8)
+            .ok_or_else(|| anyhow!("Arrow column symbols is not List<Utf8>"))?;
+
+        for i in 0..batch.len() {
+            let names = symbols.value(i);
+            let names = names.as_any().downcast_ref::<Utf8Array<i32>>().unwrap();
+            chunks.push(CodeChunk {
+                id: id.value(i).to_string(),
+                file_path: file_path.value(i).to_string(),
+                content: content.value(i).to_string(),
+                start_line: start_line.value(i),
+                end_line: end_line.value(i),
+                start_byte: start_byte.value(i),
+                end_byte: end_byte.value(i),
+                complexity: complexity.value(i),
+                token_count: token_count.value(i),
+                header_lines: header_lines.value(i),
+                original_start_line: original_start_line.and_then(|column| column.get(i)),
+                chunk_type: chunk_type.value(i).to_string(),
+                language: language.value(i).to_string(),
+                symbols: names.iter().flatten().map(str::to_string).collect(),
+                hash: hash.value(i).to_string(),
+                language_version: language_version
+                    .and_then(|column| column.get(i))
+                    .map(str::to_string),
+            });
+        }
+    }
+
+    Ok(chunks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn test_chunks_ipc_roundtrip() {
+        let content = "function add(a, b) {\n    return a + b;\n}\n\nclass Point {\n    norm() { return 0; }\n}\n";
+        let mut chunks = parser::parse_file("a.js", content, "javascript").unwrap();
+        assert!(chunks.len() > 1);
+        chunks[1].original_start_line = Some(4);
+        chunks[0].language_version = Some("\"use strict\"".to_string());
+
+        let data = chunks_to_ipc(&chunks).unwrap();
+        assert!(data.starts_with(b"ARROW1"));
+        let read = chunks_from_ipc(&data).unwrap();
+        assert_eq!(
+            serde_json::to_value(&read).unwrap(),
+            serde_json::to_value(&chunks).unwrap()
+        );
+
+        assert!(chunks_from_ipc(&chunks_to_ipc(&[]).unwrap())
+            .unwrap()
+            .is_empty());
+        assert!(chunks_from_ipc(b"not arrow").is_err());
+    }
+}
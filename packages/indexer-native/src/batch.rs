@@ -0,0 +1,240 @@
+//! Parallel batch operations over many files
+
+use crate::parser;
+use crate::{FileInput, HashAlgorithm, ParseResult, SymbolsResult};
+use anyhow::Result;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Parse many files in parallel
+///
+/// A file that fails to parse gets an `error` and no chunks; the other
+/// files are unaffected. Results are in input order.
+pub fn parse_files(files: &[FileInput]) -> Vec<ParseResult> {
+    run_batch(files, |ts_parser, file, language| {
+        parser::parse_file_with(ts_parser, &file.file_path, &file.content, language)
+    })
+    .into_iter()
+    .zip(files)
+    .map(|(result, file)| match result {
+        Ok(chunks) => ParseResult {
+            file_path: file.file_path.clone(),
+            chunks,
+            symbols: vec![],
+            imports: vec![],
+            comments: vec![],
+            error: None,
+        },
+        Err(error) => ParseResult {
+            file_path: file.file_path.clone(),
+            chunks: vec![],
+            symbols: vec![],
+            imports: vec![],
+            comments: vec![],
+            error: Some(error),
+        },
+    })
+    .collect()
+}
+
+/// Extract symbols from many files in parallel
+///
+/// Failures are isolated per file exactly as in `parse_files`.
+pub fn extract_symbols(files: &[FileInput]) -> Vec<SymbolsResult> {
+    run_batch(files, |ts_parser, file, language| {
+        parser::extract_symbols_with(ts_parser, &file.file_path, &file.content, language, false)
+    })
+    .into_iter()
+    .zip(files)
+    .map(|(result, file)| match result {
+        Ok(symbols) => SymbolsResult {
+            file_path: file.file_path.clone(),
+            symbols,
+            error: None,
+        },
+        Err(error) => SymbolsResult {
+            file_path: file.file_path.clone(),
+            symbols: vec![],
+            error: Some(error),
+        },
+    })
+    .collect()
+}
+
+/// SHA-256 hash many contents in parallel, in input order
+///
+/// On x86_64 `sha2` is built with its assembly backend and switches to the
+/// CPU's SHA extensions when available; other targets use the portable
+/// implementation. Digests are identical either way.
+pub fn hash_contents(contents: &[String]) -> Vec<String> {
+    contents
+        .par_iter()
+        .map(|content| HashAlgorithm::Sha256.hash(content))
+        .collect()
+}
+
+/// Group items by a key, keeping their order within each group
+pub fn group_by<T>(items: Vec<T>, key: impl Fn(&T) -> &str) -> HashMap<String, Vec<T>> {
+    let mut groups: HashMap<String, Vec<T>> = HashMap::new();
+    for item in items {
+        match groups.get_mut(key(&item)) {
+            Some(group) => group.push(item),
+            None => {
+                groups.insert(key(&item).to_string(), vec![item]);
+            }
+        }
+    }
+    groups
+}
+
+/// Run `op` for every file on the rayon pool, preserving input order
+///
+/// Each worker thread creates its own Tree-sitter parser and reuses it
+/// across files. Panics are caught per file and reported as errors.
+fn run_batch<T, F>(files: &[FileInput], op: F) -> Vec<std::result::Result<T, String>>
+where
+    T: Send,
+    F: Fn(&mut tree_sitter::Parser, &FileInput, &str) -> Result<T> + Sync,
+{
+    files
+        .par_iter()
+        .map_init(tree_sitter::Parser::new, |ts_parser, file| {
+            let language = file
+                .language
+                .as_deref()
+                .filter(|language| *language != "auto")
+                .or_else(|| parser::detect_language(&file.file_path, Some(&file.content)))
+                .ok_or_else(|| format!("Unsupported language: {}", file.file_path))?;
+            parser::set_parser_language(ts_parser, language).map_err(|e| e.to_string())?;
+
+            match panic::catch_unwind(AssertUnwindSafe(|| op(ts_parser, file, language))) {
+                Ok(result) => result.map_err(|e| e.to_string()),
+                Err(_) => {
+                    // The parser may be left mid-parse; start the next file fresh
+                    *ts_parser = tree_sitter::Parser::new();
+                    Err(format!("Parser panicked on {}", file.file_path))
+                }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, content: &str, language: Option<&str>) -> FileInput {
+        FileInput {
+            file_path: path.to_string(),
+            content: content.to_string(),
+            language: language.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_group_by() {
+        let files = vec![
+            file("a.ts", "function a() {}\nclass A {}\n", None),
+            file("b.py", "def b():\n    pass\n", None),
+        ];
+        let chunks: Vec<_> = parse_files(&files)
+            .into_iter()
+            .flat_map(|r| r.chunks)
+            .collect();
+
+        let by_file = group_by(chunks, |c| &c.file_path);
+        assert_eq!(by_file.len(), 2);
+        let names: Vec<&str> = by_file["a.ts"]
+            .iter()
+            .map(|c| c.symbols[0].as_str())
+            .collect();
+        assert_eq!(names, vec!["a", "A"]);
+        assert_eq!(by_file["b.py"].len(), 1);
+
+        assert!(group_by(Vec::<FileInput>::new(), |f| &f.file_path).is_empty());
+    }
+
+    #[test]
+    fn test_parse_files() {
+        let files = vec![
+            file("a.ts", "function a() {}\n", None),
+            file("notes.txt", "hello", None),
+            file("b", "def b():\n    pass\n", Some("python")),
+            file("c.rs", "fn c() {}", Some("cobol")),
+        ];
+
+        let results = parse_files(&files);
+        let paths: Vec<&str> = results.iter().map(|r| r.file_path.as_str()).collect();
+        assert_eq!(paths, vec!["a.ts", "notes.txt", "b", "c.rs"]);
+
+        assert!(results[0].error.is_none());
+        assert_eq!(results[0].chunks[0].chunk_type, "function");
+        assert!(results[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("Unsupported language"));
+        assert!(results[1].chunks.is_empty());
+        assert_eq!(results[2].chunks[0].file_path, "b");
+        assert!(results[3].error.is_some());
+    }
+
+    #[test]
+    fn test_extract_symbols_batch() {
+        let files = vec![
+            file("a.ts", "export class A {}\n", None),
+            file("b.unknown", "", None),
+            file("c.py", "def c():\n    pass\n", None),
+        ];
+
+        let results = extract_symbols(&files);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].symbols[0].name, "A");
+        assert_eq!(results[0].symbols[0].file_path, "a.ts");
+        assert!(results[1].error.is_some());
+        assert_eq!(results[2].symbols[0].name, "c");
+    }
+
+    #[test]
+    fn test_hash_contents() {
+        let contents: Vec<String> = (0..50).map(|i| format!("content {}", i)).collect();
+        let hashes = hash_contents(&contents);
+        assert_eq!(hashes.len(), 50);
+        for (content, hash) in contents.iter().zip(&hashes) {
+            assert_eq!(*hash, HashAlgorithm::Sha256.hash(content));
+        }
+        assert_eq!(
+            hashes[0],
+            "75e0d458fc2da40b5b8b8b614d0192e9da7fffc2d6042f33300fdb0e8a83dfb4"
+        );
+    }
+
+    /// Run with `cargo test --release -- --ignored hash_contents_throughput --nocapture`
+    #[test]
+    #[ignore]
+    fn test_hash_contents_throughput() {
+        let contents: Vec<String> = (0..1000)
+            .map(|i| format!("export const value{} = {};\n", i, i).repeat(500))
+            .collect();
+
+        let start = std::time::Instant::now();
+        let sequential: Vec<String> = contents
+            .iter()
+            .map(|c| HashAlgorithm::Sha256.hash(c))
+            .collect();
+        let sequential_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let batched = hash_contents(&contents);
+        let batched_time = start.elapsed();
+
+        assert_eq!(sequential, batched);
+        println!(
+            "sequential {:?}, batched {:?}, speedup {:.2}x",
+            sequential_time,
+            batched_time,
+            sequential_time.as_secs_f64() / batched_time.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,179 @@
+//! Token budget allocation across the chunks of an LLM prompt
+
+use crate::tokens;
+use crate::{AllocatedChunk, CodeChunk};
+use anyhow::Result;
+
+/// Share `total_budget` tokens between chunks
+///
+/// `strategy` weights each chunk's share: `"equal"` alike, `"proportional"`
+/// by its `token_count` and `"priority"` by its entry in `priorities`. No
+/// chunk gets more than its `token_count`; what it does not need is shared
+/// out again among the rest by the same weights. Chunks keep their order,
+/// and each is trimmed to whole lines within its allocation where possible.
+pub fn allocate(
+    chunks: Vec<CodeChunk>,
+    total_budget: u32,
+    strategy: &str,
+    priorities: Option<&[f64]>,
+) -> Result<Vec<AllocatedChunk>> {
+    let weights: Vec<f64> = match strategy {
+        "equal" => vec![1.0; chunks.len()],
+        "proportional" => chunks.iter().map(|c| c.token_count as f64).collect(),
+        "priority" => {
+            let priorities = priorities
+                .ok_or_else(|| anyhow::anyhow!("The priority strategy needs priorities"))?;
+            if priorities.len() != chunks.len() {
+                anyhow::bail!(
+                    "Got {} priorities for {} chunks",
+                    priorities.len(),
+                    chunks.len()
+                );
+            }
+            priorities.iter().map(|&p| p.max(0.0)).collect()
+        }
+        _ => anyhow::bail!("Unknown budget strategy: {}", strategy),
+    };
+
+    let needs: Vec<u32> = chunks.iter().map(|c| c.token_count).collect();
+    let allocations = fill(&needs, &weights, total_budget);
+
+    Ok(chunks
+        .into_iter()
+        .zip(allocations)
+        .map(|(chunk, allocated_tokens)| AllocatedChunk {
+            truncated_content: truncate(&chunk, allocated_tokens),
+            chunk,
+            allocated_tokens,
+        })
+        .collect())
+}
+
+/// Split `budget` by weight, capping each share at its need
+fn fill(needs: &[u32], weights: &[f64], budget: u32) -> Vec<u32> {
+    let mut allocations = vec![0u32; needs.len()];
+    let mut remaining = budget;
+    let mut active: Vec<usize> = (0..needs.len())
+        .filter(|&i| needs[i] > 0 && weights[i] > 0.0)
+        .collect();
+
+    while remaining > 0 && !active.is_empty() {
+        let total_weight: f64 = active.iter().map(|&i| weights[i]).sum();
+        let shares: Vec<f64> = active
+            .iter()
+            .map(|&i| remaining as f64 * weights[i] / total_weight)
+            .collect();
+
+        // Satisfy every chunk whose share covers it, then share again
+        let satisfied: Vec<usize> = active
+            .iter()
+            .zip(&shares)
+            .filter(|(&i, &share)| share >= needs[i] as f64)
+            .map(|(&i, _)| i)
+            .collect();
+        if !satisfied.is_empty() {
+            for &i in &satisfied {
+                allocations[i] = needs[i];
+                remaining -= needs[i];
+            }
+            active.retain(|i| !satisfied.contains(i));
+            continue;
+        }
+
+        // No share covers its chunk: hand out whole tokens and the rounding
+        // remainder to the largest fractions
+        let mut given = 0;
+        for (&i, &share) in active.iter().zip(&shares) {
+            allocations[i] = share.floor() as u32;
+            given += allocations[i];
+        }
+        let mut by_fraction: Vec<(usize, f64)> = active
+            .iter()
+            .zip(&shares)
+            .map(|(&i, &share)| (i, share.fract()))
+            .collect();
+        by_fraction.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+        for &(i, _) in by_fraction.iter().take((remaining - given) as usize) {
+            allocations[i] += 1;
+        }
+        break;
+    }
+
+    allocations
+}
+
+/// Content cut to about `allocated_tokens`, at a line end where one fits
+fn truncate(chunk: &CodeChunk, allocated_tokens: u32) -> String {
+    if allocated_tokens >= chunk.token_count {
+        return chunk.content.clone();
+    }
+
+    let prefix = &chunk.content[..tokens::fit_tokens(&chunk.content, allocated_tokens)];
+    match prefix.rfind('\n') {
+        Some(end) => prefix[..end].to_string(),
+        None => prefix.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(lines: usize) -> CodeChunk {
+        let content = vec!["let value = compute();"; lines].join("\n");
+        CodeChunk {
+            id: String::new(),
+            file_path: "a.ts".to_string(),
+            start_line: 1,
+            end_line: lines as u32,
+            start_byte: 0,
+            end_byte: content.len() as u32,
+            complexity: 0,
+            token_count: tokens::estimate_tokens(&content),
+            header_lines: 0,
+            original_start_line: None,
+            language_version: None,
+            chunk_type: "chunk".to_string(),
+            language: "typescript".to_string(),
+            symbols: vec![],
+            hash: String::new(),
+            content,
+        }
+    }
+
+    fn allocated(chunks: &[AllocatedChunk]) -> Vec<u32> {
+        chunks.iter().map(|c| c.allocated_tokens).collect()
+    }
+
+    #[test]
+    fn test_allocate_equal() {
+        // 7, 20 and 40 tokens: the small chunk's spare share goes to the rest
+        let chunks = vec![chunk(1), chunk(3), chunk(6)];
+        assert_eq!(
+            chunks.iter().map(|c| c.token_count).collect::<Vec<_>>(),
+            vec![7, 20, 40]
+        );
+        let result = allocate(chunks, 47, "equal", None).unwrap();
+        assert_eq!(allocated(&result), vec![7, 20, 20]);
+        assert_eq!(result[1].truncated_content, result[1].chunk.content);
+
+        let trimmed = &result[2].truncated_content;
+        assert!(tokens::estimate_tokens(trimmed) <= 20);
+        assert_eq!(trimmed.lines().count(), 3);
+    }
+
+    #[test]
+    fn test_allocate_proportional_and_priority() {
+        let result = allocate(vec![chunk(3), chunk(6)], 30, "proportional", None).unwrap();
+        assert_eq!(allocated(&result), vec![10, 20]);
+
+        let priorities = [3.0, 1.0];
+        let result = allocate(vec![chunk(3), chunk(6)], 40, "priority", Some(&priorities)).unwrap();
+        assert_eq!(allocated(&result), vec![20, 20]);
+        assert_eq!(allocated(&result).iter().sum::<u32>(), 40);
+
+        assert!(allocate(vec![chunk(3)], 10, "priority", None).is_err());
+        assert!(allocate(vec![chunk(3)], 10, "priority", Some(&priorities)).is_err());
+        assert!(allocate(vec![chunk(3)], 10, "random", None).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+//! Call graphs within a file from its symbols and chunks
+
+use crate::parser;
+use crate::{CallEdge, CallGraph, CodeChunk, Symbol};
+use std::collections::HashMap;
+
+/// Find which functions and methods refer to which others in their file
+///
+/// Each function or method symbol is matched to the smallest chunk of its
+/// file that spans it and names it, and that chunk is parsed again to find
+/// identifiers naming another function or method of the same file. Any
+/// use counts, so passing a function as a callback is an edge too. Roots
+/// are the functions no other function refers to, in symbol order.
+/// Functions without a chunk have no outgoing edges.
+pub fn compute_call_graph(symbols: &[Symbol], chunks: &[CodeChunk]) -> CallGraph {
+    let callables: Vec<&Symbol> = symbols
+        .iter()
+        .filter(|s| matches!(s.kind.as_str(), "function" | "method"))
+        .collect();
+    let mut parsers: HashMap<&str, Option<tree_sitter::Parser>> = HashMap::new();
+    let mut edges: Vec<CallEdge> = Vec::new();
+
+    for caller in &callables {
+        let Some(chunk) = body_chunk(caller, chunks) else {
+            continue;
+        };
+        let parser = parsers
+            .entry(&chunk.language)
+            .or_insert_with(|| parser::get_parser(&chunk.language).ok());
+        let Some(tree) = parser.as_mut().and_then(|p| p.parse(&chunk.content, None)) else {
+            continue;
+        };
+
+        let mut names = Vec::new();
+        parser::collect_identifier_uses(&tree.root_node(), &chunk.content, &mut names);
+        for (name, row) in names {
+            // Prepended header lines are not part of the body
+            if row < chunk.header_lines as usize {
+                continue;
+            }
+            let known = callables
+                .iter()
+                .any(|c| c.name == name && c.file_path == caller.file_path);
+            let seen = edges
+                .iter()
+                .any(|e| e.caller == caller.name && e.callee == name);
+            if known && !seen {
+                edges.push(CallEdge {
+                    caller: caller.name.clone(),
+                    callee: name.to_string(),
+                });
+            }
+        }
+    }
+
+    let mut roots: Vec<String> = Vec::new();
+    for symbol in &callables {
+        let called = edges
+            .iter()
+            .any(|e| e.callee == symbol.name && e.caller != symbol.name);
+        if !called && !roots.contains(&symbol.name) {
+            roots.push(symbol.name.clone());
+        }
+    }
+
+    CallGraph { edges, roots }
+}
+
+/// Smallest chunk of the symbol's file that spans the symbol and names it
+fn body_chunk<'a>(symbol: &Symbol, chunks: &'a [CodeChunk]) -> Option<&'a CodeChunk> {
+    chunks
+        .iter()
+        .filter(|c| {
+            c.file_path == symbol.file_path
+                && c.start_line <= symbol.start_line
+                && c.end_line >= symbol.end_line
+                && c.symbols.contains(&symbol.name)
+        })
+        .min_by_key(|c| c.end_line - c.start_line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(file_path: &str, content: &str, language: &str) -> CallGraph {
+        let symbols = parser::extract_symbols(file_path, content, language, false).unwrap();
+        let chunks = parser::parse_file(file_path, content, language).unwrap();
+        compute_call_graph(&symbols, &chunks)
+    }
+
+    fn edges(graph: &CallGraph) -> Vec<(&str, &str)> {
+        graph
+            .edges
+            .iter()
+            .map(|e| (e.caller.as_str(), e.callee.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_compute_call_graph() {
+        let content = "function main() {\n    const x = parse(read());\n    return check(x) && parse(x);\n}\n\nfunction parse(s) {\n    return check(s) ? s : parse(s.slice(1));\n}\n\nfunction check(s) {\n    return s.length > 0;\n}\n\nfunction read() {\n    return [1, 2].map(check);\n}\n\nfunction unused() {}\n";
+        let graph = graph("a.js", content, "javascript");
+        assert_eq!(
+            edges(&graph),
+            vec![
+                ("main", "parse"),
+                ("main", "read"),
+                ("main", "check"),
+                ("parse", "check"),
+                ("parse", "parse"),
+                ("read", "check"),
+            ]
+        );
+        assert_eq!(graph.roots, vec!["main", "unused"]);
+    }
+
+    #[test]
+    fn test_call_graph_methods() {
+        let content = "class Store:\n    def load(self):\n        return self.fetch()\n\n    def fetch(self):\n        return helper()\n\n\ndef helper():\n    return 1\n";
+        let graph = graph("a.py", content, "python");
+        assert_eq!(edges(&graph), vec![("load", "fetch"), ("fetch", "helper")]);
+        assert_eq!(graph.roots, vec!["load"]);
+
+        // Functions of another file are not callees
+        let symbols =
+            parser::extract_symbols("b.py", "def helper():\n    return 2\n", "python", false)
+                .unwrap();
+        let chunks = parser::parse_file("a.py", "def main():\n    helper()\n", "python").unwrap();
+        let mut all =
+            parser::extract_symbols("a.py", "def main():\n    helper()\n", "python", false)
+                .unwrap();
+        all.extend(symbols);
+        assert!(compute_call_graph(&all, &chunks).edges.is_empty());
+    }
+}
@@ -1,8 +1,49 @@
 //! Semantic code chunking
 
-use crate::CodeChunk;
+use crate::{parser, pragmas, tokens};
+use crate::{
+    ChunkChange, ChunkContext, ChunkDiff, ChunkOptions, CodeChunk, CoverageReport, HashAlgorithm,
+    OverlapStrategy,
+};
 use anyhow::Result;
-use sha2::{Sha256, Digest};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ops::ControlFlow;
+
+/// Default for `ChunkOptions::header_max_lines`
+const DEFAULT_HEADER_MAX_LINES: u32 = 10;
+
+/// Columns between tab stops when normalizing indentation
+const TAB_WIDTH: usize = 4;
+
+/// How the size of a chunk is measured
+///
+/// Neither strategy sets the overlap between chunks; that is up to
+/// `ChunkOptions::chunk_overlap_strategy` whichever strategy is used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkStrategy {
+    /// At most `max_lines` lines
+    ByLines { max_lines: usize },
+    /// At most `max_tokens` estimated tokens
+    ///
+    /// To overlap by tokens rather than lines, pair this with
+    /// `OverlapStrategy::FixedTokens` and `ChunkOptions::overlap_tokens`.
+    ByTokens { max_tokens: usize },
+}
+
+impl ChunkStrategy {
+    /// `ByTokens` when `max_tokens` is set, otherwise `ByLines`; either
+    /// limit is at least 1 so every chunk holds a line
+    pub fn from_options(options: &ChunkOptions) -> Self {
+        match options.max_tokens {
+            Some(max_tokens) => ChunkStrategy::ByTokens {
+                max_tokens: max_tokens.max(1) as usize,
+            },
+            None => ChunkStrategy::ByLines {
+                max_lines: options.max_chunk_size.max(1) as usize,
+            },
+        }
+    }
+}
 
 /// Chunk code into semantic blocks
 pub fn chunk_code(
@@ -10,54 +51,922 @@ pub fn chunk_code(
     language: &str,
     max_chunk_size: usize,
     overlap: usize,
+    hash_algorithm: Option<HashAlgorithm>,
+) -> Result<Vec<CodeChunk>> {
+    let options = ChunkOptions {
+        max_chunk_size: max_chunk_size as u32,
+        overlap: overlap as u32,
+        hash_algorithm,
+        chunk_overlap_strategy: Some(OverlapStrategy::FixedLines),
+        ..Default::default()
+    };
+    chunk_code_with_options(content, language, &options)
+}
+
+/// Chunk code into semantic blocks with extended options
+pub fn chunk_code_with_options(
+    content: &str,
+    language: &str,
+    options: &ChunkOptions,
 ) -> Result<Vec<CodeChunk>> {
-    let lines: Vec<&str> = content.lines().collect();
     let mut chunks = Vec::new();
+    chunk_code_each(content, language, options, |chunk| {
+        chunks.push(chunk);
+        ControlFlow::Continue(())
+    })?;
+    Ok(chunks)
+}
+
+/// Chunk like `chunk_code_with_options`, passing each chunk to `emit` as
+/// soon as it is complete instead of collecting them
+///
+/// Chunking stops early, without an error, once `emit` breaks.
+pub fn chunk_code_each(
+    content: &str,
+    language: &str,
+    options: &ChunkOptions,
+    mut emit: impl FnMut(CodeChunk) -> ControlFlow<()>,
+) -> Result<()> {
+    let language = parser::resolve_language(language, "", content)?;
+    let strategy = ChunkStrategy::from_options(options);
+    let overlap_strategy = options
+        .chunk_overlap_strategy
+        .unwrap_or(OverlapStrategy::SemanticBoundary);
+    let class_limit = options.keep_class_intact_under_lines.map(|n| n as usize);
+
+    let lines: Vec<&str> = content.lines().collect();
+    let line_starts: Vec<usize> = content
+        .split_inclusive('\n')
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some(start)
+        })
+        .collect();
+    let count_line_tokens = matches!(strategy, ChunkStrategy::ByTokens { .. })
+        || overlap_strategy == OverlapStrategy::FixedTokens;
+    let line_tokens: Vec<usize> = if count_line_tokens {
+        lines
+            .iter()
+            .map(|line| tokens::estimate_tokens(line) as usize)
+            .collect()
+    } else {
+        vec![]
+    };
+    let doc_lines = find_doc_lines(&lines);
+    let ignored = ignored_lines(content, language);
+    let header = FileHeader::new(content, language, options);
+    let language_version = parser::language_version(content, language);
+    let mut push = |mut chunk: CodeChunk| {
+        chunk.language_version = language_version.clone();
+        emit(match &header {
+            Some(header) => header.apply(chunk),
+            None => chunk,
+        })
+    };
     let mut current_start = 0;
 
     while current_start < lines.len() {
-        // Find chunk end
-        let chunk_end = find_chunk_boundary(
-            &lines,
-            current_start,
-            max_chunk_size,
-            language,
-        );
+        // Skip regions excluded by `@titan-ignore`
+        if ignored[current_start] {
+            current_start += 1;
+            continue;
+        }
+
+        // A line too long for the token budget is split on its own
+        if let ChunkStrategy::ByTokens { max_tokens, .. } = strategy {
+            if line_tokens[current_start] > max_tokens {
+                let line_start = line_starts[current_start];
+                for (from, to) in split_long_line(lines[current_start], max_tokens) {
+                    let piece = new_chunk(
+                        lines[current_start][from..to].to_string(),
+                        (current_start + 1, current_start + 1),
+                        (line_start + from, line_start + to),
+                        "chunk",
+                        language,
+                        options,
+                    );
+                    if push(piece).is_break() {
+                        return Ok(());
+                    }
+                }
+                current_start += 1;
+                continue;
+            }
+        }
+
+        // Find chunk end, with token budgets converted to the lines that fit
+        let max_lines = match strategy {
+            ChunkStrategy::ByLines { max_lines, .. } => max_lines,
+            ChunkStrategy::ByTokens { max_tokens, .. } => {
+                lines_within(&line_tokens[current_start..], max_tokens).max(1)
+            }
+        };
+        let mut chunk_end =
+            find_chunk_boundary(&lines, &doc_lines, current_start, max_lines, language);
+        let mut chunk_type = "chunk";
+        let mut apply_overlap = true;
+
+        // Keep small classes whole: emit them as one chunk, or end the
+        // current chunk where the class begins
+        if let Some(limit) = class_limit {
+            if let Some((class_start, class_end)) =
+                find_class_block(&lines, &doc_lines, current_start, chunk_end, language)
+            {
+                if class_end + 1 - class_start <= limit {
+                    if class_start == current_start {
+                        chunk_end = class_end + 1;
+                        chunk_type = "class";
+                        apply_overlap = false;
+                    } else if class_end >= chunk_end {
+                        chunk_end = class_start;
+                        apply_overlap = false;
+                    }
+                }
+            }
+        }
+
+        // End before an excluded region, without overlapping back into it
+        if let Some(i) = (current_start..chunk_end).find(|&i| ignored[i]) {
+            chunk_end = i;
+            apply_overlap = false;
+        }
 
         // Extract chunk content
         let chunk_lines = &lines[current_start..chunk_end];
-        let chunk_content = chunk_lines.join("\n");
-
-        // Generate hash
-        let hash = hash_content(&chunk_content);
-        let id = format!("chunk:{}:{}", current_start + 1, &hash[..8]);
-
-        chunks.push(CodeChunk {
-            id,
-            file_path: String::new(), // Set by caller
-            content: chunk_content,
-            start_line: (current_start + 1) as u32,
-            end_line: chunk_end as u32,
-            chunk_type: "chunk".to_string(),
-            language: language.to_string(),
-            symbols: vec![],
-            hash,
-        });
+        let chunk = new_chunk(
+            chunk_lines.join("\n"),
+            (current_start + 1, chunk_end),
+            (
+                line_starts[current_start],
+                line_starts[chunk_end - 1] + lines[chunk_end - 1].len(),
+            ),
+            chunk_type,
+            language,
+            options,
+        );
+        if push(chunk).is_break() {
+            return Ok(());
+        }
 
         // Move to next chunk with overlap
+        let overlap = match overlap_strategy {
+            OverlapStrategy::FixedLines => options.overlap as usize,
+            OverlapStrategy::FixedTokens => {
+                let mut before: Vec<usize> = line_tokens[current_start..chunk_end].to_vec();
+                before.reverse();
+                lines_within(&before, options.overlap_tokens.unwrap_or(0) as usize)
+            }
+            OverlapStrategy::SemanticBoundary => {
+                semantic_overlap(&lines, current_start, chunk_end, language)
+            }
+        };
         current_start = if chunk_end >= lines.len() {
             lines.len()
+        } else if !apply_overlap {
+            chunk_end
         } else {
-            chunk_end.saturating_sub(overlap)
+            chunk_end.saturating_sub(overlap).max(current_start + 1)
         };
     }
 
-    Ok(chunks)
+    Ok(())
+}
+
+/// Lines to repeat so the next chunk starts at the last unit beginning in
+/// the second half of `start..end`, or none when there is no such line
+///
+/// A unit begins on a non-blank line other than a closing brace that
+/// follows a blank line or closing brace, or is itself a declaration.
+fn semantic_overlap(lines: &[&str], start: usize, end: usize, language: &str) -> usize {
+    let min_start = start + (end - start).div_ceil(2);
+    (min_start.max(start + 1)..end)
+        .rev()
+        .find(|&i| {
+            let trimmed = lines[i].trim();
+            !(trimmed.is_empty() || trimmed == "}" || trimmed == "};")
+                && is_cut_point(lines, i, language)
+        })
+        .map_or(0, |i| end - i)
+}
+
+/// Check if a chunk may start at line `i`: after a blank line or closing
+/// brace, or before a declaration
+fn is_cut_point(lines: &[&str], i: usize, language: &str) -> bool {
+    let prev = lines[i - 1].trim();
+    prev.is_empty() || prev == "}" || prev == "};" || is_natural_boundary(lines[i], language)
+}
+
+/// Build a chunk from 1-based inclusive lines and a byte range
+fn new_chunk(
+    content: String,
+    (start_line, end_line): (usize, usize),
+    (start_byte, end_byte): (usize, usize),
+    chunk_type: &str,
+    language: &str,
+    options: &ChunkOptions,
+) -> CodeChunk {
+    let algorithm = options.hash_algorithm.unwrap_or(HashAlgorithm::Sha256);
+    let exact_tokens = options.estimate_tokens.unwrap_or(false);
+
+    CodeChunk {
+        id: parser::generate_chunk_id(&content, None, language),
+        file_path: String::new(), // Set by caller
+        start_line: start_line as u32,
+        end_line: end_line as u32,
+        start_byte: start_byte as u32,
+        end_byte: end_byte as u32,
+        complexity: count_branch_keywords(&content),
+        token_count: tokens::count_tokens(&content, exact_tokens),
+        header_lines: 0,
+        original_start_line: None,
+        language_version: None,
+        chunk_type: chunk_type.to_string(),
+        language: language.to_string(),
+        symbols: vec![],
+        hash: algorithm.hash(&content),
+        content,
+    }
+}
+
+/// Number of leading lines whose token counts add up to `budget` or less,
+/// allowing one token per line for the newline
+fn lines_within(line_tokens: &[usize], budget: usize) -> usize {
+    let mut total = 0;
+    line_tokens
+        .iter()
+        .take_while(|&&tokens| {
+            total += tokens + 1;
+            total <= budget + 1
+        })
+        .count()
+}
+
+/// Byte ranges splitting one line into pieces of at most `max_tokens`
+///
+/// Pieces end after a `;` or `}`, failing that after a `,` or space, where
+/// one falls in the second half of the piece, so minified code splits
+/// between statements rather than inside identifiers.
+fn split_long_line(line: &str, max_tokens: usize) -> Vec<(usize, usize)> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+
+    while start < line.len() {
+        let rest = &line[start..];
+        let mut len = tokens::fit_tokens(rest, max_tokens as u32);
+        if len < rest.len() {
+            let window = &rest[len / 2..len];
+            if let Some(cut) = window
+                .rfind([';', '}'])
+                .or_else(|| window.rfind([',', ' ']))
+            {
+                len = len / 2 + cut + 1;
+            }
+        }
+        // Always make progress, whole characters at a time
+        if len == 0 {
+            len = rest.chars().next().map_or(rest.len(), char::len_utf8);
+        }
+        pieces.push((start, start + len));
+        start += len;
+    }
+
+    pieces
+}
+
+/// Lines excluded by `@titan-ignore` pragmas, parsing only when needed
+fn ignored_lines(content: &str, language: &str) -> Vec<bool> {
+    let tree = if pragmas::has_pragmas(content) {
+        parser::get_parser(language)
+            .ok()
+            .and_then(|mut ts_parser| ts_parser.parse(content, None))
+    } else {
+        None
+    };
+    pragmas::ignored_lines("", content, tree.as_ref())
+}
+
+/// Chunk code with file-level context prepended to each chunk
+///
+/// Same as `chunk_code_with_options` with `prepend_header` forced on.
+pub fn chunk_code_with_header(
+    content: &str,
+    language: &str,
+    options: &ChunkOptions,
+) -> Result<Vec<CodeChunk>> {
+    let options = ChunkOptions {
+        prepend_header: Some(true),
+        ..options.clone()
+    };
+    chunk_code_with_options(content, language, &options)
+}
+
+/// Chunk code along AST declarations, falling back to line scanning
+///
+/// Chunk boundaries come from `parser::parse_file`, so the size options
+/// only apply when the language has no grammar and the line scanner is
+/// used instead. A file the parser cannot handle yields no chunks.
+pub fn chunk_code_with_ast(
+    file_path: &str,
+    content: &str,
+    language: &str,
+    options: &ChunkOptions,
+) -> Result<Vec<CodeChunk>> {
+    let language = parser::resolve_language(language, file_path, content)?;
+    if parser::get_parser(language).is_err() {
+        let mut chunks = chunk_code_with_options(content, language, options)?;
+        for chunk in &mut chunks {
+            chunk.file_path = file_path.to_string();
+        }
+        return Ok(chunks);
+    }
+
+    let chunks = match parser::parse_file(file_path, content, language) {
+        Ok(chunks) => chunks,
+        Err(_) => return Ok(vec![]),
+    };
+
+    let algorithm = options.hash_algorithm.unwrap_or(HashAlgorithm::Sha256);
+    let exact_tokens = options.estimate_tokens.unwrap_or(false);
+    let chunks = chunks
+        .into_iter()
+        .map(|mut chunk| {
+            chunk.hash = algorithm.hash(&chunk.content);
+            if exact_tokens {
+                chunk.token_count = tokens::count_tokens(&chunk.content, true);
+            }
+            chunk
+        })
+        .collect();
+    Ok(apply_header(chunks, content, language, options))
+}
+
+/// Prefix chunks with the file header lines that precede them
+///
+/// The header is the first `header_max_lines` imports and top-level
+/// declarations in the file. A chunk only gets the header lines above its
+/// own start, so the chunk holding the imports is left as is. Prefixed
+/// chunks are re-hashed and their tokens recounted.
+fn apply_header(
+    chunks: Vec<CodeChunk>,
+    content: &str,
+    language: &str,
+    options: &ChunkOptions,
+) -> Vec<CodeChunk> {
+    match FileHeader::new(content, language, options) {
+        Some(header) => chunks
+            .into_iter()
+            .map(|chunk| header.apply(chunk))
+            .collect(),
+        None => chunks,
+    }
+}
+
+/// Header lines of a file with the options for prefixing them
+struct FileHeader<'a> {
+    /// 0-based line number and text of each header line
+    lines: Vec<(usize, &'a str)>,
+    algorithm: HashAlgorithm,
+    exact_tokens: bool,
+}
+
+impl<'a> FileHeader<'a> {
+    /// The file's header, or `None` unless `prepend_header` is set
+    fn new(content: &'a str, language: &str, options: &ChunkOptions) -> Option<Self> {
+        if !options.prepend_header.unwrap_or(false) {
+            return None;
+        }
+
+        let max_lines = options.header_max_lines.unwrap_or(DEFAULT_HEADER_MAX_LINES) as usize;
+        Some(Self {
+            lines: content
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| is_header_line(line, language))
+                .take(max_lines)
+                .collect(),
+            algorithm: options.hash_algorithm.unwrap_or(HashAlgorithm::Sha256),
+            exact_tokens: options.estimate_tokens.unwrap_or(false),
+        })
+    }
+
+    /// Prefix a chunk with the header lines above its start
+    fn apply(&self, chunk: CodeChunk) -> CodeChunk {
+        let lines: Vec<&str> = self
+            .lines
+            .iter()
+            .take_while(|(i, _)| i + 1 < chunk.start_line as usize)
+            .map(|(_, line)| *line)
+            .collect();
+        with_header(chunk, &lines, self.algorithm, self.exact_tokens)
+    }
 }
 
+/// Prefix a chunk's content with header lines
+fn with_header(
+    mut chunk: CodeChunk,
+    header: &[&str],
+    algorithm: HashAlgorithm,
+    exact_tokens: bool,
+) -> CodeChunk {
+    if header.is_empty() {
+        return chunk;
+    }
+
+    chunk.content = format!("{}\n{}", header.join("\n"), chunk.content);
+    chunk.header_lines = header.len() as u32;
+    chunk.hash = algorithm.hash(&chunk.content);
+    chunk.token_count = tokens::count_tokens(&chunk.content, exact_tokens);
+    chunk
+}
+
+/// Check if an unindented line is an import or top-level declaration
+fn is_header_line(line: &str, language: &str) -> bool {
+    if line.starts_with(char::is_whitespace) {
+        return false;
+    }
+
+    let trimmed = line.trim();
+    if trimmed.is_empty() || trimmed.starts_with('}') || trimmed.starts_with('@') {
+        return false;
+    }
+
+    [
+        "import ", "from ", "use ", "pub use ", "mod ", "pub mod ", "package ", "#include",
+    ]
+    .iter()
+    .any(|prefix| trimmed.starts_with(prefix))
+        || is_natural_boundary(line, language)
+}
+
+/// Split a chunk so each piece's estimated token count fits `max_tokens`
+///
+/// Pieces end at natural boundaries (blank lines, closing braces, new
+/// declarations) where one falls in the second half of the piece. A single
+/// line over the budget becomes a piece of its own. Pieces are re-hashed
+/// with SHA-256. A prepended file header is repeated on every piece, and
+/// counts against the budget.
+pub fn split_chunk_to_fit_token_budget(chunk: CodeChunk, max_tokens: u32) -> Vec<CodeChunk> {
+    if tokens::estimate_tokens(&chunk.content) <= max_tokens {
+        return vec![chunk];
+    }
+
+    if chunk.header_lines > 0 {
+        let lines: Vec<&str> = chunk.content.split('\n').collect();
+        let (header, body) = lines.split_at((chunk.header_lines as usize).min(lines.len()));
+        let body_budget = max_tokens
+            .saturating_sub(tokens::estimate_tokens(&header.join("\n")))
+            .max(1);
+        let body_chunk = CodeChunk {
+            content: body.join("\n"),
+            header_lines: 0,
+            ..chunk.clone()
+        };
+        return split_chunk_to_fit_token_budget(body_chunk, body_budget)
+            .into_iter()
+            .map(|piece| with_header(piece, header, HashAlgorithm::Sha256, false))
+            .collect();
+    }
+
+    let lines: Vec<&str> = chunk.content.split('\n').collect();
+
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    while start < lines.len() {
+        // Grow the piece while the running estimate with the next line fits
+        let mut estimate = tokens::TokenEstimate::default();
+        estimate.push(lines[start]);
+        let mut end = start + 1;
+        while end < lines.len() {
+            let mut grown = estimate;
+            grown.push("\n");
+            grown.push(lines[end]);
+            if grown.tokens() > max_tokens {
+                break;
+            }
+            estimate = grown;
+            end += 1;
+        }
+
+        // Back up to a natural boundary unless that leaves a tiny piece:
+        // after a blank line or closing brace, or before a declaration
+        if end < lines.len() {
+            let min_end = start + (end - start).div_ceil(2);
+            if let Some(boundary) = (min_end..=end)
+                .rev()
+                .find(|&i| is_cut_point(&lines, i, &chunk.language))
+            {
+                end = boundary;
+            }
+        }
+
+        pieces.push((start, end));
+        start = end;
+    }
+
+    let line_offsets: Vec<usize> = lines
+        .iter()
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len() + 1;
+            Some(start)
+        })
+        .collect();
+
+    pieces
+        .into_iter()
+        .map(|(from, to)| {
+            let content = lines[from..to].join("\n");
+            let hash = HashAlgorithm::Sha256.hash(&content);
+            let start_line = chunk.start_line + from as u32;
+            let start_byte = chunk.start_byte + line_offsets[from] as u32;
+            let symbols: Vec<String> = chunk
+                .symbols
+                .iter()
+                .filter(|s| {
+                    content
+                        .split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+                        .any(|word| word == s.as_str())
+                })
+                .cloned()
+                .collect();
+            CodeChunk {
+                id: parser::generate_chunk_id(
+                    &content,
+                    symbols.first().map(String::as_str),
+                    &chunk.language,
+                ),
+                file_path: chunk.file_path.clone(),
+                start_line,
+                end_line: chunk.start_line + to as u32 - 1,
+                start_byte,
+                end_byte: start_byte + content.len() as u32,
+                complexity: count_branch_keywords(&content),
+                token_count: tokens::estimate_tokens(&content),
+                header_lines: 0,
+                original_start_line: None,
+                language_version: chunk.language_version.clone(),
+                chunk_type: chunk.chunk_type.clone(),
+                language: chunk.language.clone(),
+                symbols,
+                content,
+                hash,
+            }
+        })
+        .collect()
+}
+
+/// Merge runs of consecutive small chunks from the same file
+///
+/// A chunk under `min_size` tokens joins the small chunk before it until
+/// the merged chunk reaches `min_size`, as long as it starts at most one
+/// line after that chunk ends, so the merged content has no gaps. Lines
+/// shared through overlap are kept once and header lines only from the
+/// first chunk of a run. Merged chunks are re-hashed with SHA-256.
+pub fn merge_small_chunks(chunks: Vec<CodeChunk>, min_size: u32) -> Vec<CodeChunk> {
+    let mut merged: Vec<CodeChunk> = Vec::new();
+    let mut run_len = 0;
+
+    for chunk in chunks {
+        let extend = chunk.token_count < min_size
+            && run_len > 0
+            && merged.last().is_some_and(|last| {
+                last.file_path == chunk.file_path
+                    && last.token_count < min_size
+                    && chunk.start_line > last.start_line
+                    && chunk.start_line <= last.end_line + 1
+            });
+
+        if !extend {
+            if run_len > 1 {
+                finish_merge(merged.last_mut().unwrap());
+            }
+            run_len = usize::from(chunk.token_count < min_size);
+            merged.push(chunk);
+            continue;
+        }
+
+        let last = merged.last_mut().unwrap();
+        let skip = chunk.header_lines as usize
+            + (last.end_line + 1).saturating_sub(chunk.start_line) as usize;
+        let rest: Vec<&str> = chunk.content.split('\n').skip(skip).collect();
+        if !rest.is_empty() {
+            last.content.push('\n');
+            last.content.push_str(&rest.join("\n"));
+        }
+        last.end_line = last.end_line.max(chunk.end_line);
+        last.end_byte = last.end_byte.max(chunk.end_byte);
+        if last.chunk_type != chunk.chunk_type {
+            last.chunk_type = "chunk".to_string();
+        }
+        for symbol in chunk.symbols {
+            if !last.symbols.contains(&symbol) {
+                last.symbols.push(symbol);
+            }
+        }
+        last.token_count = tokens::estimate_tokens(&last.content);
+        run_len += 1;
+    }
+
+    if run_len > 1 {
+        finish_merge(merged.last_mut().unwrap());
+    }
+    merged
+}
+
+/// Trim the lines each chunk shares with the chunks before it in its file
+///
+/// Chunking with `overlap` repeats lines at chunk boundaries; storing them
+/// once keeps them from being embedded twice, and the overlap can be
+/// restored on retrieval from `original_start_line`, which every returned
+/// chunk records. Trimmed chunks get a new `start_line`, `start_byte`,
+/// `content`, `token_count` and SHA-256 `hash` but keep their id, and a
+/// prepended header stays in place. Chunks wholly inside earlier ones are
+/// dropped.
+pub fn dedup_overlap(chunks: Vec<CodeChunk>) -> Vec<CodeChunk> {
+    let mut covered: HashMap<String, u32> = HashMap::new();
+    let mut deduped = Vec::new();
+
+    for mut chunk in chunks {
+        let covered_to = covered.get(&chunk.file_path).copied().unwrap_or(0);
+        if chunk.end_line <= covered_to {
+            continue;
+        }
+        chunk.original_start_line = Some(chunk.start_line);
+
+        let shared = (covered_to + 1).saturating_sub(chunk.start_line) as usize;
+        if shared > 0 {
+            let mut lines: Vec<&str> = chunk.content.split('\n').collect();
+            let header = (chunk.header_lines as usize).min(lines.len());
+            let removed: u32 = lines
+                .drain(header..(header + shared).min(lines.len()))
+                .map(|line| line.len() as u32 + 1)
+                .sum();
+            chunk.content = lines.join("\n");
+            chunk.start_line += shared as u32;
+            chunk.start_byte += removed;
+            chunk.token_count = tokens::estimate_tokens(&chunk.content);
+            chunk.hash = HashAlgorithm::Sha256.hash(&chunk.content);
+        }
+
+        covered.insert(chunk.file_path.clone(), chunk.end_line);
+        deduped.push(chunk);
+    }
+    deduped
+}
+
+/// Hash chunks by their whitespace-normalized content
+///
+/// Each line loses its trailing whitespace, tabs in its indentation expand
+/// to stops every `TAB_WIDTH` columns, and the indentation all non-blank
+/// lines share is removed, as are blank lines at either end, so a function
+/// keeps its hash when re-indented or moved out of a class. Only the
+/// SHA-256 `hash` changes; `content` and the id stay as they are.
+pub fn normalize_whitespace(chunks: Vec<CodeChunk>) -> Vec<CodeChunk> {
+    chunks
+        .into_iter()
+        .map(|mut chunk| {
+            chunk.hash = HashAlgorithm::Sha256.hash(&normalized_content(&chunk.content));
+            chunk
+        })
+        .collect()
+}
+
+/// Content with trailing whitespace trimmed and indentation made canonical
+fn normalized_content(content: &str) -> String {
+    let lines: Vec<String> = content
+        .split('\n')
+        .map(|line| {
+            let line = line.trim_end();
+            let body = line.trim_start();
+            let mut width = 0;
+            for c in line[..line.len() - body.len()].chars() {
+                width = if c == '\t' {
+                    (width / TAB_WIDTH + 1) * TAB_WIDTH
+                } else {
+                    width + 1
+                };
+            }
+            format!("{}{}", " ".repeat(width), body)
+        })
+        .collect();
+
+    let common = lines
+        .iter()
+        .filter(|line| !line.is_empty())
+        .map(|line| indentation(line))
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(common..).unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim_matches('\n')
+        .to_string()
+}
+
+/// Recompute the content-derived fields of a merged chunk
+fn finish_merge(chunk: &mut CodeChunk) {
+    chunk.hash = HashAlgorithm::Sha256.hash(&chunk.content);
+    chunk.complexity = count_branch_keywords(&chunk.content);
+    chunk.id = parser::generate_chunk_id(
+        &chunk.content,
+        chunk.symbols.first().map(String::as_str),
+        &chunk.language,
+    );
+}
+
+/// Compare chunk lists, pairing chunks by location
+///
+/// Chunks are matched on `file_path` and `start_line`, so a chunk whose
+/// content changed in place is reported as modified rather than deleted
+/// and re-added. Chunks sharing a location are paired in order.
+pub fn diff_chunks(old_chunks: Vec<CodeChunk>, new_chunks: Vec<CodeChunk>) -> ChunkDiff {
+    let mut old_by_location: HashMap<(String, u32), VecDeque<CodeChunk>> = HashMap::new();
+    let mut old_order = Vec::new();
+    for chunk in old_chunks {
+        let location = (chunk.file_path.clone(), chunk.start_line);
+        if !old_by_location.contains_key(&location) {
+            old_order.push(location.clone());
+        }
+        old_by_location
+            .entry(location)
+            .or_default()
+            .push_back(chunk);
+    }
+
+    let mut added = Vec::new();
+    let mut modified = Vec::new();
+
+    for chunk in new_chunks {
+        let old = old_by_location
+            .get_mut(&(chunk.file_path.clone(), chunk.start_line))
+            .and_then(|queue| queue.pop_front());
+        match old {
+            Some(old) if old.hash == chunk.hash => {}
+            Some(old) => modified.push(ChunkChange { old, new: chunk }),
+            None => added.push(chunk),
+        }
+    }
+
+    // Whatever was not paired no longer exists
+    let deleted = old_order
+        .into_iter()
+        .flat_map(|location| old_by_location.remove(&location).unwrap_or_default())
+        .collect();
+
+    ChunkDiff {
+        added,
+        modified,
+        deleted,
+    }
+}
+
+/// Neighbours of a chunk among the chunks of its file
+///
+/// Chunks from other files are ignored, and the rest are ordered by
+/// `start_line` (then `start_byte`, for pieces of one line). Chunks at the
+/// target's own position, including the target, are in neither list.
+pub fn chunk_context(chunk: &CodeChunk, all_chunks: Vec<CodeChunk>, window: usize) -> ChunkContext {
+    let position = (chunk.start_line, chunk.start_byte);
+    let mut preceding = Vec::new();
+    let mut following = Vec::new();
+    for other in all_chunks {
+        if other.file_path != chunk.file_path {
+            continue;
+        }
+        match (other.start_line, other.start_byte).cmp(&position) {
+            std::cmp::Ordering::Less => preceding.push(other),
+            std::cmp::Ordering::Greater => following.push(other),
+            std::cmp::Ordering::Equal => {}
+        }
+    }
+
+    preceding.sort_by_key(|c| std::cmp::Reverse((c.start_line, c.start_byte)));
+    following.sort_by_key(|c| (c.start_line, c.start_byte));
+    preceding.truncate(window);
+    following.truncate(window);
+
+    ChunkContext {
+        preceding_chunks: preceding,
+        following_chunks: following,
+    }
+}
+
+/// How much of a file's non-empty lines the chunks' line ranges cover
+///
+/// Whitespace-only lines need no chunk. Uncovered ranges are 1-based and
+/// inclusive, and bridge any empty lines between uncovered ones. A file
+/// without non-empty lines is fully covered.
+pub fn chunk_coverage(content: &str, chunks: &[CodeChunk]) -> CoverageReport {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut covered = vec![false; lines.len()];
+    for chunk in chunks {
+        let start = (chunk.start_line.max(1) - 1) as usize;
+        let end = (chunk.end_line as usize).min(lines.len());
+        if start < end {
+            covered[start..end].fill(true);
+        }
+    }
+
+    let mut total_lines = 0;
+    let mut covered_lines = 0;
+    let mut uncovered_ranges: Vec<Vec<u32>> = Vec::new();
+    let mut open: Option<[u32; 2]> = None;
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        total_lines += 1;
+        let line_number = i as u32 + 1;
+        if covered[i] {
+            covered_lines += 1;
+            uncovered_ranges.extend(open.take().map(Vec::from));
+        } else {
+            let range = open.get_or_insert([line_number, line_number]);
+            range[1] = line_number;
+        }
+    }
+    uncovered_ranges.extend(open.map(Vec::from));
+
+    CoverageReport {
+        total_lines,
+        covered_lines,
+        uncovered_ranges,
+        coverage_percent: if total_lines == 0 {
+            100.0
+        } else {
+            covered_lines as f64 * 100.0 / total_lines as f64
+        },
+    }
+}
+
+/// Jaccard similarity of two files' chunk hash sets
+///
+/// Returns 0.0 when neither file has chunks.
+pub fn file_similarity(a: &[CodeChunk], b: &[CodeChunk]) -> f64 {
+    let a: HashSet<&str> = a.iter().map(|c| c.hash.as_str()).collect();
+    let b: HashSet<&str> = b.iter().map(|c| c.hash.as_str()).collect();
+    let union = a.union(&b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(&b).count() as f64 / union as f64
+}
+
+/// MinHash estimate of `file_similarity` from `num_hashes` hash functions
+///
+/// The expected error shrinks with `1 / sqrt(num_hashes)`; 1024 hashes keep
+/// it within a few percent.
+pub fn file_similarity_minhash(a: &[CodeChunk], b: &[CodeChunk], num_hashes: u32) -> f64 {
+    if (a.is_empty() && b.is_empty()) || num_hashes == 0 {
+        return 0.0;
+    }
+
+    let sig_a = minhash_signature(a, num_hashes);
+    let sig_b = minhash_signature(b, num_hashes);
+    let matching = sig_a.iter().zip(&sig_b).filter(|(x, y)| x == y).count();
+    matching as f64 / num_hashes as f64
+}
+
+/// Smallest value of each seeded hash function over the chunk hashes
+fn minhash_signature(chunks: &[CodeChunk], num_hashes: u32) -> Vec<u64> {
+    let bases: Vec<u64> = chunks.iter().map(|c| fnv1a(c.hash.as_bytes())).collect();
+    (0..num_hashes as u64)
+        .map(|seed| {
+            bases
+                .iter()
+                .map(|&base| splitmix64(base ^ splitmix64(seed)))
+                .min()
+                .unwrap_or(u64::MAX)
+        })
+        .collect()
+}
+
+/// 64-bit FNV-1a
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// SplitMix64 finaliser, used to derive independent hash functions
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+/// Longest a chunk grows to keep a Python scope or a documented
+/// declaration whole, as a multiple of the line limit
+const MAX_SCOPE_EXTENSION: usize = 2;
+
 /// Find a natural chunk boundary
 fn find_chunk_boundary(
     lines: &[&str],
+    doc_lines: &[bool],
     start: usize,
     max_size: usize,
     language: &str,
@@ -69,17 +978,210 @@ fn find_chunk_boundary(
         return lines.len();
     }
 
-    // Look backwards for a natural boundary
+    // Python scopes have no closing brace to cut after
+    if language == "python" {
+        if let Some(end) = chunk_by_scope(lines, start, max_size) {
+            return end;
+        }
+    }
+
+    // Look backwards for a natural boundary, never ending inside a doc
+    // comment so it stays with the declaration it documents
     for i in (start + max_size / 2..ideal_end).rev() {
-        if is_natural_boundary(lines[i], language) {
+        if !doc_lines[i] && is_natural_boundary(lines[i], language) {
             return i + 1;
         }
     }
 
+    if doc_lines[ideal_end - 1] {
+        let doc_start = (start..ideal_end)
+            .rev()
+            .take_while(|&i| doc_lines[i])
+            .last()
+            .unwrap_or(start);
+
+        // Cut before the doc comment so it starts the next chunk
+        if doc_start >= start + max_size / 2 {
+            return doc_start;
+        }
+
+        // Too early to cut before the doc comment: extend over the
+        // declaration, unless that grows the chunk past the limit
+        let doc_end = (doc_start..lines.len())
+            .take_while(|&i| doc_lines[i])
+            .last()
+            .unwrap_or(start);
+        let decl = (doc_end + 1..lines.len())
+            .find(|&i| !lines[i].trim().is_empty())
+            .unwrap_or(lines.len() - 1);
+        let decl_end = find_block_end(lines, decl, language) + 1;
+        if decl_end <= start + MAX_SCOPE_EXTENSION * max_size {
+            return decl_end;
+        }
+        if doc_start > start {
+            return doc_start;
+        }
+    }
+
     // No natural boundary found, use max size
     ideal_end
 }
 
+/// End of a Python chunk from `start`, cutting only where the indentation
+/// returns to that of the chunk's first line
+///
+/// The last such cut within `max_size` lines is used, or else the chunk
+/// runs on to the end of the scope it started in rather than splitting it,
+/// up to `MAX_SCOPE_EXTENSION` times `max_size` lines; `None` for scopes
+/// longer than that. Comments and decorators stay with the line after them.
+fn chunk_by_scope(lines: &[&str], start: usize, max_size: usize) -> Option<usize> {
+    let ideal_end = (start + max_size).min(lines.len());
+    let limit = start + MAX_SCOPE_EXTENSION * max_size;
+    let Some(first) = (start..lines.len()).find(|&i| !lines[i].trim().is_empty()) else {
+        return Some(lines.len());
+    };
+    let base = indentation(lines[first]);
+    let is_lead = |trimmed: &str| trimmed.starts_with('#') || trimmed.starts_with('@');
+
+    let mut best = None;
+    // First line of the comments and decorators before the current line
+    let mut lead = is_lead(lines[first].trim()).then_some(first);
+    for (i, line) in lines.iter().enumerate().skip(first + 1) {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let shallow = indentation(line) <= base;
+        if is_lead(trimmed) {
+            if shallow {
+                lead.get_or_insert(i);
+            }
+            continue;
+        }
+
+        let cut = lead.take().unwrap_or(i);
+        if shallow && cut > first {
+            if cut > ideal_end {
+                return best.or((cut <= limit).then_some(cut));
+            }
+            best = Some(cut);
+        }
+    }
+
+    best.or((lines.len() <= limit).then_some(lines.len()))
+}
+
+/// Find the first class starting in `from..to`, returning its first line
+/// (including leading doc comments and decorators) and its last line
+fn find_class_block(
+    lines: &[&str],
+    doc_lines: &[bool],
+    from: usize,
+    to: usize,
+    language: &str,
+) -> Option<(usize, usize)> {
+    let class_line = (from..to).find(|&i| is_class_start(lines[i], language))?;
+
+    let mut start = class_line;
+    while start > from && (doc_lines[start - 1] || lines[start - 1].trim().starts_with('@')) {
+        start -= 1;
+    }
+
+    Some((start, find_block_end(lines, class_line, language)))
+}
+
+/// Check if a line opens a class declaration
+fn is_class_start(line: &str, language: &str) -> bool {
+    let trimmed = line.trim();
+    match language {
+        "typescript" | "tsx" | "javascript" | "jsx" => {
+            let rest = trimmed
+                .strip_prefix("export ")
+                .map(|r| r.strip_prefix("default ").unwrap_or(r))
+                .unwrap_or(trimmed);
+            let rest = rest.strip_prefix("abstract ").unwrap_or(rest);
+            rest.starts_with("class ")
+        }
+        "python" => trimmed.starts_with("class "),
+        _ => false,
+    }
+}
+
+/// Mark lines that belong to doc comments (`///`, `//!`, `/** ... */`)
+fn find_doc_lines(lines: &[&str]) -> Vec<bool> {
+    let mut doc_lines = Vec::with_capacity(lines.len());
+    let mut in_block = false;
+
+    for line in lines {
+        let trimmed = line.trim();
+        if in_block {
+            doc_lines.push(true);
+            in_block = !trimmed.contains("*/");
+        } else if trimmed.starts_with("/**") && !trimmed.starts_with("/**/") {
+            doc_lines.push(true);
+            in_block = !trimmed[3..].contains("*/");
+        } else {
+            doc_lines.push(trimmed.starts_with("///") || trimmed.starts_with("//!"));
+        }
+    }
+
+    doc_lines
+}
+
+/// Find the last line of the declaration starting at `decl`
+fn find_block_end(lines: &[&str], decl: usize, language: &str) -> usize {
+    if language == "python" {
+        let indent = indentation(lines[decl]);
+        let mut end = decl;
+        for (i, line) in lines.iter().enumerate().skip(decl + 1) {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if indentation(line) <= indent {
+                break;
+            }
+            end = i;
+        }
+        return end;
+    }
+
+    // Brace languages: track depth until the opening brace is closed
+    let mut depth = 0i32;
+    let mut opened = false;
+    for (i, line) in lines.iter().enumerate().skip(decl) {
+        for ch in line.chars() {
+            match ch {
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if (opened && depth <= 0) || (!opened && line.trim_end().ends_with(';')) {
+            return i;
+        }
+    }
+
+    lines.len() - 1
+}
+
+/// Count branching keywords in text without an AST
+///
+/// A rough stand-in for McCabe complexity: keywords inside strings and
+/// comments are counted too.
+fn count_branch_keywords(text: &str) -> u32 {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|word| matches!(*word, "if" | "for" | "while" | "match" | "catch"))
+        .count() as u32
+}
+
+/// Leading whitespace width of a line
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
 /// Check if a line is a natural chunk boundary
 fn is_natural_boundary(line: &str, language: &str) -> bool {
     let trimmed = line.trim();
@@ -104,9 +1206,7 @@ fn is_natural_boundary(line: &str, language: &str) -> bool {
                 || trimmed.starts_with("const ")
         }
         "python" => {
-            trimmed.starts_with("def ")
-                || trimmed.starts_with("class ")
-                || trimmed.starts_with("@")
+            trimmed.starts_with("def ") || trimmed.starts_with("class ") || trimmed.starts_with("@")
         }
         "rust" => {
             trimmed.starts_with("fn ")
@@ -114,21 +1214,11 @@ fn is_natural_boundary(line: &str, language: &str) -> bool {
                 || trimmed.starts_with("impl ")
                 || trimmed.starts_with("struct ")
         }
-        "go" => {
-            trimmed.starts_with("func ")
-                || trimmed.starts_with("type ")
-        }
+        "go" => trimmed.starts_with("func ") || trimmed.starts_with("type "),
         _ => false,
     }
 }
 
-/// Hash content
-fn hash_content(content: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(content.as_bytes());
-    format!("{:x}", hasher.finalize())
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -145,10 +1235,697 @@ function bar() {
 }
 "#;
 
-        let chunks = chunk_code(content, "typescript", 5, 1).unwrap();
+        let chunks = chunk_code(content, "typescript", 5, 1, None).unwrap();
         assert!(!chunks.is_empty());
     }
 
+    #[test]
+    fn test_doc_comment_stays_with_function() {
+        let content = r#"const a = 1;
+const b = 2;
+/**
+ * Adds two numbers.
+ *
+ * The result is not clamped.
+ *
+ * @param x first operand
+ * @param y second operand
+ * @returns the sum
+ */
+function add(x: number, y: number): number {
+    return x + y;
+}
+"#;
+
+        let chunks = chunk_code(content, "typescript", 8, 1, None).unwrap();
+        let chunk = chunks
+            .iter()
+            .find(|c| c.content.contains("function add"))
+            .unwrap();
+        assert!(chunk.content.contains("/**"));
+        assert!(chunk.content.contains("@returns the sum"));
+        assert!(chunk.content.contains("return x + y;"));
+
+        // A declaration too long to keep whole is split after all, cutting
+        // before its doc comment when there is code ahead of it
+        let doc: String = (0..60).map(|i| format!(" * Line {}\n", i)).collect();
+        let methods: String = (0..500)
+            .map(|i| format!("    m{}() {{\n        return {};\n    }}\n", i, i))
+            .collect();
+        let class = format!("/**\n{} */\nclass Big {{\n{}}}\n", doc, methods);
+        for prefix in ["", "const a = 1;\nconst b = 2;\n"] {
+            let content = format!("{}{}", prefix, class);
+            let chunks = chunk_code(&content, "typescript", 50, 0, None).unwrap();
+            assert!(chunks.len() > 10);
+            assert!(chunks.iter().all(|c| c.end_line + 1 - c.start_line <= 100));
+            if !prefix.is_empty() {
+                assert_eq!((chunks[0].start_line, chunks[0].end_line), (1, 2));
+            }
+        }
+    }
+
+    #[test]
+    fn test_keep_class_intact() {
+        let content = r#"const a = 1;
+
+export class Counter {
+    private count = 0;
+
+    increment() {
+        this.count++;
+    }
+
+    reset() {
+        this.count = 0;
+    }
+}
+const b = 2;
+"#;
+
+        let options = ChunkOptions {
+            max_chunk_size: 4,
+            overlap: 1,
+            keep_class_intact_under_lines: Some(200),
+            ..Default::default()
+        };
+        let chunks = chunk_code_with_options(content, "typescript", &options).unwrap();
+        let class_chunk = chunks.iter().find(|c| c.chunk_type == "class").unwrap();
+        assert_eq!(class_chunk.start_line, 3);
+        assert_eq!(class_chunk.end_line, 13);
+
+        // Over the limit the class is split as usual
+        let options = ChunkOptions {
+            keep_class_intact_under_lines: Some(5),
+            ..options
+        };
+        let chunks = chunk_code_with_options(content, "typescript", &options).unwrap();
+        assert!(chunks.iter().all(|c| c.chunk_type == "chunk"));
+    }
+
+    #[test]
+    fn test_chunk_hash_algorithm() {
+        let options = ChunkOptions {
+            max_chunk_size: 10,
+            overlap: 0,
+            hash_algorithm: Some(HashAlgorithm::Blake3),
+            ..Default::default()
+        };
+        let chunks = chunk_code_with_options("fn main() {}", "rust", &options).unwrap();
+        assert_eq!(
+            chunks[0].hash,
+            blake3::hash(b"fn main() {}").to_hex().to_string()
+        );
+    }
+
+    #[test]
+    fn test_chunk_by_tokens() {
+        let options = ChunkOptions {
+            max_chunk_size: 1000,
+            overlap: 0,
+            max_tokens: Some(40),
+            ..Default::default()
+        };
+
+        // Short functions pack by tokens and still end at their boundaries
+        let content: String = (0..12)
+            .map(|i| format!("function f{}(x) {{\n    return x + {};\n}}\n", i, i))
+            .collect();
+        let chunks = chunk_code_with_options(&content, "javascript", &options).unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks
+            .iter()
+            .all(|c| c.token_count <= 40 && c.content.ends_with('}')));
+        assert_eq!(chunks.last().unwrap().end_line, 36);
+
+        // A minified file is one line, split into pieces that rejoin exactly
+        let line: String = (0..60).map(|i| format!("var v{}=f({});", i, i)).collect();
+        let chunks = chunk_code_with_options(&line, "javascript", &options).unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks
+            .iter()
+            .all(|c| c.start_line == 1 && c.end_line == 1 && c.token_count <= 40));
+        assert!(chunks.iter().all(|c| c.content.ends_with(';')));
+        assert!(chunks
+            .iter()
+            .all(|c| line[c.start_byte as usize..c.end_byte as usize] == c.content));
+        assert_eq!(
+            chunks
+                .iter()
+                .map(|c| c.content.as_str())
+                .collect::<String>(),
+            line
+        );
+
+        // Overlap repeats whole trailing lines
+        let options = ChunkOptions {
+            overlap_tokens: Some(8),
+            chunk_overlap_strategy: Some(OverlapStrategy::FixedTokens),
+            ..options
+        };
+        let content = "let a = 1;\nlet b = 2;\nlet c = 3;\nlet d = 4;\nlet e = 5;\nlet f = 6;\n";
+        let chunks = chunk_code_with_options(
+            content,
+            "javascript",
+            &ChunkOptions {
+                max_tokens: Some(12),
+                ..options
+            },
+        )
+        .unwrap();
+        assert!(chunks.len() > 1);
+        assert!(chunks.windows(2).all(|w| w[1].start_line <= w[0].end_line));
+    }
+
+    #[test]
+    fn test_overlap_strategies() {
+        let content: String = (0..8)
+            .map(|i| format!("function f{}(x) {{\n    return x + {};\n}}\n\n", i, i))
+            .collect();
+        let options = ChunkOptions {
+            max_chunk_size: 10,
+            overlap: 2,
+            chunk_overlap_strategy: Some(OverlapStrategy::FixedLines),
+            ..Default::default()
+        };
+
+        // A fixed count backs up into the middle of a function
+        let fixed = chunk_code_with_options(&content, "javascript", &options).unwrap();
+        assert!(fixed
+            .iter()
+            .skip(1)
+            .any(|c| !c.content.starts_with("function ")));
+
+        // The default backs up to where the last function starts instead
+        let options = ChunkOptions {
+            chunk_overlap_strategy: None,
+            ..options
+        };
+        let semantic = chunk_code_with_options(&content, "javascript", &options).unwrap();
+        assert!(semantic.len() > 1);
+        assert!(semantic.iter().all(|c| c.content.starts_with("function ")));
+        assert!(semantic
+            .windows(2)
+            .all(|w| w[1].start_line <= w[0].end_line));
+        assert_eq!(semantic.last().unwrap().end_line, 32);
+    }
+
+    #[test]
+    fn test_chunk_code_with_ast() {
+        let content = r#"const a = 1;
+
+export const handler = async (
+    event: Event,
+    context: Context,
+) =>
+    process(event)
+        .then((result) => send(result, context))
+        .catch(report);
+
+const b = 2;
+"#;
+
+        let options = ChunkOptions {
+            max_chunk_size: 3,
+            overlap: 0,
+            ..Default::default()
+        };
+        let chunks =
+            chunk_code_with_ast("src/handler.ts", content, "typescript", &options).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].file_path, "src/handler.ts");
+        assert_eq!(chunks[0].start_line, 3);
+        assert_eq!(chunks[0].end_line, 9);
+        assert!(chunks[0].content.ends_with(".catch(report);"));
+
+        // Unsupported languages use the line scanner
+        let chunks = chunk_code_with_ast("notes.md", "a\nb\nc\nd\n", "markdown", &options).unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.file_path == "notes.md"));
+    }
+
+    #[test]
+    fn test_chunk_byte_ranges() {
+        let content = "fn a() {}\r\n\r\nfn b() {\n    1\n}\n";
+        let chunks = chunk_code(content, "rust", 2, 0, None).unwrap();
+        for chunk in &chunks {
+            let range = chunk.start_byte as usize..chunk.end_byte as usize;
+            assert_eq!(content[range].replace("\r\n", "\n"), chunk.content);
+        }
+
+        let content = "fn a() {}\n\nfn b() {\n    1\n}\n";
+        let chunks = chunk_code(content, "rust", 2, 0, None).unwrap();
+        for chunk in &chunks {
+            assert_eq!(
+                &content[chunk.start_byte as usize..chunk.end_byte as usize],
+                chunk.content
+            );
+        }
+
+        let chunks = chunk_code_with_ast(
+            "lib.rs",
+            content,
+            "rust",
+            &ChunkOptions {
+                max_chunk_size: 10,
+                overlap: 0,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert_eq!((chunks[1].start_byte, chunks[1].end_byte), (11, 27));
+        assert_eq!(&content[11..27], chunks[1].content);
+    }
+
+    #[test]
+    fn test_chunk_complexity() {
+        let content = r#"function f(xs) {
+    for (const x of xs) {
+        if (x) {
+            try { g(x); } catch (e) {}
+        } else if (x === 0) {
+            while (false) {}
+        }
+    }
+    const notify = 1;
+}
+"#;
+
+        let options = ChunkOptions {
+            max_chunk_size: 50,
+            overlap: 0,
+            ..Default::default()
+        };
+        let ast = chunk_code_with_ast("f.js", content, "javascript", &options).unwrap();
+        assert_eq!(ast[0].complexity, 5);
+
+        let lines = chunk_code(content, "javascript", 50, 0, None).unwrap();
+        assert_eq!(lines[0].complexity, 5);
+    }
+
+    #[test]
+    fn test_diff_chunks() {
+        let options = ChunkOptions {
+            max_chunk_size: 50,
+            overlap: 0,
+            ..Default::default()
+        };
+        let old = "function a() {\n    return 1;\n}\n\nfunction b() {}\n\nfunction c() {}\n";
+        let new = "function a() {\n    return 2;\n}\n\nfunction b() {}\n\nfunction d() {}\nfunction e() {}\n";
+        let old_chunks = chunk_code_with_ast("f.ts", old, "typescript", &options).unwrap();
+        let new_chunks = chunk_code_with_ast("f.ts", new, "typescript", &options).unwrap();
+
+        let diff = diff_chunks(old_chunks, new_chunks);
+        assert_eq!(diff.modified.len(), 2);
+        assert!(diff.modified[0].old.content.contains("return 1"));
+        assert!(diff.modified[0].new.content.contains("return 2"));
+        // c() was replaced by d() on the same line
+        assert!(diff.modified[1].new.content.contains("function d"));
+        assert_eq!(diff.added.len(), 1);
+        assert!(diff.added[0].content.contains("function e"));
+        assert!(diff.deleted.is_empty());
+    }
+
+    #[test]
+    fn test_chunk_context() {
+        let content: String = (0..5)
+            .map(|i| format!("function f{}() {{}}\n", i))
+            .collect();
+        let mut chunks = parser::parse_file("a.ts", &content, "typescript").unwrap();
+        chunks.extend(parser::parse_file("b.ts", &content, "typescript").unwrap());
+        chunks.reverse();
+        let target = chunks
+            .iter()
+            .find(|c| c.file_path == "a.ts" && c.symbols == ["f1"])
+            .unwrap()
+            .clone();
+
+        let context = chunk_context(&target, chunks.clone(), 2);
+        let names = |chunks: &[CodeChunk]| {
+            chunks
+                .iter()
+                .map(|c| c.symbols[0].clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(&context.preceding_chunks), vec!["f0"]);
+        assert_eq!(names(&context.following_chunks), vec!["f2", "f3"]);
+        assert!(context
+            .following_chunks
+            .iter()
+            .all(|c| c.file_path == "a.ts"));
+
+        assert!(chunk_context(&target, chunks, 0)
+            .following_chunks
+            .is_empty());
+    }
+
+    #[test]
+    fn test_chunk_coverage() {
+        let content = "import a from 'a';\n\nfunction f() {\n    return a;\n}\n\nconst x = 1;\n\nconst y = 2;\n   \nfunction g() {}\n";
+        let chunks = parser::parse_file("a.ts", content, "typescript").unwrap();
+        let report = chunk_coverage(content, &chunks);
+        assert_eq!((report.total_lines, report.covered_lines), (7, 4));
+        assert_eq!(report.uncovered_ranges, vec![vec![1, 1], vec![7, 9]]);
+        assert!((report.coverage_percent - 400.0 / 7.0).abs() < 1e-9);
+
+        let options = ChunkOptions {
+            max_chunk_size: 4,
+            overlap: 0,
+            ..Default::default()
+        };
+        let chunks = chunk_code_with_options(content, "typescript", &options).unwrap();
+        let report = chunk_coverage(content, &chunks);
+        assert!(report.uncovered_ranges.is_empty());
+        assert_eq!(report.coverage_percent, 100.0);
+
+        assert_eq!(chunk_coverage("\n  \n", &[]).coverage_percent, 100.0);
+    }
+
+    #[test]
+    fn test_split_chunk_to_fit_token_budget() {
+        let content = "function a() {\n    return 1;\n}\n\nfunction b() {\n    return 2;\n}\n\nfunction c() {\n    return 3;\n}";
+        let options = ChunkOptions {
+            max_chunk_size: 100,
+            overlap: 0,
+            ..Default::default()
+        };
+        let mut chunks = chunk_code_with_ast(
+            "f.js",
+            &format!("// header\n{}", content),
+            "javascript",
+            &options,
+        )
+        .unwrap();
+        assert_eq!(chunks.len(), 3);
+
+        // Join the three functions into one oversized chunk
+        let mut chunk = chunks.remove(0);
+        chunk.content = content.to_string();
+        chunk.end_line = 12;
+        chunk.symbols = vec!["a".to_string(), "c".to_string()];
+
+        let pieces = split_chunk_to_fit_token_budget(chunk.clone(), 12);
+        assert!(pieces.len() > 1);
+        assert!(pieces.iter().all(|p| p.token_count <= 12));
+        assert_eq!(
+            pieces
+                .iter()
+                .map(|p| p.content.as_str())
+                .collect::<Vec<_>>()
+                .join("\n"),
+            content
+        );
+        assert_eq!(pieces[0].start_line, 2);
+        assert_eq!(pieces.last().unwrap().end_line, 12);
+        assert!(pieces[0].content.ends_with("}\n"));
+        assert_eq!(
+            pieces[0].id,
+            parser::generate_chunk_id(&pieces[0].content, Some("a"), "javascript")
+        );
+        assert_eq!(pieces[0].symbols, vec!["a"]);
+
+        assert_eq!(split_chunk_to_fit_token_budget(chunk, 1000).len(), 1);
+    }
+
+    #[test]
+    fn test_chunk_code_with_header() {
+        let content = "import { a } from './a';\nimport b from 'b';\n\nexport class Service {\n    run() {\n        return a(b);\n    }\n\n    stop() {\n        return 0;\n    }\n}";
+        let options = ChunkOptions {
+            max_chunk_size: 5,
+            overlap: 0,
+            header_max_lines: Some(3),
+            ..Default::default()
+        };
+        let plain = chunk_code_with_options(content, "typescript", &options).unwrap();
+        assert!(plain.iter().all(|c| c.header_lines == 0));
+
+        let chunks = chunk_code_with_header(content, "typescript", &options).unwrap();
+        assert_eq!(chunks.len(), plain.len());
+        assert_eq!(chunks[0].header_lines, 0);
+        assert_eq!(chunks[0].content, plain[0].content);
+
+        let last = chunks.last().unwrap();
+        let last_plain = plain.last().unwrap();
+        assert_eq!(last.header_lines, 3);
+        assert_eq!(last.start_line, last_plain.start_line);
+        assert_eq!(last.end_line, last_plain.end_line);
+        assert_eq!(
+            last.content,
+            format!(
+                "import {{ a }} from './a';\nimport b from 'b';\nexport class Service {{\n{}",
+                last_plain.content
+            )
+        );
+        assert_eq!(last.hash, HashAlgorithm::Sha256.hash(&last.content));
+
+        // Splitting repeats the header on every piece
+        let pieces = split_chunk_to_fit_token_budget(last.clone(), 20);
+        assert!(pieces.len() > 1);
+        assert!(pieces
+            .iter()
+            .all(|p| p.header_lines == 3 && p.content.starts_with("import {")));
+        assert_eq!(pieces[0].start_line, last.start_line);
+
+        // Emitting chunks one at a time gives the same chunks
+        let mut streamed = Vec::new();
+        let header_options = ChunkOptions {
+            prepend_header: Some(true),
+            ..options
+        };
+        chunk_code_each(content, "typescript", &header_options, |c| {
+            streamed.push(c);
+            ControlFlow::Continue(())
+        })
+        .unwrap();
+        assert_eq!(
+            serde_json::to_string(&streamed).unwrap(),
+            serde_json::to_string(&chunks).unwrap()
+        );
+
+        // Breaking stops after the chunk that broke
+        let mut first = Vec::new();
+        chunk_code_each(content, "typescript", &header_options, |c| {
+            first.push(c);
+            ControlFlow::Break(())
+        })
+        .unwrap();
+        assert_eq!(first.len(), 1);
+    }
+
+    #[test]
+    fn test_dedup_overlap() {
+        let content: String = (1..=10).map(|i| format!("line{}\n", i)).collect();
+        let options = ChunkOptions {
+            max_chunk_size: 4,
+            overlap: 2,
+            chunk_overlap_strategy: Some(OverlapStrategy::FixedLines),
+            ..Default::default()
+        };
+        let chunks = chunk_code_with_options(&content, "text", &options).unwrap();
+        let ranges: Vec<(u32, u32)> = chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(ranges, vec![(1, 4), (3, 6), (5, 8), (7, 10)]);
+
+        let deduped = dedup_overlap(chunks.clone());
+        let summary: Vec<(u32, u32, Option<u32>)> = deduped
+            .iter()
+            .map(|c| (c.start_line, c.end_line, c.original_start_line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, 4, Some(1)),
+                (5, 6, Some(3)),
+                (7, 8, Some(5)),
+                (9, 10, Some(7))
+            ]
+        );
+        assert_eq!(deduped[0].content, chunks[0].content);
+        assert_eq!(deduped[1].content, "line5\nline6");
+        assert_eq!(deduped[1].id, chunks[1].id);
+        assert_eq!(deduped[1].hash, HashAlgorithm::Sha256.hash("line5\nline6"));
+        assert_eq!(&content[deduped[3].start_byte as usize..][..5], "line9");
+        let joined: Vec<&str> = deduped.iter().map(|c| c.content.as_str()).collect();
+        assert_eq!(joined.join("\n") + "\n", content);
+
+        // A chunk inside an earlier one adds nothing
+        let mut inner = chunks[0].clone();
+        inner.end_line = 3;
+        assert_eq!(dedup_overlap(vec![chunks[0].clone(), inner]).len(), 1);
+    }
+
+    #[test]
+    fn test_normalize_whitespace() {
+        let method = "def total(items):  \n\tresult = 0\n\tfor item in items:\n\t    result += item\n\n\treturn result";
+        let function = "    def total(items):\n        result = 0\n        for item in items:\n            result += item\n    \n        return result\n";
+        let base = chunk_code(function, "python", 1000, 0, None)
+            .unwrap()
+            .remove(0);
+        let chunks: Vec<CodeChunk> = [method, function, "def total(items):\n    return 0"]
+            .iter()
+            .map(|content| CodeChunk {
+                hash: HashAlgorithm::Sha256.hash(content),
+                content: content.to_string(),
+                ..base.clone()
+            })
+            .collect();
+
+        let normalized = normalize_whitespace(chunks.clone());
+        assert_eq!(normalized[0].content, method);
+        assert_eq!(normalized[0].id, chunks[0].id);
+        assert_ne!(chunks[0].hash, chunks[1].hash);
+        assert_eq!(normalized[0].hash, normalized[1].hash);
+        assert_ne!(normalized[0].hash, normalized[2].hash);
+        assert_eq!(
+            normalized_content(function),
+            "def total(items):\n    result = 0\n    for item in items:\n        result += item\n\n    return result"
+        );
+    }
+
+    #[test]
+    fn test_merge_small_chunks() {
+        let content = "function a() { return 1; }\nfunction b() { return 2; }\nfunction c() { return 3; }\nfunction large() {\n    const values = [1, 2, 3, 4, 5, 6, 7, 8, 9, 10];\n    return values.map((v) => v * v).filter((v) => v % 2 === 0);\n}\nfunction d() { return 4; }";
+        let options = ChunkOptions {
+            max_chunk_size: 100,
+            overlap: 0,
+            ..Default::default()
+        };
+        let chunks = chunk_code_with_ast("f.js", content, "javascript", &options).unwrap();
+        assert_eq!(chunks.len(), 5);
+
+        // Chunks with lines between them are not merged
+        let mut apart = chunks.clone();
+        apart.remove(1);
+        let ranges: Vec<(u32, u32)> = merge_small_chunks(apart, 30)
+            .iter()
+            .map(|c| (c.start_line, c.end_line))
+            .collect();
+        assert_eq!(ranges[..2], [(1, 1), (3, 3)]);
+
+        let merged = merge_small_chunks(chunks, 30);
+        assert_eq!(merged.len(), 3);
+        assert_eq!((merged[0].start_line, merged[0].end_line), (1, 3));
+        assert_eq!(
+            merged[0].content,
+            "function a() { return 1; }\nfunction b() { return 2; }\nfunction c() { return 3; }"
+        );
+        assert_eq!(merged[0].symbols, vec!["a", "b", "c"]);
+        assert_eq!(
+            merged[0].hash,
+            HashAlgorithm::Sha256.hash(&merged[0].content)
+        );
+        assert!(merged[0].id.starts_with("javascript:a:"));
+        // The small chunk after a large one stands alone
+        assert_eq!(merged[2].start_line, 8);
+    }
+
+    #[test]
+    fn test_chunk_python_by_scope() {
+        let content = "import os\n\n# Helpers\n@cache\ndef load(path):\n    if path:\n        data = read(path)\n\n        return data\n    return None\n\nclass Store:\n    def get(self):\n        return 1\n\n    def put(self, value):\n        self.value = value\n        return value\n";
+        let ranges = |max_chunk_size: u32| -> Vec<(u32, u32)> {
+            chunk_code(content, "python", max_chunk_size as usize, 0, None)
+                .unwrap()
+                .iter()
+                .map(|c| (c.start_line, c.end_line))
+                .collect()
+        };
+
+        // Functions and classes longer than the limit stay whole, with their
+        // comments and decorators
+        assert_eq!(ranges(5), vec![(1, 2), (3, 11), (12, 18)]);
+        // Within the limit, the last scope boundary that fits is used
+        assert_eq!(ranges(12), vec![(1, 11), (12, 18)]);
+
+        // Scopes longer than twice the limit are split after all
+        let methods: String = (0..10)
+            .map(|i| format!("    def m{}(self):\n        return {}\n", i, i))
+            .collect();
+        let content = format!("class Big:\n{}", methods);
+        let ranges: Vec<(u32, u32)> = chunk_code(&content, "python", 4, 0, None)
+            .unwrap()
+            .iter()
+            .map(|c| (c.start_line, c.end_line))
+            .collect();
+        assert_eq!(
+            ranges,
+            vec![(1, 4), (5, 8), (9, 12), (13, 16), (17, 20), (21, 21)]
+        );
+    }
+
+    #[test]
+    fn test_chunk_code_zero_max_chunk_size() {
+        let content = "a = 1\nb = 2\n\nc = 3";
+        let chunks = chunk_code(content, "python", 0, 0, None).unwrap();
+        let ranges: Vec<(u32, u32)> = chunks.iter().map(|c| (c.start_line, c.end_line)).collect();
+        assert_eq!(ranges, vec![(1, 1), (2, 3), (4, 4)]);
+        assert!(chunks.iter().all(|c| !c.content.is_empty()));
+    }
+
+    #[test]
+    fn test_chunk_code_ignore_pragmas() {
+        let content = "a = 1\n# @titan-ignore-start\nb = 2\n# @titan-ignore-end\nc = 3\n# @titan-ignore\ndef d():\n    return 4\ne = 5";
+        let chunks = chunk_code(content, "python", 2, 1, None).unwrap();
+        let kept: Vec<(u32, u32, &str)> = chunks
+            .iter()
+            .map(|c| (c.start_line, c.end_line, c.content.as_str()))
+            .collect();
+        assert_eq!(
+            kept,
+            vec![(1, 1, "a = 1"), (5, 5, "c = 3"), (9, 9, "e = 5")]
+        );
+    }
+
+    #[test]
+    fn test_file_similarity() {
+        let chunk = |hash: String| CodeChunk {
+            id: String::new(),
+            file_path: String::new(),
+            content: String::new(),
+            start_line: 1,
+            end_line: 1,
+            start_byte: 0,
+            end_byte: 0,
+            complexity: 0,
+            token_count: 0,
+            header_lines: 0,
+            original_start_line: None,
+            language_version: None,
+            chunk_type: "chunk".to_string(),
+            language: "rust".to_string(),
+            symbols: vec![],
+            hash,
+        };
+        let file = |range: std::ops::Range<u32>| -> Vec<CodeChunk> {
+            range
+                .map(|i| chunk(HashAlgorithm::Sha256.hash(&i.to_string())))
+                .collect()
+        };
+
+        assert_eq!(file_similarity(&file(0..4), &file(2..6)), 2.0 / 6.0);
+        assert_eq!(file_similarity(&file(0..4), &file(0..4)), 1.0);
+        assert_eq!(file_similarity(&[], &[]), 0.0);
+
+        for (a, b) in [
+            (0..500, 250..750),
+            (0..300, 0..1000),
+            (0..400, 390..800),
+            (0..200, 0..200),
+        ] {
+            let (a, b) = (file(a), file(b));
+            let exact = file_similarity(&a, &b);
+            let estimate = file_similarity_minhash(&a, &b, 1024);
+            assert!(
+                (estimate - exact).abs() <= 0.05,
+                "{} vs {}",
+                estimate,
+                exact
+            );
+        }
+        assert_eq!(
+            file_similarity_minhash(&file(0..10), &file(10..20), 256),
+            0.0
+        );
+    }
+
     #[test]
     fn test_natural_boundary() {
         assert!(is_natural_boundary("}", "typescript"));
@@ -0,0 +1,235 @@
+//! Key extraction from YAML and JSON configuration files
+//!
+//! Neither parser reports positions, so each key's line is found by
+//! searching the text for it, in document order.
+
+use crate::Symbol;
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Top-level keys whose own keys are listed too: GitHub Actions jobs,
+/// Compose services and package.json scripts and dependencies
+const EXPANDED_KEYS: &[&str] = &[
+    "jobs",
+    "services",
+    "scripts",
+    "dependencies",
+    "devDependencies",
+];
+
+/// Symbols for the top-level keys of each document in a YAML file
+pub fn extract_yaml_keys(content: &str) -> Result<Vec<Symbol>> {
+    let mut symbols = Vec::new();
+    let mut cursor = 0;
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let value = yaml_to_json(serde_yaml::Value::deserialize(document)?);
+        cursor = push_keys(&value, content, cursor, true, &mut symbols);
+    }
+    Ok(symbols)
+}
+
+/// Symbols for the top-level keys of a JSON document
+pub fn extract_json_keys(content: &str) -> Result<Vec<Symbol>> {
+    let value: Value = serde_json::from_str(content)?;
+    let mut symbols = Vec::new();
+    push_keys(&value, content, 0, false, &mut symbols);
+    Ok(symbols)
+}
+
+/// Add symbols for the keys of `value` and those under `EXPANDED_KEYS`,
+/// searching from `cursor`; returns where the search got to
+///
+/// With `block_style`, top-level keys must start a line, as in YAML.
+fn push_keys(
+    value: &Value,
+    content: &str,
+    mut cursor: usize,
+    block_style: bool,
+    symbols: &mut Vec<Symbol>,
+) -> usize {
+    let Some(map) = value.as_object() else {
+        return cursor;
+    };
+
+    for (key, child) in map {
+        let at = locate(content, key, &mut cursor, block_style);
+        symbols.push(key_symbol(key, child, content, at, None));
+
+        if !EXPANDED_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        for (child_key, grandchild) in child.as_object().into_iter().flatten() {
+            let at = locate(content, child_key, &mut cursor, false);
+            symbols.push(key_symbol(child_key, grandchild, content, at, Some(key)));
+        }
+    }
+    cursor
+}
+
+/// Byte offset of the next `key`, moving `cursor` past it; a key that
+/// cannot be found is placed at `cursor`
+fn locate(content: &str, key: &str, cursor: &mut usize, line_start: bool) -> usize {
+    match find_key(content, key, *cursor, line_start) {
+        Some(at) => {
+            *cursor = at + key.len();
+            at
+        }
+        None => *cursor,
+    }
+}
+
+/// Offset of `key` written as a mapping key (optionally quoted and followed
+/// by `:`) at or after `from`
+fn find_key(content: &str, key: &str, from: usize, line_start: bool) -> Option<usize> {
+    content[from..]
+        .match_indices(key)
+        .map(|(i, _)| from + i)
+        .find(|&at| {
+            let before = &content[..at];
+            let before = before.strip_suffix(['"', '\'']).unwrap_or(before);
+            let after = &content[at + key.len()..];
+            let after = after.strip_prefix(['"', '\'']).unwrap_or(after);
+
+            let starts = if line_start {
+                before.is_empty() || before.ends_with('\n')
+            } else {
+                before.is_empty()
+                    || before.ends_with(|c: char| c.is_whitespace() || c == '{' || c == ',')
+            };
+            starts && after.trim_start_matches([' ', '\t']).starts_with(':')
+        })
+}
+
+/// Symbol for a key at byte offset `at`, named by its dot-path
+fn key_symbol(key: &str, value: &Value, content: &str, at: usize, parent: Option<&str>) -> Symbol {
+    let line_start = content[..at].rfind('\n').map_or(0, |i| i + 1);
+    let line = content[..at].matches('\n').count() as u32 + 1;
+    let name = match parent {
+        Some(parent) => format!("{}.{}", parent, key),
+        None => key.to_string(),
+    };
+    let signature = format!("{}: {}", name, value_type(value));
+
+    Symbol {
+        kind: "key".to_string(),
+        file_path: String::new(),
+        start_line: line,
+        end_line: line,
+        start_column: (at - line_start) as u32,
+        end_column: (at - line_start + key.len()) as u32,
+        start_column_char: content[line_start..at].chars().count() as u32,
+        signature: Some(signature),
+        parameters: vec![],
+        exported: false,
+        parent_name: parent.map(str::to_string),
+        parent_kind: parent.map(|_| "key".to_string()),
+        references: None,
+        generic_params: vec![],
+        signature_annotation: None,
+        name,
+    }
+}
+
+/// JSON name of a value's type
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// YAML value as JSON, with non-string keys written as YAML and tags dropped
+fn yaml_to_json(value: serde_yaml::Value) -> Value {
+    match value {
+        serde_yaml::Value::Null => Value::Null,
+        serde_yaml::Value::Bool(b) => Value::Bool(b),
+        serde_yaml::Value::Number(n) => serde_json::to_value(n).unwrap_or(Value::Null),
+        serde_yaml::Value::String(s) => Value::String(s),
+        serde_yaml::Value::Sequence(items) => {
+            Value::Array(items.into_iter().map(yaml_to_json).collect())
+        }
+        serde_yaml::Value::Mapping(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let key = match key {
+                        serde_yaml::Value::String(s) => s,
+                        other => serde_yaml::to_string(&other)
+                            .unwrap_or_default()
+                            .trim()
+                            .to_string(),
+                    };
+                    (key, yaml_to_json(value))
+                })
+                .collect(),
+        ),
+        serde_yaml::Value::Tagged(tagged) => yaml_to_json(tagged.value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(symbols: &[Symbol]) -> Vec<(&str, u32, &str)> {
+        symbols
+            .iter()
+            .map(|s| {
+                (
+                    s.name.as_str(),
+                    s.start_line,
+                    s.signature.as_deref().unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_yaml_keys() {
+        let content = "name: CI\non:\n  push:\n    branches: [main]\njobs:\n  build:\n    runs-on: ubuntu-latest\n    steps:\n      - run: make\n  test:\n    name: Test\n---\napiVersion: v1\nkind: Service\n";
+        let symbols = extract_yaml_keys(content).unwrap();
+        assert_eq!(
+            keys(&symbols),
+            vec![
+                ("name", 1, "name: string"),
+                ("on", 2, "on: object"),
+                ("jobs", 5, "jobs: object"),
+                ("jobs.build", 6, "jobs.build: object"),
+                ("jobs.test", 10, "jobs.test: object"),
+                ("apiVersion", 13, "apiVersion: string"),
+                ("kind", 14, "kind: string"),
+            ]
+        );
+        let build = &symbols[3];
+        assert_eq!(build.kind, "key");
+        assert_eq!(build.parent_name.as_deref(), Some("jobs"));
+        assert_eq!((build.start_column, build.end_column), (2, 7));
+
+        assert!(extract_yaml_keys("a: [1, 2").is_err());
+    }
+
+    #[test]
+    fn test_extract_json_keys() {
+        let content = "{\n  \"name\": \"app\",\n  \"private\": true,\n  \"scripts\": {\n    \"build\": \"tsc\",\n    \"name\": \"x\"\n  },\n  \"files\": [\"dist\"]\n}\n";
+        let symbols = extract_json_keys(content).unwrap();
+        assert_eq!(
+            keys(&symbols),
+            vec![
+                ("name", 2, "name: string"),
+                ("private", 3, "private: boolean"),
+                ("scripts", 4, "scripts: object"),
+                ("scripts.build", 5, "scripts.build: string"),
+                ("scripts.name", 6, "scripts.name: string"),
+                ("files", 8, "files: array"),
+            ]
+        );
+        assert_eq!(symbols[0].start_column, 3);
+
+        assert!(extract_json_keys("[1, 2]").unwrap().is_empty());
+        assert!(extract_json_keys("{").is_err());
+    }
+}
@@ -0,0 +1,241 @@
+//! Cross-file dependency resolution
+
+use crate::parser;
+use crate::{DependencyEdge, DependencyGraph, FileInput};
+use std::collections::{HashMap, HashSet};
+
+/// Extensions tried when resolving an extensionless import
+const RESOLVE_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "py", "rs", "go"];
+
+/// Index files tried when an import resolves to a directory
+const INDEX_FILES: &[&str] = &[
+    "index.ts",
+    "index.tsx",
+    "index.js",
+    "index.jsx",
+    "__init__.py",
+    "mod.rs",
+];
+
+/// Build a dependency graph across a set of files
+///
+/// Files that fail to parse or whose language is unknown contribute no
+/// edges. Imports that do not resolve to one of the given files (external
+/// packages, standard library) are skipped.
+pub fn extract_dependencies(files: &[FileInput]) -> DependencyGraph {
+    // Normalised path -> original path as given by the caller
+    let known: HashMap<String, &str> = files
+        .iter()
+        .map(|f| (normalize_path(&f.file_path), f.file_path.as_str()))
+        .collect();
+
+    let mut edges = Vec::new();
+    let mut seen = HashSet::new();
+
+    for file in files {
+        let language = match file
+            .language
+            .as_deref()
+            .filter(|language| *language != "auto")
+            .or_else(|| parser::detect_language(&file.file_path, Some(&file.content)))
+        {
+            Some(language) => language,
+            None => continue,
+        };
+
+        let imports = match parser::extract_imports(&file.file_path, &file.content, language) {
+            Ok(imports) => imports,
+            Err(_) => continue,
+        };
+
+        for import in imports {
+            let target = match resolve_import(&file.file_path, &import.source, language, &known) {
+                Some(target) => target,
+                None => continue,
+            };
+
+            let key = (
+                file.file_path.clone(),
+                target.to_string(),
+                import.kind.clone(),
+            );
+            if seen.insert(key) {
+                edges.push(DependencyEdge {
+                    from: file.file_path.clone(),
+                    to: target.to_string(),
+                    kind: import.kind,
+                });
+            }
+        }
+    }
+
+    DependencyGraph { edges }
+}
+
+/// Resolve an import source to one of the known files
+fn resolve_import<'a>(
+    from: &str,
+    source: &str,
+    language: &str,
+    known: &HashMap<String, &'a str>,
+) -> Option<&'a str> {
+    let dir = parent_dir(&normalize_path(from));
+
+    let base = match language {
+        "python" => python_module_path(&dir, source, known)?,
+        _ if source.starts_with("./") || source.starts_with("../") => join_path(&dir, source),
+        _ => return None,
+    };
+
+    candidates(&base)
+        .into_iter()
+        .find_map(|candidate| known.get(&candidate).copied())
+}
+
+/// Map a Python module name to a path stem
+fn python_module_path(dir: &str, source: &str, known: &HashMap<String, &str>) -> Option<String> {
+    let dots = source.chars().take_while(|c| *c == '.').count();
+    let module = source[dots..].replace('.', "/");
+
+    if dots > 0 {
+        // One dot is the current package, each further dot goes up a level
+        let mut base = dir.to_string();
+        for _ in 1..dots {
+            base = parent_dir(&base);
+        }
+        return Some(join_path(&base, &module));
+    }
+
+    // Absolute imports resolve against any known file with a matching suffix
+    let suffixes = [format!("{}.py", module), format!("{}/__init__.py", module)];
+    known
+        .keys()
+        .filter(|path| {
+            suffixes
+                .iter()
+                .any(|s| *path == s || path.ends_with(&format!("/{}", s)))
+        })
+        .min_by_key(|path| path.len())
+        .map(|path| path.trim_end_matches(".py").to_string())
+}
+
+/// Candidate file paths for an import path stem
+fn candidates(base: &str) -> Vec<String> {
+    let mut paths = vec![base.to_string()];
+    paths.extend(
+        RESOLVE_EXTENSIONS
+            .iter()
+            .map(|ext| format!("{}.{}", base, ext)),
+    );
+    paths.extend(INDEX_FILES.iter().map(|index| join_path(base, index)));
+    paths
+}
+
+/// Join a relative path onto a directory, resolving `.` and `..`
+fn join_path(dir: &str, relative: &str) -> String {
+    if dir.is_empty() {
+        normalize_path(relative)
+    } else {
+        normalize_path(&format!("{}/{}", dir, relative))
+    }
+}
+
+/// Directory part of a normalised path
+fn parent_dir(path: &str) -> String {
+    match path.rfind('/') {
+        Some(idx) => path[..idx].to_string(),
+        None => String::new(),
+    }
+}
+
+/// Normalise separators and resolve `.` and `..` segments
+fn normalize_path(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let absolute = path.starts_with('/');
+    let mut parts: Vec<&str> = Vec::new();
+
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                if parts.last().is_some_and(|p| *p != "..") {
+                    parts.pop();
+                } else if !absolute {
+                    parts.push("..");
+                }
+            }
+            _ => parts.push(part),
+        }
+    }
+
+    let joined = parts.join("/");
+    if absolute {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, content: &str) -> FileInput {
+        FileInput {
+            file_path: path.to_string(),
+            content: content.to_string(),
+            language: None,
+        }
+    }
+
+    #[test]
+    fn test_extract_dependencies() {
+        let files = vec![
+            file(
+                "src/a.ts",
+                "import { b } from './b';\nexport * from './util';\n",
+            ),
+            file(
+                "src/b.ts",
+                "import { a } from './a';\nconst c = import('../lib/c');\n",
+            ),
+            file("src/util/index.ts", "import React from 'react';\n"),
+            file("lib/c.js", ""),
+        ];
+
+        let graph = extract_dependencies(&files);
+        let edges: Vec<(&str, &str, &str)> = graph
+            .edges
+            .iter()
+            .map(|e| (e.from.as_str(), e.to.as_str(), e.kind.as_str()))
+            .collect();
+
+        assert_eq!(edges.len(), 4);
+        assert!(edges.contains(&("src/a.ts", "src/b.ts", "import")));
+        assert!(edges.contains(&("src/a.ts", "src/util/index.ts", "re-export")));
+        // Cycle a -> b -> a is reported, not followed
+        assert!(edges.contains(&("src/b.ts", "src/a.ts", "import")));
+        assert!(edges.contains(&("src/b.ts", "lib/c.js", "dynamic")));
+    }
+
+    #[test]
+    fn test_python_dependencies() {
+        let files = vec![
+            file("pkg/app.py", "from .models import User\nimport pkg.util\n"),
+            file("pkg/models.py", ""),
+            file("pkg/util.py", ""),
+        ];
+
+        let graph = extract_dependencies(&files);
+        assert_eq!(graph.edges.len(), 2);
+        assert_eq!(graph.edges[0].to, "pkg/models.py");
+        assert_eq!(graph.edges[1].to, "pkg/util.py");
+    }
+
+    #[test]
+    fn test_normalize_path() {
+        assert_eq!(normalize_path("src/./a/../b.ts"), "src/b.ts");
+        assert_eq!(normalize_path("..\\x\\y"), "../x/y");
+        assert_eq!(normalize_path("/root/../etc"), "/etc");
+    }
+}
@@ -0,0 +1,182 @@
+//! Tags of JSDoc and PHPDoc comments
+
+use crate::{DocBlock, DocParam, DocReturn};
+
+/// Languages whose `/** */` comments are read for tags
+const DOC_BLOCK_LANGUAGES: &[&str] = &["javascript", "typescript", "jsx", "tsx", "php"];
+
+/// `@param`, `@returns` (or `@return`) and `@author` tags of a `/** */` comment
+///
+/// JSDoc writes `@param {type} name`, with the type optional and `[name=default]`
+/// for optional parameters; PHPDoc writes `@param type $name`, keeping the
+/// `$`. Types may contain spaces inside brackets, as in `array<int, string>`.
+/// Tags continue over the lines up to the next tag. `None` for other
+/// languages and comments without any of these tags.
+pub fn parse_doc_block(text: &str, language: &str) -> Option<DocBlock> {
+    if !DOC_BLOCK_LANGUAGES.contains(&language) || !text.starts_with("/**") {
+        return None;
+    }
+    let body = text.trim_start_matches("/**").trim_end_matches("*/");
+
+    // Join continuation lines onto the tag they belong to
+    let mut tags: Vec<String> = Vec::new();
+    for line in body.lines() {
+        let line = line.trim().trim_start_matches('*').trim();
+        if line.starts_with('@') {
+            tags.push(line.to_string());
+        } else if let Some(tag) = tags.last_mut().filter(|_| !line.is_empty()) {
+            tag.push(' ');
+            tag.push_str(line);
+        }
+    }
+
+    let php = language == "php";
+    let mut block = DocBlock {
+        params: Vec::new(),
+        returns: None,
+        author: None,
+    };
+    for tag in &tags {
+        let (name, rest) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let rest = rest.trim();
+        match name {
+            "@param" => block.params.extend(doc_param(rest, php)),
+            "@returns" | "@return" => {
+                block.returns = Some(DocReturn {
+                    type_hint: type_hint(rest, php).map(|(hint, _)| hint),
+                })
+            }
+            "@author" if !rest.is_empty() => block.author = Some(rest.to_string()),
+            _ => {}
+        }
+    }
+
+    if block.params.is_empty() && block.returns.is_none() && block.author.is_none() {
+        return None;
+    }
+    Some(block)
+}
+
+/// Parameter named by the text after `@param`
+fn doc_param(text: &str, php: bool) -> Option<DocParam> {
+    let (hint, rest) = match type_hint(text, php) {
+        Some((hint, rest)) => (Some(hint), rest),
+        None => (None, text),
+    };
+    let name = rest.split_whitespace().next()?;
+    let name = match name.strip_prefix('[') {
+        Some(optional) => optional.split(['=', ']']).next().unwrap_or(optional),
+        None => name,
+    };
+    if name.is_empty() || name == "-" {
+        return None;
+    }
+
+    Some(DocParam {
+        name: name.to_string(),
+        type_hint: hint,
+    })
+}
+
+/// Leading type of a tag and the text after it
+///
+/// JSDoc types are in braces, which are dropped; PHPDoc types are the first
+/// word, unless that is already the `$name`.
+fn type_hint(text: &str, php: bool) -> Option<(String, &str)> {
+    if !php {
+        let inner = text.strip_prefix('{')?;
+        let end = balanced_end(inner, '{', '}')?;
+        return Some((
+            inner[..end].trim().to_string(),
+            inner[end + 1..].trim_start(),
+        ));
+    }
+
+    if text.is_empty() || text.starts_with('$') {
+        return None;
+    }
+    let mut depth = 0i32;
+    let end = text
+        .char_indices()
+        .find(|&(_, c)| {
+            match c {
+                '<' | '(' | '[' | '{' => depth += 1,
+                '>' | ')' | ']' | '}' => depth -= 1,
+                _ => {}
+            }
+            depth <= 0 && c.is_whitespace()
+        })
+        .map_or(text.len(), |(i, _)| i);
+    Some((text[..end].to_string(), text[end..].trim_start()))
+}
+
+/// Byte offset of the `close` matching an already opened `open`
+fn balanced_end(text: &str, open: char, close: char) -> Option<usize> {
+    let mut depth = 1;
+    for (i, c) in text.char_indices() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(block: &DocBlock) -> Vec<(&str, Option<&str>)> {
+        block
+            .params
+            .iter()
+            .map(|p| (p.name.as_str(), p.type_hint.as_deref()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_jsdoc() {
+        let text = "/**\n * Sum values.\n * @param {number[]} values - the values\n * @param {{ round: boolean }} [options={}] how to sum,\n *   continued\n * @param callback\n * @returns {Promise<number>} the total\n * @author Ada <ada@example.com>\n */";
+        let block = parse_doc_block(text, "typescript").unwrap();
+        assert_eq!(
+            params(&block),
+            vec![
+                ("values", Some("number[]")),
+                ("options", Some("{ round: boolean }")),
+                ("callback", None),
+            ]
+        );
+        assert_eq!(
+            block.returns.unwrap().type_hint.as_deref(),
+            Some("Promise<number>")
+        );
+        assert_eq!(block.author.as_deref(), Some("Ada <ada@example.com>"));
+
+        assert!(parse_doc_block("/** Just prose. */", "javascript").is_none());
+        assert!(parse_doc_block("/** @param {number} x */", "rust").is_none());
+        assert!(parse_doc_block("// @param {number} x", "javascript").is_none());
+    }
+
+    #[test]
+    fn test_parse_phpdoc() {
+        let text = "/**\n * @param array<int, string> $names\n * @param int|null $limit Maximum\n * @param $raw\n * @return string[]\n */";
+        let block = parse_doc_block(text, "php").unwrap();
+        assert_eq!(
+            params(&block),
+            vec![
+                ("$names", Some("array<int, string>")),
+                ("$limit", Some("int|null")),
+                ("$raw", None),
+            ]
+        );
+        assert_eq!(
+            block.returns.unwrap().type_hint.as_deref(),
+            Some("string[]")
+        );
+        assert_eq!(block.author, None);
+    }
+}
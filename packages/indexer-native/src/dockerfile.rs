@@ -0,0 +1,160 @@
+//! Instruction symbols from Dockerfiles
+
+use crate::Symbol;
+
+/// Instructions reported as symbols
+const INSTRUCTIONS: &[&str] = &["FROM", "RUN", "COPY", "ENV", "EXPOSE", "ENTRYPOINT", "CMD"];
+
+/// Symbols for the `FROM`, `RUN`, `COPY`, `ENV`, `EXPOSE`, `ENTRYPOINT` and
+/// `CMD` instructions of a Dockerfile
+///
+/// Keywords match in any case and are named in upper case. Lines continued
+/// with the escape character (`\`, or as set by an `# escape=` directive)
+/// join into one instruction, whose signature is the collapsed text.
+/// Instructions after `FROM ... AS name` have that `"stage"` as their parent.
+pub fn parse_dockerfile(file_path: &str, content: &str) -> Vec<Symbol> {
+    let lines: Vec<&str> = content.lines().collect();
+    let escape = escape_char(&lines);
+    let mut symbols = Vec::new();
+    let mut stage: Option<String> = None;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let start = i;
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        i += 1;
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        // Gather continuation lines, skipping comments between them
+        let mut parts = vec![trimmed.trim_end()];
+        while let Some(part) = parts.last().and_then(|p| p.strip_suffix(escape)) {
+            *parts.last_mut().unwrap() = part;
+            while i < lines.len() && lines[i].trim_start().starts_with('#') {
+                i += 1;
+            }
+            match lines.get(i) {
+                Some(next) => parts.push(next.trim()),
+                None => break,
+            }
+            i += 1;
+        }
+        let text = parts
+            .iter()
+            .map(|p| p.trim())
+            .filter(|p| !p.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let keyword = text.split_whitespace().next().unwrap_or("").to_uppercase();
+        if !INSTRUCTIONS.contains(&keyword.as_str()) {
+            continue;
+        }
+
+        // A `FROM` opens the next stage rather than belonging to one
+        let parent = if keyword == "FROM" {
+            stage = stage_name(&text);
+            None
+        } else {
+            stage.clone()
+        };
+
+        let column = line.len() - trimmed.len();
+        symbols.push(Symbol {
+            name: keyword,
+            kind: "instruction".to_string(),
+            file_path: file_path.to_string(),
+            start_line: start as u32 + 1,
+            end_line: i as u32,
+            start_column: column as u32,
+            end_column: lines[i - 1].len() as u32,
+            start_column_char: line[..column].chars().count() as u32,
+            signature: Some(text),
+            parameters: vec![],
+            exported: false,
+            parent_kind: parent.as_ref().map(|_| "stage".to_string()),
+            parent_name: parent,
+            references: None,
+            generic_params: vec![],
+            signature_annotation: None,
+        });
+    }
+
+    symbols
+}
+
+/// Escape character from an `# escape=` parser directive, else `\`
+///
+/// Directives are only recognised in leading comment lines.
+fn escape_char(lines: &[&str]) -> char {
+    for line in lines {
+        let Some(comment) = line.trim().strip_prefix('#') else {
+            break;
+        };
+        let Some((key, value)) = comment.split_once('=') else {
+            break;
+        };
+        if key.trim().eq_ignore_ascii_case("escape") {
+            return value.trim().chars().next().unwrap_or('\\');
+        }
+    }
+    '\\'
+}
+
+/// Name given to a build stage by `FROM image AS name`
+fn stage_name(from: &str) -> Option<String> {
+    let words: Vec<&str> = from.split_whitespace().collect();
+    let position = words.iter().position(|w| w.eq_ignore_ascii_case("as"))?;
+    words.get(position + 1).map(|name| name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dockerfile() {
+        let content = "# syntax=docker/dockerfile:1\nFROM node:20 AS build\nWORKDIR /app\nENV NODE_ENV=production\nrun apt-get update \\\n    # keep the image small\n    && apt-get install -y curl \\\n    && rm -rf /var/lib/apt/lists/*\nCOPY . .\n\nFROM nginx\nEXPOSE 80\nCMD [\"nginx\", \"-g\", \"daemon off;\"]\n";
+        let symbols = parse_dockerfile("Dockerfile", content);
+        let summary: Vec<(&str, u32, u32, Option<&str>)> = symbols
+            .iter()
+            .map(|s| {
+                (
+                    s.name.as_str(),
+                    s.start_line,
+                    s.end_line,
+                    s.parent_name.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("FROM", 2, 2, None),
+                ("ENV", 4, 4, Some("build")),
+                ("RUN", 5, 8, Some("build")),
+                ("COPY", 9, 9, Some("build")),
+                ("FROM", 11, 11, None),
+                ("EXPOSE", 12, 12, None),
+                ("CMD", 13, 13, None),
+            ]
+        );
+        assert!(symbols.iter().all(|s| s.kind == "instruction"));
+        assert_eq!(
+            symbols[2].signature.as_deref(),
+            Some("run apt-get update && apt-get install -y curl && rm -rf /var/lib/apt/lists/*")
+        );
+        assert_eq!(symbols[2].end_column, 34);
+    }
+
+    #[test]
+    fn test_escape_directive() {
+        let content = "# escape=`\nFROM mcr.microsoft.com/windows\nRUN dir `\n    C:\\\n";
+        let symbols = parse_dockerfile("Dockerfile", content);
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[1].signature.as_deref(), Some("RUN dir C:\\"));
+        assert_eq!((symbols[1].start_line, symbols[1].end_line), (3, 4));
+    }
+}
@@ -0,0 +1,176 @@
+//! Copy-pasted code found by hash equality and SimHash similarity
+
+use crate::{CodeChunk, DuplicateGroup};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+/// Tokens per shingle
+const SHINGLE_TOKENS: usize = 3;
+
+/// Group chunks whose code is the same or nearly the same
+///
+/// Chunks with equal hashes form groups of similarity 1 first. With a
+/// `threshold` below 1, each group or remaining chunk then takes in the
+/// later ones whose SimHash similarity to it reaches the threshold, so a
+/// near-duplicate joins the group of an exact one; the SimHash is taken
+/// over shingles of `SHINGLE_TOKENS` consecutive tokens plus the chunk's
+/// symbols, and similarity is the share of its 64 bits two chunks agree
+/// on. A group's similarity is the lowest between its representative, the
+/// first of its chunks, and a duplicate. Groups are in order of their
+/// representatives.
+pub fn detect_duplicates(chunks: &[CodeChunk], threshold: f64) -> Vec<DuplicateGroup> {
+    // Members of each distinct hash in order of first appearance, with the
+    // lowest similarity taken in so far
+    let mut units: Vec<(Vec<usize>, f64)> = Vec::new();
+    let mut by_hash: HashMap<&str, usize> = HashMap::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        match by_hash.get(chunk.hash.as_str()) {
+            Some(&unit) => units[unit].0.push(i),
+            None => {
+                by_hash.insert(&chunk.hash, units.len());
+                units.push((vec![i], 1.0));
+            }
+        }
+    }
+
+    // Members of a unit share their content, so its first stands for all
+    let mut absorbed = vec![false; units.len()];
+    if threshold < 1.0 {
+        let fingerprints: Vec<u64> = units.iter().map(|(m, _)| simhash(&chunks[m[0]])).collect();
+        for a in 0..units.len() {
+            if absorbed[a] {
+                continue;
+            }
+            for b in a + 1..units.len() {
+                let similarity =
+                    1.0 - (fingerprints[a] ^ fingerprints[b]).count_ones() as f64 / 64.0;
+                if !absorbed[b] && similarity >= threshold {
+                    absorbed[b] = true;
+                    let members = std::mem::take(&mut units[b].0);
+                    units[a].0.extend(members);
+                    units[a].1 = f64::min(units[a].1, similarity);
+                }
+            }
+        }
+    }
+
+    units
+        .into_iter()
+        .zip(absorbed)
+        .filter(|((members, _), absorbed)| !absorbed && members.len() > 1)
+        .map(|((mut members, similarity), _)| {
+            members.sort_unstable();
+            group(chunks, &members, similarity)
+        })
+        .collect()
+}
+
+/// Group of `chunks[members[0]]` and the other members
+fn group(chunks: &[CodeChunk], members: &[usize], similarity: f64) -> DuplicateGroup {
+    let duplicates = &members[1..];
+    DuplicateGroup {
+        representative_chunk_id: chunks[members[0]].id.clone(),
+        duplicate_ids: duplicates.iter().map(|&i| chunks[i].id.clone()).collect(),
+        representative_location: chunks[members[0]].location(),
+        duplicate_locations: duplicates.iter().map(|&i| chunks[i].location()).collect(),
+        similarity,
+    }
+}
+
+/// 64-bit SimHash of a chunk's token shingles and symbols
+fn simhash(chunk: &CodeChunk) -> u64 {
+    let tokens = tokenize(&chunk.content);
+    let shingles = tokens.windows(SHINGLE_TOKENS.min(tokens.len()).max(1));
+    let symbols = chunk.symbols.iter().map(|s| vec![s.as_str()]);
+
+    let mut weights = [0i64; 64];
+    for feature in shingles.map(<[&str]>::to_vec).chain(symbols) {
+        let mut hasher = DefaultHasher::new();
+        feature.hash(&mut hasher);
+        let hash = hasher.finish();
+        for (bit, weight) in weights.iter_mut().enumerate() {
+            *weight += if hash >> bit & 1 == 1 { 1 } else { -1 };
+        }
+    }
+
+    weights
+        .iter()
+        .enumerate()
+        .filter(|(_, &weight)| weight > 0)
+        .fold(0, |fingerprint, (bit, _)| fingerprint | 1 << bit)
+}
+
+/// Words and single punctuation characters, ignoring whitespace
+fn tokenize(content: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut word_start: Option<usize> = None;
+    for (i, c) in content.char_indices() {
+        let word = c.is_alphanumeric() || c == '_';
+        match word_start {
+            Some(start) if !word => {
+                tokens.push(&content[start..i]);
+                word_start = None;
+            }
+            None if word => word_start = Some(i),
+            _ => {}
+        }
+        if !word && !c.is_whitespace() {
+            tokens.push(&content[i..i + c.len_utf8()]);
+        }
+    }
+    if let Some(start) = word_start {
+        tokens.push(&content[start..]);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn test_detect_duplicates() {
+        let body = "    let total = 0;\n    for (const item of items) {\n        if (item.active && item.price > 0) {\n            total += item.price * item.quantity;\n        }\n    }\n    return total;\n}\n";
+        let content = format!(
+            "function sumA(items) {{\n{}\nfunction sumB(items) {{\n{}\nfunction sumC(items) {{\n{}\nfunction other(x) {{\n    return fetch(x).then(r => r.json());\n}}\n",
+            body,
+            body,
+            body.replace("quantity", "count")
+        );
+        let mut chunks = parser::parse_file("a.js", &content, "javascript").unwrap();
+        let copy = format!("\n\nfunction sumA(items) {{\n{}", body);
+        chunks.extend(parser::parse_file("b.js", &copy, "javascript").unwrap());
+        assert_eq!(chunks[4].id, chunks[0].id);
+
+        // Copies share an id, so only their locations tell them apart
+        let exact = detect_duplicates(&chunks, 1.0);
+        assert_eq!(exact.len(), 1);
+        assert_eq!(exact[0].representative_location, "a.js:1");
+        assert_eq!(exact[0].duplicate_locations, vec!["b.js:3"]);
+        assert_eq!(exact[0].duplicate_ids, vec![chunks[0].id.clone()]);
+        assert_eq!(exact[0].similarity, 1.0);
+
+        // Near-duplicates join the exact group
+        let near = detect_duplicates(&chunks, 0.8);
+        assert_eq!(near.len(), 1);
+        assert_eq!(near[0].representative_location, "a.js:1");
+        assert_eq!(
+            near[0].duplicate_locations,
+            vec!["a.js:11", "a.js:21", "b.js:3"]
+        );
+        assert!(near[0].similarity >= 0.8 && near[0].similarity < 1.0);
+
+        assert!(detect_duplicates(&[], 0.5).is_empty());
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("a_b += f(1);"),
+            vec!["a_b", "+", "=", "f", "(", "1", ")", ";"]
+        );
+        assert!(tokenize("  \n").is_empty());
+    }
+}
@@ -1,10 +1,12 @@
 // Rust embedding generator with caching support
 // packages/indexer-native/src/embeddings.rs
 
-use napi::bindgen_prelude::*;
+use indexmap::IndexMap;
 use napi_derive::napi;
-use std::collections::HashMap;
-use std::sync::RwLock;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use std::sync::atomic::AtomicU64;
+use std::sync::{OnceLock, RwLock};
 
 /// Embedding vector type
 pub type EmbeddingVector = Vec<f32>;
@@ -17,58 +19,173 @@ pub struct CacheEntry {
     pub hash: String,
 }
 
+/// A cache entry with its place in the LRU order
+struct Slot {
+    entry: CacheEntry,
+    /// Tick the entry was placed in the order at
+    placed: u64,
+    /// Tick of the latest read or write, at least `placed`
+    used: AtomicU64,
+}
+
 /// LRU Cache for embeddings
+///
+/// Entries are kept in order of `placed`. Reads only stamp `used`, so they
+/// need no exclusive access; an entry read since it was placed is moved to
+/// its true place when it reaches the front, so the front is always the
+/// least recently used once `set` has settled it.
 pub struct EmbeddingCache {
-    entries: HashMap<String, CacheEntry>,
-    order: Vec<String>,
+    entries: IndexMap<String, Slot>,
+    clock: AtomicU64,
     max_size: usize,
+    ttl_seconds: Option<u64>,
 }
 
 impl EmbeddingCache {
-    pub fn new(max_size: usize) -> Self {
+    pub fn new(max_size: usize, ttl_seconds: Option<u64>) -> Self {
         Self {
-            entries: HashMap::new(),
-            order: Vec::new(),
+            entries: IndexMap::new(),
+            clock: AtomicU64::new(0),
             max_size,
+            ttl_seconds,
         }
     }
 
-    pub fn get(&mut self, key: &str) -> Option<&EmbeddingVector> {
-        if self.entries.contains_key(key) {
-            // Move to end (most recently used)
-            self.order.retain(|k| k != key);
-            self.order.push(key.to_string());
-            Some(&self.entries.get(key).unwrap().embedding)
-        } else {
-            None
+    pub fn set_ttl(&mut self, ttl_seconds: Option<u64>) {
+        self.ttl_seconds = ttl_seconds;
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries
+            .get(key)
+            .is_some_and(|slot| !self.is_expired(&slot.entry))
+    }
+
+    /// Look up an entry, marking it most recently used
+    ///
+    /// Expired entries are not returned; `set` drops them before it evicts
+    /// any live entry.
+    pub fn get(&self, key: &str) -> Option<&EmbeddingVector> {
+        let slot = self.entries.get(key)?;
+        if self.is_expired(&slot.entry) {
+            return None;
         }
+        slot.used
+            .fetch_max(self.tick(), std::sync::atomic::Ordering::Relaxed);
+        Some(&slot.entry.embedding)
     }
 
     pub fn set(&mut self, key: String, entry: CacheEntry) {
+        if self.entries.shift_remove(&key).is_none() && self.entries.len() >= self.max_size {
+            self.remove_expired();
+        }
         if self.entries.len() >= self.max_size {
             // Evict least recently used
-            if let Some(lru_key) = self.order.first().cloned() {
-                self.entries.remove(&lru_key);
-                self.order.remove(0);
-            }
+            self.settle_front();
+            self.entries.shift_remove_index(0);
         }
-        self.order.push(key.clone());
-        self.entries.insert(key, entry);
+        let placed = self.tick();
+        self.entries.insert(
+            key,
+            Slot {
+                entry,
+                placed,
+                used: AtomicU64::new(placed),
+            },
+        );
     }
 
     pub fn clear(&mut self) {
         self.entries.clear();
-        self.order.clear();
     }
 
+    /// Number of entries that have not expired
     pub fn len(&self) -> usize {
-        self.entries.len()
+        self.entries
+            .values()
+            .filter(|slot| !self.is_expired(&slot.entry))
+            .count()
+    }
+
+    /// Check if every entry has expired or none was stored
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Rebuild with new limits, keeping the most recently used entries that fit
+    pub fn reconfigure(&mut self, max_size: usize, ttl_seconds: Option<u64>) {
+        let mut next = EmbeddingCache::new(max_size.max(1), ttl_seconds);
+        let mut entries: Vec<(String, Slot)> = self.entries.drain(..).collect();
+        entries.sort_by_key(|(_, slot)| slot.used.load(std::sync::atomic::Ordering::Relaxed));
+        let keep_from = entries.len().saturating_sub(next.max_size);
+
+        for (key, slot) in entries.drain(keep_from..) {
+            next.set(key, slot.entry);
+        }
+
+        *self = next;
+    }
+
+    /// Drop every expired entry, keeping the order of the rest
+    fn remove_expired(&mut self) {
+        if let Some(ttl) = self.ttl_seconds {
+            let now = now_secs();
+            self.entries
+                .retain(|_, slot| now.saturating_sub(slot.entry.timestamp) <= ttl);
+        }
+    }
+
+    /// Move entries read since they were placed back to the position their
+    /// last use gives them, until the front entry has not been read
+    fn settle_front(&mut self) {
+        while let Some((_, slot)) = self.entries.get_index_mut(0) {
+            let used = *slot.used.get_mut();
+            if used == slot.placed {
+                return;
+            }
+            slot.placed = used;
+            // Entries are ordered by `placed`, and this one is now the latest
+            // of those before the first placed after it
+            let to = self
+                .entries
+                .values()
+                .skip(1)
+                .position(|s| s.placed > used)
+                .unwrap_or(self.entries.len() - 1);
+            self.entries.move_index(0, to);
+        }
+    }
+
+    /// Next tick of the LRU clock
+    fn tick(&self) -> u64 {
+        self.clock
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+            + 1
+    }
+
+    fn is_expired(&self, entry: &CacheEntry) -> bool {
+        match self.ttl_seconds {
+            Some(ttl) => now_secs().saturating_sub(entry.timestamp) > ttl,
+            None => false,
+        }
     }
 }
 
-/// Global embedding cache
+/// Current Unix time in seconds
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
 lazy_static::lazy_static! {
-    static ref EMBEDDING_CACHE: RwLock<EmbeddingCache> = RwLock::new(EmbeddingCache::new(10000));
+    /// Global embedding cache
+    static ref EMBEDDING_CACHE: RwLock<EmbeddingCache> = RwLock::new(EmbeddingCache::new(10000, None));
 }
 
 /// Embedding request for batch processing
@@ -89,33 +206,128 @@ pub struct EmbeddingResult {
 }
 
 /// Text preprocessing for embeddings
+///
+/// When `language` is given, comments are stripped first and every line is
+/// kept (comment-only lines become blank) so line numbers stay valid.
 #[napi]
-pub fn preprocess_text(text: String, max_tokens: Option<u32>) -> String {
+pub fn preprocess_text(text: String, max_tokens: Option<u32>, language: Option<String>) -> String {
     let max_tokens = max_tokens.unwrap_or(8192) as usize;
-    
+
     // Normalize whitespace
-    let normalized = text
-        .lines()
-        .map(|line| line.trim())
-        .filter(|line| !line.is_empty())
-        .collect::<Vec<_>>()
-        .join("\n");
-    
+    let normalized = match language {
+        Some(language) => strip_comments(&text, &language)
+            .lines()
+            .map(|line| line.trim())
+            .collect::<Vec<_>>()
+            .join("\n"),
+        None => text
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
     // Truncate if needed (rough approximation: 1 token ≈ 4 chars)
-    let max_chars = max_tokens * 4;
+    let mut max_chars = max_tokens * 4;
     if normalized.len() > max_chars {
+        while !normalized.is_char_boundary(max_chars) {
+            max_chars -= 1;
+        }
         normalized[..max_chars].to_string()
     } else {
         normalized
     }
 }
 
+/// Remove line and block comments, keeping string literals and newlines
+fn strip_comments(text: &str, language: &str) -> String {
+    let (line_marker, blocks, quotes): (&str, &[(&str, &str)], &[char]) = match language {
+        "python" => ("#", &[("\"\"\"", "\"\"\""), ("'''", "'''")], &['"', '\'']),
+        "rust" => ("//", &[("/*", "*/")], &['"']),
+        _ => ("//", &[("/*", "*/")], &['"', '\'', '`']),
+    };
+
+    let mut out = String::with_capacity(text.len());
+    let mut in_string: Option<char> = None;
+    let mut i = 0;
+
+    while i < text.len() {
+        let rest = &text[i..];
+        let c = rest.chars().next().unwrap();
+
+        if let Some(quote) = in_string {
+            out.push(c);
+            i += c.len_utf8();
+            if c == '\\' {
+                if let Some(escaped) = text[i..].chars().next() {
+                    out.push(escaped);
+                    i += escaped.len_utf8();
+                }
+            } else if c == quote {
+                in_string = None;
+            }
+            continue;
+        }
+
+        if rest.starts_with(line_marker) {
+            i += rest.find('\n').unwrap_or(rest.len());
+            continue;
+        }
+
+        if let Some((open, close)) = blocks.iter().find(|(open, _)| rest.starts_with(open)) {
+            let body = &rest[open.len()..];
+            let len = body
+                .find(close)
+                .map(|end| end + close.len())
+                .unwrap_or(body.len());
+            out.extend(body[..len].chars().filter(|c| *c == '\n'));
+            i += open.len() + len;
+            continue;
+        }
+
+        // Rust char literals such as '"' must not open a string
+        if language == "rust" && c == '\'' {
+            if let Some(len) = rust_char_literal_len(rest) {
+                out.push_str(&rest[..len]);
+                i += len;
+                continue;
+            }
+        }
+
+        if quotes.contains(&c) {
+            in_string = Some(c);
+        }
+        out.push(c);
+        i += c.len_utf8();
+    }
+
+    out
+}
+
+/// Byte length of a Rust char literal at the start of `text`, if any
+fn rust_char_literal_len(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().skip(1);
+    let (_, first) = chars.next()?;
+    if first == '\\' {
+        chars
+            .take(10)
+            .find(|(_, c)| *c == '\'')
+            .map(|(idx, _)| idx + 1)
+    } else {
+        chars
+            .next()
+            .filter(|(_, c)| *c == '\'')
+            .map(|(idx, _)| idx + 1)
+    }
+}
+
 /// Compute content hash for caching
 #[napi]
 pub fn compute_content_hash(content: String) -> String {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::{Hash, Hasher};
-    
+
     let mut hasher = DefaultHasher::new();
     content.hash(&mut hasher);
     format!("{:016x}", hasher.finish())
@@ -125,7 +337,7 @@ pub fn compute_content_hash(content: String) -> String {
 #[napi]
 pub fn is_embedding_cached(content_hash: String) -> bool {
     if let Ok(cache) = EMBEDDING_CACHE.read() {
-        cache.entries.contains_key(&content_hash)
+        cache.contains(&content_hash)
     } else {
         false
     }
@@ -134,8 +346,10 @@ pub fn is_embedding_cached(content_hash: String) -> bool {
 /// Get cached embedding
 #[napi]
 pub fn get_cached_embedding(content_hash: String) -> Option<Vec<f64>> {
-    if let Ok(mut cache) = EMBEDDING_CACHE.write() {
-        cache.get(&content_hash).map(|v| v.iter().map(|f| *f as f64).collect())
+    if let Ok(cache) = EMBEDDING_CACHE.read() {
+        cache
+            .get(&content_hash)
+            .map(|v| v.iter().map(|f| *f as f64).collect())
     } else {
         None
     }
@@ -145,18 +359,70 @@ pub fn get_cached_embedding(content_hash: String) -> Option<Vec<f64>> {
 #[napi]
 pub fn cache_embedding(content_hash: String, embedding: Vec<f64>) {
     if let Ok(mut cache) = EMBEDDING_CACHE.write() {
-        let entry = CacheEntry {
-            embedding: embedding.iter().map(|f| *f as f32).collect(),
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            hash: content_hash.clone(),
-        };
+        let entry = new_entry(&content_hash, &embedding);
         cache.set(content_hash, entry);
     }
 }
 
+/// Get cached embeddings for many hashes under a single lock
+#[napi]
+pub fn get_cached_embeddings_batch(hashes: Vec<String>) -> Vec<Option<Vec<f64>>> {
+    if let Ok(cache) = EMBEDDING_CACHE.read() {
+        hashes
+            .iter()
+            .map(|hash| {
+                cache
+                    .get(hash)
+                    .map(|v| v.iter().map(|f| *f as f64).collect())
+            })
+            .collect()
+    } else {
+        vec![None; hashes.len()]
+    }
+}
+
+/// Store many embeddings in cache under a single lock
+#[napi]
+pub fn cache_embeddings_batch(entries: Vec<(String, Vec<f64>)>) {
+    if let Ok(mut cache) = EMBEDDING_CACHE.write() {
+        for (content_hash, embedding) in entries {
+            let entry = new_entry(&content_hash, &embedding);
+            cache.set(content_hash, entry);
+        }
+    }
+}
+
+/// Build a cache entry stamped with the current time
+fn new_entry(content_hash: &str, embedding: &[f64]) -> CacheEntry {
+    CacheEntry {
+        embedding: embedding.iter().map(|f| *f as f32).collect(),
+        timestamp: now_secs(),
+        hash: content_hash.to_string(),
+    }
+}
+
+/// Replace the global cache limits, keeping the most recently used entries
+/// that still fit. Call this before any embedding operations; calling it
+/// again with the same arguments leaves the cache unchanged.
+#[napi]
+pub fn configure_embedding_cache(max_size: u32, ttl_seconds: Option<u32>) {
+    if let Ok(mut cache) = EMBEDDING_CACHE.write() {
+        cache.reconfigure(max_size as usize, ttl_seconds.map(|s| s as u64));
+    }
+}
+
+/// Set how long cached embeddings stay valid; 0 disables expiry
+#[napi]
+pub fn set_cache_ttl(seconds: u32) {
+    if let Ok(mut cache) = EMBEDDING_CACHE.write() {
+        cache.set_ttl(if seconds > 0 {
+            Some(seconds as u64)
+        } else {
+            None
+        });
+    }
+}
+
 /// Clear embedding cache
 #[napi]
 pub fn clear_embedding_cache() {
@@ -181,17 +447,21 @@ pub fn get_cache_stats() -> CacheStats {
     let hits = CACHE_HITS.load(std::sync::atomic::Ordering::Relaxed);
     let misses = CACHE_MISSES.load(std::sync::atomic::Ordering::Relaxed);
     let total = hits + misses;
-    
-    let size = if let Ok(cache) = EMBEDDING_CACHE.read() {
-        cache.len() as u32
+
+    let (size, max_size) = if let Ok(cache) = EMBEDDING_CACHE.read() {
+        (cache.len() as u32, cache.max_size() as u32)
     } else {
-        0
+        (0, 0)
     };
-    
+
     CacheStats {
         size,
-        max_size: 10000,
-        hit_rate: if total > 0 { hits as f64 / total as f64 } else { 0.0 },
+        max_size,
+        hit_rate: if total > 0 {
+            hits as f64 / total as f64
+        } else {
+            0.0
+        },
     }
 }
 
@@ -212,37 +482,38 @@ pub fn chunk_for_embedding(
 ) -> Vec<TextChunk> {
     let chunk_size = chunk_size.unwrap_or(512) as usize;
     let overlap = overlap.unwrap_or(50) as usize;
-    
+
     let words: Vec<&str> = text.split_whitespace().collect();
     let mut chunks = Vec::new();
     let mut i = 0;
-    
+
     while i < words.len() {
         let end = (i + chunk_size).min(words.len());
         let chunk_words = &words[i..end];
         let chunk_text = chunk_words.join(" ");
-        
+
         // Calculate actual character offsets
         let start_offset = if i == 0 {
             0
         } else {
             words[..i].iter().map(|w| w.len() + 1).sum::<usize>()
         };
-        
+
         chunks.push(TextChunk {
             text: chunk_text,
             start_offset: start_offset as u32,
-            end_offset: (start_offset + chunk_words.iter().map(|w| w.len() + 1).sum::<usize>()) as u32,
+            end_offset: (start_offset + chunk_words.iter().map(|w| w.len() + 1).sum::<usize>())
+                as u32,
             overlap_prev: if i > 0 { overlap as u32 } else { 0 },
         });
-        
+
         if end >= words.len() {
             break;
         }
-        
+
         i += chunk_size - overlap;
     }
-    
+
     chunks
 }
 
@@ -260,14 +531,19 @@ pub fn normalize_embedding(embedding: Vec<f64>) -> Vec<f64> {
 /// Compute cosine similarity between two embeddings
 #[napi]
 pub fn cosine_similarity(a: Vec<f64>, b: Vec<f64>) -> f64 {
+    cosine(&a, &b)
+}
+
+/// Cosine similarity over borrowed vectors
+fn cosine(a: &[f64], b: &[f64]) -> f64 {
     if a.len() != b.len() {
         return 0.0;
     }
-    
+
     let dot_product: f64 = a.iter().zip(b.iter()).map(|(x, y)| x * y).sum();
     let magnitude_a: f64 = a.iter().map(|x| x * x).sum::<f64>().sqrt();
     let magnitude_b: f64 = b.iter().map(|x| x * x).sum::<f64>().sqrt();
-    
+
     if magnitude_a > 0.0 && magnitude_b > 0.0 {
         dot_product / (magnitude_a * magnitude_b)
     } else {
@@ -275,10 +551,212 @@ pub fn cosine_similarity(a: Vec<f64>, b: Vec<f64>) -> f64 {
     }
 }
 
+/// Whether the AVX2 similarity path can be used, detected once
+#[cfg(target_arch = "x86_64")]
+fn use_avx2() -> bool {
+    static AVX2: OnceLock<bool> = OnceLock::new();
+    *AVX2.get_or_init(|| crate::performance::detect_simd_capabilities().avx2)
+}
+
+/// Cosine similarity over `f32` vectors, using AVX2 when available
+pub fn cosine_similarity_simd(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    {
+        if use_avx2() {
+            // SAFETY: AVX2 support was checked at runtime
+            return unsafe { cosine_similarity_avx2(a, b) };
+        }
+    }
+
+    cosine_similarity_scalar(a, b)
+}
+
+/// Scalar fallback for `cosine_similarity_simd`
+fn cosine_similarity_scalar(a: &[f32], b: &[f32]) -> f32 {
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b.iter()) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    finish_cosine(dot, norm_a, norm_b)
+}
+
+/// AVX2 kernel processing 8 floats per iteration
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2")]
+unsafe fn cosine_similarity_avx2(a: &[f32], b: &[f32]) -> f32 {
+    use std::arch::x86_64::*;
+
+    let lanes = a.len() / 8 * 8;
+    let mut dot = _mm256_setzero_ps();
+    let mut norm_a = _mm256_setzero_ps();
+    let mut norm_b = _mm256_setzero_ps();
+
+    for i in (0..lanes).step_by(8) {
+        let va = _mm256_loadu_ps(a.as_ptr().add(i));
+        let vb = _mm256_loadu_ps(b.as_ptr().add(i));
+        dot = _mm256_add_ps(dot, _mm256_mul_ps(va, vb));
+        norm_a = _mm256_add_ps(norm_a, _mm256_mul_ps(va, va));
+        norm_b = _mm256_add_ps(norm_b, _mm256_mul_ps(vb, vb));
+    }
+
+    let sum = |v: __m256| {
+        let mut out = [0.0f32; 8];
+        _mm256_storeu_ps(out.as_mut_ptr(), v);
+        out.iter().sum::<f32>()
+    };
+    let (mut dot, mut norm_a, mut norm_b) = (sum(dot), sum(norm_a), sum(norm_b));
+
+    // Remaining elements that do not fill a full register
+    for (x, y) in a[lanes..].iter().zip(b[lanes..].iter()) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    finish_cosine(dot, norm_a, norm_b)
+}
+
+/// Combine dot product and squared norms into a cosine similarity
+fn finish_cosine(dot: f32, norm_a: f32, norm_b: f32) -> f32 {
+    if norm_a > 0.0 && norm_b > 0.0 {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    } else {
+        0.0
+    }
+}
+
+/// Cosine similarity computed in `f32` on the SIMD path
+#[napi]
+pub fn cosine_similarity_f32(a: Vec<f64>, b: Vec<f64>) -> f64 {
+    let a: Vec<f32> = a.iter().map(|f| *f as f32).collect();
+    let b: Vec<f32> = b.iter().map(|f| *f as f32).collect();
+    cosine_similarity_simd(&a, &b) as f64
+}
+
 /// Batch cosine similarity computation
 #[napi]
 pub fn batch_cosine_similarity(query: Vec<f64>, embeddings: Vec<Vec<f64>>) -> Vec<f64> {
-    embeddings.iter().map(|e| cosine_similarity(query.clone(), e.clone())).collect()
+    embeddings.iter().map(|e| cosine(&query, e)).collect()
+}
+
+/// Compute Euclidean distance between two embeddings
+#[napi]
+pub fn euclidean_distance(a: Vec<f64>, b: Vec<f64>) -> f64 {
+    euclidean(&a, &b)
+}
+
+/// Euclidean distance over borrowed vectors
+fn euclidean(a: &[f64], b: &[f64]) -> f64 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    a.iter()
+        .zip(b.iter())
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum::<f64>()
+        .sqrt()
+}
+
+/// Batch Euclidean distance computation
+#[napi]
+pub fn batch_euclidean_distance(query: Vec<f64>, embeddings: Vec<Vec<f64>>) -> Vec<f64> {
+    embeddings.iter().map(|e| euclidean(&query, e)).collect()
+}
+
+/// Compute the dot product of two embeddings
+#[napi]
+pub fn dot_product(a: Vec<f64>, b: Vec<f64>) -> f64 {
+    dot(&a, &b)
+}
+
+/// Dot product over borrowed vectors
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+/// Batch dot product computation
+#[napi]
+pub fn batch_dot_product(query: Vec<f64>, embeddings: Vec<Vec<f64>>) -> Vec<f64> {
+    embeddings.iter().map(|e| dot(&query, e)).collect()
+}
+
+/// Index and score of a similarity match
+#[napi(object)]
+pub struct SimilarityResult {
+    pub index: u32,
+    pub score: f64,
+}
+
+/// Heap entry ordered by score
+struct ScoredIndex {
+    score: f64,
+    index: usize,
+}
+
+impl PartialEq for ScoredIndex {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for ScoredIndex {}
+
+impl PartialOrd for ScoredIndex {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScoredIndex {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Lower index wins ties so results are stable
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| other.index.cmp(&self.index))
+    }
+}
+
+/// Find the `k` embeddings most similar to `query`, best first
+#[napi]
+pub fn top_k_similar(query: Vec<f64>, embeddings: Vec<Vec<f64>>, k: u32) -> Vec<SimilarityResult> {
+    let k = k as usize;
+    if k == 0 {
+        return vec![];
+    }
+
+    // Min-heap of the best k seen so far
+    let mut heap: BinaryHeap<Reverse<ScoredIndex>> = BinaryHeap::with_capacity(k + 1);
+    for (index, embedding) in embeddings.iter().enumerate() {
+        let candidate = ScoredIndex {
+            score: cosine(&query, embedding),
+            index,
+        };
+        if heap.len() < k {
+            heap.push(Reverse(candidate));
+        } else if heap.peek().is_some_and(|Reverse(worst)| candidate > *worst) {
+            heap.pop();
+            heap.push(Reverse(candidate));
+        }
+    }
+
+    heap.into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(s)| SimilarityResult {
+            index: s.index as u32,
+            score: s.score,
+        })
+        .collect()
 }
 
 /// Quantize embedding to reduce memory
@@ -287,12 +765,16 @@ pub fn quantize_embedding(embedding: Vec<f64>, bits: Option<u32>) -> Vec<i32> {
     let bits = bits.unwrap_or(8);
     let max_val = (1 << (bits - 1)) - 1;
     let min_val = -(1 << (bits - 1));
-    
+
     // Find min/max for scaling
     let e_min = embedding.iter().cloned().fold(f64::INFINITY, f64::min);
     let e_max = embedding.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-    let scale = if e_max > e_min { (max_val - min_val) as f64 / (e_max - e_min) } else { 1.0 };
-    
+    let scale = if e_max > e_min {
+        (max_val - min_val) as f64 / (e_max - e_min)
+    } else {
+        1.0
+    };
+
     embedding
         .iter()
         .map(|v| ((v - e_min) * scale + min_val as f64).round() as i32)
@@ -301,14 +783,229 @@ pub fn quantize_embedding(embedding: Vec<f64>, bits: Option<u32>) -> Vec<i32> {
 
 /// Dequantize embedding
 #[napi]
-pub fn dequantize_embedding(quantized: Vec<i32>, original_min: f64, original_max: f64, bits: Option<u32>) -> Vec<f64> {
+pub fn dequantize_embedding(
+    quantized: Vec<i32>,
+    original_min: f64,
+    original_max: f64,
+    bits: Option<u32>,
+) -> Vec<f64> {
     let bits = bits.unwrap_or(8);
     let max_val = (1 << (bits - 1)) - 1;
     let min_val = -(1 << (bits - 1));
-    let scale = if original_max > original_min { (original_max - original_min) / (max_val - min_val) as f64 } else { 1.0 };
-    
+    let scale = if original_max > original_min {
+        (original_max - original_min) / (max_val - min_val) as f64
+    } else {
+        1.0
+    };
+
     quantized
         .iter()
         .map(|v| (*v - min_val) as f64 * scale + original_min)
         .collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(embedding: Vec<f32>) -> CacheEntry {
+        CacheEntry {
+            embedding,
+            timestamp: now_secs(),
+            hash: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_cache_ttl_expiry() {
+        let mut cache = EmbeddingCache::new(10, Some(1));
+        cache.set("a".to_string(), entry(vec![1.0]));
+        assert!(cache.get("a").is_some());
+
+        std::thread::sleep(std::time::Duration::from_secs(2));
+        assert!(!cache.contains("a"));
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn test_cache_drops_expired_before_evicting() {
+        let mut cache = EmbeddingCache::new(2, Some(60));
+        cache.set("live".to_string(), entry(vec![1.0]));
+        let stale = CacheEntry {
+            timestamp: now_secs() - 120,
+            ..entry(vec![2.0])
+        };
+        cache.set("stale".to_string(), stale);
+
+        cache.set("new".to_string(), entry(vec![3.0]));
+        assert!(cache.contains("live") && cache.contains("new"));
+        assert_eq!(cache.entries.len(), 2);
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_read() {
+        let mut cache = EmbeddingCache::new(3, None);
+        for key in ["a", "b", "c"] {
+            cache.set(key.to_string(), entry(vec![1.0]));
+        }
+        cache.get("b");
+        cache.get("a");
+
+        cache.set("d".to_string(), entry(vec![1.0]));
+        assert!(!cache.contains("c"));
+        cache.set("e".to_string(), entry(vec![1.0]));
+        assert!(!cache.contains("b"));
+        cache.set("f".to_string(), entry(vec![1.0]));
+        assert!(!cache.contains("a"));
+        assert!(cache.contains("d") && cache.contains("e") && cache.contains("f"));
+
+        // Replacing an entry does not evict another
+        cache.set("d".to_string(), entry(vec![2.0]));
+        assert_eq!(cache.len(), 3);
+        assert_eq!(cache.get("d"), Some(&vec![2.0]));
+    }
+
+    #[test]
+    fn test_cache_concurrent_readers() {
+        let cache = std::sync::Arc::new(RwLock::new(EmbeddingCache::new(101, None)));
+        for i in 0..100 {
+            cache
+                .write()
+                .unwrap()
+                .set(format!("k{}", i), entry(vec![i as f32]));
+        }
+
+        let readers: Vec<_> = (0..32)
+            .map(|reader| {
+                let cache = cache.clone();
+                std::thread::spawn(move || {
+                    for i in 0..1000 {
+                        let key = format!("k{}", (i * 7 + reader) % 100);
+                        let cache = cache.read().unwrap();
+                        assert!(cache.get(&key).is_some());
+                    }
+                })
+            })
+            .collect();
+        // A writer between the readers must still get its turn
+        cache
+            .write()
+            .unwrap()
+            .set("extra".to_string(), entry(vec![0.0]));
+        for reader in readers {
+            reader.join().unwrap();
+        }
+
+        let cache = cache.read().unwrap();
+        assert_eq!(cache.len(), 101);
+        assert!(cache.contains("extra"));
+    }
+
+    #[test]
+    fn test_cosine_similarity_simd_matches_scalar() {
+        let a: Vec<f32> = (0..1539).map(|i| (i as f32 * 0.37).sin()).collect();
+        let b: Vec<f32> = (0..1539).map(|i| (i as f32 * 0.11).cos()).collect();
+
+        let simd = cosine_similarity_simd(&a, &b);
+        let scalar = cosine_similarity_scalar(&a, &b);
+        assert!((simd - scalar).abs() < 1e-4);
+        assert!((cosine_similarity_simd(&a, &a) - 1.0).abs() < 1e-4);
+        assert_eq!(cosine_similarity_simd(&a, &b[..10]), 0.0);
+    }
+
+    #[test]
+    fn test_distance_metrics() {
+        assert_eq!(euclidean_distance(vec![0.0, 0.0], vec![3.0, 4.0]), 5.0);
+        assert_eq!(dot_product(vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]), 32.0);
+        assert_eq!(euclidean_distance(vec![1.0], vec![1.0, 2.0]), 0.0);
+        assert_eq!(dot_product(vec![1.0], vec![1.0, 2.0]), 0.0);
+
+        let embeddings = vec![vec![1.0, 0.0], vec![0.0, 2.0], vec![1.0]];
+        assert_eq!(
+            batch_dot_product(vec![1.0, 1.0], embeddings.clone()),
+            vec![1.0, 2.0, 0.0]
+        );
+        assert_eq!(
+            batch_euclidean_distance(vec![1.0, 0.0], embeddings),
+            vec![0.0, 5f64.sqrt(), 0.0]
+        );
+    }
+
+    #[test]
+    fn test_top_k_similar() {
+        let embeddings = vec![
+            vec![0.0, 1.0],
+            vec![1.0, 0.0],
+            vec![0.7, 0.7],
+            vec![-1.0, 0.0],
+            vec![0.9, 0.1],
+        ];
+
+        let results = top_k_similar(vec![1.0, 0.0], embeddings.clone(), 3);
+        let indices: Vec<u32> = results.iter().map(|r| r.index).collect();
+        assert_eq!(indices, vec![1, 4, 2]);
+        assert!((results[0].score - 1.0).abs() < 1e-9);
+
+        assert_eq!(
+            top_k_similar(vec![1.0, 0.0], embeddings.clone(), 10).len(),
+            5
+        );
+        assert!(top_k_similar(vec![1.0, 0.0], embeddings, 0).is_empty());
+    }
+
+    #[test]
+    fn test_batch_cache_roundtrip() {
+        cache_embeddings_batch(vec![
+            ("batch-a".to_string(), vec![1.0, 2.0]),
+            ("batch-b".to_string(), vec![3.0]),
+        ]);
+
+        let found = get_cached_embeddings_batch(vec![
+            "batch-a".to_string(),
+            "batch-missing".to_string(),
+            "batch-b".to_string(),
+        ]);
+        assert_eq!(found[0], Some(vec![1.0, 2.0]));
+        assert_eq!(found[1], None);
+        assert_eq!(found[2], Some(vec![3.0]));
+    }
+
+    #[test]
+    fn test_preprocess_text_strips_comments() {
+        let code =
+            "const url = \"http://x\"; // TODO: remove\n/* block\n   comment */\nreturn url;\n";
+        let out = preprocess_text(code.to_string(), None, Some("typescript".to_string()));
+        assert!(!out.contains("TODO"));
+        assert!(!out.contains("block"));
+        assert!(out.contains("\"http://x\""));
+        assert_eq!(out.lines().count(), code.lines().count());
+        assert_eq!(out.lines().nth(3), Some("return url;"));
+
+        let python = "x = '#not'  # comment\n\"\"\"doc\"\"\"\ny = 1\n";
+        let out = preprocess_text(python.to_string(), None, Some("python".to_string()));
+        assert_eq!(out, "x = '#not'\n\ny = 1");
+
+        let rust = "let q = '\"'; // quote\nlet s = \"a\";";
+        let out = preprocess_text(rust.to_string(), None, Some("rust".to_string()));
+        assert_eq!(out, "let q = '\"';\nlet s = \"a\";");
+    }
+
+    #[test]
+    fn test_cache_reconfigure_keeps_most_recent() {
+        let mut cache = EmbeddingCache::new(10, None);
+        for key in ["a", "b", "c", "d"] {
+            cache.set(key.to_string(), entry(vec![1.0]));
+        }
+        cache.get("a");
+
+        cache.reconfigure(2, None);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.contains("a"));
+        assert!(cache.contains("d"));
+
+        cache.reconfigure(2, None);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.max_size(), 2);
+    }
+}
@@ -4,32 +4,229 @@
 //! and Merkle trees for O(log N) incremental synchronization.
 
 use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{
+    ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::JsFunction;
 use napi_derive::napi;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 
-mod parser;
-mod merkle;
+mod batch;
 mod chunker;
-mod embeddings;
-mod performance;
+mod dependencies;
+mod merkle;
+mod parser;
+// Public: the crate itself uses few of their items, and their napi exports
+// are not registered in test builds
+pub mod embeddings;
+pub mod performance;
+
+mod arrow;
+mod budget;
+mod callgraph;
+mod config;
+mod docblock;
+mod dockerfile;
+mod duplication;
+mod lint;
+mod markdown;
+mod pragmas;
+mod search;
+mod snapshot;
+mod stream;
+mod tokens;
+mod watcher;
+
+/// Content hashing algorithm
+#[napi]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+}
+
+impl HashAlgorithm {
+    /// Hex digest of content
+    pub fn hash(self, content: &str) -> String {
+        match self {
+            HashAlgorithm::Sha256 => {
+                use sha2::{Digest, Sha256};
+                let mut hasher = Sha256::new();
+                hasher.update(content.as_bytes());
+                format!("{:x}", hasher.finalize())
+            }
+            HashAlgorithm::Blake3 => blake3::hash(content.as_bytes()).to_hex().to_string(),
+        }
+    }
+}
+
+/// Where each chunk after the first starts relative to the one before
+#[napi]
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OverlapStrategy {
+    /// Repeat the last `overlap` lines
+    FixedLines,
+    /// Repeat whole trailing lines of up to `overlap_tokens` estimated tokens
+    FixedTokens,
+    /// Repeat from the last natural boundary in the second half of the
+    /// chunk, so a declaration near the end appears whole in the next
+    SemanticBoundary,
+}
 
 /// Code chunk extracted from source
+///
+/// Fields added since snapshot schema version 1 come last and default when
+/// missing, so older snapshots, including positional MessagePack ones,
+/// still decode.
 #[napi(object)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CodeChunk {
+    /// Content-addressed id, see `generate_stable_chunk_id`
     pub id: String,
     pub file_path: String,
     pub content: String,
     pub start_line: u32,
     pub end_line: u32,
+    /// Byte offset of the first character, inclusive
+    pub start_byte: u32,
+    /// Byte offset just past the last character, exclusive
+    pub end_byte: u32,
+    /// Number of branching constructs, a rough stand-in for McCabe
+    /// cyclomatic complexity rather than an exact value
+    pub complexity: u32,
+    /// Estimated LLM tokens in `content`
+    pub token_count: u32,
+    /// Number of file header lines prepended to `content`; `start_line`
+    /// and `end_line` still refer to the chunk's own lines
+    pub header_lines: u32,
     pub chunk_type: String,
     pub language: String,
     pub symbols: Vec<String>,
     pub hash: String,
+    /// `start_line` before `chunk_code_overlap_dedup` trimmed the lines
+    /// shared with the previous chunk
+    #[serde(default)]
+    pub original_start_line: Option<u32>,
+    /// Version and dialect pragmas near the top of the file, such as
+    /// `"use client"` or `#![feature(...)]`, comma-separated
+    #[serde(default)]
+    pub language_version: Option<String>,
+}
+
+impl CodeChunk {
+    /// `file_path:start_line`, which tells apart copies of the same code
+    /// even though their content-addressed ids match
+    pub(crate) fn location(&self) -> String {
+        format!("{}:{}", self.file_path, self.start_line)
+    }
+}
+
+/// Chunk whose content changed between two versions of a file
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkChange {
+    pub old: CodeChunk,
+    pub new: CodeChunk,
+}
+
+/// Chunk-level diff result
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkDiff {
+    pub added: Vec<CodeChunk>,
+    pub modified: Vec<ChunkChange>,
+    pub deleted: Vec<CodeChunk>,
+}
+
+/// Chunks around a target chunk in the same file
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkContext {
+    /// Chunks before the target, nearest first
+    pub preceding_chunks: Vec<CodeChunk>,
+    /// Chunks after the target, nearest first
+    pub following_chunks: Vec<CodeChunk>,
+}
+
+/// Share of a file's non-empty lines covered by chunks
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CoverageReport {
+    /// Non-empty lines in the file
+    pub total_lines: u32,
+    pub covered_lines: u32,
+    /// 1-based inclusive `[start, end]` line ranges no chunk covers
+    pub uncovered_ranges: Vec<Vec<u32>>,
+    pub coverage_percent: f64,
+}
+
+/// Chunk with its share of a prompt's token budget
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocatedChunk {
+    pub chunk: CodeChunk,
+    pub allocated_tokens: u32,
+    /// Chunk content trimmed to the allocation, at a line end if possible
+    pub truncated_content: String,
+}
+
+/// Problem found in a chunk by `lint_chunks`
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkLintResult {
+    pub chunk_id: String,
+    /// `"error"` or `"warning"`
+    pub severity: String,
+    pub message: String,
+}
+
+/// Chunks found to hold the same or nearly the same code
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateGroup {
+    pub representative_chunk_id: String,
+    pub duplicate_ids: Vec<String>,
+    /// `file_path:start_line` of the representative; exact copies share
+    /// its id, so this is what tells them apart
+    pub representative_location: String,
+    /// `file_path:start_line` of each duplicate, in `duplicate_ids` order
+    pub duplicate_locations: Vec<String>,
+    /// Lowest similarity between the representative and a duplicate, 1
+    /// for identical code
+    pub similarity: f64,
+}
+
+/// Reference from one function or method to another in the same file
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallEdge {
+    pub caller: String,
+    pub callee: String,
+}
+
+/// Calls between the functions and methods of a file
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallGraph {
+    pub edges: Vec<CallEdge>,
+    /// Functions and methods no other one refers to
+    pub roots: Vec<String>,
+}
+
+/// Parameter of a function or method symbol
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolParameter {
+    pub name: String,
+    pub type_annotation: Option<String>,
 }
 
 /// Symbol extracted from code
+///
+/// As in `CodeChunk`, fields added since snapshot schema version 1 come
+/// last and default when missing.
 #[napi(object)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Symbol {
@@ -38,18 +235,256 @@ pub struct Symbol {
     pub file_path: String,
     pub start_line: u32,
     pub end_line: u32,
+    /// 0-based byte offset of the start within its line, as in Tree-sitter
+    pub start_column: u32,
+    /// 0-based byte offset of the end within its line, as in Tree-sitter
+    pub end_column: u32,
+    /// 0-based start column counted in Unicode characters
+    pub start_column_char: u32,
     pub signature: Option<String>,
+    pub parameters: Vec<SymbolParameter>,
     pub exported: bool,
+    pub parent_name: Option<String>,
+    pub parent_kind: Option<String>,
+    /// Uses of the symbol's name in the same file, when requested
+    pub references: Option<Vec<SymbolRef>>,
+    /// Names of generic type parameters, such as `T` in `fn f<T: Clone>()`
+    #[serde(default)]
+    pub generic_params: Vec<String>,
+    /// Type signature declared apart from the definition it belongs to,
+    /// such as Haskell's `area :: Shape -> Double` before `area r = ...`,
+    /// with whitespace collapsed; `None` when no signature precedes it
+    #[serde(default)]
+    pub signature_annotation: Option<String>,
 }
 
-/// Merkle tree node for incremental sync
+/// Location where a symbol's name is used
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SymbolRef {
+    pub file_path: String,
+    /// 1-based line
+    pub line: u32,
+    /// 0-based byte offset within the line
+    pub column: u32,
+}
+
+/// Import statement extracted from code
 #[napi(object)]
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Import {
+    pub source: String,
+    pub specifiers: Vec<String>,
+    pub is_type_only: bool,
+    pub kind: String,
+    pub start_line: u32,
+}
+
+/// Comment or docstring extracted from code
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeComment {
+    pub content: String,
+    pub kind: String,
+    pub start_line: u32,
+    pub end_line: u32,
+    pub associated_symbol: Option<String>,
+    /// Tags of a JSDoc or PHPDoc comment
+    pub doc_block: Option<DocBlock>,
+}
+
+/// Tags read from a JSDoc or PHPDoc comment
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocBlock {
+    pub params: Vec<DocParam>,
+    pub returns: Option<DocReturn>,
+    pub author: Option<String>,
+}
+
+/// `@param` tag of a doc comment
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocParam {
+    /// As written, so PHP names keep their `$`
+    pub name: String,
+    pub type_hint: Option<String>,
+}
+
+/// `@returns` or `@return` tag of a doc comment
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocReturn {
+    pub type_hint: Option<String>,
+}
+
+/// Source file passed to batch operations
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileInput {
+    pub file_path: String,
+    pub content: String,
+    pub language: Option<String>,
+}
+
+/// Outcome of parsing one file, alone or in a batch
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseResult {
+    pub file_path: String,
+    pub chunks: Vec<CodeChunk>,
+    /// Only filled by `parse_file_with_options` with `extract_symbols`
+    pub symbols: Vec<Symbol>,
+    /// Only filled by `parse_file_with_options` with `extract_imports`
+    pub imports: Vec<Import>,
+    /// Only filled by `parse_file_with_options` with `extract_comments`
+    pub comments: Vec<CodeComment>,
+    pub error: Option<String>,
+}
+
+/// Chunks and symbols of a file with the syntax errors found in it
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseFileResult {
+    pub chunks: Vec<CodeChunk>,
+    pub symbols: Vec<Symbol>,
+    pub syntax_errors: Vec<SyntaxError>,
+}
+
+/// Time spent in each phase of parsing a file, with its node counts
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParseProfile {
+    pub total_ms: f64,
+    pub tree_construction_ms: f64,
+    pub chunk_extraction_ms: f64,
+    pub symbol_extraction_ms: f64,
+    pub node_count_by_kind: HashMap<String, u32>,
+}
+
+/// Place where Tree-sitter could not parse a file
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyntaxError {
+    pub message: String,
+    /// 1-based line
+    pub start_line: u32,
+    /// 0-based byte offset within the line
+    pub start_column: u32,
+}
+
+/// Outcome of extracting symbols from one file in a batch
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolsResult {
+    pub file_path: String,
+    pub symbols: Vec<Symbol>,
+    pub error: Option<String>,
+}
+
+/// Edge between two files in a dependency graph
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyEdge {
+    pub from: String,
+    pub to: String,
+    pub kind: String,
+}
+
+/// Dependency graph across a set of files
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DependencyGraph {
+    pub edges: Vec<DependencyEdge>,
+}
+
+/// Chunks and symbols restored from an index snapshot
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexSnapshot {
+    pub chunks: Vec<CodeChunk>,
+    pub symbols: Vec<Symbol>,
+}
+
+/// Supported language and what the native indexer can do with it
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageInfo {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub grammar_version: String,
+    pub ast_chunking: bool,
+    pub supported_symbol_kinds: Vec<String>,
+}
+
+/// Options for `parse_file` and `parse_file_with_options`
+#[napi(object)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ParseOptions {
+    /// Also return string literals as `string_literal` chunks
+    pub extract_strings: Option<bool>,
+    /// Return declaration chunks, defaults to true
+    pub extract_chunks: Option<bool>,
+    /// Return symbols, as from `extract_symbols`
+    pub extract_symbols: Option<bool>,
+    /// Return imports, as from `extract_imports`
+    pub extract_imports: Option<bool>,
+    /// Return comments, as from `extract_comments`
+    pub extract_comments: Option<bool>,
+    /// Return nothing for files `is_test_file` takes for tests
+    pub exclude_test_files: Option<bool>,
+}
+
+/// Options for `chunk_code_with_options`
+#[napi(object)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChunkOptions {
+    pub max_chunk_size: u32,
+    /// Lines repeated between chunks with `OverlapStrategy::FixedLines`
+    pub overlap: u32,
+    pub keep_class_intact_under_lines: Option<u32>,
+    pub hash_algorithm: Option<HashAlgorithm>,
+    /// Count tokens exactly with the GPT-4 tokenizer (requires the
+    /// `tiktoken` feature) instead of the character heuristic
+    pub estimate_tokens: Option<bool>,
+    /// Prefix each chunk with the file's imports and top-level
+    /// declarations that precede it
+    pub prepend_header: Option<bool>,
+    /// Most header lines to prepend, defaults to 10
+    pub header_max_lines: Option<u32>,
+    /// Size chunks in estimated tokens instead of lines, so long lines
+    /// count for more; `max_chunk_size` is then unused
+    pub max_tokens: Option<u32>,
+    /// Estimated tokens repeated with `OverlapStrategy::FixedTokens`,
+    /// defaults to 0
+    pub overlap_tokens: Option<u32>,
+    /// Defaults to `SemanticBoundary`, which suits embedding best
+    pub chunk_overlap_strategy: Option<OverlapStrategy>,
+}
+
+/// Merkle tree node for incremental sync
+#[napi(object)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MerkleNode {
     pub hash: String,
     pub path: String,
     pub is_file: bool,
     pub children: Vec<String>,
+    /// Caller-defined attributes such as size or mtime; never hashed, so
+    /// they do not affect roots or diffs
+    pub metadata: Option<HashMap<String, String>>,
+}
+
+/// Shape of a Merkle tree, for spotting degenerate layouts
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeStats {
+    pub leaf_count: u32,
+    /// Hashing levels from the deepest leaf to the root
+    pub depth: u32,
+    pub total_nodes: u32,
+    /// `depth` over that of a balanced binary tree of the same leaves
+    pub balance_factor: f64,
 }
 
 /// Sync diff result
@@ -59,43 +494,748 @@ pub struct SyncDiff {
     pub added: Vec<String>,
     pub modified: Vec<String>,
     pub deleted: Vec<String>,
+    pub renamed: Vec<RenamedFile>,
+    pub total_changed: u32,
+    pub total_files: u32,
+    pub change_ratio: f64,
+}
+
+/// File that moved paths without its content changing
+#[napi(object)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RenamedFile {
+    pub old_path: String,
+    pub new_path: String,
+}
+
+/// Byte and point edit applied before an incremental re-parse
+#[napi(object)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeEdit {
+    pub start_byte: u32,
+    pub old_end_byte: u32,
+    pub new_end_byte: u32,
+    pub start_row: u32,
+    pub start_column: u32,
+    pub old_end_row: u32,
+    pub old_end_column: u32,
+    pub new_end_row: u32,
+    pub new_end_column: u32,
+}
+
+impl From<parser::ParserError> for Error {
+    fn from(e: parser::ParserError) -> Self {
+        let status = match e {
+            parser::ParserError::UnsupportedLanguage(_) => Status::InvalidArg,
+            parser::ParserError::ParseFailed { .. } | parser::ParserError::GrammarInitFailed(_) => {
+                Status::GenericFailure
+            }
+        };
+        Error::new(status, e.to_string())
+    }
+}
+
+/// JS error for a failure that may come from the parser, with the status
+/// of a `ParserError` and `GenericFailure` for anything else
+fn parser_error(e: anyhow::Error) -> Error {
+    match e.downcast::<parser::ParserError>() {
+        Ok(e) => e.into(),
+        Err(e) => Error::from_reason(e.to_string()),
+    }
 }
 
 /// Parse a file and extract code chunks
 #[napi]
-pub fn parse_file(file_path: String, content: String, language: String) -> Result<Vec<CodeChunk>> {
-    parser::parse_file(&file_path, &content, &language)
+pub fn parse_file(
+    file_path: String,
+    content: String,
+    language: String,
+    options: Option<ParseOptions>,
+) -> Result<Vec<CodeChunk>> {
+    parser::parse_file_with_options(
+        &file_path,
+        &content,
+        &language,
+        &options.unwrap_or_default(),
+    )
+    .map(|result| result.chunks)
+    .map_err(parser_error)
+}
+
+/// Chunk a Markdown document into `"section"` chunks per heading and
+/// `"code_example"` chunks per fenced code block
+#[napi]
+pub fn parse_markdown(file_path: String, content: String) -> Result<Vec<CodeChunk>> {
+    markdown::parse_markdown(&file_path, &content).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// Parse a file once and extract chunks, symbols, imports and comments
+///
+/// Each requested kind is collected by its own walk over the shared tree.
+#[napi]
+pub fn parse_file_with_options(
+    file_path: String,
+    content: String,
+    language: String,
+    options: ParseOptions,
+) -> Result<ParseResult> {
+    parser::parse_file_with_options(&file_path, &content, &language, &options).map_err(parser_error)
+}
+
+/// Parse a file into chunks and symbols, also reporting its syntax errors
+#[napi]
+pub fn parse_file_with_errors(
+    file_path: String,
+    content: String,
+    language: String,
+) -> Result<ParseFileResult> {
+    parser::parse_file_with_errors(&file_path, &content, &language).map_err(parser_error)
+}
+
+/// Time the phases of parsing a file and count its syntax nodes by kind,
+/// for finding out why a file parses slowly
+#[napi]
+pub fn profile_parse_file(
+    file_path: String,
+    content: String,
+    language: String,
+) -> Result<ParseProfile> {
+    parser::profile_parse_file(&file_path, &content, &language).map_err(parser_error)
+}
+
+/// Background task for `parse_file_async`
+pub struct ParseFileTask {
+    file_path: String,
+    content: String,
+    language: String,
+}
+
+impl Task for ParseFileTask {
+    type Output = Vec<CodeChunk>;
+    type JsValue = Vec<CodeChunk>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        parser::parse_file(&self.file_path, &self.content, &self.language).map_err(parser_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+/// Parse a file on the libuv thread pool
+#[napi]
+pub fn parse_file_async(
+    file_path: String,
+    content: String,
+    language: String,
+) -> AsyncTask<ParseFileTask> {
+    AsyncTask::new(ParseFileTask {
+        file_path,
+        content,
+        language,
+    })
+}
+
+/// Parse many files in parallel, reporting failures per file
+#[napi]
+pub fn parse_files_batch(files: Vec<FileInput>) -> Vec<ParseResult> {
+    batch::parse_files(&files)
+}
+
+/// Group chunks by `file_path`, keeping their order within each file
+#[napi]
+pub fn group_chunks_by_file(chunks: Vec<CodeChunk>) -> HashMap<String, Vec<CodeChunk>> {
+    batch::group_by(chunks, |c| &c.file_path)
+}
+
+/// Group symbols by `file_path`, keeping their order within each file
+#[napi]
+pub fn group_symbols_by_file(symbols: Vec<Symbol>) -> HashMap<String, Vec<Symbol>> {
+    batch::group_by(symbols, |s| &s.file_path)
+}
+
+/// Group symbols by `kind`, keeping their order within each kind
+#[napi]
+pub fn group_symbols_by_kind(symbols: Vec<Symbol>) -> HashMap<String, Vec<Symbol>> {
+    batch::group_by(symbols, |s| &s.kind)
+}
+
+/// Parse tree kept in memory between edits of a file
+///
+/// Tree-sitter trees have no byte format, so the host keeps this handle
+/// instead and passes it to `parse_file_incremental` after each edit.
+#[napi]
+pub struct ParseTree {
+    inner: parser::ParsedTree,
+}
+
+#[napi]
+impl ParseTree {
+    #[napi(constructor)]
+    pub fn new(content: String, language: String) -> Result<Self> {
+        parser::parse_tree(&content, &language)
+            .map(|inner| ParseTree { inner })
+            .map_err(parser_error)
+    }
+
+    /// Language the tree was parsed with
+    #[napi(getter)]
+    pub fn language(&self) -> String {
+        self.inner.language.clone()
+    }
+}
+
+/// Re-parse an edited file from its previous tree, which is then replaced
+/// by the new one
+#[napi]
+pub fn parse_file_incremental(
+    file_path: String,
+    tree: &mut ParseTree,
+    new_content: String,
+    edits: Vec<TreeEdit>,
+) -> Result<Vec<CodeChunk>> {
+    parser::parse_file_incremental(&file_path, &mut tree.inner, &new_content, &edits)
+        .map_err(parser_error)
+}
+
+/// Parse a file reusing a held tree, re-parsing from it if the file changed
+#[napi]
+pub fn parse_file_from_tree(
+    file_path: String,
+    content: String,
+    tree: &mut ParseTree,
+) -> Result<Vec<CodeChunk>> {
+    parser::parse_file_from_tree(&file_path, &content, &mut tree.inner).map_err(parser_error)
+}
+
+/// Extract symbols from a file, optionally with same-file references
+#[napi]
+pub fn extract_symbols(
+    file_path: String,
+    content: String,
+    language: String,
+    include_references: Option<bool>,
+) -> Result<Vec<Symbol>> {
+    parser::extract_symbols(
+        &file_path,
+        &content,
+        &language,
+        include_references.unwrap_or(false),
+    )
+    .map_err(parser_error)
+}
+
+/// Top-level keys of a YAML file, one `"key"` symbol per dot-path
+///
+/// Keys under well-known sections such as GitHub Actions `jobs` are listed
+/// too. Each signature gives the value's type.
+#[napi]
+pub fn extract_yaml_keys(content: String) -> Result<Vec<Symbol>> {
+    config::extract_yaml_keys(&content).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// Top-level keys of a JSON file, one `"key"` symbol per dot-path
+///
+/// Keys under well-known sections such as package.json `scripts` are
+/// listed too. Each signature gives the value's type.
+#[napi]
+pub fn extract_json_keys(content: String) -> Result<Vec<Symbol>> {
+    config::extract_json_keys(&content).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// Symbols for the `FROM`, `RUN`, `COPY`, `ENV`, `EXPOSE`, `ENTRYPOINT` and
+/// `CMD` instructions of a Dockerfile, with continued lines joined
+#[napi]
+pub fn parse_dockerfile(file_path: String, content: String) -> Vec<Symbol> {
+    dockerfile::parse_dockerfile(&file_path, &content)
+}
+
+/// Background task for `extract_symbols_async`
+pub struct ExtractSymbolsTask {
+    file_path: String,
+    content: String,
+    language: String,
+}
+
+impl Task for ExtractSymbolsTask {
+    type Output = Vec<Symbol>;
+    type JsValue = Vec<Symbol>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        parser::extract_symbols(&self.file_path, &self.content, &self.language, false)
+            .map_err(parser_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+/// Extract symbols on the libuv thread pool
+#[napi]
+pub fn extract_symbols_async(
+    file_path: String,
+    content: String,
+    language: String,
+) -> AsyncTask<ExtractSymbolsTask> {
+    AsyncTask::new(ExtractSymbolsTask {
+        file_path,
+        content,
+        language,
+    })
+}
+
+/// Extract symbols from many files in parallel, reporting failures per file
+#[napi]
+pub fn extract_symbols_batch(files: Vec<FileInput>) -> Vec<SymbolsResult> {
+    batch::extract_symbols(&files)
+}
+
+/// Find the innermost symbol at a 1-based line and column
+#[napi]
+pub fn get_symbol_at_position(
+    content: String,
+    language: String,
+    line: u32,
+    column: u32,
+) -> Result<Option<Symbol>> {
+    parser::get_symbol_at_position(&content, &language, line, column).map_err(parser_error)
+}
+
+/// Extract import statements from a file
+#[napi]
+pub fn extract_imports(
+    file_path: String,
+    content: String,
+    language: String,
+) -> Result<Vec<Import>> {
+    parser::extract_imports(&file_path, &content, &language).map_err(parser_error)
+}
+
+/// Dump the syntax tree as pretty-printed JSON for debugging
+#[napi]
+pub fn get_ast_json(content: String, language: String) -> Result<String> {
+    parser::get_ast_json(&content, &language).map_err(parser_error)
+}
+
+/// Extract module-level variables and constants from a file
+#[napi]
+pub fn extract_variables(
+    file_path: String,
+    content: String,
+    language: String,
+) -> Result<Vec<Symbol>> {
+    parser::extract_variables(&file_path, &content, &language).map_err(parser_error)
+}
+
+/// Extract comments and docstrings from a file
+#[napi]
+pub fn extract_comments(
+    file_path: String,
+    content: String,
+    language: String,
+) -> Result<Vec<CodeComment>> {
+    parser::extract_comments(&file_path, &content, &language).map_err(parser_error)
+}
+
+/// Resolve imports across a set of files into a dependency graph
+#[napi]
+pub fn extract_dependencies(files: Vec<FileInput>) -> DependencyGraph {
+    dependencies::extract_dependencies(&files)
+}
+
+/// Serialise chunks and symbols as a versioned JSON snapshot
+#[napi]
+pub fn export_index_to_json(chunks: Vec<CodeChunk>, symbols: Vec<Symbol>) -> Result<String> {
+    snapshot::export_json(chunks, symbols).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// Restore chunks and symbols from a JSON snapshot
+#[napi]
+pub fn import_index_from_json(json: String) -> Result<IndexSnapshot> {
+    snapshot::import_json(&json).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// Serialise chunks and symbols as a compact MessagePack snapshot
+#[napi]
+pub fn export_index_to_msgpack(chunks: Vec<CodeChunk>, symbols: Vec<Symbol>) -> Result<Buffer> {
+    snapshot::export_msgpack(chunks, symbols)
+        .map(Buffer::from)
         .map_err(|e| Error::from_reason(e.to_string()))
 }
 
-/// Extract symbols from a file
+/// Restore chunks and symbols from a MessagePack snapshot
+#[napi]
+pub fn import_index_from_msgpack(data: Buffer) -> Result<IndexSnapshot> {
+    snapshot::import_msgpack(&data).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// Serialise chunks as an Apache Arrow IPC file, readable by PyArrow
+/// without copying
 #[napi]
-pub fn extract_symbols(file_path: String, content: String, language: String) -> Result<Vec<Symbol>> {
-    parser::extract_symbols(&file_path, &content, &language)
+pub fn serialize_chunks_to_arrow(chunks: Vec<CodeChunk>) -> Result<Buffer> {
+    arrow::chunks_to_ipc(&chunks)
+        .map(Buffer::from)
         .map_err(|e| Error::from_reason(e.to_string()))
 }
 
+/// Read chunks from an Apache Arrow IPC file
+#[napi]
+pub fn deserialize_chunks_from_arrow(data: Buffer) -> Result<Vec<CodeChunk>> {
+    arrow::chunks_from_ipc(&data).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// Reusable parser that keeps its Tree-sitter state between calls
+#[napi]
+pub struct NativeParser {
+    parser: tree_sitter::Parser,
+    language: String,
+}
+
+#[napi]
+impl NativeParser {
+    #[napi(constructor)]
+    pub fn new(language: String) -> Result<Self> {
+        let parser = parser::get_parser(&language).map_err(parser_error)?;
+        Ok(Self { parser, language })
+    }
+
+    /// Language the parser is configured for
+    #[napi(getter)]
+    pub fn language(&self) -> String {
+        self.language.clone()
+    }
+
+    /// Switch the parser to another language
+    #[napi]
+    pub fn set_language(&mut self, language: String) -> Result<()> {
+        parser::set_parser_language(&mut self.parser, &language).map_err(parser_error)?;
+        self.language = language;
+        Ok(())
+    }
+
+    /// Parse a file and extract code chunks
+    #[napi]
+    pub fn parse(&mut self, file_path: String, content: String) -> Result<Vec<CodeChunk>> {
+        parser::parse_file_with(&mut self.parser, &file_path, &content, &self.language)
+            .map_err(parser_error)
+    }
+
+    /// Extract symbols from source content
+    #[napi]
+    pub fn extract_symbols(&mut self, content: String) -> Result<Vec<Symbol>> {
+        parser::extract_symbols_with(&mut self.parser, "", &content, &self.language, false)
+            .map_err(parser_error)
+    }
+}
+
+/// Symbol lookup by name over an in-memory inverted index
+#[napi]
+pub struct CodeSearch {
+    index: search::SymbolIndex,
+}
+
+#[napi]
+impl CodeSearch {
+    #[napi(constructor)]
+    pub fn new(symbols: Vec<Symbol>) -> Self {
+        Self {
+            index: search::SymbolIndex::new(symbols),
+        }
+    }
+
+    /// Restore an index written by `to_json`
+    #[napi(factory)]
+    pub fn from_json(json: String) -> Result<Self> {
+        let index = serde_json::from_str(&json).map_err(|e| Error::from_reason(e.to_string()))?;
+        Ok(Self { index })
+    }
+
+    /// Serialise the index so it can be persisted with the chunk index
+    #[napi]
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string(&self.index).map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    /// Number of indexed symbols
+    #[napi(getter)]
+    pub fn size(&self) -> u32 {
+        self.index.symbol_count() as u32
+    }
+
+    /// Symbols whose name, or a camelCase/snake_case word of it, matches
+    /// `name` ignoring case; exact matches first
+    #[napi]
+    pub fn find_symbol(&self, name: String) -> Vec<Symbol> {
+        self.index.find(&name)
+    }
+
+    /// Symbols with names most similar to `query` by trigram overlap
+    #[napi]
+    pub fn fuzzy_find_symbol(&self, query: String, max_results: u32) -> Vec<Symbol> {
+        self.index.fuzzy_find(&query, max_results as usize)
+    }
+}
+
+/// Canonical lower-case, space-separated form of a camelCase, PascalCase
+/// or snake_case symbol name
+#[napi]
+pub fn normalize_symbol_name(name: String, language: String) -> String {
+    search::normalize_name(&name, &language)
+}
+
+/// Similarity from 0 to 1 of two symbol names once normalised
+#[napi]
+pub fn symbol_name_similarity(a: String, b: String) -> f64 {
+    search::name_similarity(&a, &b)
+}
+
 /// Build a Merkle tree from file hashes
 #[napi]
 pub fn build_merkle_tree(files: Vec<MerkleNode>) -> Result<String> {
-    merkle::build_tree(&files)
-        .map_err(|e| Error::from_reason(e.to_string()))
+    merkle::build_tree(&files).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// Build the same Merkle tree with each level hashed in parallel
+#[napi]
+pub fn build_merkle_tree_parallel(files: Vec<MerkleNode>) -> Result<String> {
+    merkle::build_tree_parallel(&files).map_err(|e| Error::from_reason(e.to_string()))
 }
 
-/// Compute diff between two Merkle trees
+/// Describe the shape of the Merkle tree built from file hashes
 #[napi]
-pub fn compute_merkle_diff(old_root: String, new_files: Vec<MerkleNode>) -> Result<SyncDiff> {
-    merkle::compute_diff(&old_root, &new_files)
+pub fn get_tree_stats(files: Vec<MerkleNode>) -> TreeStats {
+    merkle::tree_stats(&files)
+}
+
+/// Hashing levels from the deepest leaf of the Merkle tree to its root
+#[napi]
+pub fn get_tree_depth(files: Vec<MerkleNode>) -> u32 {
+    merkle::tree_stats(&files).depth
+}
+
+/// Generate a Merkle proof for a file
+///
+/// Only top-level files have proofs; files inside a directory are rejected.
+#[napi]
+pub fn generate_proof(files: Vec<MerkleNode>, path: String) -> Result<Vec<String>> {
+    merkle::generate_proof(&files, &path).map_err(|e| Error::from_reason(e.to_string()))
+}
+
+/// Verify a Merkle proof against a known root
+#[napi]
+pub fn verify_proof(leaf_hash: String, proof: Vec<String>, root: String, index: u32) -> bool {
+    merkle::verify_proof(&leaf_hash, &proof, &root, index as usize)
+}
+
+/// Compute diff between two Merkle trees, pruning unchanged directories
+/// when the old nodes are given
+#[napi]
+pub fn compute_merkle_diff(
+    old_root: String,
+    new_files: Vec<MerkleNode>,
+    old_files: Option<Vec<MerkleNode>>,
+) -> Result<SyncDiff> {
+    merkle::compute_diff(&old_root, &new_files, old_files.as_deref())
         .map_err(|e| Error::from_reason(e.to_string()))
 }
 
+/// Background task for `build_merkle_tree_async`
+pub struct BuildMerkleTreeTask {
+    files: Vec<MerkleNode>,
+}
+
+impl Task for BuildMerkleTreeTask {
+    type Output = String;
+    type JsValue = String;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        merkle::build_tree(&self.files).map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+/// Build a Merkle tree on the libuv thread pool
+#[napi]
+pub fn build_merkle_tree_async(files: Vec<MerkleNode>) -> AsyncTask<BuildMerkleTreeTask> {
+    AsyncTask::new(BuildMerkleTreeTask { files })
+}
+
+/// Background task for `compute_merkle_diff_async`
+pub struct MerkleDiffTask {
+    old_root: String,
+    new_files: Vec<MerkleNode>,
+    old_files: Option<Vec<MerkleNode>>,
+}
+
+impl Task for MerkleDiffTask {
+    type Output = SyncDiff;
+    type JsValue = SyncDiff;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        merkle::compute_diff(&self.old_root, &self.new_files, self.old_files.as_deref())
+            .map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+/// Compute a Merkle diff on the libuv thread pool
+#[napi]
+pub fn compute_merkle_diff_async(
+    old_root: String,
+    new_files: Vec<MerkleNode>,
+    old_files: Option<Vec<MerkleNode>>,
+) -> AsyncTask<MerkleDiffTask> {
+    AsyncTask::new(MerkleDiffTask {
+        old_root,
+        new_files,
+        old_files,
+    })
+}
+
+/// Compute diff between two full file sets
+#[napi]
+pub fn compute_incremental_diff(
+    old_files: Vec<MerkleNode>,
+    new_files: Vec<MerkleNode>,
+) -> SyncDiff {
+    merkle::compute_incremental_diff(&old_files, &new_files)
+}
+
+/// Merkle state persisted to disk between sessions
+#[napi]
+pub struct MerkleStore {
+    db_path: std::path::PathBuf,
+    state: merkle::MerkleState,
+}
+
+#[napi]
+impl MerkleStore {
+    #[napi(constructor)]
+    pub fn new(db_path: String) -> Self {
+        Self {
+            db_path: db_path.into(),
+            state: merkle::MerkleState::default(),
+        }
+    }
+
+    /// Diff against the stored file set and replace it with `files`
+    #[napi]
+    pub fn update(&mut self, files: Vec<MerkleNode>) -> SyncDiff {
+        self.state.update(&files)
+    }
+
+    /// Root hash over the stored files in path order
+    #[napi]
+    pub fn get_root(&self) -> Result<String> {
+        self.state
+            .root()
+            .map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    /// Write the stored file set to `db_path`
+    #[napi]
+    pub fn save(&self) -> Result<()> {
+        self.state
+            .save(&self.db_path)
+            .map_err(|e| Error::from_reason(e.to_string()))
+    }
+
+    /// Replace the stored file set with the contents of `db_path`
+    #[napi]
+    pub fn load(&mut self) -> Result<()> {
+        self.state = merkle::MerkleState::load(&self.db_path)
+            .map_err(|e| Error::from_reason(e.to_string()))?;
+        Ok(())
+    }
+}
+
+/// Watches a directory and reports incremental diffs as files change
+#[napi]
+pub struct FileWatcher {
+    root_dir: String,
+    watcher: Option<watcher::Watcher>,
+}
+
+#[napi]
+impl FileWatcher {
+    #[napi(constructor)]
+    pub fn new(root_dir: String) -> Self {
+        Self {
+            root_dir,
+            watcher: None,
+        }
+    }
+
+    /// Start watching, calling `callback` with a `SyncDiff` once each burst
+    /// of changes has been quiet for 50 ms
+    #[napi]
+    pub fn start(&mut self, callback: JsFunction) -> Result<()> {
+        if self.watcher.is_some() {
+            return Err(Error::from_reason("FileWatcher is already started"));
+        }
+
+        let callback: ThreadsafeFunction<SyncDiff, ErrorStrategy::Fatal> = callback
+            .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<SyncDiff>| {
+                Ok(vec![ctx.value])
+            })?;
+        let watcher = watcher::Watcher::start(std::path::Path::new(&self.root_dir), move |diff| {
+            callback.call(diff, ThreadsafeFunctionCallMode::NonBlocking);
+        })
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+
+        self.watcher = Some(watcher);
+        Ok(())
+    }
+
+    /// Stop watching; does nothing if not started
+    #[napi]
+    pub fn stop(&mut self) {
+        if let Some(watcher) = self.watcher.take() {
+            watcher.stop();
+        }
+    }
+}
+
 /// Hash file content
 #[napi]
 pub fn hash_content(content: String) -> String {
-    use sha2::{Sha256, Digest};
-    let mut hasher = Sha256::new();
-    hasher.update(content.as_bytes());
-    format!("{:x}", hasher.finalize())
+    HashAlgorithm::Sha256.hash(&content)
+}
+
+/// SHA-256 hash many contents in parallel, one hash per input in order
+#[napi]
+pub fn hash_contents_batch(contents: Vec<String>) -> Vec<String> {
+    batch::hash_contents(&contents)
+}
+
+/// Hash file content with BLAKE3
+#[napi]
+pub fn hash_content_blake3(content: String) -> String {
+    HashAlgorithm::Blake3.hash(&content)
+}
+
+/// Hash file content with the given algorithm
+#[napi]
+pub fn hash_content_with_algorithm(content: String, algorithm: HashAlgorithm) -> String {
+    algorithm.hash(&content)
+}
+
+/// Compute the stable, content-addressed id of a chunk
+#[napi]
+pub fn generate_stable_chunk_id(
+    content: String,
+    symbol_name: Option<String>,
+    language: String,
+) -> String {
+    parser::generate_chunk_id(&content, symbol_name.as_deref(), &language)
 }
 
 /// Chunk code into semantic blocks
@@ -105,21 +1245,290 @@ pub fn chunk_code(
     language: String,
     max_chunk_size: u32,
     overlap: u32,
+    hash_algorithm: Option<HashAlgorithm>,
 ) -> Result<Vec<CodeChunk>> {
-    chunker::chunk_code(&content, &language, max_chunk_size as usize, overlap as usize)
+    chunker::chunk_code(
+        &content,
+        &language,
+        max_chunk_size as usize,
+        overlap as usize,
+        hash_algorithm,
+    )
+    .map_err(parser_error)
+}
+
+/// Chunk code with extended options
+#[napi]
+pub fn chunk_code_with_options(
+    content: String,
+    language: String,
+    options: ChunkOptions,
+) -> Result<Vec<CodeChunk>> {
+    chunker::chunk_code_with_options(&content, &language, &options).map_err(parser_error)
+}
+
+/// Chunk code with file-level context prepended to each chunk
+#[napi]
+pub fn chunk_code_with_header(
+    content: String,
+    language: String,
+    options: ChunkOptions,
+) -> Result<Vec<CodeChunk>> {
+    chunker::chunk_code_with_header(&content, &language, &options).map_err(parser_error)
+}
+
+/// Chunks of one file, computed on a background thread as they are read
+///
+/// Chunking runs at most 16 chunks ahead of the reader and pauses until
+/// they are read. Wrap it in a Node `Readable` with `chunkCodeStreaming`
+/// from `stream.js`.
+#[napi]
+pub struct ChunkStream {
+    inner: Arc<stream::ChunkStream>,
+}
+
+#[napi]
+impl ChunkStream {
+    #[napi(constructor)]
+    pub fn new(content: String, language: String, options: ChunkOptions) -> Self {
+        Self {
+            inner: Arc::new(stream::ChunkStream::start(content, language, options)),
+        }
+    }
+
+    /// The next chunk on the libuv thread pool, `null` once all have been
+    /// read or the stream was cancelled
+    #[napi(ts_return_type = "Promise<CodeChunk | null>")]
+    pub fn next(&self) -> AsyncTask<NextChunkTask> {
+        AsyncTask::new(NextChunkTask {
+            stream: self.inner.clone(),
+        })
+    }
+
+    /// Stop chunking; a pending `next` resolves to `null`
+    #[napi]
+    pub fn cancel(&self) {
+        self.inner.cancel();
+    }
+}
+
+pub struct NextChunkTask {
+    stream: Arc<stream::ChunkStream>,
+}
+
+impl Task for NextChunkTask {
+    type Output = Option<CodeChunk>;
+    type JsValue = Option<CodeChunk>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        self.stream.next().map_err(parser_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+/// Chunk code along AST declarations, using line scanning for languages
+/// without a grammar
+#[napi]
+pub fn chunk_code_with_ast(
+    file_path: String,
+    content: String,
+    language: String,
+    options: ChunkOptions,
+) -> Result<Vec<CodeChunk>> {
+    chunker::chunk_code_with_ast(&file_path, &content, &language, &options).map_err(parser_error)
+}
+
+/// Compare two chunk lists to find function-level changes
+#[napi]
+pub fn diff_chunks(old_chunks: Vec<CodeChunk>, new_chunks: Vec<CodeChunk>) -> ChunkDiff {
+    chunker::diff_chunks(old_chunks, new_chunks)
+}
+
+/// Get up to `window` chunks either side of a chunk in the same file
+#[napi]
+pub fn get_chunk_context(
+    chunk: CodeChunk,
+    all_chunks: Vec<CodeChunk>,
+    window: u32,
+) -> ChunkContext {
+    chunker::chunk_context(&chunk, all_chunks, window as usize)
+}
+
+/// Check that chunks' line ranges cover every non-empty line of a file
+#[napi]
+pub fn validate_chunk_coverage(content: String, chunks: Vec<CodeChunk>) -> CoverageReport {
+    chunker::chunk_coverage(&content, &chunks)
+}
+
+/// Jaccard similarity of two files' chunk hash sets
+#[napi]
+pub fn compute_file_similarity(a: Vec<CodeChunk>, b: Vec<CodeChunk>) -> f64 {
+    chunker::file_similarity(&a, &b)
+}
+
+/// MinHash approximation of `compute_file_similarity`
+#[napi]
+pub fn compute_file_similarity_minhash(
+    a: Vec<CodeChunk>,
+    b: Vec<CodeChunk>,
+    num_hashes: u32,
+) -> f64 {
+    chunker::file_similarity_minhash(&a, &b, num_hashes)
+}
+
+/// Split a chunk into pieces that each fit a token budget
+#[napi]
+pub fn split_chunk_to_fit_token_budget(chunk: CodeChunk, max_tokens: u32) -> Vec<CodeChunk> {
+    chunker::split_chunk_to_fit_token_budget(chunk, max_tokens)
+}
+
+/// Share a token budget between chunks
+///
+/// `strategy` is `"equal"`, `"proportional"` (to each chunk's token count)
+/// or `"priority"`, which weights chunks by `priorities`.
+#[napi]
+pub fn allocate_token_budget(
+    chunks: Vec<CodeChunk>,
+    total_budget: u32,
+    strategy: String,
+    priorities: Option<Vec<f64>>,
+) -> Result<Vec<AllocatedChunk>> {
+    budget::allocate(chunks, total_budget, &strategy, priorities.as_deref())
         .map_err(|e| Error::from_reason(e.to_string()))
 }
 
-/// Get supported languages
+/// Check chunks for overlaps, copies within a file, sizes embedding models
+/// handle badly and declarations without symbols
+#[napi]
+pub fn lint_chunks(chunks: Vec<CodeChunk>) -> Vec<ChunkLintResult> {
+    lint::lint_chunks(&chunks)
+}
+
+/// Group chunks holding identical code and, below a `threshold` of 1,
+/// code at least that similar
+#[napi]
+pub fn detect_code_duplication(chunks: Vec<CodeChunk>, threshold: f64) -> Vec<DuplicateGroup> {
+    duplication::detect_duplicates(&chunks, threshold)
+}
+
+/// Find which functions and methods call which others within each file,
+/// from the symbols and chunks of a parse
+#[napi]
+pub fn compute_call_graph(symbols: Vec<Symbol>, chunks: Vec<CodeChunk>) -> CallGraph {
+    callgraph::compute_call_graph(&symbols, &chunks)
+}
+
+/// Merge consecutive chunks under `min_size` tokens from the same file
+#[napi]
+pub fn merge_small_chunks(chunks: Vec<CodeChunk>, min_size: u32) -> Vec<CodeChunk> {
+    chunker::merge_small_chunks(chunks, min_size)
+}
+
+/// Trim the lines each chunk repeats from the one before it in its file,
+/// recording the untrimmed start in `original_start_line`
+#[napi]
+pub fn chunk_code_overlap_dedup(chunks: Vec<CodeChunk>) -> Vec<CodeChunk> {
+    chunker::dedup_overlap(chunks)
+}
+
+/// Re-hash chunks by their content with whitespace made canonical, so
+/// re-indented copies of the same code share a hash
+#[napi]
+pub fn normalize_whitespace_in_chunks(chunks: Vec<CodeChunk>) -> Vec<CodeChunk> {
+    chunker::normalize_whitespace(chunks)
+}
+
+/// Background task for `chunk_code_async`
+pub struct ChunkCodeTask {
+    content: String,
+    language: String,
+    options: ChunkOptions,
+}
+
+impl Task for ChunkCodeTask {
+    type Output = Vec<CodeChunk>;
+    type JsValue = Vec<CodeChunk>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        chunker::chunk_code_with_options(&self.content, &self.language, &self.options)
+            .map_err(parser_error)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+}
+
+/// Chunk code on the libuv thread pool
+#[napi]
+pub fn chunk_code_async(
+    content: String,
+    language: String,
+    max_chunk_size: u32,
+    overlap: u32,
+    hash_algorithm: Option<HashAlgorithm>,
+) -> AsyncTask<ChunkCodeTask> {
+    let options = ChunkOptions {
+        max_chunk_size,
+        overlap,
+        hash_algorithm,
+        chunk_overlap_strategy: Some(OverlapStrategy::FixedLines),
+        ..Default::default()
+    };
+    AsyncTask::new(ChunkCodeTask {
+        content,
+        language,
+        options,
+    })
+}
+
+/// Detect a file's language from its extension, shebang line or content
+///
+/// Functions taking a `language` also accept `"auto"` to detect it.
+#[napi]
+pub fn detect_language(file_path: String, content_prefix: Option<String>) -> Option<String> {
+    parser::detect_language(&file_path, content_prefix.as_deref()).map(str::to_string)
+}
+
+/// Guess whether a file holds tests, from its path or the start of its
+/// content
+#[napi]
+pub fn is_test_file(file_path: String, content_prefix: String, language: String) -> bool {
+    parser::is_test_file(&file_path, &content_prefix, &language)
+}
+
+/// Detect a language from content alone, for files without an extension
+///
+/// `min_confidence` is between 0 and 1 and defaults to 0.7.
+#[napi]
+pub fn detect_language_from_content(
+    content: String,
+    min_confidence: Option<f64>,
+) -> Option<String> {
+    let min_confidence = min_confidence.unwrap_or(parser::DEFAULT_CONTENT_CONFIDENCE);
+    parser::language_from_content(&content, min_confidence).map(str::to_string)
+}
+
+/// Get supported languages with grammar metadata
+#[napi]
+pub fn get_supported_languages() -> Vec<LanguageInfo> {
+    parser::supported_languages()
+}
+
+/// Every node kind Tree-sitter produces for a representative snippet of
+/// the language, sorted; for debugging language support
+#[napi]
+pub fn get_supported_node_kinds(language: String) -> Result<Vec<String>> {
+    parser::node_kinds(&language).map_err(parser_error)
+}
+
+/// Get supported language names
 #[napi]
-pub fn get_supported_languages() -> Vec<String> {
-    vec![
-        "typescript".to_string(),
-        "javascript".to_string(),
-        "python".to_string(),
-        "rust".to_string(),
-        "go".to_string(),
-    ]
+pub fn get_supported_language_names() -> Vec<String> {
+    parser::supported_language_names()
 }
 
 /// Version info
@@ -0,0 +1,168 @@
+//! Quality checks for chunks before they are embedded
+
+use crate::{ChunkLintResult, CodeChunk};
+use std::collections::HashMap;
+
+/// Chunks above this many tokens are cut off by most embedding models
+const MAX_CHUNK_TOKENS: u32 = 4096;
+
+/// Chunks below this many tokens carry too little to retrieve on
+const MIN_CHUNK_TOKENS: u32 = 5;
+
+/// Report problems with chunks, in chunk order
+///
+/// Chunks over `MAX_CHUNK_TOKENS` are errors. Overlapping line ranges and
+/// repeated hashes within a file, tiny chunks and declaration chunks
+/// without symbols are warnings, as chunking with overlap or indexing
+/// generated code can produce them on purpose. Overlaps and duplicates are
+/// reported on the later chunk, naming the earlier one by its location as
+/// copies share a content-addressed id.
+pub fn lint_chunks(chunks: &[CodeChunk]) -> Vec<ChunkLintResult> {
+    let mut findings: Vec<(usize, ChunkLintResult)> = Vec::new();
+    let mut report = |i: usize, severity: &str, message: String| {
+        findings.push((
+            i,
+            ChunkLintResult {
+                chunk_id: chunks[i].id.clone(),
+                severity: severity.to_string(),
+                message,
+            },
+        ))
+    };
+
+    // Chunks of each file by start, with the chunk reaching furthest so far
+    let mut by_file: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        by_file.entry(&chunk.file_path).or_default().push(i);
+    }
+    for positions in by_file.values_mut() {
+        positions.sort_by_key(|&i| (chunks[i].start_line, chunks[i].end_line, i));
+        let mut furthest: Option<usize> = None;
+        for &i in positions.iter() {
+            if let Some(prev) = furthest {
+                if chunks[i].start_line <= chunks[prev].end_line {
+                    report(
+                        i,
+                        "warning",
+                        format!(
+                            "Lines {}-{} overlap the chunk at {} (lines {}-{})",
+                            chunks[i].start_line,
+                            chunks[i].end_line,
+                            chunks[prev].location(),
+                            chunks[prev].start_line,
+                            chunks[prev].end_line
+                        ),
+                    );
+                }
+            }
+            if furthest.is_none_or(|prev| chunks[i].end_line > chunks[prev].end_line) {
+                furthest = Some(i);
+            }
+        }
+    }
+
+    let mut first_with_hash: HashMap<(&str, &str), usize> = HashMap::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        match first_with_hash.get(&(chunk.file_path.as_str(), chunk.hash.as_str())) {
+            Some(&first) => report(
+                i,
+                "warning",
+                format!("Same content as the chunk at {}", chunks[first].location()),
+            ),
+            None => {
+                first_with_hash.insert((&chunk.file_path, &chunk.hash), i);
+            }
+        }
+
+        if chunk.token_count > MAX_CHUNK_TOKENS {
+            report(
+                i,
+                "error",
+                format!(
+                    "{} tokens is over the {} token limit",
+                    chunk.token_count, MAX_CHUNK_TOKENS
+                ),
+            );
+        } else if chunk.token_count < MIN_CHUNK_TOKENS {
+            report(
+                i,
+                "warning",
+                format!(
+                    "{} tokens is under the {} token minimum",
+                    chunk.token_count, MIN_CHUNK_TOKENS
+                ),
+            );
+        }
+
+        if chunk.symbols.is_empty() && chunk.chunk_type != "chunk" {
+            report(
+                i,
+                "warning",
+                format!("{} chunk has no symbols", chunk.chunk_type),
+            );
+        }
+    }
+
+    // Stable, so each chunk keeps its findings in check order
+    findings.sort_by_key(|(i, _)| *i);
+    findings.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    fn lint(chunks: &[CodeChunk]) -> Vec<(String, &'static str, String)> {
+        lint_chunks(chunks)
+            .into_iter()
+            .map(|r| {
+                let check = ["overlap", "Same content", "limit", "minimum", "no symbols"]
+                    .into_iter()
+                    .find(|c| r.message.contains(c))
+                    .unwrap();
+                (r.chunk_id, check, r.message)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_chunks() {
+        let content = "function add(a, b) {\n    return a + b;\n}\n\nfunction sub(a, b) {\n    return a - b;\n}\n";
+        let mut chunks = parser::parse_file("a.js", content, "javascript").unwrap();
+        assert_eq!(chunks.len(), 2);
+        assert!(lint_chunks(&chunks).is_empty());
+
+        // The same chunks in another file do not clash with these
+        chunks.extend(parser::parse_file("b.js", content, "javascript").unwrap());
+        assert!(lint_chunks(&chunks).is_empty());
+
+        chunks[1].start_line = 3;
+        chunks[2].token_count = 5000;
+        chunks[3].token_count = 2;
+        chunks[3].symbols.clear();
+        // A copy further down shares the first chunk's id
+        let copy = format!("{}\n\n\n\n\n\n\n\n\n\n\n\n{}", content, content);
+        let copies = parser::parse_file("a.js", &copy, "javascript").unwrap();
+        assert_eq!(copies[2].id, chunks[0].id);
+        chunks.push(copies[2].clone());
+
+        let findings = lint(&chunks);
+        let checks: Vec<(&str, &str)> = findings
+            .iter()
+            .map(|(id, check, _)| (id.as_str(), *check))
+            .collect();
+        assert_eq!(
+            checks,
+            vec![
+                (chunks[1].id.as_str(), "overlap"),
+                (chunks[2].id.as_str(), "limit"),
+                (chunks[3].id.as_str(), "minimum"),
+                (chunks[3].id.as_str(), "no symbols"),
+                (chunks[0].id.as_str(), "Same content"),
+            ]
+        );
+        assert!(findings[0].2.contains("at a.js:1 "));
+        assert!(findings[4].2.ends_with("at a.js:1"));
+    }
+}
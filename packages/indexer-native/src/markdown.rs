@@ -0,0 +1,177 @@
+//! Documentation chunks from Markdown headings and code fences
+
+use crate::parser;
+use crate::tokens;
+use crate::{CodeChunk, HashAlgorithm};
+use anyhow::Result;
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag, TagEnd};
+
+/// A heading with the byte range of its section
+struct Section {
+    level: usize,
+    title: String,
+    start: usize,
+    end: usize,
+}
+
+/// Chunk a Markdown document into sections and code examples
+///
+/// Each heading starts a `"section"` chunk running to the next heading of
+/// the same or a higher level, so sections nest. Each fenced code block is
+/// a `"code_example"` chunk of the code alone, in the language named by
+/// the fence (`"text"` if none), with the title of the section holding it
+/// as its symbol. Chunks are ordered by start.
+pub fn parse_markdown(file_path: &str, content: &str) -> Result<Vec<CodeChunk>> {
+    let mut sections: Vec<Section> = Vec::new();
+    let mut heading: Option<Section> = None;
+    let mut fence: Option<(String, Option<(usize, usize)>)> = None;
+    let mut chunks = Vec::new();
+
+    for (event, range) in Parser::new(content).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { level, .. }) => {
+                let level = level as usize;
+                for section in sections
+                    .iter_mut()
+                    .filter(|s| s.end == content.len() && s.level >= level)
+                {
+                    section.end = range.start;
+                }
+                heading = Some(Section {
+                    level,
+                    title: String::new(),
+                    start: range.start,
+                    end: content.len(),
+                });
+            }
+            Event::End(TagEnd::Heading(_)) => sections.extend(heading.take()),
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info))) => {
+                let language = info
+                    .split(|c: char| c.is_whitespace() || c == ',')
+                    .next()
+                    .unwrap_or("");
+                let language = if language.is_empty() {
+                    "text"
+                } else {
+                    language
+                };
+                fence = Some((language.to_string(), None));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((language, Some((start, end)))) = fence.take() {
+                    let title = sections.last().map(|s| s.title.clone());
+                    chunks.push(chunk(
+                        file_path,
+                        content,
+                        (start, end),
+                        "code_example",
+                        &language,
+                        title,
+                    ));
+                }
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some(heading) = heading.as_mut() {
+                    heading.title.push_str(&text);
+                } else if let Some((_, code)) = fence.as_mut() {
+                    let start = code.map_or(range.start, |(start, _)| start);
+                    *code = Some((start, range.end));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for section in sections {
+        let end = section.start + content[section.start..section.end].trim_end().len();
+        let title = Some(section.title.trim().to_string());
+        chunks.push(chunk(
+            file_path,
+            content,
+            (section.start, end),
+            "section",
+            "markdown",
+            title,
+        ));
+    }
+    chunks.sort_by_key(|c| (c.start_byte, c.chunk_type != "section"));
+    Ok(chunks)
+}
+
+/// Chunk of `content[start..end]`
+fn chunk(
+    file_path: &str,
+    content: &str,
+    (start, end): (usize, usize),
+    chunk_type: &str,
+    language: &str,
+    title: Option<String>,
+) -> CodeChunk {
+    let text = &content[start..end];
+    let start_line = content[..start].matches('\n').count() as u32 + 1;
+    let symbols: Vec<String> = title.into_iter().filter(|t| !t.is_empty()).collect();
+
+    CodeChunk {
+        id: parser::generate_chunk_id(text, symbols.first().map(String::as_str), language),
+        file_path: file_path.to_string(),
+        content: text.to_string(),
+        start_line,
+        end_line: start_line + text.trim_end_matches('\n').matches('\n').count() as u32,
+        start_byte: start as u32,
+        end_byte: end as u32,
+        complexity: 0,
+        token_count: tokens::estimate_tokens(text),
+        header_lines: 0,
+        original_start_line: None,
+        language_version: None,
+        chunk_type: chunk_type.to_string(),
+        language: language.to_string(),
+        symbols,
+        hash: HashAlgorithm::Sha256.hash(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_markdown() {
+        let content = "# Titan\n\nIntro text.\n\n## Install\n\nRun this:\n\n```sh\nnpm install\n```\n\n### From source\n\nBuild it.\n\n## Usage `api`\n\n```ts title=\"a.ts\"\nconst a = 1;\nconst b = 2;\n```\n\n```\nplain\n```\n";
+        let chunks = parse_markdown("README.md", content).unwrap();
+        let summary: Vec<(&str, &str, &str, u32, u32)> = chunks
+            .iter()
+            .map(|c| {
+                (
+                    c.chunk_type.as_str(),
+                    c.language.as_str(),
+                    c.symbols.first().map_or("", String::as_str),
+                    c.start_line,
+                    c.end_line,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("section", "markdown", "Titan", 1, 26),
+                ("section", "markdown", "Install", 5, 15),
+                ("code_example", "sh", "Install", 10, 10),
+                ("section", "markdown", "From source", 13, 15),
+                ("section", "markdown", "Usage api", 17, 26),
+                ("code_example", "ts", "Usage api", 20, 21),
+                ("code_example", "text", "Usage api", 25, 25),
+            ]
+        );
+
+        assert_eq!(chunks[2].content, "npm install\n");
+        assert!(chunks[1].content.starts_with("## Install\n"));
+        assert!(chunks[1].content.ends_with("Build it."));
+        assert_eq!(
+            &content[chunks[5].start_byte as usize..chunks[5].end_byte as usize],
+            chunks[5].content
+        );
+
+        assert!(parse_markdown("a.md", "").unwrap().is_empty());
+    }
+}
@@ -1,106 +1,551 @@
 //! Merkle tree for incremental synchronization
 
-use crate::{MerkleNode, SyncDiff};
+use crate::{MerkleNode, RenamedFile, SyncDiff, TreeStats};
 use anyhow::Result;
-use sha2::{Sha256, Digest};
-use std::collections::HashMap;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
 
 /// Build a Merkle tree from file nodes
+///
+/// Directory nodes (`is_file == false`) are hashed from their children, so
+/// only top-level nodes (those not listed as another node's child) form the
+/// leaves of the tree. A flat list of files hashes exactly as before.
 pub fn build_tree(files: &[MerkleNode]) -> Result<String> {
     if files.is_empty() {
         return Ok(hash_empty());
     }
 
     // Build leaf hashes
-    let mut hashes: Vec<String> = files.iter().map(|f| f.hash.clone()).collect();
+    let mut hashes = top_level_hashes(files);
+    if hashes.is_empty() {
+        return Ok(hash_empty());
+    }
 
     // Build tree bottom-up
     while hashes.len() > 1 {
-        let mut next_level = Vec::new();
+        hashes = hashes.chunks(2).map(hash_pair).collect();
+    }
 
-        for chunk in hashes.chunks(2) {
-            let combined = if chunk.len() == 2 {
-                format!("{}{}", chunk[0], chunk[1])
+    Ok(hashes.into_iter().next().unwrap_or_else(hash_empty))
+}
+
+/// Build the same tree as `build_tree`, hashing each level on the Rayon pool
+///
+/// Every pair of a level is hashed independently, so a level is split
+/// across threads; the levels themselves still run one after another as
+/// each depends on the one below. Directory hashes are computed up front
+/// as in `build_tree`. Worth it for many thousands of files, where the
+/// leaf level dominates; for small trees `build_tree` avoids the pool.
+pub fn build_tree_parallel(files: &[MerkleNode]) -> Result<String> {
+    let node_hashes = node_hashes(files);
+    let mut hashes: Vec<String> = top_level(files)
+        .into_par_iter()
+        .map(|f| {
+            if f.is_file {
+                f.hash.clone()
             } else {
-                chunk[0].clone()
-            };
-            next_level.push(hash_string(&combined));
-        }
+                node_hashes[f.path.as_str()].clone()
+            }
+        })
+        .collect();
 
-        hashes = next_level;
+    while hashes.len() > 1 {
+        hashes = hashes
+            .par_chunks(2)
+            .with_min_len(PARALLEL_MIN_PAIRS)
+            .map(hash_pair)
+            .collect();
     }
 
     Ok(hashes.into_iter().next().unwrap_or_else(hash_empty))
 }
 
+/// Fewest pairs of a level handed to one Rayon task
+const PARALLEL_MIN_PAIRS: usize = 256;
+
+/// Parent hash of two siblings, or of a node left without one
+fn hash_pair(pair: &[String]) -> String {
+    if pair.len() == 2 {
+        hash_string(&format!("{}{}", pair[0], pair[1]))
+    } else {
+        hash_string(&pair[0])
+    }
+}
+
+/// Shape of the tree `build_tree` hashes
+///
+/// Leaves are files and empty directories. The depth counts the pairwise
+/// levels over the top-level nodes plus the deepest directory nesting below
+/// them; `balance_factor` compares it with the depth of a balanced binary
+/// tree over the leaves, so a value well above 1 means deep directories.
+pub fn tree_stats(files: &[MerkleNode]) -> TreeStats {
+    let index: HashMap<&str, &MerkleNode> = files.iter().map(|f| (f.path.as_str(), f)).collect();
+    let top = top_level(files);
+    let leaf_count = files
+        .iter()
+        .filter(|f| f.is_file || f.children.is_empty())
+        .count();
+
+    // Every level but the last hashes half as many nodes, rounding up
+    let mut pair_nodes = 0;
+    let mut width = top.len();
+    while width > 1 {
+        width = width.div_ceil(2);
+        pair_nodes += width;
+    }
+
+    let nesting = top
+        .iter()
+        .map(|f| nesting_depth(&f.path, &index, &mut HashSet::new()))
+        .max()
+        .unwrap_or(0);
+    let depth = ceil_log2(top.len()) + nesting;
+    let ideal = ceil_log2(leaf_count);
+
+    TreeStats {
+        leaf_count: leaf_count as u32,
+        depth,
+        total_nodes: (files.len() + pair_nodes) as u32,
+        balance_factor: depth.max(1) as f64 / ideal.max(1) as f64,
+    }
+}
+
+/// Levels of directories above the deepest node under `path`
+fn nesting_depth<'a>(
+    path: &'a str,
+    index: &HashMap<&'a str, &'a MerkleNode>,
+    visiting: &mut HashSet<&'a str>,
+) -> u32 {
+    let Some(node) = index.get(path) else {
+        return 0;
+    };
+    if node.is_file || node.children.is_empty() || !visiting.insert(path) {
+        return 0;
+    }
+    let depth = node
+        .children
+        .iter()
+        .map(|child| nesting_depth(child, index, visiting))
+        .max()
+        .unwrap_or(0);
+    visiting.remove(path);
+    depth + 1
+}
+
+/// Levels of a balanced binary tree over `n` leaves
+fn ceil_log2(n: usize) -> u32 {
+    if n <= 1 {
+        0
+    } else {
+        usize::BITS - (n - 1).leading_zeros()
+    }
+}
+
+/// Generate the sibling hashes from a file's leaf up to the root
+///
+/// The proof is ordered for `verify_proof`, with the file's position among
+/// the top-level nodes as the index; for a flat list of files that is its
+/// position in `files`. A node without a sibling on its level is
+/// represented by an empty string, matching how `build_tree` hashes it.
+/// Files inside a directory are folded into its hash rather than paired,
+/// so they have no proof and are rejected.
+pub fn generate_proof(files: &[MerkleNode], path: &str) -> Result<Vec<String>> {
+    let mut hashes = top_level_hashes(files);
+    let top = top_level(files);
+    let mut idx = match top.iter().position(|f| f.path == path) {
+        Some(idx) if top[idx].is_file => idx,
+        Some(_) => anyhow::bail!("Not a file: {}", path),
+        None if files.iter().any(|f| f.path == path) => {
+            anyhow::bail!("File is inside a directory: {}", path)
+        }
+        None => anyhow::bail!("File not in tree: {}", path),
+    };
+
+    let mut proof = Vec::new();
+    while hashes.len() > 1 {
+        let sibling = idx ^ 1;
+        proof.push(hashes.get(sibling).cloned().unwrap_or_default());
+
+        hashes = hashes.chunks(2).map(hash_pair).collect();
+        idx /= 2;
+    }
+
+    Ok(proof)
+}
+
 /// Compute diff between old and new states
-pub fn compute_diff(old_root: &str, new_files: &[MerkleNode]) -> Result<SyncDiff> {
+///
+/// Matching roots mean no changes. Otherwise, with the old nodes at hand
+/// the hierarchy is walked as in `compute_incremental_diff`, skipping every
+/// directory whose hash is unchanged; a root alone cannot tell which files
+/// changed, so without them every new file is reported as modified.
+pub fn compute_diff(
+    old_root: &str,
+    new_files: &[MerkleNode],
+    old_files: Option<&[MerkleNode]>,
+) -> Result<SyncDiff> {
     // Build new tree
     let new_root = build_tree(new_files)?;
 
     // If roots match, no changes
+    let total_files = new_files
+        .iter()
+        .map(|f| f.path.as_str())
+        .collect::<HashSet<_>>()
+        .len();
     if old_root == new_root {
-        return Ok(SyncDiff {
-            added: vec![],
-            modified: vec![],
-            deleted: vec![],
-        });
+        return Ok(diff_with_stats(vec![], vec![], vec![], vec![], total_files));
+    }
+
+    if let Some(old_files) = old_files {
+        return Ok(compute_incremental_diff(old_files, new_files));
+    }
+    Ok(diff_with_stats(
+        vec![],
+        new_files
+            .iter()
+            .filter(|f| f.is_file)
+            .map(|f| f.path.clone())
+            .collect(),
+        vec![],
+        vec![],
+        total_files,
+    ))
+}
+
+/// Compute incremental diff between two file sets
+pub fn compute_incremental_diff(old_files: &[MerkleNode], new_files: &[MerkleNode]) -> SyncDiff {
+    let old_index: HashMap<&str, &MerkleNode> =
+        old_files.iter().map(|f| (f.path.as_str(), f)).collect();
+    let new_index: HashMap<&str, &MerkleNode> =
+        new_files.iter().map(|f| (f.path.as_str(), f)).collect();
+    let old_hashes = node_hashes(old_files);
+    let new_hashes = node_hashes(new_files);
+
+    let mut added = Vec::new();
+    let mut modified = Vec::new();
+    let mut deleted = Vec::new();
+
+    // Find added and modified, skipping unchanged directories
+    let mut visited = HashSet::new();
+    for node in top_level(new_files) {
+        walk_changes(
+            &node.path,
+            &new_index,
+            &new_hashes,
+            &old_hashes,
+            &mut visited,
+            &mut |path, existed| {
+                if existed {
+                    modified.push(path.to_string());
+                } else {
+                    added.push(path.to_string());
+                }
+            },
+        );
     }
 
-    // Build index of new files
-    let new_index: HashMap<&str, &MerkleNode> = new_files
+    // Find deleted
+    let mut visited = HashSet::new();
+    for node in top_level(old_files) {
+        walk_changes(
+            &node.path,
+            &old_index,
+            &old_hashes,
+            &new_hashes,
+            &mut visited,
+            &mut |path, exists| {
+                if !exists {
+                    deleted.push(path.to_string());
+                }
+            },
+        );
+    }
+
+    // Unique paths across both sets: everything old plus what was added
+    let total_files = old_index.len() + added.len();
+    let renamed = match_renames(&mut added, &mut deleted, &old_index, &new_index);
+    diff_with_stats(added, modified, deleted, renamed, total_files)
+}
+
+/// Pair deleted and added files with identical hashes as renames
+///
+/// Files are grouped by hash first, so only files with the same content
+/// are compared. Within a group the pairs with the closest paths (by edit
+/// distance) are matched first. Matched paths are removed from `added` and
+/// `deleted`.
+fn match_renames(
+    added: &mut Vec<String>,
+    deleted: &mut Vec<String>,
+    old_index: &HashMap<&str, &MerkleNode>,
+    new_index: &HashMap<&str, &MerkleNode>,
+) -> Vec<RenamedFile> {
+    let file_hash = |index: &HashMap<&str, &MerkleNode>, path: &str| -> Option<String> {
+        index
+            .get(path)
+            .filter(|n| n.is_file)
+            .map(|n| n.hash.clone())
+    };
+
+    // Positions in `deleted` and `added` by hash
+    let mut buckets: HashMap<String, (Vec<usize>, Vec<usize>)> = HashMap::new();
+    for (i, path) in deleted.iter().enumerate() {
+        if let Some(hash) = file_hash(old_index, path) {
+            buckets.entry(hash).or_default().0.push(i);
+        }
+    }
+    for (j, path) in added.iter().enumerate() {
+        if let Some((_, news)) = file_hash(new_index, path).and_then(|hash| buckets.get_mut(&hash))
+        {
+            news.push(j);
+        }
+    }
+
+    let mut used_old = vec![false; deleted.len()];
+    let mut used_new = vec![false; added.len()];
+    let mut pairs = Vec::new();
+    for (olds, news) in buckets
+        .values()
+        .filter(|(olds, news)| !olds.is_empty() && !news.is_empty())
+    {
+        let mut candidates: Vec<(usize, usize, usize)> = olds
+            .iter()
+            .flat_map(|&i| news.iter().map(move |&j| (i, j)))
+            .map(|(i, j)| (edit_distance(&deleted[i], &added[j]), i, j))
+            .collect();
+        candidates.sort();
+        for (_, i, j) in candidates {
+            if !used_old[i] && !used_new[j] {
+                used_old[i] = true;
+                used_new[j] = true;
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs.sort();
+
+    let renamed = pairs
         .iter()
-        .map(|f| (f.path.as_str(), f))
+        .map(|&(i, j)| RenamedFile {
+            old_path: deleted[i].clone(),
+            new_path: added[j].clone(),
+        })
         .collect();
+    let mut unmatched = used_new.iter();
+    added.retain(|_| !unmatched.next().unwrap());
+    let mut unmatched = used_old.iter();
+    deleted.retain(|_| !unmatched.next().unwrap());
+    renamed
+}
+
+/// Levenshtein distance between two paths
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
 
-    // For now, return all files as modified since we don't have old state
-    // In production, you'd compare against stored old tree
-    Ok(SyncDiff {
-        added: vec![],
-        modified: new_files.iter().map(|f| f.path.clone()).collect(),
-        deleted: vec![],
-    })
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(row[j + 1])
+            };
+            prev = current;
+        }
+    }
+
+    row[b.len()]
 }
 
-/// Compute incremental diff between two file sets
-pub fn compute_incremental_diff(
-    old_files: &[MerkleNode],
-    new_files: &[MerkleNode],
-) -> SyncDiff {
-    let old_index: HashMap<&str, &str> = old_files
+/// Report every node under `path` whose hash differs from `other`
+///
+/// Subtrees with a matching hash are skipped entirely. The callback gets
+/// the path and whether it exists in `other` at all.
+fn walk_changes<'a>(
+    path: &'a str,
+    nodes: &HashMap<&'a str, &'a MerkleNode>,
+    hashes: &HashMap<&str, String>,
+    other: &HashMap<&str, String>,
+    visited: &mut HashSet<&'a str>,
+    on_change: &mut impl FnMut(&'a str, bool),
+) {
+    if !visited.insert(path) {
+        return;
+    }
+    let (node, hash) = match (nodes.get(path), hashes.get(path)) {
+        (Some(node), Some(hash)) => (*node, hash),
+        _ => return,
+    };
+
+    let other_hash = other.get(path);
+    if other_hash == Some(hash) {
+        return;
+    }
+    on_change(path, other_hash.is_some());
+
+    if !node.is_file {
+        for child in &node.children {
+            walk_changes(child, nodes, hashes, other, visited, on_change);
+        }
+    }
+}
+
+/// Nodes not listed as a child of any directory, in input order
+fn top_level(files: &[MerkleNode]) -> Vec<&MerkleNode> {
+    let children: HashSet<&str> = files
         .iter()
-        .map(|f| (f.path.as_str(), f.hash.as_str()))
+        .filter(|f| !f.is_file)
+        .flat_map(|f| f.children.iter().map(String::as_str))
         .collect();
 
-    let new_index: HashMap<&str, &str> = new_files
+    files
         .iter()
-        .map(|f| (f.path.as_str(), f.hash.as_str()))
-        .collect();
+        .filter(|f| !children.contains(f.path.as_str()))
+        .collect()
+}
 
-    let mut added = Vec::new();
-    let mut modified = Vec::new();
-    let mut deleted = Vec::new();
+/// Leaf hashes of the tree: the effective hash of each top-level node
+fn top_level_hashes(files: &[MerkleNode]) -> Vec<String> {
+    let node_hashes = node_hashes(files);
+    top_level(files)
+        .into_iter()
+        .map(|f| node_hashes[f.path.as_str()].clone())
+        .collect()
+}
 
-    // Find added and modified
-    for (path, new_hash) in &new_index {
-        match old_index.get(path) {
-            None => added.push(path.to_string()),
-            Some(old_hash) if old_hash != new_hash => modified.push(path.to_string()),
-            _ => {}
-        }
+/// Effective hash of every node by path
+///
+/// Files keep their own hash. A directory hashes each child's path and
+/// effective hash in order, so renames inside it change its hash too.
+fn node_hashes(files: &[MerkleNode]) -> HashMap<&str, String> {
+    let index: HashMap<&str, &MerkleNode> = files.iter().map(|f| (f.path.as_str(), f)).collect();
+    let mut hashes = HashMap::new();
+
+    for file in files {
+        node_hash(&file.path, &index, &mut hashes, &mut HashSet::new());
     }
 
-    // Find deleted
-    for path in old_index.keys() {
-        if !new_index.contains_key(path) {
-            deleted.push(path.to_string());
+    hashes
+}
+
+/// Hash a single node, memoising into `hashes` and breaking cycles
+fn node_hash<'a>(
+    path: &'a str,
+    index: &HashMap<&'a str, &'a MerkleNode>,
+    hashes: &mut HashMap<&'a str, String>,
+    visiting: &mut HashSet<&'a str>,
+) -> Option<String> {
+    if let Some(hash) = hashes.get(path) {
+        return Some(hash.clone());
+    }
+
+    let node: &'a MerkleNode = index.get(path)?;
+    if node.is_file {
+        hashes.insert(path, node.hash.clone());
+        return Some(node.hash.clone());
+    }
+
+    if !visiting.insert(path) {
+        return None;
+    }
+    let mut combined = String::new();
+    for child in &node.children {
+        if let Some(hash) = node_hash(child, index, hashes, visiting) {
+            combined.push_str(child);
+            combined.push_str(&hash);
         }
     }
+    visiting.remove(path);
 
+    let hash = hash_string(&combined);
+    hashes.insert(path, hash.clone());
+    Some(hash)
+}
+
+/// Assemble a diff with its aggregate counts
+///
+/// A rename counts as one change.
+fn diff_with_stats(
+    added: Vec<String>,
+    modified: Vec<String>,
+    deleted: Vec<String>,
+    renamed: Vec<RenamedFile>,
+    total_files: usize,
+) -> SyncDiff {
+    let total_changed = added.len() + modified.len() + deleted.len() + renamed.len();
     SyncDiff {
         added,
         modified,
         deleted,
+        renamed,
+        total_changed: total_changed as u32,
+        total_files: total_files as u32,
+        change_ratio: total_changed as f64 / total_files.max(1) as f64,
+    }
+}
+
+/// File hashes persisted between sessions, keyed by path
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct MerkleState {
+    pub files: BTreeMap<String, String>,
+}
+
+impl MerkleState {
+    /// Diff against a new file set and replace the state with it
+    pub fn update(&mut self, files: &[MerkleNode]) -> SyncDiff {
+        let old = self.nodes();
+        let diff = compute_incremental_diff(&old, files);
+
+        self.files = files
+            .iter()
+            .map(|f| (f.path.clone(), f.hash.clone()))
+            .collect();
+
+        diff
+    }
+
+    /// Diff against a new path-to-hash map and replace the state with it
+    pub fn update_files(&mut self, files: BTreeMap<String, String>) -> SyncDiff {
+        let new = MerkleState { files }.nodes();
+        self.update(&new)
+    }
+
+    /// Root hash over the stored files in path order
+    pub fn root(&self) -> Result<String> {
+        build_tree(&self.nodes())
+    }
+
+    /// Write the state to a JSON file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self)?;
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Read the state from a JSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Stored files as leaf nodes
+    fn nodes(&self) -> Vec<MerkleNode> {
+        self.files
+            .iter()
+            .map(|(path, hash)| MerkleNode {
+                hash: hash.clone(),
+                path: path.clone(),
+                is_file: true,
+                children: vec![],
+                metadata: None,
+            })
+            .collect()
     }
 }
 
@@ -117,17 +562,12 @@ fn hash_string(s: &str) -> String {
 }
 
 /// Verify a Merkle proof
-pub fn verify_proof(
-    leaf_hash: &str,
-    proof: &[String],
-    root: &str,
-    index: usize,
-) -> bool {
+pub fn verify_proof(leaf_hash: &str, proof: &[String], root: &str, index: usize) -> bool {
     let mut current = leaf_hash.to_string();
     let mut idx = index;
 
     for sibling in proof {
-        current = if idx % 2 == 0 {
+        current = if idx.is_multiple_of(2) {
             hash_string(&format!("{}{}", current, sibling))
         } else {
             hash_string(&format!("{}{}", sibling, current))
@@ -142,57 +582,290 @@ pub fn verify_proof(
 mod tests {
     use super::*;
 
+    fn leaf(path: &str, hash: &str) -> MerkleNode {
+        MerkleNode {
+            hash: hash.to_string(),
+            path: path.to_string(),
+            is_file: true,
+            ..Default::default()
+        }
+    }
+
+    fn dir(path: &str, children: &[String]) -> MerkleNode {
+        MerkleNode {
+            path: path.to_string(),
+            children: children.to_vec(),
+            ..Default::default()
+        }
+    }
+
     #[test]
     fn test_build_tree() {
+        let files = vec![leaf("file1.ts", "a"), leaf("file2.ts", "b")];
+
+        let root = build_tree(&files).unwrap();
+        assert!(!root.is_empty());
+    }
+
+    #[test]
+    fn test_build_tree_parallel_matches() {
+        assert_eq!(build_tree_parallel(&[]).unwrap(), build_tree(&[]).unwrap());
+        for n in [1, 2, 5, 1000, 1025] {
+            let files: Vec<MerkleNode> = (0..n)
+                .map(|i| leaf(&format!("f{}.ts", i), &i.to_string()))
+                .collect();
+            assert_eq!(
+                build_tree_parallel(&files).unwrap(),
+                build_tree(&files).unwrap()
+            );
+        }
+
+        let paths: Vec<String> = (0..600).map(|i| format!("src/f{}.ts", i)).collect();
+        let mut nested: Vec<MerkleNode> = paths.iter().map(|p| leaf(p, p)).collect();
+        nested.push(dir("src", &paths));
+        nested.push(leaf("README.md", "readme"));
+        assert_eq!(
+            build_tree_parallel(&nested).unwrap(),
+            build_tree(&nested).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_tree_stats() {
+        let node = |path: &str, children: &[&str]| match children {
+            [] => leaf(path, path),
+            _ => dir(
+                path,
+                &children.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+            ),
+        };
+
+        let flat: Vec<MerkleNode> = (0..5).map(|i| node(&format!("f{}", i), &[])).collect();
+        let stats = tree_stats(&flat);
+        assert_eq!(
+            (stats.leaf_count, stats.depth, stats.total_nodes),
+            (5, 3, 11)
+        );
+        assert_eq!(stats.balance_factor, 1.0);
+
+        // Four files at the bottom of a chain of directories
+        let nested = vec![
+            node("a", &["a/b"]),
+            node("a/b", &["a/b/c"]),
+            node("a/b/c", &["a/b/c/1", "a/b/c/2", "a/b/c/3", "a/b/c/4"]),
+            node("a/b/c/1", &[]),
+            node("a/b/c/2", &[]),
+            node("a/b/c/3", &[]),
+            node("a/b/c/4", &[]),
+        ];
+        let stats = tree_stats(&nested);
+        assert_eq!(
+            (stats.leaf_count, stats.depth, stats.total_nodes),
+            (4, 3, 7)
+        );
+        assert_eq!(stats.balance_factor, 1.5);
+
+        let empty = tree_stats(&[]);
+        assert_eq!(
+            (empty.leaf_count, empty.depth, empty.total_nodes),
+            (0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_generate_and_verify_proof() {
+        let files: Vec<MerkleNode> = ["a", "b", "c", "d", "e"]
+            .iter()
+            .map(|h| leaf(&format!("{}.ts", h), h))
+            .collect();
+
+        let root = build_tree(&files).unwrap();
+        for (index, file) in files.iter().enumerate() {
+            let proof = generate_proof(&files, &file.path).unwrap();
+            assert!(verify_proof(&file.hash, &proof, &root, index));
+            assert!(!verify_proof("x", &proof, &root, index));
+        }
+
+        assert!(generate_proof(&files, "missing.ts").is_err());
+    }
+
+    #[test]
+    fn test_proof_with_directories() {
+        let src = vec!["src/a.ts".to_string(), "src/b.ts".to_string()];
         let files = vec![
-            MerkleNode {
-                hash: "a".to_string(),
-                path: "file1.ts".to_string(),
-                is_file: true,
-                children: vec![],
-            },
-            MerkleNode {
-                hash: "b".to_string(),
-                path: "file2.ts".to_string(),
-                is_file: true,
-                children: vec![],
-            },
+            leaf("src/a.ts", "a"),
+            leaf("src/b.ts", "b"),
+            dir("src", &src),
+            leaf("README.md", "readme"),
+            leaf("package.json", "package"),
         ];
 
+        // Top-level files prove against the hierarchical root
         let root = build_tree(&files).unwrap();
-        assert!(!root.is_empty());
+        for (index, path) in [(1, "README.md"), (2, "package.json")] {
+            let hash = &files.iter().find(|f| f.path == path).unwrap().hash;
+            let proof = generate_proof(&files, path).unwrap();
+            assert!(verify_proof(hash, &proof, &root, index));
+        }
+
+        assert!(generate_proof(&files, "src/a.ts").is_err());
+        assert!(generate_proof(&files, "src").is_err());
     }
 
     #[test]
-    fn test_incremental_diff() {
+    fn test_directory_diff_short_circuits() {
+        let tree = |changed: &str| {
+            let dirs: Vec<String> = (0..100).map(|d| format!("src/dir{}", d)).collect();
+            let mut nodes = vec![dir("src", &dirs)];
+            for path in &dirs {
+                let files: Vec<String> =
+                    (0..10).map(|f| format!("{}/file{}.ts", path, f)).collect();
+                for file in &files {
+                    let hash = if file == changed {
+                        "changed"
+                    } else {
+                        file.as_str()
+                    };
+                    nodes.push(leaf(file, hash));
+                }
+                nodes.push(dir(path, &files));
+            }
+            nodes
+        };
+
+        let old = tree("");
+        let new = tree("src/dir42/file7.ts");
+        assert_eq!(new.len(), 1101);
+        assert_ne!(build_tree(&old).unwrap(), build_tree(&new).unwrap());
+
+        let diff = compute_incremental_diff(&old, &new);
+        assert_eq!(
+            diff.modified,
+            vec!["src", "src/dir42", "src/dir42/file7.ts"]
+        );
+        assert!(diff.added.is_empty());
+        assert!(diff.deleted.is_empty());
+
+        assert_eq!(build_tree(&old).unwrap(), build_tree(&tree("")).unwrap());
+        assert!(compute_incremental_diff(&old, &tree(""))
+            .modified
+            .is_empty());
+    }
+
+    #[test]
+    fn test_compute_diff_skips_unchanged_directories() {
+        let tree = |hash: &str| {
+            vec![
+                dir("lib", &["lib/a.ts".to_string(), "lib/b.ts".to_string()]),
+                leaf("lib/a.ts", "1"),
+                leaf("lib/b.ts", "2"),
+                dir("src", &["src/c.ts".to_string()]),
+                leaf("src/c.ts", hash),
+            ]
+        };
+        let (old, new) = (tree("3"), tree("4"));
+        let old_root = build_tree(&old).unwrap();
+
+        let diff = compute_diff(&old_root, &new, Some(&old)).unwrap();
+        assert_eq!(diff.modified, vec!["src", "src/c.ts"]);
+        assert_eq!(
+            compute_diff(&old_root, &old, None).unwrap().total_changed,
+            0
+        );
+        assert_eq!(
+            compute_diff(&old_root, &new, None).unwrap().modified.len(),
+            3
+        );
+
+        // The walk stops at `lib`, whose hash did not change
+        let index: HashMap<&str, &MerkleNode> = new.iter().map(|f| (f.path.as_str(), f)).collect();
+        let mut visited = HashSet::new();
+        for node in top_level(&new) {
+            walk_changes(
+                &node.path,
+                &index,
+                &node_hashes(&new),
+                &node_hashes(&old),
+                &mut visited,
+                &mut |_, _| {},
+            );
+        }
+        assert!(visited.contains("lib") && visited.contains("src/c.ts"));
+        assert!(!visited.contains("lib/a.ts") && !visited.contains("lib/b.ts"));
+    }
+
+    #[test]
+    fn test_rename_detection() {
         let old = vec![
-            MerkleNode {
-                hash: "a".to_string(),
-                path: "file1.ts".to_string(),
-                is_file: true,
-                children: vec![],
-            },
+            leaf("src/foo.ts", "1"),
+            leaf("a/x.ts", "same"),
+            leaf("b/x.ts", "same"),
         ];
-
         let new = vec![
-            MerkleNode {
-                hash: "a".to_string(),
-                path: "file1.ts".to_string(),
-                is_file: true,
-                children: vec![],
-            },
-            MerkleNode {
-                hash: "b".to_string(),
-                path: "file2.ts".to_string(),
-                is_file: true,
-                children: vec![],
-            },
+            leaf("src/bar.ts", "1"),
+            leaf("b/y.ts", "same"),
+            leaf("a/y.ts", "same"),
+            leaf("c.ts", "2"),
         ];
 
+        let diff = compute_incremental_diff(&old, &new);
+        let rename = |from: &str, to: &str| RenamedFile {
+            old_path: from.to_string(),
+            new_path: to.to_string(),
+        };
+        assert_eq!(diff.renamed.len(), 3);
+        assert!(diff.renamed.contains(&rename("src/foo.ts", "src/bar.ts")));
+        assert!(diff.renamed.contains(&rename("a/x.ts", "a/y.ts")));
+        assert!(diff.renamed.contains(&rename("b/x.ts", "b/y.ts")));
+        assert_eq!(diff.added, vec!["c.ts"]);
+        assert!(diff.deleted.is_empty());
+        assert_eq!(diff.total_changed, 4);
+    }
+
+    #[test]
+    fn test_merkle_state_roundtrip() {
+        let mut state = MerkleState::default();
+        let diff = state.update(&[leaf("a.ts", "1"), leaf("b.ts", "2")]);
+        assert_eq!(diff.added.len(), 2);
+
+        let path = std::env::temp_dir().join(format!("titan-merkle-{}.json", std::process::id()));
+        state.save(&path).unwrap();
+        let mut loaded = MerkleState::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.root().unwrap(), state.root().unwrap());
+
+        let diff = loaded.update(&[leaf("a.ts", "1"), leaf("b.ts", "3"), leaf("c.ts", "4")]);
+        assert_eq!(diff.modified, vec!["b.ts"]);
+        assert_eq!(diff.added, vec!["c.ts"]);
+        assert!(diff.deleted.is_empty());
+    }
+
+    #[test]
+    fn test_incremental_diff() {
+        let old = vec![leaf("file1.ts", "a")];
+        let new = vec![leaf("file1.ts", "a"), leaf("file2.ts", "b")];
+
         let diff = compute_incremental_diff(&old, &new);
         assert_eq!(diff.added.len(), 1);
         assert_eq!(diff.added[0], "file2.ts");
         assert!(diff.modified.is_empty());
         assert!(diff.deleted.is_empty());
+        assert_eq!(diff.total_changed, 1);
+        assert_eq!(diff.total_files, 2);
+        assert_eq!(diff.change_ratio, 0.5);
+    }
+
+    #[test]
+    fn test_metadata_not_hashed() {
+        let plain = vec![leaf("file1.ts", "a")];
+        let mut metadata = HashMap::new();
+        metadata.insert("mtime".to_string(), "1700000000".to_string());
+        let tagged = vec![MerkleNode {
+            metadata: Some(metadata),
+            ..plain[0].clone()
+        }];
+
+        assert_eq!(build_tree(&plain).unwrap(), build_tree(&tagged).unwrap());
+        assert_eq!(compute_incremental_diff(&plain, &tagged).total_changed, 0);
     }
 }
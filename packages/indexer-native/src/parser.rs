@@ -1,279 +1,4655 @@
 //! Tree-sitter based code parser
 
-use crate::{CodeChunk, Symbol};
+use crate::{docblock, pragmas, tokens};
+use crate::{
+    CodeChunk, CodeComment, Import, LanguageInfo, ParseFileResult, ParseOptions, ParseProfile,
+    ParseResult, Symbol, SymbolParameter, SymbolRef, SyntaxError, TreeEdit,
+};
 use anyhow::Result;
-use sha2::{Sha256, Digest};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Parser failures that callers can tell apart
+#[derive(Debug, thiserror::Error)]
+pub enum ParserError {
+    #[error("Unsupported language: {0}")]
+    UnsupportedLanguage(String),
+    #[error("Failed to parse file{}: {reason}", path_suffix(.file_path))]
+    ParseFailed { file_path: String, reason: String },
+    #[error("Failed to load grammar: {0}")]
+    GrammarInitFailed(String),
+}
+
+/// ` path` to follow "file", or nothing for content without a path
+fn path_suffix(file_path: &str) -> String {
+    if file_path.is_empty() {
+        String::new()
+    } else {
+        format!(" {}", file_path)
+    }
+}
+
+/// Error for a parse that produced no tree
+fn parse_failed(file_path: &str) -> ParserError {
+    ParserError::ParseFailed {
+        file_path: file_path.to_string(),
+        reason: "parsing was cancelled or timed out".to_string(),
+    }
+}
 
 /// Parse a file and extract code chunks
 pub fn parse_file(file_path: &str, content: &str, language: &str) -> Result<Vec<CodeChunk>> {
-    let parser = get_parser(language)?;
-    let tree = parser.parse(content, None)
-        .ok_or_else(|| anyhow::anyhow!("Failed to parse file"))?;
+    Ok(parse_file_with_options(file_path, content, language, &ParseOptions::default())?.chunks)
+}
 
-    let root = tree.root_node();
-    let mut chunks = Vec::new();
+/// Parse a file once and extract whatever the options ask for
+///
+/// Chunks are extracted unless `extract_chunks` is false; symbols, imports
+/// and comments only when their flag is set. Everything not requested is
+/// left empty. All requested kinds are collected in one walk over the tree.
+pub fn parse_file_with_options(
+    file_path: &str,
+    content: &str,
+    language: &str,
+    options: &ParseOptions,
+) -> Result<ParseResult> {
+    let language = resolve_language(language, file_path, content)?;
+    if options.exclude_test_files.unwrap_or(false) && is_test_file(file_path, content, language) {
+        return Ok(ParseResult {
+            file_path: file_path.to_string(),
+            chunks: vec![],
+            symbols: vec![],
+            imports: vec![],
+            comments: vec![],
+            error: None,
+        });
+    }
 
-    // Extract top-level declarations as chunks
-    extract_chunks_recursive(&root, content, file_path, language, &mut chunks);
+    let mut parser = get_parser(language)?;
+    let tree = parser
+        .parse(content, None)
+        .ok_or_else(|| parse_failed(file_path))?;
 
-    Ok(chunks)
-}
+    let extract_chunks = options.extract_chunks.unwrap_or(true);
+    let extract_strings = extract_chunks && options.extract_strings.unwrap_or(false);
+    let mut collected = Collected {
+        chunks: extract_chunks.then(Vec::new),
+        strings: extract_strings.then(Vec::new),
+        symbols: options.extract_symbols.unwrap_or(false).then(Vec::new),
+        imports: options.extract_imports.unwrap_or(false).then(Vec::new),
+        comments: options.extract_comments.unwrap_or(false).then(Vec::new),
+    };
+    collect_recursive(
+        &tree.root_node(),
+        content,
+        file_path,
+        language,
+        &mut collected,
+        false,
+    );
 
-/// Extract symbols from a file
-pub fn extract_symbols(file_path: &str, content: &str, language: &str) -> Result<Vec<Symbol>> {
-    let parser = get_parser(language)?;
-    let tree = parser.parse(content, None)
-        .ok_or_else(|| anyhow::anyhow!("Failed to parse file"))?;
+    Ok(ParseResult {
+        file_path: file_path.to_string(),
+        chunks: finish_chunks(&tree, content, file_path, language, &mut collected),
+        symbols: collected.symbols.unwrap_or_default(),
+        imports: collected.imports.unwrap_or_default(),
+        comments: collected.comments.unwrap_or_default(),
+        error: None,
+    })
+}
 
+/// Parse a file into chunks and symbols, reporting syntax errors too
+///
+/// Tree-sitter recovers from syntax errors, so the chunks and symbols of
+/// the valid parts are still returned. Each error or missing node is one
+/// `SyntaxError`; nodes inside an error are not reported again.
+pub fn parse_file_with_errors(
+    file_path: &str,
+    content: &str,
+    language: &str,
+) -> Result<ParseFileResult> {
+    let language = resolve_language(language, file_path, content)?;
+    let mut parser = get_parser(language)?;
+    let tree = parser
+        .parse(content, None)
+        .ok_or_else(|| parse_failed(file_path))?;
     let root = tree.root_node();
-    let mut symbols = Vec::new();
 
-    extract_symbols_recursive(&root, content, file_path, &mut symbols);
+    let mut collected = Collected {
+        chunks: Some(Vec::new()),
+        symbols: Some(Vec::new()),
+        ..Default::default()
+    };
+    collect_recursive(&root, content, file_path, language, &mut collected, false);
+    let mut syntax_errors = Vec::new();
+    collect_syntax_errors(&root, content, &mut syntax_errors);
 
-    Ok(symbols)
+    Ok(ParseFileResult {
+        chunks: finish_chunks(&tree, content, file_path, language, &mut collected),
+        symbols: collected.symbols.unwrap_or_default(),
+        syntax_errors,
+    })
 }
 
-/// Get parser for a language
-fn get_parser(language: &str) -> Result<tree_sitter::Parser> {
-    let mut parser = tree_sitter::Parser::new();
-
-    let lang = match language {
-        "typescript" | "tsx" => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
-        "javascript" | "jsx" => tree_sitter_javascript::LANGUAGE,
-        "python" => tree_sitter_python::LANGUAGE,
-        "rust" => tree_sitter_rust::LANGUAGE,
-        "go" => tree_sitter_go::LANGUAGE,
-        _ => return Err(anyhow::anyhow!("Unsupported language: {}", language)),
+/// Report error and missing nodes under `node`
+fn collect_syntax_errors(node: &tree_sitter::Node, content: &str, errors: &mut Vec<SyntaxError>) {
+    let message = if node.is_missing() {
+        Some(format!("Missing `{}`", node.kind()))
+    } else if node.is_error() {
+        let text = content[node.byte_range()]
+            .lines()
+            .next()
+            .unwrap_or("")
+            .trim();
+        Some(match text.char_indices().nth(30) {
+            Some((end, _)) => format!("Unexpected `{}...`", &text[..end]),
+            None if text.is_empty() => "Syntax error".to_string(),
+            None => format!("Unexpected `{}`", text),
+        })
+    } else {
+        None
     };
 
-    parser.set_language(&lang.into())?;
-    Ok(parser)
+    if let Some(message) = message {
+        let start = node.start_position();
+        errors.push(SyntaxError {
+            message,
+            start_line: start.row as u32 + 1,
+            start_column: start.column as u32,
+        });
+        return;
+    }
+
+    if node.has_error() {
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            collect_syntax_errors(&child, content, errors);
+        }
+    }
 }
 
-/// Recursively extract chunks from AST
-fn extract_chunks_recursive(
-    node: &tree_sitter::Node,
-    content: &str,
+/// Time the phases of parsing a file and count its nodes by kind
+///
+/// Phases are timed separately: building the syntax tree, extracting
+/// chunks and extracting symbols. `total_ms` also covers grammar setup and
+/// the node count.
+pub fn profile_parse_file(file_path: &str, content: &str, language: &str) -> Result<ParseProfile> {
+    let ms = |start: Instant| start.elapsed().as_secs_f64() * 1000.0;
+    let started = Instant::now();
+
+    let language = resolve_language(language, file_path, content)?;
+    let mut parser = get_parser(language)?;
+    let phase = Instant::now();
+    let tree = parser
+        .parse(content, None)
+        .ok_or_else(|| parse_failed(file_path))?;
+    let tree_construction_ms = ms(phase);
+
+    let phase = Instant::now();
+    let chunks = chunks_from_tree(&tree, content, file_path, language, false);
+    let chunk_extraction_ms = ms(phase);
+    drop(chunks);
+
+    let phase = Instant::now();
+    let symbols = symbols_from_tree(&tree, content, file_path, language);
+    let symbol_extraction_ms = ms(phase);
+    drop(symbols);
+
+    let node_count_by_kind = count_node_kinds(&tree);
+
+    Ok(ParseProfile {
+        total_ms: ms(started),
+        tree_construction_ms,
+        chunk_extraction_ms,
+        symbol_extraction_ms,
+        node_count_by_kind,
+    })
+}
+
+/// Number of nodes of each kind in a tree, named or not
+fn count_node_kinds(tree: &tree_sitter::Tree) -> HashMap<String, u32> {
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    let mut cursor = tree.walk();
+    'walk: loop {
+        *counts.entry(cursor.node().kind().to_string()).or_default() += 1;
+        if cursor.goto_first_child() {
+            continue;
+        }
+        while !cursor.goto_next_sibling() {
+            if !cursor.goto_parent() {
+                break 'walk;
+            }
+        }
+    }
+    counts
+}
+
+/// Parse a file with an already configured parser
+pub fn parse_file_with(
+    parser: &mut tree_sitter::Parser,
     file_path: &str,
+    content: &str,
     language: &str,
-    chunks: &mut Vec<CodeChunk>,
-) {
-    let kind = node.kind();
+) -> Result<Vec<CodeChunk>> {
+    let tree = parser
+        .parse(content, None)
+        .ok_or_else(|| parse_failed(file_path))?;
 
-    // Check if this node is a chunk-worthy declaration
-    if is_chunk_node(kind, language) {
-        let start_line = node.start_position().row as u32 + 1;
-        let end_line = node.end_position().row as u32 + 1;
-        let node_content = &content[node.byte_range()];
+    Ok(chunks_from_tree(&tree, content, file_path, language, false))
+}
 
-        // Extract symbols from this node
-        let symbols = extract_node_symbols(node, content);
+/// Parse tree held in memory together with its source
+pub struct ParsedTree {
+    pub tree: tree_sitter::Tree,
+    pub content: String,
+    pub language: String,
+}
 
-        // Generate chunk ID and hash
-        let hash = hash_content(node_content);
-        let id = format!("{}:{}:{}", file_path, start_line, &hash[..8]);
+/// Parse a file and keep its tree for incremental re-parsing
+pub fn parse_tree(content: &str, language: &str) -> Result<ParsedTree> {
+    let mut parser = get_parser(language)?;
+    let tree = parser
+        .parse(content, None)
+        .ok_or_else(|| parse_failed(""))?;
 
-        chunks.push(CodeChunk {
-            id,
-            file_path: file_path.to_string(),
-            content: node_content.to_string(),
-            start_line,
-            end_line,
-            chunk_type: map_node_kind(kind, language),
-            language: language.to_string(),
-            symbols,
-            hash,
-        });
+    Ok(ParsedTree {
+        tree,
+        content: content.to_string(),
+        language: language.to_string(),
+    })
+}
+
+/// Re-parse an edited file from its previous tree
+///
+/// The edits describing how `parsed.content` became `new_content` are
+/// applied to a copy of the held tree, which then seeds the parse so
+/// Tree-sitter only rebuilds the changed subtrees. On success `parsed` is
+/// left holding the new tree and content, ready for the next edit; on
+/// failure it is unchanged.
+pub fn parse_file_incremental(
+    file_path: &str,
+    parsed: &mut ParsedTree,
+    new_content: &str,
+    edits: &[TreeEdit],
+) -> Result<Vec<CodeChunk>> {
+    let mut parser = get_parser(&parsed.language)?;
+
+    // Edit a copy so a failed parse leaves the held tree and content in step
+    let mut old_tree = parsed.tree.clone();
+    for edit in edits {
+        old_tree.edit(&to_input_edit(edit));
     }
 
-    // Recurse into children
-    let mut cursor = node.walk();
-    for child in node.children(&mut cursor) {
-        extract_chunks_recursive(&child, content, file_path, language, chunks);
+    let tree = parser
+        .parse(new_content, Some(&old_tree))
+        .ok_or_else(|| parse_failed(file_path))?;
+    let chunks = chunks_from_tree(&tree, new_content, file_path, &parsed.language, false);
+
+    parsed.tree = tree;
+    parsed.content = new_content.to_string();
+    Ok(chunks)
+}
+
+/// Extract chunks from a held tree, re-parsing from it if the file changed
+///
+/// Matching content is chunked straight from the tree. Otherwise the change
+/// is taken to be the span between the longest common prefix and suffix of
+/// the old and new content, and the file is re-parsed incrementally as by
+/// `parse_file_incremental`, leaving `parsed` with the new tree.
+pub fn parse_file_from_tree(
+    file_path: &str,
+    content: &str,
+    parsed: &mut ParsedTree,
+) -> Result<Vec<CodeChunk>> {
+    if content == parsed.content {
+        return Ok(chunks_from_tree(
+            &parsed.tree,
+            content,
+            file_path,
+            &parsed.language,
+            false,
+        ));
+    }
+
+    let edit = content_edit(&parsed.content, content);
+    parse_file_incremental(file_path, parsed, content, &[edit])
+}
+
+/// The single edit turning `old` into `new`, keeping their common ends
+fn content_edit(old: &str, new: &str) -> TreeEdit {
+    let mut prefix = old
+        .bytes()
+        .zip(new.bytes())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(prefix) || !new.is_char_boundary(prefix) {
+        prefix -= 1;
+    }
+    let mut suffix = old[prefix..]
+        .bytes()
+        .rev()
+        .zip(new[prefix..].bytes().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    while !old.is_char_boundary(old.len() - suffix) || !new.is_char_boundary(new.len() - suffix) {
+        suffix -= 1;
+    }
+
+    let point = |text: &str, byte: usize| {
+        let before = &text[..byte];
+        let row = before.matches('\n').count();
+        let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1);
+        (row as u32, column as u32)
+    };
+    let (start_row, start_column) = point(old, prefix);
+    let (old_end_row, old_end_column) = point(old, old.len() - suffix);
+    let (new_end_row, new_end_column) = point(new, new.len() - suffix);
+    TreeEdit {
+        start_byte: prefix as u32,
+        old_end_byte: (old.len() - suffix) as u32,
+        new_end_byte: (new.len() - suffix) as u32,
+        start_row,
+        start_column,
+        old_end_row,
+        old_end_column,
+        new_end_row,
+        new_end_column,
+    }
+}
+
+/// Convert a NAPI edit into a Tree-sitter edit
+fn to_input_edit(edit: &TreeEdit) -> tree_sitter::InputEdit {
+    tree_sitter::InputEdit {
+        start_byte: edit.start_byte as usize,
+        old_end_byte: edit.old_end_byte as usize,
+        new_end_byte: edit.new_end_byte as usize,
+        start_position: tree_sitter::Point::new(
+            edit.start_row as usize,
+            edit.start_column as usize,
+        ),
+        old_end_position: tree_sitter::Point::new(
+            edit.old_end_row as usize,
+            edit.old_end_column as usize,
+        ),
+        new_end_position: tree_sitter::Point::new(
+            edit.new_end_row as usize,
+            edit.new_end_column as usize,
+        ),
     }
 }
 
-/// Recursively extract symbols from AST
-fn extract_symbols_recursive(
+/// Extract code chunks from a parsed tree
+fn chunks_from_tree(
+    tree: &tree_sitter::Tree,
+    content: &str,
+    file_path: &str,
+    language: &str,
+    extract_strings: bool,
+) -> Vec<CodeChunk> {
+    let mut collected = Collected {
+        chunks: Some(Vec::new()),
+        strings: extract_strings.then(Vec::new),
+        ..Default::default()
+    };
+    collect_recursive(
+        &tree.root_node(),
+        content,
+        file_path,
+        language,
+        &mut collected,
+        false,
+    );
+    finish_chunks(tree, content, file_path, language, &mut collected)
+}
+
+/// Symbols of a whole tree
+fn symbols_from_tree(
+    tree: &tree_sitter::Tree,
+    content: &str,
+    file_path: &str,
+    language: &str,
+) -> Vec<Symbol> {
+    let mut collected = Collected {
+        symbols: Some(Vec::new()),
+        ..Default::default()
+    };
+    collect_recursive(
+        &tree.root_node(),
+        content,
+        file_path,
+        language,
+        &mut collected,
+        false,
+    );
+    collected.symbols.unwrap_or_default()
+}
+
+/// What one walk over a tree collects; kinds left `None` are skipped
+#[derive(Default)]
+struct Collected {
+    chunks: Option<Vec<CodeChunk>>,
+    strings: Option<Vec<CodeChunk>>,
+    symbols: Option<Vec<Symbol>>,
+    imports: Option<Vec<Import>>,
+    comments: Option<Vec<CodeComment>>,
+}
+
+/// Walk the tree once, collecting every requested kind at each node
+///
+/// `in_comment` is set below a node already taken as a whole comment, so
+/// its parts are not collected as comments again.
+fn collect_recursive(
     node: &tree_sitter::Node,
     content: &str,
     file_path: &str,
-    symbols: &mut Vec<Symbol>,
+    language: &str,
+    collected: &mut Collected,
+    mut in_comment: bool,
 ) {
-    let kind = node.kind();
-
-    // Check if this node defines a symbol
-    if let Some(symbol) = extract_symbol(node, content, file_path) {
-        symbols.push(symbol);
+    if let Some(chunks) = &mut collected.chunks {
+        chunks.extend(declaration_chunk(node, content, file_path, language));
+    }
+    if let Some(strings) = &mut collected.strings {
+        strings.extend(string_chunk(node, content, file_path, language));
+    }
+    if let Some(symbols) = &mut collected.symbols {
+        symbols.extend(extract_symbol(node, content, file_path, language));
+    }
+    if let Some(imports) = &mut collected.imports {
+        extract_node_imports(node, content, language, imports);
+    }
+    if let (Some(comments), false) = (&mut collected.comments, in_comment) {
+        in_comment = extract_node_comments(node, content, language, comments);
     }
 
     // Recurse into children
     let mut cursor = node.walk();
     for child in node.children(&mut cursor) {
-        extract_symbols_recursive(&child, content, file_path, symbols);
+        collect_recursive(&child, content, file_path, language, collected, in_comment);
     }
 }
 
-/// Check if node kind should be a chunk
-fn is_chunk_node(kind: &str, language: &str) -> bool {
-    match language {
-        "typescript" | "javascript" => matches!(
-            kind,
-            "function_declaration"
-                | "method_definition"
-                | "class_declaration"
-                | "interface_declaration"
-                | "type_alias_declaration"
-                | "enum_declaration"
-                | "export_statement"
-        ),
-        "python" => matches!(kind, "function_definition" | "class_definition"),
-        "rust" => matches!(
-            kind,
-            "function_item"
-                | "impl_item"
-                | "struct_item"
-                | "enum_item"
-                | "trait_item"
-                | "mod_item"
-        ),
-        "go" => matches!(kind, "function_declaration" | "method_declaration" | "type_declaration"),
-        _ => false,
+/// Declaration chunks followed by string chunks, without those touching
+/// regions excluded by pragmas
+fn finish_chunks(
+    tree: &tree_sitter::Tree,
+    content: &str,
+    file_path: &str,
+    language: &str,
+    collected: &mut Collected,
+) -> Vec<CodeChunk> {
+    let mut chunks = collected.chunks.take().unwrap_or_default();
+    chunks.extend(collected.strings.take().unwrap_or_default());
+
+    // Drop nodes touching regions excluded by `@titan-ignore`
+    if pragmas::has_pragmas(content) {
+        let ignored = pragmas::ignored_lines(file_path, content, Some(tree));
+        chunks.retain(|chunk| !pragmas::overlaps(&ignored, chunk.start_line, chunk.end_line));
     }
-}
 
-/// Map node kind to chunk type
-fn map_node_kind(kind: &str, language: &str) -> String {
-    match kind {
-        "function_declaration" | "function_definition" | "function_item" => "function",
-        "method_definition" | "method_declaration" => "method",
-        "class_declaration" | "class_definition" => "class",
-        "interface_declaration" | "trait_item" => "interface",
-        "struct_item" | "type_declaration" => "type",
-        "enum_declaration" | "enum_item" => "enum",
-        "impl_item" => "impl",
-        "mod_item" => "module",
-        _ => "other",
+    if let Some(version) = language_version(content, language) {
+        for chunk in &mut chunks {
+            chunk.language_version = Some(version.clone());
+        }
     }
-    .to_string()
+
+    chunks
 }
 
-/// Extract symbols from a node
-fn extract_node_symbols(node: &tree_sitter::Node, content: &str) -> Vec<String> {
-    let mut symbols = Vec::new();
+/// Extract symbols from a file
+///
+/// With `include_references`, each symbol also lists every use of its name
+/// elsewhere in the same file.
+pub fn extract_symbols(
+    file_path: &str,
+    content: &str,
+    language: &str,
+    include_references: bool,
+) -> Result<Vec<Symbol>> {
+    let language = resolve_language(language, file_path, content)?;
+    let mut parser = get_parser(language)?;
+    extract_symbols_with(
+        &mut parser,
+        file_path,
+        content,
+        language,
+        include_references,
+    )
+}
 
-    // Find identifier children
-    let mut cursor = node.walk();
-    for child in node.children(&mut cursor) {
-        if child.kind() == "identifier" || child.kind() == "type_identifier" {
-            if let Ok(name) = child.utf8_text(content.as_bytes()) {
-                symbols.push(name.to_string());
-            }
+/// Extract symbols with an already configured parser
+pub fn extract_symbols_with(
+    parser: &mut tree_sitter::Parser,
+    file_path: &str,
+    content: &str,
+    language: &str,
+    include_references: bool,
+) -> Result<Vec<Symbol>> {
+    let tree = parser
+        .parse(content, None)
+        .ok_or_else(|| parse_failed(file_path))?;
+
+    let root = tree.root_node();
+    let mut symbols = symbols_from_tree(&tree, content, file_path, language);
+
+    if include_references {
+        for symbol in &mut symbols {
+            symbol.references = Some(Vec::new());
         }
+        collect_references(&root, content, file_path, &mut symbols);
     }
 
-    symbols
+    Ok(symbols)
 }
 
-/// Extract a symbol from a node
-fn extract_symbol(node: &tree_sitter::Node, content: &str, file_path: &str) -> Option<Symbol> {
-    let kind = node.kind();
+/// Find the innermost symbol enclosing a 1-based line and column
+///
+/// Columns are byte offsets within the line, as in Tree-sitter.
+pub fn get_symbol_at_position(
+    content: &str,
+    language: &str,
+    line: u32,
+    column: u32,
+) -> Result<Option<Symbol>> {
+    let mut parser = get_parser(language)?;
+    let tree = parser
+        .parse(content, None)
+        .ok_or_else(|| parse_failed(""))?;
 
-    // Only process declaration nodes
-    if !is_symbol_node(kind) {
-        return None;
+    let point = tree_sitter::Point::new(
+        line.saturating_sub(1) as usize,
+        column.saturating_sub(1) as usize,
+    );
+    let root = tree.root_node();
+    let mut current = root.descendant_for_point_range(point, point);
+
+    // Walk up to the nearest symbol-defining ancestor
+    while let Some(node) = current {
+        if let Some(symbol) = extract_symbol(&node, content, "", language) {
+            return Ok(Some(symbol));
+        }
+        current = node.parent();
     }
 
-    // Find the name
-    let name = find_name_child(node, content)?;
+    Ok(None)
+}
 
-    // Check if exported
-    let exported = is_exported(node, content);
+/// Extract import statements from a file
+pub fn extract_imports(file_path: &str, content: &str, language: &str) -> Result<Vec<Import>> {
+    let mut parser = get_parser(language)?;
+    let tree = parser
+        .parse(content, None)
+        .ok_or_else(|| parse_failed(file_path))?;
 
-    // Get signature (first line)
-    let start = node.start_position();
-    let end_of_sig = content[node.byte_range()]
-        .find('{')
-        .or_else(|| content[node.byte_range()].find(':'))
-        .unwrap_or(content[node.byte_range()].len().min(100));
-    let signature = content[node.start_byte()..node.start_byte() + end_of_sig]
-        .trim()
-        .to_string();
+    let mut collected = Collected {
+        imports: Some(Vec::new()),
+        ..Default::default()
+    };
+    collect_recursive(
+        &tree.root_node(),
+        content,
+        file_path,
+        language,
+        &mut collected,
+        false,
+    );
 
-    Some(Symbol {
-        name,
-        kind: map_symbol_kind(kind),
-        file_path: file_path.to_string(),
-        start_line: start.row as u32 + 1,
-        end_line: node.end_position().row as u32 + 1,
-        signature: Some(signature),
-        exported,
-    })
+    Ok(collected.imports.unwrap_or_default())
 }
 
-/// Check if node kind defines a symbol
-fn is_symbol_node(kind: &str) -> bool {
-    matches!(
-        kind,
-        "function_declaration"
-            | "function_definition"
-            | "function_item"
-            | "method_definition"
-            | "method_declaration"
-            | "class_declaration"
-            | "class_definition"
-            | "interface_declaration"
-            | "struct_item"
-            | "enum_declaration"
-            | "enum_item"
-            | "type_alias_declaration"
-            | "trait_item"
-    )
+/// Extract comments and docstrings from a file
+pub fn extract_comments(
+    file_path: &str,
+    content: &str,
+    language: &str,
+) -> Result<Vec<CodeComment>> {
+    let mut parser = get_parser(language)?;
+    let tree = parser
+        .parse(content, None)
+        .ok_or_else(|| parse_failed(file_path))?;
+
+    let mut collected = Collected {
+        comments: Some(Vec::new()),
+        ..Default::default()
+    };
+    collect_recursive(
+        &tree.root_node(),
+        content,
+        file_path,
+        language,
+        &mut collected,
+        false,
+    );
+
+    Ok(collected.comments.unwrap_or_default())
 }
 
-/// Map node kind to symbol kind
-fn map_symbol_kind(kind: &str) -> String {
-    match kind {
-        "function_declaration" | "function_definition" | "function_item" => "function",
-        "method_definition" | "method_declaration" => "method",
-        "class_declaration" | "class_definition" => "class",
-        "interface_declaration" | "trait_item" => "interface",
-        "struct_item" => "class",
-        "enum_declaration" | "enum_item" => "enum",
-        "type_alias_declaration" => "type",
-        _ => "variable",
+/// Extract module-level variables and constants from a file
+pub fn extract_variables(file_path: &str, content: &str, language: &str) -> Result<Vec<Symbol>> {
+    let mut parser = get_parser(language)?;
+    let tree = parser
+        .parse(content, None)
+        .ok_or_else(|| parse_failed(file_path))?;
+
+    let root = tree.root_node();
+    let mut variables = Vec::new();
+
+    let mut cursor = root.walk();
+    for child in root.named_children(&mut cursor) {
+        extract_variable_node(&child, content, file_path, language, &mut variables);
     }
-    .to_string()
+
+    Ok(variables)
 }
 
-/// Find name child of a node
-fn find_name_child(node: &tree_sitter::Node, content: &str) -> Option<String> {
+/// Dump the syntax tree as pretty-printed JSON for debugging
+///
+/// Every node has `kind`, `start_position`, `end_position`, `is_named` and
+/// `children`; only leaf nodes carry their `text`.
+pub fn get_ast_json(content: &str, language: &str) -> Result<String> {
+    let mut parser = get_parser(language)?;
+    let tree = parser
+        .parse(content, None)
+        .ok_or_else(|| parse_failed(""))?;
+
+    Ok(serde_json::to_string_pretty(&node_json(
+        &tree.root_node(),
+        content,
+    ))?)
+}
+
+/// JSON form of a node and its subtree
+fn node_json(node: &tree_sitter::Node, content: &str) -> serde_json::Value {
+    let position = |p: tree_sitter::Point| serde_json::json!({ "row": p.row, "column": p.column });
+
     let mut cursor = node.walk();
-    for child in node.children(&mut cursor) {
-        if child.kind() == "identifier" || child.kind() == "type_identifier" {
-            return child.utf8_text(content.as_bytes()).ok().map(|s| s.to_string());
-        }
+    let children: Vec<serde_json::Value> = node
+        .children(&mut cursor)
+        .map(|child| node_json(&child, content))
+        .collect();
+
+    let mut value = serde_json::json!({
+        "kind": node.kind(),
+        "start_position": position(node.start_position()),
+        "end_position": position(node.end_position()),
+        "is_named": node.is_named(),
+        "children": children,
+    });
+    if node.child_count() == 0 {
+        value["text"] = serde_json::Value::String(node_text(node, content));
     }
-    None
+    value
 }
 
-/// Check if a node is exported
-fn is_exported(node: &tree_sitter::Node, content: &str) -> bool {
-    // Check parent for export
-    if let Some(parent) = node.parent() {
-        if parent.kind() == "export_statement" {
-            return true;
-        }
+/// Infer a supported language from a file extension
+pub fn language_from_path(file_path: &str) -> Option<&'static str> {
+    let ext = std::path::Path::new(file_path).extension()?.to_str()?;
+    match ext {
+        "ts" | "mts" | "cts" => Some("typescript"),
+        "tsx" => Some("tsx"),
+        "js" | "mjs" | "cjs" => Some("javascript"),
+        "jsx" => Some("jsx"),
+        "py" => Some("python"),
+        "rs" => Some("rust"),
+        "go" => Some("go"),
+        "php" => Some("php"),
+        "sql" => Some("sql"),
+        "lua" => Some("lua"),
+        "scala" | "sc" => Some("scala"),
+        "ex" | "exs" => Some("elixir"),
+        "hs" => Some("haskell"),
+        _ => None,
     }
+}
 
-    // Check for pub keyword (Rust)
-    let text = &content[node.byte_range()];
-    text.starts_with("pub ") || text.starts_with("export ")
+/// Infer a supported language from a file extension, or failing that from
+/// a `#!` line or the look of the content
+pub fn detect_language(file_path: &str, content_prefix: Option<&str>) -> Option<&'static str> {
+    language_from_path(file_path).or_else(|| {
+        content_prefix.and_then(|c| language_from_content(c, DEFAULT_CONTENT_CONFIDENCE))
+    })
 }
 
-/// Hash content
-fn hash_content(content: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(content.as_bytes());
-    format!("{:x}", hasher.finalize())
+/// Use `language`, or the detected language when it is `"auto"`
+pub fn resolve_language<'a>(language: &'a str, file_path: &str, content: &str) -> Result<&'a str> {
+    if language != "auto" {
+        return Ok(language);
+    }
+    detect_language(file_path, Some(content))
+        .ok_or_else(|| anyhow::anyhow!("Could not detect language: {}", file_path))
+}
+
+/// Infer a language from the interpreter named in a shebang line
+///
+/// `#!/usr/bin/env` is looked through, and version suffixes such as
+/// `python3.12` are ignored.
+fn language_from_shebang(content: &str) -> Option<&'static str> {
+    let line = content.lines().next()?.strip_prefix("#!")?;
+    let mut args = line.split_whitespace();
+    let mut program = args.next()?.rsplit('/').next()?;
+    if program == "env" {
+        program = args
+            .find(|arg| !arg.starts_with('-') && !arg.contains('='))?
+            .rsplit('/')
+            .next()?;
+    }
+
+    match program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.') {
+        "python" | "pypy" => Some("python"),
+        "node" | "nodejs" | "bun" => Some("javascript"),
+        "deno" | "ts-node" | "tsx" => Some("typescript"),
+        "rust-script" => Some("rust"),
+        "php" => Some("php"),
+        "lua" | "luajit" => Some("lua"),
+        "elixir" => Some("elixir"),
+        "runhaskell" | "runghc" => Some("haskell"),
+        _ => None,
+    }
+}
+
+/// Confidence `detect_language` needs to go by content alone
+pub const DEFAULT_CONTENT_CONFIDENCE: f64 = 0.7;
+
+/// Lines of content examined for signals
+const CONTENT_SCAN_LINES: usize = 200;
+
+/// Where a content signal must appear on a line, ignoring indentation
+enum Signal {
+    LineStart(&'static str),
+    Anywhere(&'static str),
+    /// Line start, ignoring case, for SQL keywords
+    Keyword(&'static str),
+}
+
+/// Content signals with the weight each adds to its language's score
+///
+/// `"javascript"` signals also count towards `"typescript"`, which is
+/// written the same way plus types.
+const CONTENT_SIGNALS: &[(&str, Signal, f64)] = &[
+    ("go", Signal::LineStart("package "), 0.6),
+    ("go", Signal::LineStart("func "), 0.4),
+    ("go", Signal::LineStart("import ("), 0.3),
+    ("go", Signal::Anywhere(" := "), 0.2),
+    ("go", Signal::Anywhere("fmt."), 0.2),
+    ("rust", Signal::Anywhere("#[derive("), 0.6),
+    ("rust", Signal::LineStart("use std::"), 0.5),
+    ("rust", Signal::LineStart("fn "), 0.4),
+    ("rust", Signal::LineStart("pub fn "), 0.4),
+    ("rust", Signal::LineStart("impl "), 0.3),
+    ("rust", Signal::Anywhere("let mut "), 0.3),
+    ("rust", Signal::Anywhere("println!("), 0.3),
+    ("python", Signal::Anywhere("__name__ == "), 0.8),
+    ("python", Signal::LineStart("def "), 0.4),
+    ("python", Signal::LineStart("elif "), 0.4),
+    ("python", Signal::LineStart("from "), 0.2),
+    ("python", Signal::Anywhere("self."), 0.2),
+    ("python", Signal::Anywhere("print("), 0.2),
+    ("javascript", Signal::Anywhere("use strict"), 0.4),
+    ("javascript", Signal::Anywhere("module.exports"), 0.5),
+    ("javascript", Signal::Anywhere("require("), 0.4),
+    ("javascript", Signal::Anywhere(" from '"), 0.3),
+    ("javascript", Signal::Anywhere(" from \""), 0.3),
+    ("javascript", Signal::Anywhere("console.log("), 0.3),
+    ("javascript", Signal::LineStart("function "), 0.3),
+    ("javascript", Signal::LineStart("export default "), 0.3),
+    ("javascript", Signal::LineStart("const "), 0.2),
+    ("javascript", Signal::Anywhere("=> "), 0.2),
+    ("typescript", Signal::LineStart("interface "), 0.4),
+    ("typescript", Signal::LineStart("export interface "), 0.4),
+    ("typescript", Signal::LineStart("export type "), 0.4),
+    ("typescript", Signal::LineStart("type "), 0.3),
+    ("typescript", Signal::Anywhere(": string"), 0.4),
+    ("typescript", Signal::Anywhere(": number"), 0.4),
+    ("typescript", Signal::Anywhere(": boolean"), 0.4),
+    ("php", Signal::LineStart("<?php"), 1.0),
+    ("php", Signal::Anywhere("$this->"), 0.5),
+    ("php", Signal::LineStart("echo "), 0.2),
+    ("sql", Signal::Keyword("CREATE TABLE "), 0.8),
+    ("sql", Signal::Keyword("CREATE INDEX "), 0.6),
+    ("sql", Signal::Keyword("CREATE VIEW "), 0.6),
+    ("sql", Signal::Keyword("ALTER TABLE "), 0.5),
+    ("sql", Signal::Keyword("INSERT INTO "), 0.5),
+    ("sql", Signal::Keyword("SELECT "), 0.3),
+];
+
+/// Markup that makes JavaScript or TypeScript JSX
+const JSX_SIGNALS: &[&str] = &["import React", "className=", "/>"];
+
+/// Infer a supported language from the content alone
+///
+/// A shebang decides outright. Otherwise each language scores the weights
+/// of its `CONTENT_SIGNALS` found, capped at 1, and the confidence is the
+/// best score less half the runner-up's, so that mixed signals count for
+/// less. Returns the best language if that reaches `min_confidence`, as
+/// `"jsx"` or `"tsx"` when the code also contains JSX.
+pub fn language_from_content(content: &str, min_confidence: f64) -> Option<&'static str> {
+    if let Some(language) = language_from_shebang(content) {
+        return Some(language);
+    }
+
+    let lines: Vec<&str> = content
+        .lines()
+        .take(CONTENT_SCAN_LINES)
+        .map(str::trim_start)
+        .collect();
+    let found = |signal: &Signal| {
+        lines.iter().any(|line| match signal {
+            Signal::LineStart(prefix) => line.starts_with(prefix),
+            Signal::Anywhere(text) => line.contains(text),
+            Signal::Keyword(keyword) => line
+                .get(..keyword.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(keyword)),
+        })
+    };
+
+    let mut scores: Vec<(&'static str, f64)> = Vec::new();
+    for (language, signal, weight) in CONTENT_SIGNALS {
+        if !found(signal) {
+            continue;
+        }
+        match scores.iter_mut().find(|(l, _)| l == language) {
+            Some((_, score)) => *score += weight,
+            None => scores.push((language, *weight)),
+        }
+    }
+
+    // TypeScript-only signals make the JavaScript ones TypeScript's too
+    if let Some(js) = scores.iter().position(|(l, _)| *l == "javascript") {
+        if let Some(ts) = scores.iter().position(|(l, _)| *l == "typescript") {
+            scores[ts].1 += scores[js].1;
+            scores.remove(js);
+        }
+    }
+
+    for (_, score) in &mut scores {
+        *score = score.min(1.0);
+    }
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let (best, score) = *scores.first()?;
+    let runner_up = scores.get(1).map_or(0.0, |(_, s)| *s);
+    if score - runner_up / 2.0 < min_confidence {
+        return None;
+    }
+
+    let jsx = JSX_SIGNALS
+        .iter()
+        .any(|text| lines.iter().any(|line| line.contains(text)));
+    Some(match best {
+        "javascript" if jsx => "jsx",
+        "typescript" if jsx => "tsx",
+        other => other,
+    })
+}
+
+/// Bytes of content examined by `is_test_file`
+const TEST_MARKER_BYTES: usize = 200;
+
+/// Path words that mark test files and directories
+const TEST_PATH_WORDS: &[&str] = &["test", "tests", "spec", "specs", "__tests__"];
+
+/// Guess whether a file holds tests rather than production code
+///
+/// A file is a test if a directory or file name has a word, separated by
+/// `.`, `_` or `-`, in `TEST_PATH_WORDS` (`__tests__/a.js`, `a.test.ts`,
+/// `test_a.py`, `a_test.go`), or if the first `TEST_MARKER_BYTES` bytes of
+/// content hold a test marker for the language: `describe(`, `it(` or
+/// `test(` in JavaScript and TypeScript, `#[test]` in Rust, `def test_` in
+/// Python and `func Test` in Go. Unknown languages are checked for all of
+/// them.
+pub fn is_test_file(file_path: &str, content_prefix: &str, language: &str) -> bool {
+    let test_path = file_path.split(['/', '\\']).any(|segment| {
+        TEST_PATH_WORDS.contains(&segment)
+            || segment
+                .split(['.', '_', '-'])
+                .any(|word| TEST_PATH_WORDS.contains(&word))
+    });
+    if test_path {
+        return true;
+    }
+
+    let mut end = content_prefix.len().min(TEST_MARKER_BYTES);
+    while !content_prefix.is_char_boundary(end) {
+        end -= 1;
+    }
+    let prefix = &content_prefix[..end];
+
+    let markers: &[&str] = match language {
+        "typescript" | "tsx" | "javascript" | "jsx" => &["describe(", "it(", "test("],
+        "rust" => &["#[test]"],
+        "python" => &["def test_"],
+        "go" => &["func Test"],
+        _ => &[
+            "describe(",
+            "it(",
+            "test(",
+            "#[test]",
+            "def test_",
+            "func Test",
+        ],
+    };
+    markers.iter().any(|marker| {
+        prefix.match_indices(marker).any(|(at, _)| {
+            // `it(` and the like must not end a longer name such as `submit(`
+            !prefix[..at]
+                .ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '$' || c == '.')
+        })
+    })
+}
+
+/// Lines at the top of a file searched for version pragmas
+const VERSION_PRAGMA_LINES: usize = 10;
+
+/// Version and dialect pragmas in the first `VERSION_PRAGMA_LINES` lines
+///
+/// JavaScript and TypeScript report the `"use strict"`, `"use client"` and
+/// `"use server"` directives and `// @ts-check` or `// @ts-nocheck`; Python
+/// a `python2` or `python3` in a comment or shebang and each
+/// `from __future__ import` line; Rust the `#![feature(...)]`,
+/// `#![no_std]` and `#![allow(unstable_features)]` attributes. Pragmas are
+/// joined with `, ` in file order, `None` without any.
+pub fn language_version(content: &str, language: &str) -> Option<String> {
+    let mut found: Vec<String> = Vec::new();
+    for line in content.lines().take(VERSION_PRAGMA_LINES) {
+        let line = line.trim();
+        let pragma = match language {
+            "typescript" | "tsx" | "javascript" | "jsx" => js_version_pragma(line),
+            "python" => python_version_pragma(line),
+            "rust" => rust_version_pragma(line),
+            _ => None,
+        };
+        if let Some(pragma) = pragma.filter(|p| !found.contains(p)) {
+            found.push(pragma);
+        }
+    }
+    (!found.is_empty()).then(|| found.join(", "))
+}
+
+/// Directive or `@ts-` comment on a JavaScript or TypeScript line
+fn js_version_pragma(line: &str) -> Option<String> {
+    if let Some(comment) = line.strip_prefix("//") {
+        let word = comment.split_whitespace().next()?;
+        return matches!(word, "@ts-check" | "@ts-nocheck").then(|| word.to_string());
+    }
+    let directive = line.trim_end_matches(';').trim_end();
+    let quote = directive
+        .chars()
+        .next()
+        .filter(|c| matches!(c, '"' | '\''))?;
+    let inner = directive.strip_prefix(quote)?.strip_suffix(quote)?;
+    matches!(inner, "use strict" | "use client" | "use server").then(|| format!("\"{}\"", inner))
+}
+
+/// Interpreter version comment or `__future__` import on a Python line
+fn python_version_pragma(line: &str) -> Option<String> {
+    if line.starts_with("from __future__ import") {
+        return Some(
+            line.split('#')
+                .next()
+                .unwrap_or(line)
+                .trim_end()
+                .to_string(),
+        );
+    }
+    let comment = line.strip_prefix('#')?;
+    comment
+        .split(|c: char| !c.is_alphanumeric())
+        .find(|word| matches!(*word, "python2" | "python3"))
+        .map(str::to_string)
+}
+
+/// Feature or `no_std` inner attribute on a Rust line
+fn rust_version_pragma(line: &str) -> Option<String> {
+    let attribute = line.strip_prefix("#![")?.strip_suffix(']')?;
+    let attribute = attribute.trim();
+    let versioned = attribute.starts_with("feature(")
+        || attribute == "no_std"
+        || attribute.replace(' ', "") == "allow(unstable_features)";
+    versioned.then(|| line.to_string())
+}
+
+/// Get parser for a language
+pub fn get_parser(language: &str) -> Result<tree_sitter::Parser> {
+    let mut parser = tree_sitter::Parser::new();
+    set_parser_language(&mut parser, language)?;
+    Ok(parser)
+}
+
+/// Switch an existing parser to another language
+pub fn set_parser_language(parser: &mut tree_sitter::Parser, language: &str) -> Result<()> {
+    let lang =
+        grammar(language).ok_or_else(|| ParserError::UnsupportedLanguage(language.to_string()))?;
+
+    parser
+        .set_language(&lang)
+        .map_err(|e| ParserError::GrammarInitFailed(format!("{}: {}", language, e)))?;
+    Ok(())
+}
+
+/// Tree-sitter grammar for a language name or alias
+fn grammar(language: &str) -> Option<tree_sitter::Language> {
+    let lang = match language {
+        "typescript" => tree_sitter_typescript::LANGUAGE_TYPESCRIPT,
+        "tsx" => tree_sitter_typescript::LANGUAGE_TSX,
+        "javascript" | "jsx" => tree_sitter_javascript::LANGUAGE,
+        "python" => tree_sitter_python::LANGUAGE,
+        "rust" => tree_sitter_rust::LANGUAGE,
+        "go" => tree_sitter_go::LANGUAGE,
+        "php" => tree_sitter_php::LANGUAGE_PHP,
+        "sql" => tree_sitter_sequel::LANGUAGE,
+        "lua" => tree_sitter_lua::LANGUAGE,
+        "scala" => tree_sitter_scala::LANGUAGE,
+        "elixir" => tree_sitter_elixir::LANGUAGE,
+        "haskell" => tree_sitter_haskell::LANGUAGE,
+        _ => return None,
+    };
+    Some(lang.into())
+}
+
+/// Supported languages with their aliases
+const LANGUAGES: &[(&str, &[&str])] = &[
+    ("typescript", &["tsx"]),
+    ("javascript", &["jsx"]),
+    ("python", &[]),
+    ("rust", &[]),
+    ("go", &[]),
+    ("php", &[]),
+    ("sql", &[]),
+    ("lua", &[]),
+    ("scala", &[]),
+    ("elixir", &[]),
+    ("haskell", &[]),
+];
+
+/// Source exercising the main constructs of each language, for `node_kinds`
+const NODE_KIND_SNIPPETS: &[(&str, &str)] = &[
+    (
+        "javascript",
+        r#"import fs, { readFile as read } from "fs";
+import * as path from "path";
+export default class Store extends Base {
+  static #count = 0;
+  constructor(items = []) { super(); this.items = [...items]; }
+  get size() { return this.items.length; }
+  async *load(...names) {
+    for await (const name of names) yield await read(name);
+  }
+}
+export function sum({ a, b: [c] }, d = 1) {
+  let total = a ?? c;
+  for (let i = 0; i < d; i++) { total += i ** 2; }
+  for (const key in this) continue;
+  while (total > 10 && !done) total--;
+  do { total >>= 1; } while (total);
+  if (typeof total === "number") { return total; } else if (d) { throw new Error(`bad ${d}`); }
+  switch (d) { case 1: break; default: label: { break label; } }
+  try { fs.sync?.(); } catch (e) { console.error(e); } finally { delete this.x; }
+  const f = async (x) => x ? x : /re+/gi.test(String(x));
+  return [f, { total, [d]: function () {}, ...rest }, void 0, null, true];
+}
+"#,
+    ),
+    (
+        "typescript",
+        r#"import type { Options } from "./options";
+export interface Shape<T extends object = {}> { readonly id: string; area?(): number; [key: string]: unknown; }
+export type Pair<K, V> = [K, V] | { key: K; value: V } & Partial<Options>;
+type Mapped<T> = { [P in keyof T]-?: T[P] extends Function ? never : T[P] };
+export enum Color { Red = 1, Green }
+declare module "ext" { export const x: number; }
+namespace Geometry { export const pi = 3.14; }
+@sealed
+abstract class Base<T> implements Shape<T> {
+  private static count: number = 0;
+  constructor(protected readonly id: string, public name?: string) {}
+  abstract area(): number;
+  get label(): string { return `${this.name}`; }
+}
+export async function load<T>(items: T[], fn: (item: T) => Promise<void>): Promise<T | undefined> {
+  for (const item of items) { await fn(item as T); }
+  const first = items[0]!;
+  let maybe: unknown = first satisfies unknown;
+  if (maybe instanceof Base) { return <T>maybe; }
+  try { throw new Error("x"); } catch { return undefined; }
+}
+export const handler = async <T,>(x: T): Promise<T> => x;
+"#,
+    ),
+    (
+        "python",
+        r#"from __future__ import annotations
+import os, sys as system
+from typing import Generic, TypeVar
+
+T = TypeVar("T")
+
+@dataclass(frozen=True)
+class Store(Generic[T], metaclass=Meta):
+    '''Docstring.'''
+    count: int = 0
+
+    def __init__(self, *args, key=None, **kwargs) -> None:
+        super().__init__()
+        self.items = [x ** 2 for x in args if x] + list({k: v for k, v in kwargs.items()})
+
+    async def load(self, name: str, /, *, strict: bool = False) -> list[T]:
+        async with open(name) as f:
+            return [line async for line in f]
+
+    @property
+    def size(self):
+        return len(self.items) if self.items else 0
+
+def gen(n):
+    global total
+    for i in range(n):
+        if i % 2 == 0 and not i:
+            continue
+        elif i > 10 or i is None:
+            break
+        else:
+            yield i
+    while n:
+        n -= 1
+    try:
+        assert n == 0, "nonzero"
+        raise ValueError(f"bad {n!r}")
+    except (ValueError, TypeError) as e:
+        pass
+    finally:
+        del n
+    with open("a") as a, open("b") as b:
+        print(a, b, sep="", file=system.stderr)
+    match n:
+        case [x, *rest]:
+            return lambda y: y + x
+        case {"key": value} | Point(x=0):
+            return {1, 2}, (3,), b"bytes", None, True, ...
+"#,
+    ),
+    (
+        "rust",
+        r#"//! Crate doc
+#![allow(dead_code)]
+use std::collections::{HashMap, HashSet as Set};
+use std::fmt::{self, Display};
+
+/// A point
+#[derive(Debug, Clone)]
+pub struct Point<T: Copy = i32> { pub x: T, y: T }
+pub struct Unit;
+struct Tuple(u8, pub String);
+pub enum Shape<'a> { Circle { r: f64 }, Named(&'a str), Empty }
+pub union Bits { i: u32, f: f32 }
+pub trait Area: Display { const SIDES: usize; type Output; fn area(&self) -> f64; }
+type Map<K> = HashMap<K, Vec<u8>>;
+const MAX: usize = 10;
+static mut COUNT: u32 = 0;
+mod inner { pub(crate) fn f() {} }
+macro_rules! square { ($x:expr) => { $x * $x }; }
+extern "C" { fn abs(x: i32) -> i32; }
+
+impl<T: Copy + Into<f64>> Point<T> where T: Default {
+    pub const fn origin(x: T) -> Self { Self { x, y: x } }
+    pub async unsafe fn load<'a, F>(&'a mut self, f: F) -> Result<Option<&'a T>, Box<dyn std::error::Error>>
+    where
+        F: Fn(&T) -> bool,
+    {
+        let mut total = 0u64;
+        for (i, value) in [1, 2, 3].iter().enumerate() { total += *value as u64 * i as u64; }
+        while total > 100 { total /= 2; }
+        let closure = move |x: i32| -> i32 { x + 1 };
+        loop { if total == 0 { break; } else { total -= 1; continue; } }
+        let Point { x, .. } = self;
+        match total { 0 => {}, 1..=9 | 20 if f(x) => return Ok(None), n @ _ => { let _ = n; } }
+        let s = format!("{} {}", r"raw", 'c');
+        let v: Vec<_> = vec![square!(2); 3];
+        if let Some(first) = v.first() { let _ = (first, &v[..], closure(1), s, b'b', b"bytes"); }
+        unsafe { COUNT += 1; }
+        async { 1 }.await;
+        Ok(Some(&self.y))
+    }
+}
+"#,
+    ),
+    (
+        "go",
+        r#"package main
+
+import (
+	"fmt"
+	str "strings"
+)
+
+const (
+	Max = iota
+	Min
+)
+
+var total, count int = 0, 1
+
+type Shape interface {
+	Area() float64
+	~int | ~float64
+}
+
+type Set[T comparable] map[T]struct{}
+
+type Point struct {
+	X, Y int `json:"x"`
+	*Base
+}
+
+func (p *Point) Area() float64 { return float64(p.X * p.Y) }
+
+func Map[K comparable, V any](m map[K]V, f func(V) V) []V {
+	out := make([]V, 0, len(m))
+	for _, v := range m {
+		out = append(out, f(v))
+	}
+	for i := 0; i < 10; i++ {
+		if i%2 == 0 && i != 4 {
+			continue
+		} else if i > 8 {
+			break
+		}
+	}
+	ch := make(chan int, 1)
+	go func() { ch <- 1 }()
+	select {
+	case v := <-ch:
+		fmt.Println(v)
+	default:
+	}
+	switch x := len(out); {
+	case x > 1:
+		fallthrough
+	default:
+	}
+	var s interface{} = str.ToUpper("a")
+	switch s.(type) {
+	case string:
+	}
+	defer func() { recover() }()
+	label:
+	goto label
+	return out[1:2:3]
+}
+"#,
+    ),
+    (
+        "php",
+        r#"<?php
+namespace App\Models;
+
+use App\Contracts\{Shape, Named as N};
+
+interface Area { public function area(): float; }
+trait Greets { public function greet(string $name = "x"): string { return "Hi {$name}"; } }
+enum Suit: string { case Hearts = 'H'; }
+
+#[Attribute]
+abstract class Base implements Area {
+    use Greets;
+    public const MAX = 10;
+    private static ?int $count = null;
+    public function __construct(protected readonly array $items = []) {}
+    abstract public function area(): float;
+}
+
+final class Store extends Base {
+    public function area(): float {
+        $total = 0;
+        foreach ($this->items as $key => $value) { $total += $value ** 2; }
+        for ($i = 0; $i < 10; $i++) { if ($i % 2 === 0 && !$done) { continue; } elseif ($i > 8) { break; } else { $total--; } }
+        while ($total > 100) { $total /= 2; }
+        try { throw new \Exception('bad'); } catch (\Exception | \Error $e) { echo $e->getMessage(); } finally { unset($x); }
+        $f = fn($x) => $x ?? static::MAX;
+        $g = function ($y) use ($total) { return match ($y) { 1, 2 => 'a', default => null }; };
+        switch ($total) { case 1: break; default: }
+        return (float) self::$count + count([1, 'a' => true]);
+    }
+}
+
+function helper(int ...$nums): ?int { global $config; return $nums[0] ?? null; }
+"#,
+    ),
+    (
+        "sql",
+        r#"CREATE TABLE users (
+    id INTEGER PRIMARY KEY,
+    email VARCHAR(255) NOT NULL UNIQUE,
+    team_id INT REFERENCES teams(id) ON DELETE CASCADE,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX idx_users_email ON users (email);
+CREATE VIEW active AS SELECT id, email FROM users WHERE created_at > '2024-01-01';
+ALTER TABLE users ADD COLUMN name TEXT;
+INSERT INTO users (id, email) VALUES (1, 'a@b.c');
+UPDATE users SET email = 'x' WHERE id = 1;
+DELETE FROM users WHERE id IN (SELECT id FROM teams);
+WITH recent AS (SELECT * FROM users ORDER BY created_at DESC LIMIT 10)
+SELECT u.email, COUNT(*) AS n, CASE WHEN n > 1 THEN 'many' ELSE 'one' END
+FROM recent u LEFT JOIN teams t ON t.id = u.team_id
+GROUP BY u.email HAVING COUNT(*) > 1 ORDER BY n;
+DROP TABLE IF EXISTS old;
+"#,
+    ),
+    (
+        "lua",
+        r#"-- Module
+local M = {}
+local json = require("json")
+
+local function clamp(x, lo, hi)
+  if x < lo then return lo elseif x > hi then return hi else return x end
+end
+
+function M.new(name, ...)
+  local self = setmetatable({ name = name, items = { ... }, [1] = true }, { __index = M })
+  return self
+end
+
+function M:each(fn)
+  for i, item in ipairs(self.items) do fn(i, item) end
+  for i = 1, #self.items, 2 do
+    while i > 0 do i = i - 1 end
+    repeat i = i + 1 until i >= 3
+  end
+  goto done
+  ::done::
+  local ok, err = pcall(function() error("x") end)
+  return not ok and err or nil, [[long string]], 0x1F, 1.5e3, self.name .. "!"
+end
+
+M.handler = function(event) return event ~= nil end
+return M
+"#,
+    ),
+    (
+        "scala",
+        r#"package jobs
+
+import org.apache.spark.sql.{DataFrame, functions => F}
+import scala.util._
+
+/** Job */
+@deprecated("old", "1.0")
+object SalesJob extends App with Logging {
+  val threshold: Int = 10
+  private var count = 0L
+  lazy val names = List("a", "b").map(_.toUpperCase)
+  type Key = (String, Int)
+
+  def transform[T <: AnyRef](df: DataFrame, minQty: Int = 1)(implicit spark: SparkSession): DataFrame =
+    df.filter(F.col("qty") >= minQty).groupBy("region").agg(F.sum("amount").as("total"))
+
+  private def describe(x: Any): String = x match {
+    case n: Int if n > 0 => s"positive $n"
+    case (a, b) => "pair"
+    case Some(value) | None => "option"
+    case _ => throw new IllegalArgumentException("bad")
+  }
+
+  def loop(xs: Seq[Int]): Unit = {
+    for (x <- xs if x % 2 == 0; y = x * 2) yield y
+    while (count < 10) { count += 1 }
+    if (xs.isEmpty) println("empty") else xs.foreach { x => println(x) }
+    try { xs.head } catch { case e: Exception => () } finally { count = 0 }
+    val f: Int => Int = (x: Int) => x + 1
+    do { count -= 1 } while (count > 0)
+    return
+  }
+}
+
+sealed trait Step[-A, +B] { def apply(a: A): B }
+final case class Row(id: Long, name: String = "") extends Step[Int, Row] {
+  override def apply(a: Int): Row = copy(id = a.toLong)
+}
+abstract class Base(protected val spark: SparkSession) { this: Logging =>
+  def run(): Unit
+}
+enum Color { case Red, Green }
+given Ordering[Row] = Ordering.by(_.id)
+extension (r: Row) def label: String = r.name
+"#,
+    ),
+    (
+        "elixir",
+        r#"defmodule MyApp.Accounts do
+  @moduledoc """
+  Accounts context.
+  """
+  use Ecto.Schema
+  import Ecto.Query, only: [from: 2]
+  alias MyApp.{Repo, User}
+
+  @behaviour MyApp.Store
+  @type id :: integer() | String.t()
+  defstruct [:name, age: 0]
+
+  @doc "Fetch a user."
+  @spec get_user(id()) :: {:ok, User.t()} | {:error, :not_found}
+  def get_user(id) when is_integer(id) and id > 0, do: Repo.get(User, id)
+
+  def list_users(opts \\ []) do
+    query = from u in User, where: u.age > ^Keyword.get(opts, :min_age, 0), select: u
+    query |> Repo.all() |> Enum.map(&normalize/1)
+  end
+
+  defp normalize(%User{name: name} = user) do
+    case String.trim(name) do
+      "" -> {:error, :blank}
+      trimmed when byte_size(trimmed) > 1 -> %{user | name: trimmed}
+      _ -> cond do
+        true -> raise ArgumentError, message: "bad #{name}"
+      end
+    end
+  end
+
+  defmacro debug(expr), do: quote(do: IO.inspect(unquote(expr)))
+
+  def stream(path) do
+    with {:ok, file} <- File.open(path),
+         data when is_binary(data) <- IO.read(file, :all) do
+      for <<byte <- data>>, byte != ?\n, into: "", do: <<byte>>
+    else
+      {:error, reason} -> {:error, reason}
+    end
+  rescue
+    e in RuntimeError -> {:error, e}
+  end
+
+  def handle(fun), do: fn x -> fun.(x) end
+  def sigils, do: {~r/ab+c/i, ~w(a b c)a, 1.5e3, 0x1F, 'chars', [1 | [2]], %{a: 1}, {1, 2}}
+end
+"#,
+    ),
+    (
+        "haskell",
+        r#"{-# LANGUAGE ScopedTypeVariables #-}
+module Data.Shape
+  ( Shape(..)
+  , area
+  , module Data.Maybe
+  ) where
+
+import qualified Data.Map as M
+import Data.Maybe (fromMaybe, mapMaybe)
+
+-- | A point
+type Point = (Double, Double)
+
+data Shape = Circle Double | Rect { width :: Double, height :: !Double }
+  deriving (Show, Eq)
+
+newtype Name = Name { unName :: String }
+
+class Describe a where
+  describe :: a -> String
+  describe _ = "thing"
+
+instance Describe Shape where
+  describe (Circle _) = "circle"
+  describe Rect {} = "rect"
+
+area :: Shape -> Double
+area (Circle r) = pi * r ^ 2
+area (Rect w h)
+  | w < 0 || h < 0 = 0
+  | otherwise = w * h
+
+scale :: forall a. Num a => a -> [a] -> [a]
+scale k = map (* k) . filter (/= 0)
+
+lookupAll :: Ord k => M.Map k v -> [k] -> [v]
+lookupAll m = mapMaybe (`M.lookup` m)
+
+main :: IO ()
+main = do
+  let shapes = [Circle 1.5, Rect 2 3]
+      total = sum (map area shapes)
+  name <- getLine
+  if null name then putStrLn "none" else print (fromMaybe 0 (Just total))
+  mapM_ (\s -> putStrLn (describe s)) shapes
+  print [x * 2 | x <- [1 .. 10 :: Int], even x]
+  print $ case shapes of
+    (s : _) -> Just s
+    [] -> Nothing
+  where
+    helper = 'c'
+"#,
+    ),
+];
+
+/// JSX added to the JavaScript and TypeScript snippets for `jsx` and `tsx`
+const JSX_SNIPPET: &str = r#"export const App = ({ items }) => (
+  <>
+    <List className="list" {...items} onClick={() => go(1)}>
+      {items.map(item => <Item key={item.id} />)}
+      text &amp; more
+    </List>
+  </>
+);
+"#;
+
+/// Node kinds, named and anonymous, found in a representative snippet
+///
+/// A developer aid: the snippet covers each language's common
+/// declarations, statements and expressions, so kinds only rarer syntax
+/// produces may be missing. Sorted alphabetically.
+pub fn node_kinds(language: &str) -> Result<Vec<String>> {
+    let (base, jsx) = match language {
+        "tsx" => ("typescript", true),
+        "jsx" => ("javascript", true),
+        other => (other, false),
+    };
+    let mut parser = get_parser(language)?;
+    let snippet = NODE_KIND_SNIPPETS
+        .iter()
+        .find(|(name, _)| *name == base)
+        .map(|(_, snippet)| *snippet)
+        .ok_or_else(|| ParserError::UnsupportedLanguage(language.to_string()))?;
+    let source = if jsx {
+        format!("{}{}", snippet, JSX_SNIPPET)
+    } else {
+        snippet.to_string()
+    };
+    let tree = parser
+        .parse(&source, None)
+        .ok_or_else(|| parse_failed(language))?;
+
+    let mut kinds: Vec<String> = count_node_kinds(&tree).into_keys().collect();
+    kinds.sort();
+    Ok(kinds)
+}
+
+/// Names of the supported languages, without aliases
+pub fn supported_language_names() -> Vec<String> {
+    LANGUAGES.iter().map(|(name, _)| name.to_string()).collect()
+}
+
+/// Describe each supported language from its linked grammar
+///
+/// `grammar_version` is the grammar's Tree-sitter language (ABI) version.
+/// Chunking support and symbol kinds are derived from the node types the
+/// grammar actually defines.
+pub fn supported_languages() -> Vec<LanguageInfo> {
+    LANGUAGES
+        .iter()
+        .filter_map(|(name, aliases)| {
+            let lang = grammar(name)?;
+            let node_kinds: Vec<&str> = (0..lang.node_kind_count() as u16)
+                .filter(|&id| lang.node_kind_is_named(id))
+                .filter_map(|id| lang.node_kind_for_id(id))
+                .collect();
+
+            let mut supported_symbol_kinds: Vec<String> = Vec::new();
+            for kind in SYMBOL_NODE_KINDS.iter().filter(|k| node_kinds.contains(k)) {
+                let symbol_kind = map_symbol_kind(kind);
+                if !supported_symbol_kinds.contains(&symbol_kind) {
+                    supported_symbol_kinds.push(symbol_kind);
+                }
+            }
+
+            Some(LanguageInfo {
+                name: name.to_string(),
+                aliases: aliases.iter().map(|a| a.to_string()).collect(),
+                grammar_version: lang.version().to_string(),
+                ast_chunking: node_kinds.iter().any(|kind| is_chunk_node(kind, name)),
+                supported_symbol_kinds,
+            })
+        })
+        .collect()
+}
+
+/// Chunk for a node that is a chunk-worthy declaration
+fn declaration_chunk(
+    node: &tree_sitter::Node,
+    content: &str,
+    file_path: &str,
+    language: &str,
+) -> Option<CodeChunk> {
+    let kind = node.kind();
+    let jsx = matches!(language, "tsx" | "jsx");
+    let component = jsx && is_jsx_component(node);
+    let elixir = if language == "elixir" {
+        elixir_definition(node, content)
+    } else {
+        None
+    };
+    let haskell = if language == "haskell" {
+        haskell_declaration(node)
+    } else {
+        None
+    };
+    let haskell_span = haskell.and_then(|_| haskell_chunk_span(node, content));
+
+    // Check if this node is a chunk-worthy declaration; of nested JSX only
+    // the outermost element is a chunk, and of the Elixir and Haskell
+    // kinds only definitions and declarations are
+    let chunk_node = match language {
+        "elixir" => elixir.is_some(),
+        "haskell" => haskell_span.is_some(),
+        _ => {
+            component
+                || (is_chunk_node(kind, language)
+                    && !(kind == "jsx_element" && is_inside_jsx(node)))
+        }
+    };
+    if !chunk_node {
+        return None;
+    }
+    let (first, last) = haskell_span.unwrap_or((*node, *node));
+    let start_line = first.start_position().row as u32 + 1;
+    let end_line = last.end_position().row as u32 + 1;
+    let node_content = &content[first.start_byte()..last.end_byte()];
+    let mut complexity = count_branches(node);
+    let mut clause = *node;
+    while clause.id() != last.id() {
+        let Some(next) = clause.next_named_sibling() else {
+            break;
+        };
+        complexity += count_branches(&next);
+        clause = next;
+    }
+
+    // Extract symbols from this node
+    let symbols = if component && kind != "function_declaration" {
+        declarator_names(node, content)
+    } else if let Some(definition) = &elixir {
+        vec![definition.name.clone()]
+    } else if haskell.is_some() {
+        find_name_child(node, content, language)
+            .into_iter()
+            .collect()
+    } else if kind == "jsx_element" {
+        jsx_tag_name(node, content).into_iter().collect()
+    } else {
+        extract_node_symbols(node, content, language)
+    };
+    let chunk_type = if component {
+        "jsx_component".to_string()
+    } else if let Some(definition) = &elixir {
+        definition.kind().to_string()
+    } else if let Some(haskell_kind) = haskell {
+        haskell_kind.to_string()
+    } else if kind == "jsx_element" && jsx_tag_name(node, content).is_none() {
+        "jsx_fragment".to_string()
+    } else {
+        map_node_kind(kind, language)
+    };
+
+    // Generate chunk ID and hash
+    let hash = hash_content(node_content);
+    let id = generate_chunk_id(node_content, symbols.first().map(String::as_str), language);
+
+    Some(CodeChunk {
+        id,
+        file_path: file_path.to_string(),
+        content: node_content.to_string(),
+        start_line,
+        end_line,
+        start_byte: first.start_byte() as u32,
+        end_byte: last.end_byte() as u32,
+        complexity,
+        token_count: tokens::estimate_tokens(node_content),
+        header_lines: 0,
+        original_start_line: None,
+        language_version: None,
+        chunk_type,
+        language: language.to_string(),
+        symbols,
+        hash,
+    })
+}
+
+/// Check if a node defines a React component: a function declaration, or
+/// a `const`/`let`/`var` bound to a function, that returns JSX
+fn is_jsx_component(node: &tree_sitter::Node) -> bool {
+    match node.kind() {
+        "function_declaration" => returns_jsx(node),
+        "lexical_declaration" | "variable_declaration" => {
+            let mut cursor = node.walk();
+            let component = node.named_children(&mut cursor).any(|declarator| {
+                declarator
+                    .child_by_field_name("value")
+                    .is_some_and(|value| {
+                        matches!(value.kind(), "arrow_function" | "function_expression")
+                            && returns_jsx(&value)
+                    })
+            });
+            component
+        }
+        _ => false,
+    }
+}
+
+/// Check if a function's body is JSX or returns JSX
+fn returns_jsx(function: &tree_sitter::Node) -> bool {
+    let Some(body) = function.child_by_field_name("body") else {
+        return false;
+    };
+    if body.kind() != "statement_block" {
+        return is_jsx_expression(&body);
+    }
+
+    // Look for `return <...>` outside any nested function
+    let mut stack = vec![body];
+    while let Some(node) = stack.pop() {
+        if node.kind() == "return_statement" {
+            if node
+                .named_child(0)
+                .is_some_and(|value| is_jsx_expression(&value))
+            {
+                return true;
+            }
+            continue;
+        }
+        let mut cursor = node.walk();
+        stack.extend(node.named_children(&mut cursor).filter(|child| {
+            !matches!(
+                child.kind(),
+                "function_declaration"
+                    | "function_expression"
+                    | "arrow_function"
+                    | "method_definition"
+                    | "class_declaration"
+            )
+        }));
+    }
+    false
+}
+
+/// Check if an expression is JSX, looking through parentheses and `?:`
+fn is_jsx_expression(node: &tree_sitter::Node) -> bool {
+    match node.kind() {
+        "jsx_element" | "jsx_self_closing_element" => true,
+        "parenthesized_expression" => node
+            .named_child(0)
+            .is_some_and(|inner| is_jsx_expression(&inner)),
+        "ternary_expression" => ["consequence", "alternative"].iter().any(|field| {
+            node.child_by_field_name(field)
+                .is_some_and(|branch| is_jsx_expression(&branch))
+        }),
+        _ => false,
+    }
+}
+
+/// Check if a node is nested in a JSX element
+fn is_inside_jsx(node: &tree_sitter::Node) -> bool {
+    let mut parent = node.parent();
+    while let Some(p) = parent {
+        if p.kind() == "jsx_element" {
+            return true;
+        }
+        parent = p.parent();
+    }
+    false
+}
+
+/// Tag name of a JSX element, or `None` for a `<>` fragment
+fn jsx_tag_name(node: &tree_sitter::Node, content: &str) -> Option<String> {
+    let name = node
+        .child_by_field_name("open_tag")?
+        .child_by_field_name("name")?;
+    Some(content[name.byte_range()].to_string())
+}
+
+/// Names bound by a `const`/`let`/`var` declaration
+fn declarator_names(node: &tree_sitter::Node, content: &str) -> Vec<String> {
+    let mut cursor = node.walk();
+    let names = node
+        .named_children(&mut cursor)
+        .filter_map(|declarator| declarator.child_by_field_name("name"))
+        .filter(|name| name.kind() == "identifier")
+        .map(|name| content[name.byte_range()].to_string())
+        .collect();
+    names
+}
+
+/// Count branching nodes in a subtree
+///
+/// Approximates McCabe cyclomatic complexity; boolean operators and
+/// individual switch cases are not counted.
+fn count_branches(node: &tree_sitter::Node) -> u32 {
+    let mut count = 0;
+    let mut cursor = node.walk();
+
+    loop {
+        if is_branch_node(cursor.node().kind()) {
+            count += 1;
+        }
+        if cursor.goto_first_child() {
+            continue;
+        }
+        loop {
+            if cursor.node() == *node {
+                return count;
+            }
+            if cursor.goto_next_sibling() {
+                break;
+            }
+            if !cursor.goto_parent() {
+                return count;
+            }
+        }
+    }
+}
+
+/// Check if a node kind branches control flow
+fn is_branch_node(kind: &str) -> bool {
+    matches!(
+        kind,
+        "if_statement"
+            | "if_expression"
+            | "match_expression"
+            | "switch_statement"
+            | "expression_switch_statement"
+            | "type_switch_statement"
+            | "for_statement"
+            | "for_in_statement"
+            | "for_expression"
+            | "while_statement"
+            | "while_expression"
+            | "do_statement"
+            | "loop_expression"
+            | "catch_clause"
+            | "except_clause"
+    )
+}
+
+/// `string_literal` chunk for a node that is a string literal
+fn string_chunk(
+    node: &tree_sitter::Node,
+    content: &str,
+    file_path: &str,
+    language: &str,
+) -> Option<CodeChunk> {
+    if !is_string_node(node.kind()) {
+        return None;
+    }
+    let start_line = node.start_position().row as u32 + 1;
+    let value = string_literal_value(&content[node.byte_range()]).to_string();
+    let hash = hash_content(&value);
+    let token_count = tokens::estimate_tokens(&value);
+    let symbols: Vec<String> = enclosing_symbol_name(node, content, language)
+        .into_iter()
+        .collect();
+
+    Some(CodeChunk {
+        id: generate_chunk_id(&value, symbols.first().map(String::as_str), language),
+        file_path: file_path.to_string(),
+        content: value,
+        start_line,
+        end_line: node.end_position().row as u32 + 1,
+        start_byte: node.start_byte() as u32,
+        end_byte: node.end_byte() as u32,
+        complexity: 0,
+        token_count,
+        header_lines: 0,
+        original_start_line: None,
+        language_version: None,
+        chunk_type: "string_literal".to_string(),
+        language: language.to_string(),
+        symbols,
+        hash,
+    })
+}
+
+/// Check if node kind is a string literal
+fn is_string_node(kind: &str) -> bool {
+    matches!(
+        kind,
+        "string"
+            | "template_string"
+            | "string_literal"
+            | "raw_string_literal"
+            | "interpreted_string_literal"
+    )
+}
+
+/// Source text of a string literal without its prefix and delimiters
+///
+/// Escape sequences are left as written.
+fn string_literal_value(text: &str) -> &str {
+    // Prefixes such as Python `rb` / `f` or Rust `r` and `b`
+    let body = text.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    let hashes = body.len() - body.trim_start_matches('#').len();
+    let body = &body[hashes..];
+
+    let quote = ["\"\"\"", "'''", "\"", "'", "`"]
+        .into_iter()
+        .find(|q| body.starts_with(q) && body.len() >= 2 * q.len() + hashes);
+    match quote {
+        Some(quote) => &body[quote.len()..body.len() - quote.len() - hashes],
+        None => text,
+    }
+}
+
+/// Name of the nearest enclosing declaration or variable
+fn enclosing_symbol_name(
+    node: &tree_sitter::Node,
+    content: &str,
+    language: &str,
+) -> Option<String> {
+    let mut current = node.parent();
+    while let Some(parent) = current {
+        if is_symbol_node(parent.kind()) || parent.kind() == "variable_declarator" {
+            if let Some(name) = find_name_child(&parent, content, language) {
+                return Some(name);
+            }
+        }
+        current = parent.parent();
+    }
+    None
+}
+
+/// Check if node kind should be a chunk
+fn is_chunk_node(kind: &str, language: &str) -> bool {
+    match language {
+        "typescript" | "javascript" => is_js_chunk_node(kind),
+        // React components are recognised from their body, so are not
+        // listed by kind
+        "tsx" | "jsx" => is_js_chunk_node(kind) || kind == "jsx_element",
+        "python" => matches!(kind, "function_definition" | "class_definition"),
+        "rust" => matches!(
+            kind,
+            "function_item" | "impl_item" | "struct_item" | "enum_item" | "trait_item" | "mod_item"
+        ),
+        "go" => matches!(
+            kind,
+            "function_declaration" | "method_declaration" | "type_declaration"
+        ),
+        "php" => matches!(
+            kind,
+            "function_definition"
+                | "method_declaration"
+                | "class_declaration"
+                | "interface_declaration"
+                | "trait_declaration"
+        ),
+        "sql" => matches!(
+            kind,
+            "create_table" | "create_view" | "create_function" | "create_trigger" | "create_index"
+        ),
+        // `local function f()`, `function M.f()` and `function M:f()` are
+        // all declarations; definitions are function expressions
+        "lua" => matches!(kind, "function_declaration" | "function_definition"),
+        "scala" => matches!(
+            kind,
+            "function_definition"
+                | "class_definition"
+                | "object_definition"
+                | "trait_definition"
+                | "val_definition"
+        ),
+        // Definitions are calls such as `def f(x)`; which calls define
+        // something is decided by `elixir_definition`
+        "elixir" => kind == "call",
+        // Kinds of Haskell declarations; which nodes of these kinds are
+        // declarations is decided by `haskell_declaration`
+        "haskell" => matches!(
+            kind,
+            "function" | "bind" | "type_synomym" | "data_type" | "newtype"
+        ),
+        _ => false,
+    }
+}
+
+/// Check if a TypeScript or JavaScript node kind is a chunk
+fn is_js_chunk_node(kind: &str) -> bool {
+    matches!(
+        kind,
+        "function_declaration"
+            | "method_definition"
+            | "class_declaration"
+            | "interface_declaration"
+            | "type_alias_declaration"
+            | "enum_declaration"
+            | "export_statement"
+    )
+}
+
+/// Map node kind to chunk type
+fn map_node_kind(kind: &str, _language: &str) -> String {
+    match kind {
+        "function_declaration" | "function_definition" | "function_item" | "create_function" => {
+            "function"
+        }
+        "method_definition" | "method_declaration" => "method",
+        "class_declaration" | "abstract_class_declaration" | "class_definition" => "class",
+        "interface_declaration" | "trait_item" | "trait_declaration" => "interface",
+        "struct_item" | "type_declaration" => "type",
+        "enum_declaration" | "enum_item" => "enum",
+        "impl_item" => "impl",
+        "jsx_element" => "jsx_element",
+        "mod_item" | "internal_module" => "module",
+        "object_definition" => "object",
+        "trait_definition" => "interface",
+        "val_definition" => "variable",
+        "create_table" => "table",
+        "create_view" => "view",
+        "create_trigger" => "trigger",
+        "create_index" => "index",
+        _ => "other",
+    }
+    .to_string()
+}
+
+/// Extract symbols from a node
+fn extract_node_symbols(node: &tree_sitter::Node, content: &str, language: &str) -> Vec<String> {
+    let mut symbols = Vec::new();
+
+    // Scala writes declared and return types as bare children
+    let types: Vec<tree_sitter::Node> = match node.kind() {
+        "val_definition" | "function_definition" | "function_declaration"
+            if language == "scala" =>
+        {
+            ["type", "return_type"]
+                .iter()
+                .filter_map(|field| node.child_by_field_name(field))
+                .collect()
+        }
+        _ => vec![],
+    };
+
+    // Find identifier children (`name` in PHP)
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if matches!(child.kind(), "identifier" | "type_identifier" | "name")
+            && !types.contains(&child)
+        {
+            if let Ok(name) = child.utf8_text(content.as_bytes()) {
+                symbols.push(name.to_string());
+            }
+        }
+    }
+
+    // SQL names the created object through an `object_reference`, Lua
+    // functions through a table field or the variable they are assigned to
+    let named_elsewhere = node.kind().starts_with("create_")
+        || (language == "lua"
+            && matches!(node.kind(), "function_declaration" | "function_definition"));
+    if symbols.is_empty() && named_elsewhere {
+        symbols.extend(find_name_child(node, content, language));
+    }
+
+    symbols
+}
+
+/// Extract a symbol from a node
+fn extract_symbol(
+    node: &tree_sitter::Node,
+    content: &str,
+    file_path: &str,
+    language: &str,
+) -> Option<Symbol> {
+    let kind = node.kind();
+    let elixir = if language == "elixir" {
+        elixir_definition(node, content)
+    } else {
+        None
+    };
+    let haskell = if language == "haskell" {
+        haskell_declaration(node)
+    } else {
+        None
+    };
+
+    // Only process declaration nodes
+    if !is_symbol_node(kind) && elixir.is_none() && haskell.is_none() {
+        return None;
+    }
+
+    // Find the name
+    let name = match &elixir {
+        Some(definition) => definition.name.clone(),
+        None => find_name_child(node, content, language)?,
+    };
+
+    // A Haskell function given by several equations is one symbol
+    let last_clause = match haskell {
+        Some(_) => haskell_last_clause(node, &name, content)?,
+        None => *node,
+    };
+
+    // Check if exported
+    let exported = is_exported(node, content, language);
+
+    // Get signature from the AST where supported, else the first line
+    let start = node.start_position();
+    let extracted = match (&elixir, haskell) {
+        (Some(definition), _) => Some((definition.signature.clone(), vec![])),
+        (None, Some(_)) => Some((haskell_head(node, content), vec![])),
+        (None, None) => extract_signature(node, &name, content, language),
+    };
+    let (signature, parameters) = match extracted {
+        Some(extracted) => extracted,
+        None => {
+            let end_of_sig = content[node.byte_range()]
+                .find('{')
+                .or_else(|| content[node.byte_range()].find(':'))
+                .unwrap_or(content[node.byte_range()].len().min(100));
+            let signature = content[node.start_byte()..node.start_byte() + end_of_sig]
+                .trim()
+                .to_string();
+            (signature, vec![])
+        }
+    };
+
+    let signature_annotation = haskell.and_then(|_| haskell_type_signature(node, &name, content));
+
+    // Find the enclosing class, impl or module
+    let (parent_name, parent_kind) = match find_parent_scope(node, content, language) {
+        Some((name, kind)) => (Some(name), Some(kind)),
+        None => (None, None),
+    };
+
+    Some(Symbol {
+        name,
+        kind: match (&elixir, haskell) {
+            (Some(definition), _) => definition.kind().to_string(),
+            (None, Some(haskell_kind)) => haskell_kind.to_string(),
+            (None, None) => map_symbol_kind(kind),
+        },
+        file_path: file_path.to_string(),
+        start_line: start.row as u32 + 1,
+        end_line: last_clause.end_position().row as u32 + 1,
+        start_column: start.column as u32,
+        end_column: last_clause.end_position().column as u32,
+        start_column_char: char_column(node, content),
+        signature: Some(signature),
+        parameters,
+        exported,
+        parent_name,
+        parent_kind,
+        references: None,
+        generic_params: generic_params(node, content),
+        signature_annotation,
+    })
+}
+
+/// Elixir `defmodule`, `def`, `defp`, `defmacro` or `defmacrop` call
+struct ElixirDefinition {
+    keyword: String,
+    /// Module alias or function name
+    name: String,
+    /// Keyword and head, such as `def get(id) when is_integer(id)`
+    signature: String,
+}
+
+impl ElixirDefinition {
+    /// Chunk and symbol kind
+    fn kind(&self) -> &'static str {
+        if self.keyword == "defmodule" {
+            "module"
+        } else {
+            "function"
+        }
+    }
+}
+
+/// The definition a node makes, if it is an Elixir definition call
+///
+/// The head of a function is its first argument, a call such as `get(id)`,
+/// possibly guarded with `when`, or a bare name for zero-arity functions.
+fn elixir_definition(node: &tree_sitter::Node, content: &str) -> Option<ElixirDefinition> {
+    if node.kind() != "call" {
+        return None;
+    }
+    let target = node
+        .child_by_field_name("target")
+        .filter(|t| t.kind() == "identifier")?;
+    let keyword = node_text(&target, content);
+    if !matches!(
+        keyword.as_str(),
+        "defmodule" | "def" | "defp" | "defmacro" | "defmacrop"
+    ) {
+        return None;
+    }
+    let head = node
+        .named_children(&mut node.walk())
+        .find(|c| c.kind() == "arguments")?
+        .named_child(0)?;
+
+    let mut callee = head;
+    if callee.kind() == "binary_operator" {
+        callee = callee.child_by_field_name("left")?;
+    }
+    let name = match callee.kind() {
+        "alias" | "identifier" if keyword == "defmodule" || callee.kind() == "identifier" => {
+            node_text(&callee, content)
+        }
+        "call" => node_text(&callee.child_by_field_name("target")?, content),
+        _ => return None,
+    };
+
+    Some(ElixirDefinition {
+        signature: format!(
+            "{} {}",
+            keyword,
+            collapse_whitespace(&node_text(&head, content))
+        ),
+        keyword,
+        name,
+    })
+}
+
+/// Chunk and symbol kind of a Haskell declaration, `None` for other nodes
+///
+/// Functions are equations with arguments and binds those without, such as
+/// `main = do ...`; both count at the top level and in classes and
+/// instances, so local `let` and `where` bindings are left out. `type`,
+/// `data` and `newtype` declarations are types. Type-level functions such
+/// as `a -> b` share the `function` kind but are never declarations.
+fn haskell_declaration(node: &tree_sitter::Node) -> Option<&'static str> {
+    let parent = node.parent()?;
+    if !matches!(
+        parent.kind(),
+        "declarations" | "class_declarations" | "instance_declarations"
+    ) {
+        return None;
+    }
+    match node.kind() {
+        "function" | "bind" => Some("function"),
+        // Spelled so by the grammar
+        "type_synomym" | "data_type" | "newtype" => Some("type"),
+        _ => None,
+    }
+}
+
+/// Last of the consecutive equations of the Haskell function `name` that
+/// begin at `node`, `None` if `node` follows an earlier equation
+fn haskell_last_clause<'t>(
+    node: &tree_sitter::Node<'t>,
+    name: &str,
+    content: &str,
+) -> Option<tree_sitter::Node<'t>> {
+    if haskell_clause_of(node.prev_named_sibling(), name, content) {
+        return None;
+    }
+    let mut last = *node;
+    while let Some(next) = last
+        .next_named_sibling()
+        .filter(|n| haskell_clause_of(Some(*n), name, content))
+    {
+        last = next;
+    }
+    Some(last)
+}
+
+/// First and last node of the chunk for a Haskell declaration
+///
+/// A function's chunk runs from its type signature through its last
+/// equation; `None` for the equations after the first.
+fn haskell_chunk_span<'t>(
+    node: &tree_sitter::Node<'t>,
+    content: &str,
+) -> Option<(tree_sitter::Node<'t>, tree_sitter::Node<'t>)> {
+    let Some(name) = find_name_child(node, content, "haskell") else {
+        return Some((*node, *node));
+    };
+    let last = haskell_last_clause(node, &name, content)?;
+    Some((
+        haskell_signature(node, &name, content).unwrap_or(*node),
+        last,
+    ))
+}
+
+/// Check if a node is an equation of the Haskell function `name`
+fn haskell_clause_of(node: Option<tree_sitter::Node>, name: &str, content: &str) -> bool {
+    node.is_some_and(|node| {
+        node.kind() == "function"
+            && node
+                .child_by_field_name("name")
+                .is_some_and(|n| content[n.byte_range()] == *name)
+    })
+}
+
+/// Head of a Haskell declaration, before its `=` or guards
+///
+/// `area (Circle r) = pi * r * r` gives `area (Circle r)` and
+/// `data Shape = Circle Double` gives `data Shape`.
+fn haskell_head(node: &tree_sitter::Node, content: &str) -> String {
+    let end = match node.child_by_field_name("match") {
+        Some(body) => body.start_byte(),
+        None => {
+            let mut cursor = node.walk();
+            let equals = node.children(&mut cursor).find(|c| c.kind() == "=");
+            equals.map_or(node.end_byte(), |c| c.start_byte())
+        }
+    };
+    collapse_whitespace(&content[node.start_byte()..end])
+}
+
+/// Type signature written for a Haskell definition just before it, as
+/// collapsed text
+fn haskell_type_signature(node: &tree_sitter::Node, name: &str, content: &str) -> Option<String> {
+    haskell_signature(node, name, content)
+        .map(|signature| collapse_whitespace(&node_text(&signature, content)))
+}
+
+/// Type signature node written for a Haskell definition just before it
+///
+/// Comments may come between the two; `a, b :: Int` serves both names.
+fn haskell_signature<'t>(
+    node: &tree_sitter::Node<'t>,
+    name: &str,
+    content: &str,
+) -> Option<tree_sitter::Node<'t>> {
+    let mut previous = node.prev_named_sibling();
+    while let Some(sibling) = previous.filter(|s| matches!(s.kind(), "comment" | "haddock")) {
+        previous = sibling.prev_named_sibling();
+    }
+    let signature = previous.filter(|s| s.kind() == "signature")?;
+    let list = signature.child_by_field_name("names").unwrap_or(signature);
+    let mut cursor = list.walk();
+    let named = list
+        .children_by_field_name("name", &mut cursor)
+        .any(|n| content[n.byte_range()] == *name);
+    named.then_some(signature)
+}
+
+/// Haskell visibility of a declaration, `None` outside Haskell
+///
+/// Without an export list every top-level declaration is exported; with
+/// one, those it names are. Class and instance members are not exported
+/// themselves.
+fn haskell_exported(node: &tree_sitter::Node, content: &str) -> Option<bool> {
+    haskell_declaration(node)?;
+    let parent = node.parent()?;
+    if parent.kind() != "declarations" {
+        return Some(false);
+    }
+    let Some(name) = node.child_by_field_name("name") else {
+        return Some(false);
+    };
+    let root = parent.parent()?;
+    let mut cursor = root.walk();
+    let header = root.children(&mut cursor).find(|c| c.kind() == "header");
+    let Some(exports) = header.and_then(|h| h.child_by_field_name("exports")) else {
+        return Some(true);
+    };
+
+    let mut cursor = exports.walk();
+    let exported = exports.named_children(&mut cursor).any(|export| {
+        ["variable", "type"]
+            .iter()
+            .filter_map(|field| export.child_by_field_name(field))
+            .any(|n| content[n.byte_range()] == content[name.byte_range()])
+    });
+    Some(exported)
+}
+
+/// Names of a declaration's generic type parameters, in order
+///
+/// Read from the `type_parameters` field: Rust `<'a, T: Clone, const N:
+/// usize>` gives `'a`, `T` and `N`, TypeScript `<T extends string>` gives
+/// `T`, and Go `[K, V any]` gives `K` and `V`.
+fn generic_params(node: &tree_sitter::Node, content: &str) -> Vec<String> {
+    let Some(list) = node.child_by_field_name("type_parameters") else {
+        return vec![];
+    };
+
+    let mut names = Vec::new();
+    let mut cursor = list.walk();
+    for param in list.named_children(&mut cursor) {
+        let mut name_cursor = param.walk();
+        let mut param_names: Vec<String> = param
+            .children_by_field_name("name", &mut name_cursor)
+            .map(|name| node_text(&name, content))
+            .collect();
+        if param_names.is_empty() {
+            // Lifetimes have no name field, older grammars bare identifiers
+            match param.kind() {
+                "lifetime_parameter" => {
+                    param_names.extend(param.named_child(0).map(|l| node_text(&l, content)))
+                }
+                "type_identifier" | "identifier" | "lifetime" => {
+                    param_names.push(node_text(&param, content))
+                }
+                _ => {}
+            }
+        }
+        names.extend(param_names);
+    }
+    names
+}
+
+/// Append identifier uses matching a symbol name to that symbol's references
+///
+/// Matching is by name only, so same-named symbols share references.
+/// The name in a symbol's own declaration is not a reference.
+fn collect_references(
+    node: &tree_sitter::Node,
+    content: &str,
+    file_path: &str,
+    symbols: &mut [Symbol],
+) {
+    if is_identifier_node(node.kind()) && !is_declaration_name(node) {
+        let name = &content[node.byte_range()];
+        let position = node.start_position();
+        for symbol in symbols.iter_mut().filter(|s| s.name == name) {
+            if let Some(references) = symbol.references.as_mut() {
+                references.push(SymbolRef {
+                    file_path: file_path.to_string(),
+                    line: position.row as u32 + 1,
+                    column: position.column as u32,
+                });
+            }
+        }
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_references(&child, content, file_path, symbols);
+    }
+}
+
+/// Collect the identifier uses in a subtree with their 0-based rows,
+/// leaving out the names of declarations
+pub fn collect_identifier_uses<'a>(
+    node: &tree_sitter::Node,
+    content: &'a str,
+    uses: &mut Vec<(&'a str, usize)>,
+) {
+    if is_identifier_node(node.kind()) && !is_declaration_name(node) {
+        uses.push((&content[node.byte_range()], node.start_position().row));
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_identifier_uses(&child, content, uses);
+    }
+}
+
+/// Check if a node kind is an identifier that can name a symbol
+fn is_identifier_node(kind: &str) -> bool {
+    matches!(
+        kind,
+        "identifier"
+            | "type_identifier"
+            | "property_identifier"
+            | "field_identifier"
+            | "shorthand_property_identifier"
+    )
+}
+
+/// Check if an identifier is the name of the symbol declaration containing it
+fn is_declaration_name(node: &tree_sitter::Node) -> bool {
+    match node.parent() {
+        Some(parent) => {
+            is_symbol_node(parent.kind())
+                && parent
+                    .child_by_field_name("name")
+                    .is_some_and(|name| name == *node)
+        }
+        None => false,
+    }
+}
+
+/// Start column of a node counted in characters from the line start
+///
+/// Tree-sitter columns are byte offsets, which differ for multi-byte text.
+fn char_column(node: &tree_sitter::Node, content: &str) -> u32 {
+    let line_start = node.start_byte() - node.start_position().column;
+    content[line_start..node.start_byte()].chars().count() as u32
+}
+
+/// Collect variable symbols from a module-level statement
+fn extract_variable_node(
+    node: &tree_sitter::Node,
+    content: &str,
+    file_path: &str,
+    language: &str,
+    variables: &mut Vec<Symbol>,
+) {
+    match node.kind() {
+        "export_statement" => {
+            if let Some(declaration) = node.child_by_field_name("declaration") {
+                extract_variable_node(&declaration, content, file_path, language, variables);
+            }
+        }
+        "lexical_declaration" | "variable_declaration" => {
+            let keyword = node
+                .child(0)
+                .map(|k| node_text(&k, content))
+                .unwrap_or_default();
+            let kind = if keyword == "const" {
+                "constant"
+            } else {
+                "variable"
+            };
+            let exported = is_exported(node, content, language);
+
+            let mut cursor = node.walk();
+            for declarator in node.named_children(&mut cursor) {
+                if declarator.kind() != "variable_declarator" {
+                    continue;
+                }
+                let type_annotation = declarator
+                    .child_by_field_name("type")
+                    .map(|t| collapse_whitespace(&node_text(&t, content)))
+                    .unwrap_or_default();
+
+                let mut names = Vec::new();
+                if let Some(pattern) = declarator.child_by_field_name("name") {
+                    collect_pattern_names(&pattern, content, &mut names);
+                }
+                for name in names {
+                    let signature = format!("{} {}{}", keyword, name, type_annotation);
+                    variables.push(variable_symbol(
+                        &declarator,
+                        name,
+                        kind,
+                        signature,
+                        exported,
+                        content,
+                        file_path,
+                    ));
+                }
+            }
+        }
+        "static_item" | "const_item" => {
+            let name = match node.child_by_field_name("name") {
+                Some(name) => node_text(&name, content),
+                None => return,
+            };
+            let mutable = (0..node.child_count())
+                .filter_map(|i| node.child(i))
+                .any(|c| c.kind() == "mutable_specifier");
+            let kind = if mutable { "variable" } else { "constant" };
+
+            // Everything before the initialiser, e.g. `pub static NAME: &str`
+            let end = node
+                .child_by_field_name("value")
+                .map(|v| v.start_byte())
+                .unwrap_or(node.end_byte());
+            let signature = collapse_whitespace(
+                content[node.start_byte()..end]
+                    .trim_end()
+                    .trim_end_matches(['=', ';'])
+                    .trim_end(),
+            );
+            let exported = is_exported(node, content, language);
+            variables.push(variable_symbol(
+                node, name, kind, signature, exported, content, file_path,
+            ));
+        }
+        "expression_statement" => {
+            let mut cursor = node.walk();
+            for child in node.named_children(&mut cursor) {
+                if child.kind() == "assignment" {
+                    extract_python_assignment(&child, content, file_path, variables);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collect the names bound by a Python assignment, following chains like `x = y = 1`
+fn extract_python_assignment(
+    node: &tree_sitter::Node,
+    content: &str,
+    file_path: &str,
+    variables: &mut Vec<Symbol>,
+) {
+    let type_annotation = node
+        .child_by_field_name("type")
+        .map(|t| format!(": {}", collapse_whitespace(&node_text(&t, content))))
+        .unwrap_or_default();
+
+    let mut names = Vec::new();
+    if let Some(left) = node.child_by_field_name("left") {
+        collect_pattern_names(&left, content, &mut names);
+    }
+    for name in names {
+        // Python marks constants by convention only
+        let is_constant =
+            name.chars().any(|c| c.is_ascii_uppercase()) && !name.chars().any(|c| c.is_lowercase());
+        let kind = if is_constant { "constant" } else { "variable" };
+        let signature = format!("{}{}", name, type_annotation);
+        let exported = !name.starts_with('_');
+        variables.push(variable_symbol(
+            node, name, kind, signature, exported, content, file_path,
+        ));
+    }
+
+    if let Some(right) = node.child_by_field_name("right") {
+        if right.kind() == "assignment" {
+            extract_python_assignment(&right, content, file_path, variables);
+        }
+    }
+}
+
+/// Collect identifiers bound by a name or destructuring pattern
+fn collect_pattern_names(node: &tree_sitter::Node, content: &str, names: &mut Vec<String>) {
+    match node.kind() {
+        "identifier" | "shorthand_property_identifier_pattern" => {
+            names.push(node_text(node, content))
+        }
+        // The key of `{ key: value }` is not bound, only the value
+        "pair_pattern" => {
+            if let Some(value) = node.child_by_field_name("value") {
+                collect_pattern_names(&value, content, names);
+            }
+        }
+        _ => {
+            let mut cursor = node.walk();
+            for child in node.named_children(&mut cursor) {
+                collect_pattern_names(&child, content, names);
+            }
+        }
+    }
+}
+
+/// Build a variable or constant symbol spanning `node`
+fn variable_symbol(
+    node: &tree_sitter::Node,
+    name: String,
+    kind: &str,
+    signature: String,
+    exported: bool,
+    content: &str,
+    file_path: &str,
+) -> Symbol {
+    let start = node.start_position();
+    Symbol {
+        name,
+        kind: kind.to_string(),
+        file_path: file_path.to_string(),
+        start_line: start.row as u32 + 1,
+        end_line: node.end_position().row as u32 + 1,
+        start_column: start.column as u32,
+        end_column: node.end_position().column as u32,
+        start_column_char: char_column(node, content),
+        signature: Some(signature),
+        parameters: vec![],
+        exported,
+        parent_name: None,
+        parent_kind: None,
+        references: None,
+        generic_params: vec![],
+        signature_annotation: None,
+    }
+}
+
+/// Build a signature and parameter list for TypeScript/JavaScript and Rust functions
+fn extract_signature(
+    node: &tree_sitter::Node,
+    name: &str,
+    content: &str,
+    language: &str,
+) -> Option<(String, Vec<SymbolParameter>)> {
+    let params = node.child_by_field_name("parameters")?;
+    let is_rust = node.kind() == "function_item";
+    // Python's `parameters` are not handled
+    let is_lua = language == "lua" && params.kind() == "parameters";
+    let is_scala = language == "scala" && params.kind() == "parameters";
+    if !is_rust && !is_lua && !is_scala && params.kind() != "formal_parameters" {
+        return None;
+    }
+
+    let mut parameters = Vec::new();
+    let mut cursor = params.walk();
+    for param in params.named_children(&mut cursor) {
+        let parameter = match param.kind() {
+            "required_parameter" | "optional_parameter" | "parameter" => {
+                // Scala parameters have a `name` rather than a `pattern`
+                let pattern = param
+                    .child_by_field_name("pattern")
+                    .or_else(|| param.child_by_field_name("name"))?;
+                let mut name = collapse_whitespace(&node_text(&pattern, content));
+                if param.kind() == "optional_parameter" {
+                    name.push('?');
+                }
+                let type_annotation = param
+                    .child_by_field_name("type")
+                    .map(|t| type_text(&t, content));
+                SymbolParameter {
+                    name,
+                    type_annotation,
+                }
+            }
+            "simple_parameter" => SymbolParameter {
+                name: node_text(&param.child_by_field_name("name")?, content),
+                type_annotation: param
+                    .child_by_field_name("type")
+                    .map(|t| type_text(&t, content)),
+            },
+            "self_parameter" | "identifier" | "vararg_expression" => SymbolParameter {
+                name: collapse_whitespace(&node_text(&param, content)),
+                type_annotation: None,
+            },
+            _ => continue,
+        };
+        parameters.push(parameter);
+    }
+
+    let rendered: Vec<String> = parameters
+        .iter()
+        .map(|p| match &p.type_annotation {
+            Some(t) => format!("{}: {}", p.name, t),
+            None => p.name.clone(),
+        })
+        .collect();
+
+    let return_type = match node.child_by_field_name("return_type") {
+        Some(ret) if is_rust => format!(" -> {}", type_text(&ret, content)),
+        Some(ret) => format!(": {}", type_text(&ret, content)),
+        None => String::new(),
+    };
+
+    Some((
+        format!("{}({}){}", name, rendered.join(", "), return_type),
+        parameters,
+    ))
+}
+
+/// Text of a type, unwrapping TypeScript `: T` annotations
+fn type_text(node: &tree_sitter::Node, content: &str) -> String {
+    let inner = if node.kind() == "type_annotation" {
+        node.named_child(0).unwrap_or(*node)
+    } else {
+        *node
+    };
+    collapse_whitespace(&node_text(&inner, content))
+}
+
+/// Collapse runs of whitespace, including newlines, into single spaces
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Walk up to the nearest enclosing scope and return its name and kind
+fn find_parent_scope(
+    node: &tree_sitter::Node,
+    content: &str,
+    language: &str,
+) -> Option<(String, String)> {
+    let mut current = node.parent();
+    while let Some(parent) = current {
+        if language == "elixir" {
+            if let Some(module) =
+                elixir_definition(&parent, content).filter(|d| d.keyword == "defmodule")
+            {
+                return Some((module.name, "module".to_string()));
+            }
+        }
+        // Haskell class and instance members; an instance is named by its class
+        if language == "haskell"
+            && matches!(
+                parent.kind(),
+                "class_declarations" | "instance_declarations"
+            )
+        {
+            let owner = parent
+                .parent()
+                .filter(|o| matches!(o.kind(), "class" | "instance"));
+            if let Some(name) = owner.and_then(|o| o.child_by_field_name("name")) {
+                let kind = if parent.kind() == "class_declarations" {
+                    "interface"
+                } else {
+                    "impl"
+                };
+                return Some((node_text(&name, content), kind.to_string()));
+            }
+        }
+        if is_scope_node(parent.kind()) {
+            let name = match parent.kind() {
+                "impl_item" => parent
+                    .child_by_field_name("type")
+                    .map(|t| node_text(&t, content)),
+                _ => find_name_child(&parent, content, language),
+            };
+            if let Some(name) = name {
+                return Some((name, map_node_kind(parent.kind(), "")));
+            }
+        }
+        current = parent.parent();
+    }
+    None
+}
+
+/// Check if node kind opens a named scope for its members
+fn is_scope_node(kind: &str) -> bool {
+    matches!(
+        kind,
+        "class_declaration"
+            | "abstract_class_declaration"
+            | "class_definition"
+            | "impl_item"
+            | "trait_item"
+            | "trait_declaration"
+            | "mod_item"
+            | "internal_module"
+            | "object_definition"
+            | "trait_definition"
+    )
+}
+
+/// Check if node kind defines a symbol
+fn is_symbol_node(kind: &str) -> bool {
+    SYMBOL_NODE_KINDS.contains(&kind)
+}
+
+/// Node kinds that define a symbol
+const SYMBOL_NODE_KINDS: &[&str] = &[
+    "function_declaration",
+    "function_definition",
+    "function_item",
+    "method_definition",
+    "method_declaration",
+    "class_declaration",
+    "class_definition",
+    "interface_declaration",
+    "struct_item",
+    "enum_declaration",
+    "enum_item",
+    "type_alias_declaration",
+    "trait_item",
+    "trait_declaration",
+    "object_definition",
+    "trait_definition",
+    "val_definition",
+    "create_table",
+    "create_view",
+    "create_function",
+    "create_trigger",
+    "create_index",
+];
+
+/// Map node kind to symbol kind
+fn map_symbol_kind(kind: &str) -> String {
+    match kind {
+        "function_declaration" | "function_definition" | "function_item" | "create_function" => {
+            "function"
+        }
+        "method_definition" | "method_declaration" => "method",
+        "class_declaration" | "class_definition" => "class",
+        "interface_declaration" | "trait_item" | "trait_declaration" | "trait_definition" => {
+            "interface"
+        }
+        "object_definition" => "object",
+        "struct_item" => "class",
+        "enum_declaration" | "enum_item" => "enum",
+        "type_alias_declaration" => "type",
+        "create_table" => "table",
+        "create_view" => "view",
+        "create_trigger" => "trigger",
+        "create_index" => "index",
+        _ => "variable",
+    }
+    .to_string()
+}
+
+/// Find name child of a node
+fn find_name_child(node: &tree_sitter::Node, content: &str, language: &str) -> Option<String> {
+    if language == "lua" {
+        if let Some(name) = lua_function_name(node, content) {
+            return Some(name);
+        }
+    }
+    if let Some(name) = node.child_by_field_name("name") {
+        return name
+            .utf8_text(content.as_bytes())
+            .ok()
+            .map(|s| s.to_string());
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        if child.kind() == "identifier" || child.kind() == "type_identifier" {
+            return child
+                .utf8_text(content.as_bytes())
+                .ok()
+                .map(|s| s.to_string());
+        }
+        // SQL: `public.users` is named `users`
+        if child.kind() == "object_reference" {
+            return find_name_child(&child, content, language);
+        }
+    }
+    None
+}
+
+/// Name of a Lua function found past the `function` keyword
+///
+/// `function M.f()` and `function M:f()` are named `f`. A function
+/// expression takes the name it is assigned to, as in `local f = function()`,
+/// `M.f = function()` or `{ f = function() }`. `None` for anything else,
+/// including `local function f()`, whose name is a plain identifier.
+fn lua_function_name(node: &tree_sitter::Node, content: &str) -> Option<String> {
+    let target = match node.kind() {
+        "function_declaration" => node.child_by_field_name("name")?,
+        "function_definition" if node.child_by_field_name("name").is_none() => {
+            let parent = node.parent()?;
+            match parent.kind() {
+                "field" => parent.child_by_field_name("name")?,
+                "expression_list" if parent.named_child_count() == 1 => {
+                    let assignment = parent
+                        .parent()
+                        .filter(|p| p.kind() == "assignment_statement")?;
+                    let variables = assignment
+                        .named_child(0)
+                        .filter(|v| v.kind() == "variable_list")?;
+                    variables.named_child(0)?
+                }
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    let name = match target.kind() {
+        "dot_index_expression" => target.child_by_field_name("field")?,
+        "method_index_expression" => target.child_by_field_name("method")?,
+        "identifier" if node.kind() == "function_definition" => target,
+        _ => return None,
+    };
+    Some(node_text(&name, content))
+}
+
+/// Check if a node is exported
+fn is_exported(node: &tree_sitter::Node, content: &str, language: &str) -> bool {
+    if language == "scala" {
+        if let Some(exported) = scala_exported(node, content) {
+            return exported;
+        }
+    }
+    if language == "haskell" {
+        if let Some(exported) = haskell_exported(node, content) {
+            return exported;
+        }
+    }
+    // Elixir: `defp` and `defmacrop` are private
+    if language == "elixir" {
+        if let Some(definition) = elixir_definition(node, content) {
+            return !definition.keyword.ends_with('p');
+        }
+    }
+
+    // Check parent for export
+    if let Some(parent) = node.parent() {
+        if parent.kind() == "export_statement" {
+            return true;
+        }
+    }
+
+    // Check for public visibility (PHP)
+    let mut cursor = node.walk();
+    let public = node.children(&mut cursor).any(|child| {
+        child.kind() == "visibility_modifier" && &content[child.byte_range()] == "public"
+    });
+    if public {
+        return true;
+    }
+
+    // Check for pub keyword (Rust)
+    let text = &content[node.byte_range()];
+    text.starts_with("pub ") || text.starts_with("export ")
+}
+
+/// Scala visibility of a definition, `None` outside Scala
+///
+/// Members are public unless marked `private`, and only count as exported
+/// while the nearest enclosing object, class or trait is not `private`
+/// either. Definitions local to a block are never exported.
+fn scala_exported(node: &tree_sitter::Node, content: &str) -> Option<bool> {
+    let mut root = *node;
+    while let Some(parent) = root.parent() {
+        root = parent;
+    }
+    if root.kind() != "compilation_unit" {
+        return None;
+    }
+
+    let is_private = |node: &tree_sitter::Node| {
+        let mut cursor = node.walk();
+        let modifiers = node.children(&mut cursor).find(|c| c.kind() == "modifiers");
+        modifiers.is_some_and(|modifiers| {
+            let mut cursor = modifiers.walk();
+            let private = modifiers.children(&mut cursor).any(|m| {
+                m.kind() == "access_modifier" && content[m.byte_range()].starts_with("private")
+            });
+            private
+        })
+    };
+    if is_private(node) {
+        return Some(false);
+    }
+
+    match node.parent().map(|p| p.kind()) {
+        Some("compilation_unit") => Some(true),
+        Some("template_body") => {
+            let owner = node.parent().and_then(|body| body.parent());
+            Some(!owner.is_some_and(|owner| is_private(&owner)))
+        }
+        _ => Some(false),
+    }
+}
+
+/// Extract the imports a node declares
+fn extract_node_imports(
+    node: &tree_sitter::Node,
+    content: &str,
+    language: &str,
+    imports: &mut Vec<Import>,
+) {
+    match (language, node.kind()) {
+        ("typescript" | "tsx" | "javascript" | "jsx", "import_statement") => {
+            imports.extend(extract_es_import(node, content));
+        }
+        ("typescript" | "tsx" | "javascript" | "jsx", "export_statement") => {
+            imports.extend(extract_re_export(node, content));
+        }
+        ("typescript" | "tsx" | "javascript" | "jsx", "call_expression") => {
+            imports.extend(extract_require(node, content));
+        }
+        ("python", "import_statement") => {
+            let mut cursor = node.walk();
+            for name in node.children_by_field_name("name", &mut cursor) {
+                let module = name.child_by_field_name("name").unwrap_or(name);
+                imports.push(Import {
+                    source: node_text(&module, content),
+                    specifiers: vec![],
+                    is_type_only: false,
+                    kind: "import".to_string(),
+                    start_line: node.start_position().row as u32 + 1,
+                });
+            }
+        }
+        ("python", "import_from_statement") => {
+            imports.extend(extract_python_from_import(node, content));
+        }
+        ("rust", "use_declaration") => {
+            if let Some(argument) = node.child_by_field_name("argument") {
+                let (source, specifiers) = split_rust_use(&argument, content);
+                imports.push(Import {
+                    source,
+                    specifiers,
+                    is_type_only: false,
+                    kind: "import".to_string(),
+                    start_line: node.start_position().row as u32 + 1,
+                });
+            }
+        }
+        ("go", "import_spec") => {
+            if let Some(path) = node.child_by_field_name("path") {
+                imports.push(Import {
+                    source: unquote(&node_text(&path, content)),
+                    specifiers: node
+                        .child_by_field_name("name")
+                        .map(|n| vec![node_text(&n, content)])
+                        .unwrap_or_default(),
+                    is_type_only: false,
+                    kind: "import".to_string(),
+                    start_line: node.start_position().row as u32 + 1,
+                });
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Extract an ES module `import` statement
+fn extract_es_import(node: &tree_sitter::Node, content: &str) -> Option<Import> {
+    let source = node.child_by_field_name("source")?;
+    let mut specifiers = Vec::new();
+    let mut is_type_only = false;
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "type" => is_type_only = true,
+            "import_clause" => collect_import_clause(&child, content, &mut specifiers),
+            _ => {}
+        }
+    }
+
+    Some(Import {
+        source: unquote(&node_text(&source, content)),
+        specifiers,
+        is_type_only,
+        kind: "import".to_string(),
+        start_line: node.start_position().row as u32 + 1,
+    })
+}
+
+/// Collect imported names from an ES `import_clause`
+fn collect_import_clause(node: &tree_sitter::Node, content: &str, specifiers: &mut Vec<String>) {
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "identifier" => specifiers.push(node_text(&child, content)),
+            "namespace_import" => specifiers.push("*".to_string()),
+            "named_imports" => {
+                let mut inner = child.walk();
+                for spec in child.children(&mut inner) {
+                    if spec.kind() == "import_specifier" {
+                        if let Some(name) = spec.child_by_field_name("name") {
+                            specifiers.push(node_text(&name, content));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Extract an `export ... from "..."` re-export
+fn extract_re_export(node: &tree_sitter::Node, content: &str) -> Option<Import> {
+    let source = node.child_by_field_name("source")?;
+    let mut specifiers = Vec::new();
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        match child.kind() {
+            "*" | "namespace_export" => specifiers.push("*".to_string()),
+            "export_clause" => {
+                let mut inner = child.walk();
+                for spec in child.named_children(&mut inner) {
+                    if let Some(name) = spec.child_by_field_name("name") {
+                        specifiers.push(node_text(&name, content));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Some(Import {
+        source: unquote(&node_text(&source, content)),
+        specifiers,
+        is_type_only: false,
+        kind: "re-export".to_string(),
+        start_line: node.start_position().row as u32 + 1,
+    })
+}
+
+/// Extract a CommonJS `require("...")` or dynamic `import("...")` call
+fn extract_require(node: &tree_sitter::Node, content: &str) -> Option<Import> {
+    let function = node.child_by_field_name("function")?;
+    let kind = match function.kind() {
+        "import" => "dynamic",
+        "identifier" if node_text(&function, content) == "require" => "import",
+        _ => return None,
+    };
+
+    let arguments = node.child_by_field_name("arguments")?;
+    let source = arguments.named_child(0).filter(|a| a.kind() == "string")?;
+
+    // Names bound by `const x = require(...)` or `const { a, b } = require(...)`
+    let mut specifiers = Vec::new();
+    if let Some(declarator) = node.parent().filter(|p| p.kind() == "variable_declarator") {
+        if let Some(name) = declarator.child_by_field_name("name") {
+            match name.kind() {
+                "identifier" => specifiers.push(node_text(&name, content)),
+                "object_pattern" => {
+                    let mut cursor = name.walk();
+                    for prop in name.named_children(&mut cursor) {
+                        let key = prop.child_by_field_name("key").unwrap_or(prop);
+                        specifiers.push(node_text(&key, content));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Some(Import {
+        source: unquote(&node_text(&source, content)),
+        specifiers,
+        is_type_only: false,
+        kind: kind.to_string(),
+        start_line: node.start_position().row as u32 + 1,
+    })
+}
+
+/// Extract a Python `from ... import ...` statement
+fn extract_python_from_import(node: &tree_sitter::Node, content: &str) -> Option<Import> {
+    let module = node.child_by_field_name("module_name")?;
+    let mut specifiers = Vec::new();
+
+    let mut cursor = node.walk();
+    for name in node.children_by_field_name("name", &mut cursor) {
+        let name = name.child_by_field_name("name").unwrap_or(name);
+        specifiers.push(node_text(&name, content));
+    }
+
+    let mut cursor = node.walk();
+    if node
+        .children(&mut cursor)
+        .any(|c| c.kind() == "wildcard_import")
+    {
+        specifiers.push("*".to_string());
+    }
+
+    Some(Import {
+        source: node_text(&module, content),
+        specifiers,
+        is_type_only: false,
+        kind: "import".to_string(),
+        start_line: node.start_position().row as u32 + 1,
+    })
+}
+
+/// Split a Rust use tree into its source path and imported names
+fn split_rust_use(node: &tree_sitter::Node, content: &str) -> (String, Vec<String>) {
+    match node.kind() {
+        "scoped_identifier" => {
+            let source = node
+                .child_by_field_name("path")
+                .map(|p| node_text(&p, content))
+                .unwrap_or_default();
+            let name = node
+                .child_by_field_name("name")
+                .map(|n| node_text(&n, content))
+                .unwrap_or_default();
+            (source, vec![name])
+        }
+        "use_as_clause" => match node.child_by_field_name("path") {
+            Some(path) => split_rust_use(&path, content),
+            None => (node_text(node, content), vec![]),
+        },
+        "scoped_use_list" => {
+            let source = node
+                .child_by_field_name("path")
+                .map(|p| node_text(&p, content))
+                .unwrap_or_default();
+            let mut specifiers = Vec::new();
+            if let Some(list) = node.child_by_field_name("list") {
+                collect_rust_use_list(&list, content, "", &mut specifiers);
+            }
+            (source, specifiers)
+        }
+        "use_list" => {
+            let mut specifiers = Vec::new();
+            collect_rust_use_list(node, content, "", &mut specifiers);
+            (String::new(), specifiers)
+        }
+        "use_wildcard" => {
+            let text = node_text(node, content);
+            let source = text
+                .trim_end_matches('*')
+                .trim_end_matches("::")
+                .to_string();
+            (source, vec!["*".to_string()])
+        }
+        _ => (node_text(node, content), vec![]),
+    }
+}
+
+/// Flatten a Rust `use_list` into `prefix::name` specifiers
+fn collect_rust_use_list(
+    node: &tree_sitter::Node,
+    content: &str,
+    prefix: &str,
+    specifiers: &mut Vec<String>,
+) {
+    let mut cursor = node.walk();
+    for item in node.named_children(&mut cursor) {
+        match item.kind() {
+            "scoped_use_list" => {
+                let path = item
+                    .child_by_field_name("path")
+                    .map(|p| format!("{}{}::", prefix, node_text(&p, content)))
+                    .unwrap_or_else(|| prefix.to_string());
+                if let Some(list) = item.child_by_field_name("list") {
+                    collect_rust_use_list(&list, content, &path, specifiers);
+                }
+            }
+            "use_as_clause" => {
+                if let Some(path) = item.child_by_field_name("path") {
+                    specifiers.push(format!("{}{}", prefix, node_text(&path, content)));
+                }
+            }
+            _ => specifiers.push(format!("{}{}", prefix, node_text(&item, content))),
+        }
+    }
+}
+
+/// Extract the comments a node is or holds
+///
+/// Returns whether the node was taken as a whole comment, so its
+/// descendants hold no further ones.
+fn extract_node_comments(
+    node: &tree_sitter::Node,
+    content: &str,
+    language: &str,
+    comments: &mut Vec<CodeComment>,
+) -> bool {
+    if is_comment_node(node.kind()) {
+        comments.extend(extract_comment(node, content, language));
+        return true;
+    }
+
+    if language == "elixir" {
+        if let Some(comment) = elixir_doc_attribute(node, content) {
+            comments.push(comment);
+            return true;
+        }
+    }
+
+    if language == "python" && node.kind() == "expression_statement" {
+        if let Some(owner) = python_docstring_owner(node) {
+            comments.push(CodeComment {
+                content: node_text(node, content),
+                kind: "doc".to_string(),
+                start_line: node.start_position().row as u32 + 1,
+                end_line: node.end_position().row as u32 + 1,
+                associated_symbol: find_name_child(&owner, content, language),
+                doc_block: None,
+            });
+        }
+    }
+
+    false
+}
+
+/// Elixir `@moduledoc`, `@doc` or `@typedoc` attribute with a string
+///
+/// `@moduledoc` belongs to the module it is in, the others to the next
+/// definition among the following siblings; `@doc false` is no comment.
+fn elixir_doc_attribute(node: &tree_sitter::Node, content: &str) -> Option<CodeComment> {
+    if node.kind() != "unary_operator" || !content[node.byte_range()].starts_with('@') {
+        return None;
+    }
+    let operand = node
+        .child_by_field_name("operand")
+        .filter(|o| o.kind() == "call")?;
+    let attribute = node_text(&operand.child_by_field_name("target")?, content);
+    if !matches!(attribute.as_str(), "moduledoc" | "doc" | "typedoc") {
+        return None;
+    }
+    let value = operand
+        .named_children(&mut operand.walk())
+        .find(|c| c.kind() == "arguments")?
+        .named_child(0)?;
+    if !matches!(value.kind(), "string" | "sigil") {
+        return None;
+    }
+
+    let associated_symbol = if attribute == "moduledoc" {
+        let mut scope = node.parent();
+        while let Some(parent) = scope {
+            if elixir_definition(&parent, content).is_some_and(|d| d.keyword == "defmodule") {
+                break;
+            }
+            scope = parent.parent();
+        }
+        scope
+            .and_then(|module| elixir_definition(&module, content))
+            .map(|d| d.name)
+    } else {
+        let mut next = node.next_named_sibling();
+        let mut name = None;
+        while let Some(sibling) = next {
+            if let Some(definition) = elixir_definition(&sibling, content) {
+                name = Some(definition.name);
+                break;
+            }
+            next = sibling.next_named_sibling();
+        }
+        name
+    };
+
+    Some(CodeComment {
+        content: node_text(node, content),
+        kind: "doc".to_string(),
+        start_line: node.start_position().row as u32 + 1,
+        end_line: node.end_position().row as u32 + 1,
+        associated_symbol,
+        doc_block: None,
+    })
+}
+
+/// Check if node kind is a comment
+fn is_comment_node(kind: &str) -> bool {
+    matches!(kind, "comment" | "line_comment" | "block_comment")
+}
+
+/// Build a comment, folding consecutive line comments into one
+fn extract_comment(node: &tree_sitter::Node, content: &str, language: &str) -> Option<CodeComment> {
+    // Already folded into the comment on the line above
+    if let Some(prev) = node.prev_named_sibling() {
+        if continues_line_comment(&prev, node, content) {
+            return None;
+        }
+    }
+
+    let mut last = *node;
+    while let Some(next) = last.next_named_sibling() {
+        if !continues_line_comment(&last, &next, content) {
+            break;
+        }
+        last = next;
+    }
+
+    let text = content[node.start_byte()..last.end_byte()].trim_end();
+    let kind = comment_kind(text);
+    let associated_symbol = if kind == "doc" && !is_inner_doc(text) {
+        following_symbol_name(&last, content, language)
+    } else {
+        None
+    };
+
+    Some(CodeComment {
+        content: text.to_string(),
+        kind: kind.to_string(),
+        // Counted on the trimmed text: line comment nodes may end past their
+        // newline, block comments end on their closing line
+        start_line: node.start_position().row as u32 + 1,
+        end_line: (node.start_position().row + text.matches('\n').count()) as u32 + 1,
+        associated_symbol,
+        doc_block: if kind == "doc" {
+            docblock::parse_doc_block(text, language)
+        } else {
+            None
+        },
+    })
+}
+
+/// Check if `next` is a line comment continuing `prev` on the following line
+fn continues_line_comment(
+    prev: &tree_sitter::Node,
+    next: &tree_sitter::Node,
+    content: &str,
+) -> bool {
+    if !is_comment_node(prev.kind()) || !is_comment_node(next.kind()) {
+        return false;
+    }
+
+    let prev_text = &content[prev.byte_range()];
+    let next_text = &content[next.byte_range()];
+    is_line_comment(prev_text)
+        && is_line_comment(next_text)
+        && comment_kind(prev_text) == comment_kind(next_text)
+        && is_inner_doc(prev_text) == is_inner_doc(next_text)
+        && next.start_position().row == prev.start_position().row + 1
+        && next.start_position().column == prev.start_position().column
+}
+
+/// Check if comment text is a single-line comment
+fn is_line_comment(text: &str) -> bool {
+    text.starts_with("//") || text.starts_with('#')
+}
+
+/// Classify comment text as `line`, `block` or `doc`
+fn comment_kind(text: &str) -> &'static str {
+    if (text.starts_with("///") && !text.starts_with("////"))
+        || text.starts_with("//!")
+        || (text.starts_with("/**") && !text.starts_with("/**/"))
+        || text.starts_with("/*!")
+    {
+        "doc"
+    } else if text.starts_with("/*") {
+        "block"
+    } else {
+        "line"
+    }
+}
+
+/// Check if a doc comment documents its enclosing item (`//!`, `/*!`)
+fn is_inner_doc(text: &str) -> bool {
+    text.starts_with("//!") || text.starts_with("/*!")
+}
+
+/// Name of the declaration immediately following a comment
+fn following_symbol_name(
+    node: &tree_sitter::Node,
+    content: &str,
+    language: &str,
+) -> Option<String> {
+    let mut next = node.next_named_sibling();
+    while let Some(sibling) = next {
+        if !is_comment_node(sibling.kind())
+            && !matches!(sibling.kind(), "attribute_item" | "decorator")
+        {
+            return declaration_name(&sibling, content, language);
+        }
+        next = sibling.next_named_sibling();
+    }
+    None
+}
+
+/// Name declared by a node, looking through export and variable wrappers
+fn declaration_name(node: &tree_sitter::Node, content: &str, language: &str) -> Option<String> {
+    match node.kind() {
+        "export_statement" | "decorated_definition" => {
+            let inner = node
+                .child_by_field_name("declaration")
+                .or_else(|| node.child_by_field_name("definition"))?;
+            declaration_name(&inner, content, language)
+        }
+        "lexical_declaration" | "variable_declaration" => {
+            let mut cursor = node.walk();
+            let declarator = node
+                .named_children(&mut cursor)
+                .find(|c| c.kind() == "variable_declarator")?;
+            find_name_child(&declarator, content, language)
+        }
+        _ => find_name_child(node, content, language),
+    }
+}
+
+/// Enclosing function or class when a Python statement is its docstring
+fn python_docstring_owner<'a>(node: &tree_sitter::Node<'a>) -> Option<tree_sitter::Node<'a>> {
+    if node.named_child(0)?.kind() != "string" {
+        return None;
+    }
+
+    let block = node.parent().filter(|p| p.kind() == "block")?;
+    let mut cursor = block.walk();
+    let first = block
+        .named_children(&mut cursor)
+        .find(|c| !is_comment_node(c.kind()))?;
+    if first.id() != node.id() {
+        return None;
+    }
+
+    block
+        .parent()
+        .filter(|p| matches!(p.kind(), "function_definition" | "class_definition"))
+}
+
+/// Text of a node
+fn node_text(node: &tree_sitter::Node, content: &str) -> String {
+    content[node.byte_range()].to_string()
+}
+
+/// Strip surrounding quotes from a string literal
+fn unquote(text: &str) -> String {
+    text.trim_matches(|c| c == '"' || c == '\'' || c == '`')
+        .to_string()
+}
+
+/// Hash content
+fn hash_content(content: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(content.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Content-addressed chunk id, stable across file renames and moves
+///
+/// The id is `language:symbol:hash` (or `language:hash` without a symbol),
+/// where `hash` is the first 16 hex digits of the SHA-256 of `content`.
+/// Identical chunks therefore share an id.
+pub fn generate_chunk_id(content: &str, symbol_name: Option<&str>, language: &str) -> String {
+    let hash = hash_content(content);
+    match symbol_name {
+        Some(name) => format!("{}:{}:{}", language, name, &hash[..16]),
+        None => format!("{}:{}", language, &hash[..16]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_incremental() {
+        let old = "function foo() {\n    return 1;\n}\n";
+        let new = "function foo() {\n    return 42;\n}\n";
+        let mut parsed = parse_tree(old, "typescript").unwrap();
+
+        // "1" at byte 28 replaced by "42"
+        let edit = TreeEdit {
+            start_byte: 28,
+            old_end_byte: 29,
+            new_end_byte: 30,
+            start_row: 1,
+            start_column: 11,
+            old_end_row: 1,
+            old_end_column: 12,
+            new_end_row: 1,
+            new_end_column: 13,
+        };
+
+        let old_tree = parsed.tree.clone();
+        let chunks =
+            parse_file_incremental("a.ts", &mut parsed, new, std::slice::from_ref(&edit)).unwrap();
+        let full = parse_file("a.ts", new, "typescript").unwrap();
+        assert_eq!(chunks.len(), full.len());
+        assert_eq!(chunks[0].hash, full[0].hash);
+        assert!(chunks[0].content.contains("42"));
+        assert_eq!(parsed.content, new);
+
+        // Only the edited number was re-parsed
+        let mut edited = old_tree;
+        edited.edit(&to_input_edit(&edit));
+        let changed: Vec<_> = edited.changed_ranges(&parsed.tree).collect();
+        assert!(
+            changed
+                .iter()
+                .all(|r| r.start_byte >= 28 && r.end_byte <= 30),
+            "{:?}",
+            changed
+        );
+
+        // The held tree seeds the next edit: "42" at byte 28 back to "1"
+        let undo = TreeEdit {
+            old_end_byte: 30,
+            new_end_byte: 29,
+            old_end_column: 13,
+            new_end_column: 12,
+            ..edit
+        };
+        let chunks = parse_file_incremental("a.ts", &mut parsed, old, &[undo]).unwrap();
+        assert_eq!(
+            chunks[0].hash,
+            parse_file("a.ts", old, "typescript").unwrap()[0].hash
+        );
+    }
+
+    #[test]
+    fn test_parse_file_from_tree() {
+        let content = "fn main() {}\n\nstruct Foo;\n";
+        let mut parsed = parse_tree(content, "rust").unwrap();
+
+        let reused = parse_file_from_tree("main.rs", content, &mut parsed).unwrap();
+        assert_eq!(reused.len(), 2);
+
+        let changed = parse_file_from_tree("main.rs", "fn main() {}\n", &mut parsed).unwrap();
+        assert_eq!(changed.len(), 1);
+        assert_eq!(parsed.content, "fn main() {}\n");
+
+        // Edits anywhere, including inside multi-byte characters, match a full parse
+        for new in [
+            "fn main() { let é = 1; }\n",
+            "/// ö\nfn main() {}\nfn ü() {}\n",
+            "",
+        ] {
+            let chunks = parse_file_from_tree("main.rs", new, &mut parsed).unwrap();
+            let full = parse_file("main.rs", new, "rust").unwrap();
+            assert_eq!(
+                chunks.iter().map(|c| &c.hash).collect::<Vec<_>>(),
+                full.iter().map(|c| &c.hash).collect::<Vec<_>>()
+            );
+            assert_eq!(
+                parsed.tree.root_node().to_sexp(),
+                get_parser("rust")
+                    .unwrap()
+                    .parse(new, None)
+                    .unwrap()
+                    .root_node()
+                    .to_sexp()
+            );
+        }
+    }
+
+    #[test]
+    fn test_content_edit() {
+        let edit = content_edit("ab\ncd\nef", "ab\ncXYd\nef");
+        assert_eq!(
+            (edit.start_byte, edit.old_end_byte, edit.new_end_byte),
+            (4, 4, 6)
+        );
+        assert_eq!(
+            (
+                edit.start_row,
+                edit.start_column,
+                edit.new_end_row,
+                edit.new_end_column
+            ),
+            (1, 1, 1, 3)
+        );
+
+        let edit = content_edit("aé", "aè");
+        assert_eq!(
+            (edit.start_byte, edit.old_end_byte, edit.new_end_byte),
+            (1, 3, 3)
+        );
+    }
+
+    #[test]
+    fn test_extract_imports_typescript() {
+        let content = r#"
+import Foo, { a, b as c } from './x';
+import type { T } from "./t";
+const { p, q } = require('pq');
+"#;
+        let imports = extract_imports("a.ts", content, "typescript").unwrap();
+        assert_eq!(imports.len(), 3);
+        assert_eq!(imports[0].source, "./x");
+        assert_eq!(imports[0].specifiers, vec!["Foo", "a", "b"]);
+        assert!(imports[1].is_type_only);
+        assert_eq!(imports[2].source, "pq");
+        assert_eq!(imports[2].specifiers, vec!["p", "q"]);
+
+        let content = "export { r } from './r';\nconst m = import('./lazy');\n";
+        let imports = extract_imports("a.ts", content, "typescript").unwrap();
+        assert_eq!(imports[0].kind, "re-export");
+        assert_eq!(imports[0].specifiers, vec!["r"]);
+        assert_eq!(imports[1].kind, "dynamic");
+    }
+
+    #[test]
+    fn test_extract_imports_python_and_rust() {
+        let python = "import os, a.b as c\nfrom ..pkg import y as z, w\n";
+        let imports = extract_imports("a.py", python, "python").unwrap();
+        assert_eq!(imports.len(), 3);
+        assert_eq!(imports[1].source, "a.b");
+        assert_eq!(imports[2].source, "..pkg");
+        assert_eq!(imports[2].specifiers, vec!["y", "w"]);
+
+        let rust = "use std::collections::HashMap;\nuse crate::{CodeChunk, a::{B, C as D}};\n";
+        let imports = extract_imports("lib.rs", rust, "rust").unwrap();
+        assert_eq!(imports[0].source, "std::collections");
+        assert_eq!(imports[0].specifiers, vec!["HashMap"]);
+        assert_eq!(imports[1].source, "crate");
+        assert_eq!(imports[1].specifiers, vec!["CodeChunk", "a::B", "a::C"]);
+    }
+
+    #[test]
+    fn test_extract_comments_rust() {
+        let content = "//! Crate doc\n/// Doc for foo\n/// more\n#[inline]\npub fn foo() {} // trailing\n/* block */\nstruct S;\n";
+        let comments = extract_comments("lib.rs", content, "rust").unwrap();
+        assert_eq!(comments.len(), 4);
+        assert_eq!(comments[0].kind, "doc");
+        assert_eq!(comments[0].associated_symbol, None);
+        assert_eq!(comments[1].content, "/// Doc for foo\n/// more");
+        assert_eq!(comments[1].end_line, 3);
+        assert_eq!(comments[1].associated_symbol.as_deref(), Some("foo"));
+        assert_eq!(comments[2].kind, "line");
+        assert_eq!(comments[3].kind, "block");
+        assert_eq!((comments[3].start_line, comments[3].end_line), (6, 6));
+    }
+
+    #[test]
+    fn test_extract_comments_python_docstring() {
+        let content = "# hash\ndef f():\n    \"\"\"Doc f.\"\"\"\n    x = \"not a doc\"\n";
+        let comments = extract_comments("a.py", content, "python").unwrap();
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].kind, "line");
+        assert_eq!(comments[1].kind, "doc");
+        assert_eq!(comments[1].associated_symbol.as_deref(), Some("f"));
+    }
+
+    #[test]
+    fn test_extract_comments_doc_block() {
+        let content = "/**\n * @param {string} name\n * @returns {string}\n */\nfunction greet(name) {\n    return name; // echo\n}\n";
+        let comments = extract_comments("a.js", content, "javascript").unwrap();
+        let block = comments[0].doc_block.as_ref().unwrap();
+        assert_eq!((comments[0].start_line, comments[0].end_line), (1, 4));
+        assert_eq!(comments[0].associated_symbol.as_deref(), Some("greet"));
+        assert_eq!(block.params[0].name, "name");
+        assert_eq!(block.params[0].type_hint.as_deref(), Some("string"));
+        assert_eq!(
+            block.returns.as_ref().unwrap().type_hint.as_deref(),
+            Some("string")
+        );
+        assert!(comments[1].doc_block.is_none());
+
+        let content = "<?php\n/**\n * @param int $n\n */\nfunction twice($n) { return $n * 2; }\n";
+        let comments = extract_comments("a.php", content, "php").unwrap();
+        let block = comments[0].doc_block.as_ref().unwrap();
+        assert_eq!(block.params[0].name, "$n");
+        assert_eq!(block.params[0].type_hint.as_deref(), Some("int"));
+    }
+
+    #[test]
+    fn test_symbol_parent_scope() {
+        let content = "class Greeter {\n    greet() {\n        return 1;\n    }\n}\n";
+        let symbols = extract_symbols("a.ts", content, "typescript", false).unwrap();
+        let method = symbols.iter().find(|s| s.name == "greet").unwrap();
+        assert_eq!(method.parent_name.as_deref(), Some("Greeter"));
+        assert_eq!(method.parent_kind.as_deref(), Some("class"));
+
+        let content = "impl Foo {\n    fn bar() {}\n}\n";
+        let symbols = extract_symbols("a.rs", content, "rust", false).unwrap();
+        assert_eq!(symbols[0].parent_name.as_deref(), Some("Foo"));
+        assert_eq!(symbols[0].parent_kind.as_deref(), Some("impl"));
+    }
+
+    #[test]
+    fn test_parse_php() {
+        let content = "<?php\ntrait Loud {\n    public function shout() {}\n}\n\nclass User {\n    public function greet(string $name): string {\n        return $name;\n    }\n\n    private function secret() {}\n}\n\nfunction helper($x) { return $x; }\n";
+        let chunks = parse_file("a.php", content, "auto").unwrap();
+        let kinds: Vec<(&str, &str)> = chunks
+            .iter()
+            .map(|c| {
+                (
+                    c.chunk_type.as_str(),
+                    c.symbols.first().map_or("", String::as_str),
+                )
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("interface", "Loud"),
+                ("method", "shout"),
+                ("class", "User"),
+                ("method", "greet"),
+                ("method", "secret"),
+                ("function", "helper"),
+            ]
+        );
+
+        let symbols = extract_symbols("a.php", content, "php", false).unwrap();
+        let find = |name: &str| symbols.iter().find(|s| s.name == name).unwrap();
+        assert_eq!(find("Loud").kind, "interface");
+        assert_eq!(find("shout").parent_name.as_deref(), Some("Loud"));
+        assert!(find("greet").exported);
+        assert!(!find("secret").exported);
+        assert_eq!(
+            find("greet").signature.as_deref(),
+            Some("greet($name: string): string")
+        );
+    }
+
+    #[test]
+    fn test_parse_jsx_components() {
+        let content = "export const Foo = () => <div className=\"a\" />;\n\nfunction Bar({ x }: { x: number }) {\n  const label = () => 'x';\n  return (\n    <>\n      <Foo />\n      {x > 0 && <span>{x}</span>}\n    </>\n  );\n}\n\nfunction total(a: number): number {\n  return a;\n}\n";
+        let chunks = parse_file("a.tsx", content, "tsx").unwrap();
+        let summary: Vec<(&str, Vec<&str>, u32)> = chunks
+            .iter()
+            .map(|c| {
+                (
+                    c.chunk_type.as_str(),
+                    c.symbols.iter().map(String::as_str).collect(),
+                    c.start_line,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("other", vec![], 1),
+                ("jsx_component", vec!["Foo"], 1),
+                ("jsx_component", vec!["Bar"], 3),
+                ("jsx_fragment", vec![], 6),
+                ("function", vec!["total"], 13),
+            ]
+        );
+
+        let content = "function App() {\n  return cond ? <Main /> : <div><p>hi</p></div>;\n}\n";
+        let chunks = parse_file("a.jsx", content, "jsx").unwrap();
+        let types: Vec<&str> = chunks.iter().map(|c| c.chunk_type.as_str()).collect();
+        assert_eq!(types, vec!["jsx_component", "jsx_element"]);
+        assert_eq!(chunks[1].symbols, vec!["div"]);
+        assert_eq!(chunks[1].content, "<div><p>hi</p></div>");
+    }
+
+    #[test]
+    fn test_parse_lua() {
+        let content = "-- Inventory\nlocal M = {}\n\nlocal function clamp(x, lo, hi)\n  return math.max(lo, math.min(x, hi))\nend\n\nfunction M.new(name)\n  return setmetatable({ name = name }, { __index = M })\nend\n\nfunction M:add(item, ...)\n  table.insert(self.items, item)\nend\n\nlocal on_event = function(e) return e end\n\nreturn M\n";
+        let chunks = parse_file("scripts/inventory.lua", content, "auto").unwrap();
+        let summary: Vec<(&str, Vec<String>, u32)> = chunks
+            .iter()
+            .map(|c| (c.chunk_type.as_str(), c.symbols.clone(), c.start_line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("function", vec!["clamp".to_string()], 4),
+                ("function", vec!["new".to_string()], 8),
+                ("function", vec!["add".to_string()], 12),
+                ("function", vec!["on_event".to_string()], 16),
+            ]
+        );
+
+        let symbols = extract_symbols("a.lua", content, "lua", false).unwrap();
+        let names: Vec<(&str, &str)> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("clamp", "function"),
+                ("new", "function"),
+                ("add", "function"),
+                ("on_event", "function")
+            ]
+        );
+        assert_eq!(symbols[0].signature.as_deref(), Some("clamp(x, lo, hi)"));
+        assert_eq!(symbols[2].signature.as_deref(), Some("add(item, ...)"));
+        assert!(supported_language_names().contains(&"lua".to_string()));
+        assert_eq!(detect_language("game/main.lua", None), Some("lua"));
+    }
+
+    #[test]
+    fn test_parse_scala() {
+        let content = "package jobs\n\nimport org.apache.spark.sql.DataFrame\nimport org.apache.spark.sql.functions._\n\nobject SalesJob {\n  val minAmount: Double = 10.0\n\n  def transform(df: DataFrame, region: String): DataFrame =\n    df.filter(col(\"region\") === region)\n      .withColumn(\"net\", col(\"amount\") * 0.9)\n      .groupBy(\"product\")\n      .agg(sum(\"net\").as(\"total\"))\n\n  private def audit(df: DataFrame): Unit = df.show()\n}\n\nprivate object Internal {\n  def run(): Unit = ()\n}\n\ntrait Step {\n  def apply(df: DataFrame): DataFrame\n}\n";
+        let chunks = parse_file("src/main/scala/SalesJob.scala", content, "auto").unwrap();
+        let summary: Vec<(&str, Vec<String>)> = chunks
+            .iter()
+            .map(|c| (c.chunk_type.as_str(), c.symbols.clone()))
+            .collect();
+        assert_eq!(summary[0], ("object", vec!["SalesJob".to_string()]));
+        assert!(summary.contains(&("variable", vec!["minAmount".to_string()])));
+        assert!(summary.contains(&("function", vec!["transform".to_string()])));
+        assert!(summary.contains(&("interface", vec!["Step".to_string()])));
+
+        let symbols = extract_symbols("SalesJob.scala", content, "scala", false).unwrap();
+        let find = |name: &str| symbols.iter().find(|s| s.name == name).unwrap();
+        let transform = find("transform");
+        assert_eq!(transform.kind, "function");
+        assert_eq!(transform.parent_name.as_deref(), Some("SalesJob"));
+        assert_eq!(transform.parent_kind.as_deref(), Some("object"));
+        assert_eq!(
+            transform.signature.as_deref(),
+            Some("transform(df: DataFrame, region: String): DataFrame")
+        );
+        assert!(transform.exported);
+        assert!(find("SalesJob").exported);
+        assert!(!find("audit").exported);
+        assert!(!find("Internal").exported);
+        assert!(!find("run").exported);
+        assert_eq!(find("Step").kind, "interface");
+        assert_eq!(find("apply").parent_name.as_deref(), Some("Step"));
+    }
+
+    #[test]
+    fn test_parse_elixir() {
+        let content = "defmodule MyApp.Accounts do\n  @moduledoc \"\"\"\n  Accounts context.\n  \"\"\"\n  alias MyApp.Repo\n\n  @doc \"Fetch a user.\"\n  @spec get_user(integer()) :: term()\n  def get_user(id) when is_integer(id), do: Repo.get(User, id)\n\n  def list_users do\n    Repo.all(User)\n  end\n\n  @doc false\n  # Trims names\n  defp normalize(user), do: user\nend\n";
+        let chunks = parse_file("lib/my_app/accounts.ex", content, "auto").unwrap();
+        let summary: Vec<(&str, Vec<String>, u32)> = chunks
+            .iter()
+            .map(|c| (c.chunk_type.as_str(), c.symbols.clone(), c.start_line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("module", vec!["MyApp.Accounts".to_string()], 1),
+                ("function", vec!["get_user".to_string()], 9),
+                ("function", vec!["list_users".to_string()], 11),
+                ("function", vec!["normalize".to_string()], 17),
+            ]
+        );
+        assert!(supported_languages()
+            .iter()
+            .any(|l| l.name == "elixir" && l.ast_chunking));
+
+        let symbols = extract_symbols("accounts.ex", content, "elixir", false).unwrap();
+        let find = |name: &str| symbols.iter().find(|s| s.name == name).unwrap();
+        assert_eq!(symbols.len(), 4);
+        assert_eq!(find("MyApp.Accounts").kind, "module");
+        assert_eq!(
+            find("get_user").signature.as_deref(),
+            Some("def get_user(id) when is_integer(id)")
+        );
+        assert_eq!(
+            find("get_user").parent_name.as_deref(),
+            Some("MyApp.Accounts")
+        );
+        assert!(find("get_user").exported);
+        assert!(find("list_users").exported);
+        assert!(!find("normalize").exported);
+
+        let comments = extract_comments("accounts.ex", content, "elixir").unwrap();
+        let summary: Vec<(&str, Option<&str>)> = comments
+            .iter()
+            .map(|c| (c.kind.as_str(), c.associated_symbol.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("doc", Some("MyApp.Accounts")),
+                ("doc", Some("get_user")),
+                ("line", None)
+            ]
+        );
+        assert_eq!(comments[1].content, "@doc \"Fetch a user.\"");
+    }
+
+    #[test]
+    fn test_parse_haskell() {
+        let content = "module Data.Shape (Shape(..), area) where\n\ntype Point = (Double, Double)\n\ndata Shape = Circle Double | Rect Double Double\n\nclass Describe a where\n  describe :: a -> String\n\ninstance Describe Shape where\n  describe _ = \"shape\"\n\n-- | Area of a shape\narea :: Shape -> Double\narea (Circle r) = pi * r * r\narea (Rect w h) = w * h\n\nmain :: IO ()\nmain = print (area (Circle 1))\n  where unit = 1\n";
+        let chunks = parse_file("src/Data/Shape.hs", content, "auto").unwrap();
+        let summary: Vec<(&str, Vec<String>, u32)> = chunks
+            .iter()
+            .map(|c| (c.chunk_type.as_str(), c.symbols.clone(), c.start_line))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("type", vec!["Point".to_string()], 3),
+                ("type", vec!["Shape".to_string()], 5),
+                ("function", vec!["describe".to_string()], 11),
+                ("function", vec!["area".to_string()], 14),
+                ("function", vec!["main".to_string()], 18),
+            ]
+        );
+        // One chunk from the type signature through the last equation
+        assert_eq!(chunks[3].end_line, 16);
+        assert!(chunks[3].content.starts_with("area :: Shape -> Double\n"));
+        assert!(chunks[3].content.ends_with("area (Rect w h) = w * h"));
+        assert!(supported_languages()
+            .iter()
+            .any(|l| l.name == "haskell" && l.ast_chunking));
+
+        let symbols = extract_symbols("Shape.hs", content, "haskell", false).unwrap();
+        let find = |name: &str| symbols.iter().find(|s| s.name == name).unwrap();
+        assert_eq!(symbols.len(), 5);
+        assert_eq!(find("Shape").signature.as_deref(), Some("data Shape"));
+        assert!(find("Shape").exported);
+        assert!(!find("Point").exported);
+
+        // Equations of one function make one symbol
+        let area = find("area");
+        assert_eq!((area.start_line, area.end_line), (15, 16));
+        assert_eq!(area.signature.as_deref(), Some("area (Circle r)"));
+        assert_eq!(
+            area.signature_annotation.as_deref(),
+            Some("area :: Shape -> Double")
+        );
+        assert!(area.exported);
+
+        assert_eq!(
+            find("main").signature_annotation.as_deref(),
+            Some("main :: IO ()")
+        );
+        assert!(!find("main").exported);
+        let describe = find("describe");
+        assert_eq!(describe.parent_name.as_deref(), Some("Describe"));
+        assert_eq!(describe.parent_kind.as_deref(), Some("impl"));
+        assert_eq!(describe.signature_annotation, None);
+    }
+
+    #[test]
+    fn test_parse_sql() {
+        let content = "-- Schema\nCREATE TABLE IF NOT EXISTS public.users (\n    id SERIAL PRIMARY KEY,\n    name TEXT NOT NULL\n);\n\nCREATE INDEX idx_users_name ON users (name);\n\nCREATE VIEW active_users AS SELECT * FROM users WHERE id > 0;\n\nCREATE TRIGGER users_audit AFTER INSERT ON users FOR EACH ROW EXECUTE FUNCTION audit();\n";
+        let chunks = parse_file("migrations/001_init.sql", content, "auto").unwrap();
+        let kinds: Vec<(&str, Vec<String>)> = chunks
+            .iter()
+            .map(|c| (c.chunk_type.as_str(), c.symbols.clone()))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                ("table", vec!["users".to_string()]),
+                ("index", vec!["idx_users_name".to_string()]),
+                ("view", vec!["active_users".to_string()]),
+                ("trigger", vec!["users_audit".to_string()]),
+            ]
+        );
+        assert_eq!(chunks[0].start_line, 2);
+        assert_eq!(chunks[0].end_line, 5);
+
+        let symbols = extract_symbols("a.sql", content, "sql", false).unwrap();
+        let names: Vec<(&str, &str)> = symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind.as_str()))
+            .collect();
+        assert_eq!(names[0], ("users", "table"));
+        assert!(names.contains(&("active_users", "view")));
+    }
+
+    #[test]
+    fn test_extract_strings() {
+        let content = "const greeting = \"hi\\n\";\nfunction f() {\n    return `x ${1}`;\n}\n";
+        let options = ParseOptions {
+            extract_strings: Some(true),
+            ..Default::default()
+        };
+        let chunks = parse_file_with_options("a.ts", content, "typescript", &options)
+            .unwrap()
+            .chunks;
+        let strings: Vec<(&str, Vec<String>)> = chunks
+            .iter()
+            .filter(|c| c.chunk_type == "string_literal")
+            .map(|c| (c.content.as_str(), c.symbols.clone()))
+            .collect();
+        assert_eq!(
+            strings,
+            vec![
+                ("hi\\n", vec!["greeting".to_string()]),
+                ("x ${1}", vec!["f".to_string()]),
+            ]
+        );
+
+        let chunks = parse_file("a.ts", content, "typescript").unwrap();
+        assert!(chunks.iter().all(|c| c.chunk_type != "string_literal"));
+
+        assert_eq!(string_literal_value("r#\"raw\"#"), "raw");
+        assert_eq!(string_literal_value("f'''doc'''"), "doc");
+    }
+
+    #[test]
+    fn test_generate_chunk_id() {
+        let id = generate_chunk_id("fn a() {}", Some("a"), "rust");
+        assert_eq!(id, format!("rust:a:{}", &hash_content("fn a() {}")[..16]));
+        assert_eq!(
+            generate_chunk_id("x", None, "go"),
+            format!("go:{}", &hash_content("x")[..16])
+        );
+
+        // Renaming the file keeps the id
+        let old = parse_file("old.rs", "fn a() {}\n", "rust").unwrap();
+        let new = parse_file("src/new.rs", "\n\nfn a() {}\n", "rust").unwrap();
+        assert_eq!(old[0].id, id);
+        assert_eq!(new[0].id, id);
+    }
+
+    #[test]
+    fn test_parse_file_ignore_pragmas() {
+        let content = "function a() {}\n// @titan-ignore\nfunction b() {}\n// @titan-ignore-start\nfunction c() {}\n// @titan-ignore-end\nclass D {\n    // @titan-ignore\n    e() {}\n    f() {}\n}\n";
+        let chunks = parse_file("a.ts", content, "typescript").unwrap();
+        let kept: Vec<(u32, &str)> = chunks
+            .iter()
+            .map(|c| (c.start_line, c.content.as_str()))
+            .collect();
+        // The class overlaps the ignored method, its other methods remain
+        assert_eq!(kept, vec![(1, "function a() {}"), (10, "f() {}")]);
+    }
+
+    #[test]
+    fn test_parse_file_with_options() {
+        let content = "import { b } from './b';\n\n/** Adds */\nexport function add(x: number) {\n    return x + 1;\n}\n";
+        let options = ParseOptions {
+            extract_symbols: Some(true),
+            extract_imports: Some(true),
+            extract_comments: Some(true),
+            ..Default::default()
+        };
+        let result = parse_file_with_options("a.ts", content, "typescript", &options).unwrap();
+        assert_eq!(
+            result.chunks.len(),
+            parse_file("a.ts", content, "typescript").unwrap().len()
+        );
+        assert_eq!(
+            result.symbols.len(),
+            extract_symbols("a.ts", content, "typescript", false)
+                .unwrap()
+                .len()
+        );
+        assert_eq!(result.imports.len(), 1);
+        assert_eq!(
+            result.comments.len(),
+            extract_comments("a.ts", content, "typescript")
+                .unwrap()
+                .len()
+        );
+        assert!(!result.comments.is_empty());
+        assert!(result.error.is_none());
+
+        let options = ParseOptions {
+            extract_chunks: Some(false),
+            ..Default::default()
+        };
+        let result = parse_file_with_options("a.ts", content, "typescript", &options).unwrap();
+        assert!(result.chunks.is_empty() && result.symbols.is_empty() && result.imports.is_empty());
+
+        let options = ParseOptions {
+            exclude_test_files: Some(true),
+            ..Default::default()
+        };
+        assert!(
+            !parse_file_with_options("a.ts", content, "typescript", &options)
+                .unwrap()
+                .chunks
+                .is_empty()
+        );
+        let result = parse_file_with_options("a.test.ts", content, "typescript", &options).unwrap();
+        assert!(result.chunks.is_empty() && result.error.is_none());
+    }
+
+    #[test]
+    fn test_node_kinds() {
+        for language in supported_language_names() {
+            let kinds = node_kinds(&language).unwrap();
+            assert!(kinds.windows(2).all(|w| w[0] < w[1]), "{}", language);
+            assert!(
+                !kinds.iter().any(|k| k == "ERROR"),
+                "{} snippet has syntax errors",
+                language
+            );
+        }
+
+        let rust = node_kinds("rust").unwrap();
+        for kind in [
+            "function_item",
+            "impl_item",
+            "match_expression",
+            "macro_definition",
+            "{",
+        ] {
+            assert!(rust.iter().any(|k| k == kind), "{}", kind);
+        }
+        assert!(node_kinds("tsx")
+            .unwrap()
+            .iter()
+            .any(|k| k == "jsx_self_closing_element"));
+        assert!(!node_kinds("typescript")
+            .unwrap()
+            .iter()
+            .any(|k| k.starts_with("jsx")));
+        assert!(node_kinds("cobol").is_err());
+    }
+
+    #[test]
+    fn test_symbol_generic_params() {
+        let generics =
+            |file_path: &str, content: &str, language: &str| -> Vec<(String, Vec<String>)> {
+                extract_symbols(file_path, content, language, false)
+                    .unwrap()
+                    .into_iter()
+                    .map(|s| (s.name, s.generic_params))
+                    .collect()
+            };
+        let owned = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        let rust = "fn f<'a, T: Clone, U, const N: usize>(x: &'a T) {}\nstruct S<K> where K: Copy { k: K }\nfn plain() {}\n";
+        assert_eq!(
+            generics("a.rs", rust, "rust"),
+            vec![
+                ("f".to_string(), owned(&["'a", "T", "U", "N"])),
+                ("S".to_string(), owned(&["K"])),
+                ("plain".to_string(), vec![]),
+            ]
+        );
+
+        let ts =
+            "function f<T extends string, U = number>(x: T): U {}\ninterface Box<V> { v: V }\n";
+        assert_eq!(
+            generics("a.ts", ts, "typescript"),
+            vec![
+                ("f".to_string(), owned(&["T", "U"])),
+                ("Box".to_string(), owned(&["V"]))
+            ]
+        );
+
+        let go = "package a\n\nfunc Map[K comparable, V, W any](m map[K]V) W {}\n";
+        assert_eq!(
+            generics("a.go", go, "go"),
+            vec![("Map".to_string(), owned(&["K", "V", "W"]))]
+        );
+    }
+
+    #[test]
+    fn test_language_version() {
+        let content = "'use client';\n// @ts-nocheck\n\"use strict\"\nconst use = 'use server' + x;\nexport function f() {}\n";
+        let chunks = parse_file("a.tsx", content, "tsx").unwrap();
+        assert!(!chunks.is_empty());
+        assert!(chunks.iter().all(|c| c.language_version.as_deref()
+            == Some("\"use client\", @ts-nocheck, \"use strict\"")));
+
+        let python = "#!/usr/bin/env python3\nfrom __future__ import annotations  # PEP 563\n\ndef f():\n    pass\n";
+        assert_eq!(
+            language_version(python, "python").as_deref(),
+            Some("python3, from __future__ import annotations")
+        );
+        let rust = "#![no_std]\n#![allow(dead_code)]\n#![feature(let_chains)]\n#![allow(unstable_features)]\nfn f() {}\n";
+        assert_eq!(
+            language_version(rust, "rust").as_deref(),
+            Some("#![no_std], #![feature(let_chains)], #![allow(unstable_features)]")
+        );
+
+        assert_eq!(language_version("function f() {}\n", "javascript"), None);
+        assert_eq!(
+            language_version(
+                &format!("{}\"use strict\";\n", "\n".repeat(10)),
+                "javascript"
+            ),
+            None
+        );
+        assert_eq!(language_version("#![no_std]\n", "python"), None);
+        assert!(
+            parse_file("a.js", "export function f() {}\n", "javascript").unwrap()[0]
+                .language_version
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_is_test_file() {
+        for path in [
+            "src/__tests__/a.js",
+            "src/a.test.ts",
+            "a.spec.tsx",
+            "tests/helpers.rs",
+            "test_a.py",
+            "pkg/a_test.go",
+            "app\\spec\\a.rb",
+        ] {
+            assert!(is_test_file(path, "", "auto"), "{}", path);
+        }
+        for path in [
+            "src/latest.ts",
+            "src/contest/a.py",
+            "testing/a.go",
+            "src/attestation.rs",
+        ] {
+            assert!(!is_test_file(path, "", "auto"), "{}", path);
+        }
+
+        assert!(is_test_file(
+            "a.js",
+            "const x = require('x');\n\ndescribe('x', () => {",
+            "javascript"
+        ));
+        assert!(is_test_file("a.js", "it('works', () => {})", "javascript"));
+        assert!(!is_test_file(
+            "a.js",
+            "form.submit();\nemit('x');\n",
+            "javascript"
+        ));
+        assert!(is_test_file(
+            "a.rs",
+            "use super::*;\n\n#[test]\nfn a() {}\n",
+            "rust"
+        ));
+        assert!(is_test_file(
+            "a.py",
+            "import pytest\n\ndef test_a():\n",
+            "python"
+        ));
+        assert!(is_test_file(
+            "a.go",
+            "package a\n\nfunc TestA(t *testing.T) {",
+            "go"
+        ));
+        assert!(!is_test_file(
+            "a.go",
+            "package a\n\nfunc TestA(t *testing.T) {",
+            "python"
+        ));
+
+        // Only the start of the content is looked at
+        let late = format!("{}\ndescribe('x', () => {{}});\n", "// filler\n".repeat(30));
+        assert!(!is_test_file("a.js", &late, "javascript"));
+    }
+
+    #[test]
+    fn test_language_from_content() {
+        let detect = |content: &str| language_from_content(content, DEFAULT_CONTENT_CONFIDENCE);
+        assert_eq!(
+            detect("package main\n\nimport \"fmt\"\n\nfunc main() {\n    fmt.Println(1)\n}\n"),
+            Some("go")
+        );
+        assert_eq!(
+            detect("#[derive(Debug)]\nstruct A;\n\nfn main() {}\n"),
+            Some("rust")
+        );
+        assert_eq!(
+            detect("def main():\n    print(1)\n\nif __name__ == \"__main__\":\n    main()\n"),
+            Some("python")
+        );
+        assert_eq!(
+            detect("'use strict';\nconst fs = require('fs');\nmodule.exports = {};\n"),
+            Some("javascript")
+        );
+        assert_eq!(
+            detect("import x from './x';\nexport interface A {\n  name: string;\n}\n"),
+            Some("typescript")
+        );
+        assert_eq!(
+            detect("import React from 'react';\nexport default () => <div className=\"a\" />;\n"),
+            Some("jsx")
+        );
+        assert_eq!(detect("create table users (\n  id int\n);\n"), Some("sql"));
+        assert_eq!(detect("<?php\necho 1;\n"), Some("php"));
+        assert_eq!(detect("#!/usr/bin/env node\n"), Some("javascript"));
+
+        // Too little to go on, or signals of two languages
+        assert_eq!(detect("hello world\n"), None);
+        assert_eq!(detect("const a = 1;\n"), None);
+        assert_eq!(detect("fn a() {}\ndef b():\n    pass\n"), None);
+        assert_eq!(
+            language_from_content("const a = 1;\n", 0.1),
+            Some("javascript")
+        );
+    }
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(detect_language("src/a.mjs", None), Some("javascript"));
+        assert_eq!(
+            detect_language("bin/tool", Some("#!/usr/bin/env python3\nprint(1)\n")),
+            Some("python")
+        );
+        assert_eq!(
+            detect_language("bin/tool", Some("#!/usr/bin/python3.12\n")),
+            Some("python")
+        );
+        assert_eq!(
+            detect_language("bin/tool", Some("#!/usr/bin/env -S deno run\n")),
+            Some("typescript")
+        );
+        assert_eq!(detect_language("bin/tool", Some("#!/bin/sh\n")), None);
+        assert_eq!(detect_language("README", None), None);
+        assert_eq!(
+            detect_language("main", Some("package main\n\nfunc main() {}\n")),
+            Some("go")
+        );
+
+        let content = "#!/usr/bin/env node\nfunction main() {}\n";
+        let chunks = parse_file("bin/cli", content, "auto").unwrap();
+        assert_eq!(chunks[0].language, "javascript");
+        assert!(parse_file("README", "text", "auto").is_err());
+    }
+
+    #[test]
+    fn test_get_ast_json() {
+        let json = get_ast_json("let x = 1;", "typescript").unwrap();
+        let ast: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(ast["kind"], "program");
+        assert!(ast.get("text").is_none());
+
+        let declaration = &ast["children"][0];
+        assert_eq!(declaration["kind"], "lexical_declaration");
+        assert_eq!(declaration["end_position"]["column"], 10);
+        let keyword = &declaration["children"][0];
+        assert_eq!(keyword["text"], "let");
+        assert_eq!(keyword["is_named"], false);
+    }
+
+    #[test]
+    fn test_symbol_references() {
+        let content = "function add(a, b) {\n    return a + b;\n}\nconst x = add(1, add(2, 3));\n";
+        let symbols = extract_symbols("a.ts", content, "typescript", false).unwrap();
+        assert!(symbols[0].references.is_none());
+
+        let symbols = extract_symbols("a.ts", content, "typescript", true).unwrap();
+        let refs = symbols[0].references.as_ref().unwrap();
+        let positions: Vec<(u32, u32)> = refs.iter().map(|r| (r.line, r.column)).collect();
+        assert_eq!(positions, vec![(4, 10), (4, 17)]);
+        assert!(refs.iter().all(|r| r.file_path == "a.ts"));
+    }
+
+    #[test]
+    fn test_symbol_columns() {
+        let content = "let x = 1;\n/* é */ function f() {}\n";
+        let symbols = extract_symbols("a.ts", content, "typescript", false).unwrap();
+        let f = symbols.iter().find(|s| s.name == "f").unwrap();
+        assert_eq!(f.start_column, 9);
+        assert_eq!(f.end_column, 24);
+        assert_eq!(f.start_column_char, 8);
+    }
+
+    #[test]
+    fn test_extract_variables() {
+        let content = "export const A: number = 1, b = 2;\nlet c;\nvar { d, e: f } = g;\nfunction h() { const inner = 1; }\n";
+        let vars = extract_variables("a.ts", content, "typescript").unwrap();
+        let summary: Vec<(&str, &str, &str, bool)> = vars
+            .iter()
+            .map(|v| {
+                (
+                    v.name.as_str(),
+                    v.kind.as_str(),
+                    v.signature.as_deref().unwrap(),
+                    v.exported,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("A", "constant", "const A: number", true),
+                ("b", "constant", "const b", true),
+                ("c", "variable", "let c", false),
+                ("d", "variable", "var d", false),
+                ("f", "variable", "var f", false),
+            ]
+        );
+
+        let content = "static mut COUNT: u32 = 0;\npub const MAX: usize = 3;\nfn f() { static INNER: u8 = 1; }\n";
+        let vars = extract_variables("a.rs", content, "rust").unwrap();
+        assert_eq!(vars.len(), 2);
+        assert_eq!(
+            (vars[0].kind.as_str(), vars[0].signature.as_deref()),
+            ("variable", Some("static mut COUNT: u32"))
+        );
+        assert_eq!(
+            (vars[1].kind.as_str(), vars[1].signature.as_deref()),
+            ("constant", Some("pub const MAX: usize"))
+        );
+        assert!(vars[1].exported);
+
+        let content = "MAX_SIZE: int = 10\nx = _y = 3\ndef f():\n    inner = 1\n";
+        let vars = extract_variables("a.py", content, "python").unwrap();
+        let names: Vec<(&str, &str)> = vars
+            .iter()
+            .map(|v| (v.name.as_str(), v.kind.as_str()))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("MAX_SIZE", "constant"),
+                ("x", "variable"),
+                ("_y", "variable")
+            ]
+        );
+        assert_eq!(vars[0].signature.as_deref(), Some("MAX_SIZE: int"));
+        assert!(!vars[2].exported);
+    }
+
+    #[test]
+    fn test_get_symbol_at_position() {
+        let content = "class Greeter {\n    greet() {\n        return 1;\n    }\n}\n";
+        let symbol = get_symbol_at_position(content, "typescript", 3, 9)
+            .unwrap()
+            .unwrap();
+        assert_eq!(symbol.name, "greet");
+
+        let symbol = get_symbol_at_position(content, "typescript", 1, 3)
+            .unwrap()
+            .unwrap();
+        assert_eq!(symbol.name, "Greeter");
+
+        assert!(get_symbol_at_position(content, "typescript", 8, 1)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_extract_signature() {
+        let content =
+            "function foo(\n    a: number,\n    b: string,\n): boolean {\n    return true;\n}\n";
+        let symbols = extract_symbols("a.ts", content, "typescript", false).unwrap();
+        assert_eq!(
+            symbols[0].signature.as_deref(),
+            Some("foo(a: number, b: string): boolean")
+        );
+        assert_eq!(symbols[0].parameters.len(), 2);
+        assert_eq!(
+            symbols[0].parameters[1].type_annotation.as_deref(),
+            Some("string")
+        );
+
+        let content = "impl A {\n    pub fn foo(&self, a: i32) -> bool { true }\n}\n";
+        let symbols = extract_symbols("a.rs", content, "rust", false).unwrap();
+        assert_eq!(
+            symbols[0].signature.as_deref(),
+            Some("foo(&self, a: i32) -> bool")
+        );
+        assert_eq!(symbols[0].parameters[0].name, "&self");
+        assert!(symbols[0].parameters[0].type_annotation.is_none());
+    }
+
+    #[test]
+    fn test_supported_languages() {
+        let languages = supported_languages();
+        assert_eq!(languages.len(), supported_language_names().len());
+
+        let ts = &languages[0];
+        assert_eq!(ts.name, "typescript");
+        assert_eq!(ts.aliases, vec!["tsx"]);
+        assert!(ts.ast_chunking);
+        assert!(ts.supported_symbol_kinds.contains(&"interface".to_string()));
+        assert!(ts.grammar_version.parse::<u32>().unwrap() >= 13);
+
+        let go = languages.iter().find(|l| l.name == "go").unwrap();
+        assert_eq!(go.supported_symbol_kinds, vec!["function", "method"]);
+    }
+
+    #[test]
+    fn test_parser_errors() {
+        let err = parse_file("a.cob", "IDENTIFICATION DIVISION.", "cobol").unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ParserError>(),
+            Some(ParserError::UnsupportedLanguage(language)) if language == "cobol"
+        ));
+        assert_eq!(err.to_string(), "Unsupported language: cobol");
+        assert!(extract_symbols("a.cob", "", "cobol", false)
+            .unwrap_err()
+            .is::<ParserError>());
+
+        assert_eq!(
+            parse_failed("a.ts").to_string(),
+            "Failed to parse file a.ts: parsing was cancelled or timed out"
+        );
+        assert_eq!(
+            parse_failed("").to_string(),
+            "Failed to parse file: parsing was cancelled or timed out"
+        );
+    }
+
+    #[test]
+    fn test_profile_parse_file() {
+        let content =
+            "function a(x) {\n    return x + 1;\n}\n\nfunction b() {\n    return a(2);\n}\n";
+        let profile = profile_parse_file("a.js", content, "javascript").unwrap();
+        assert_eq!(profile.node_count_by_kind["program"], 1);
+        assert_eq!(profile.node_count_by_kind["function_declaration"], 2);
+        assert_eq!(profile.node_count_by_kind["return_statement"], 2);
+        assert_eq!(profile.node_count_by_kind["function"], 2);
+
+        let phases = profile.tree_construction_ms
+            + profile.chunk_extraction_ms
+            + profile.symbol_extraction_ms;
+        assert!(profile.tree_construction_ms > 0.0);
+        assert!(profile.total_ms >= phases);
+
+        assert!(profile_parse_file("a.txt", content, "auto").is_err());
+    }
+
+    #[test]
+    fn test_parse_file_with_errors() {
+        let content = "function ok(a) {\n    return a;\n}\n\nfunction broken() {\n    const x = (1 + ;\n}\n\nclass Fine {}\n";
+        let result = parse_file_with_errors("a.ts", content, "typescript").unwrap();
+        let names: Vec<&str> = result.symbols.iter().map(|s| s.name.as_str()).collect();
+        assert!(
+            names.contains(&"ok") && names.contains(&"broken") && names.contains(&"Fine"),
+            "{:?}",
+            names
+        );
+        assert!(result
+            .chunks
+            .iter()
+            .any(|c| c.symbols.first().map(String::as_str) == Some("ok")));
+
+        assert!(!result.syntax_errors.is_empty());
+        assert!(
+            result
+                .syntax_errors
+                .iter()
+                .all(|e| (5..=7).contains(&e.start_line)),
+            "{:?}",
+            result.syntax_errors
+        );
+
+        let clean = parse_file_with_errors("a.ts", "const a = 1;\n", "typescript").unwrap();
+        assert!(clean.syntax_errors.is_empty());
+
+        let missing = parse_file_with_errors("a.py", "def f(:\n    pass\n", "python").unwrap();
+        assert!(!missing.syntax_errors.is_empty());
+    }
 }
@@ -1,9 +1,14 @@
 // Rust performance module with warmup, GPU hooks, quantization
 // packages/indexer-native/src/performance.rs
 
+use dashmap::DashMap;
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
 use std::time::Instant;
 
 /// GPU availability flags
@@ -15,6 +20,162 @@ static VULKAN_AVAILABLE: AtomicBool = AtomicBool::new(false);
 static TOTAL_OPERATIONS: AtomicU64 = AtomicU64::new(0);
 static TOTAL_DURATION_NS: AtomicU64 = AtomicU64::new(0);
 
+/// Durations of the latest operations, for percentiles
+static LATENCIES: Mutex<LatencyRing> = Mutex::new(LatencyRing::new(10_000));
+
+/// The last `capacity` durations, oldest overwritten first
+struct LatencyRing {
+    samples: Vec<u64>,
+    capacity: usize,
+    next: usize,
+}
+
+impl LatencyRing {
+    const fn new(capacity: usize) -> Self {
+        Self {
+            samples: Vec::new(),
+            capacity,
+            next: 0,
+        }
+    }
+
+    fn push(&mut self, duration_ns: u64) {
+        if self.samples.len() < self.capacity {
+            self.samples.push(duration_ns);
+        } else {
+            self.samples[self.next] = duration_ns;
+        }
+        self.next = (self.next + 1) % self.capacity;
+    }
+
+    fn clear(&mut self) {
+        self.samples.clear();
+        self.next = 0;
+    }
+}
+
+/// Nearest-rank percentiles of durations; all zero without any
+///
+/// The samples are sorted exactly, which the ring's size keeps cheap.
+fn latency_histogram(mut samples: Vec<u64>) -> LatencyHistogram {
+    samples.sort_unstable();
+    let percentile = |p: f64| -> f64 {
+        if samples.is_empty() {
+            return 0.0;
+        }
+        let rank = (p * samples.len() as f64).ceil() as usize;
+        samples[rank.clamp(1, samples.len()) - 1] as f64
+    };
+
+    LatencyHistogram {
+        p50_ns: percentile(0.50),
+        p90_ns: percentile(0.90),
+        p95_ns: percentile(0.95),
+        p99_ns: percentile(0.99),
+        p999_ns: percentile(0.999),
+        min_ns: samples.first().copied().unwrap_or(0) as f64,
+        max_ns: samples.last().copied().unwrap_or(0) as f64,
+    }
+}
+
+lazy_static::lazy_static! {
+    /// Per-label performance metrics
+    static ref LABELED_METRICS: LabeledMetrics = LabeledMetrics::default();
+
+    /// Indexing throughput, calibrated by `run_warmup`
+    static ref INDEXING_THROUGHPUT: Mutex<ThroughputModel> = Mutex::new(ThroughputModel::default());
+}
+
+/// Assumed indexing throughput until a warmup has measured one
+const DEFAULT_INDEXING_BYTES_PER_SECOND: f64 = 2.0 * 1024.0 * 1024.0;
+
+/// Seconds of indexing work a recommended batch aims for
+const TARGET_BATCH_SECONDS: f64 = 0.5;
+
+/// Largest recommended batch, as for `optimize_batch_size`
+const MAX_RECOMMENDED_BATCH: u32 = 1024;
+
+/// Bytes per second parsed and chunked, averaged over every calibration
+struct ThroughputModel {
+    bytes_per_second: f64,
+    samples: u32,
+}
+
+impl Default for ThroughputModel {
+    fn default() -> Self {
+        Self {
+            bytes_per_second: DEFAULT_INDEXING_BYTES_PER_SECOND,
+            samples: 0,
+        }
+    }
+}
+
+impl ThroughputModel {
+    /// Fold in a measurement; the first replaces the default
+    fn record(&mut self, bytes_per_second: f64) {
+        if !bytes_per_second.is_finite() || bytes_per_second <= 0.0 {
+            return;
+        }
+        let samples = self.samples as f64;
+        self.bytes_per_second =
+            (self.bytes_per_second * samples + bytes_per_second) / (samples + 1.0);
+        self.samples += 1;
+    }
+}
+
+/// Operation counts and total durations keyed by label
+#[derive(Default)]
+struct LabeledMetrics {
+    /// Label -> (total operations, total duration in ns)
+    entries: DashMap<String, (AtomicU64, AtomicU64)>,
+}
+
+impl LabeledMetrics {
+    /// Add one operation under a label
+    fn record(&self, label: &str, duration_ns: u64) {
+        // Avoid the write lock and key allocation for labels already seen
+        if let Some(entry) = self.entries.get(label) {
+            entry.0.fetch_add(1, Ordering::Relaxed);
+            entry.1.fetch_add(duration_ns, Ordering::Relaxed);
+            return;
+        }
+        let entry = self
+            .entries
+            .entry(label.to_string())
+            .or_insert_with(|| (AtomicU64::new(0), AtomicU64::new(0)));
+        entry.0.fetch_add(1, Ordering::Relaxed);
+        entry.1.fetch_add(duration_ns, Ordering::Relaxed);
+    }
+
+    /// Snapshot of every label, sorted by label
+    fn snapshot(&self) -> Vec<LabeledMetricEntry> {
+        let mut entries: Vec<LabeledMetricEntry> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let total_ops = entry.value().0.load(Ordering::Relaxed);
+                let total_duration = entry.value().1.load(Ordering::Relaxed);
+                LabeledMetricEntry {
+                    label: entry.key().clone(),
+                    total_operations: total_ops as i64,
+                    average_latency_ns: if total_ops > 0 {
+                        total_duration as f64 / total_ops as f64
+                    } else {
+                        0.0
+                    },
+                    ops_per_second: if total_duration > 0 {
+                        (total_ops as f64 * 1_000_000_000.0) / total_duration as f64
+                    } else {
+                        0.0
+                    },
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| a.label.cmp(&b.label));
+        entries
+    }
+}
+
 /// GPU device info
 #[napi(object)]
 pub struct GpuDeviceInfo {
@@ -29,7 +190,7 @@ pub struct GpuDeviceInfo {
 #[napi]
 pub fn detect_gpu_backends() -> Vec<GpuDeviceInfo> {
     let mut devices = Vec::new();
-    
+
     // Check CUDA availability (placeholder - real implementation would use cuda-sys)
     #[cfg(target_os = "windows")]
     {
@@ -44,7 +205,7 @@ pub fn detect_gpu_backends() -> Vec<GpuDeviceInfo> {
             });
         }
     }
-    
+
     // Check Metal availability (macOS)
     #[cfg(target_os = "macos")]
     {
@@ -57,7 +218,7 @@ pub fn detect_gpu_backends() -> Vec<GpuDeviceInfo> {
             backend: "metal".to_string(),
         });
     }
-    
+
     // Check Vulkan availability
     #[cfg(any(target_os = "windows", target_os = "linux"))]
     {
@@ -73,7 +234,7 @@ pub fn detect_gpu_backends() -> Vec<GpuDeviceInfo> {
             });
         }
     }
-    
+
     devices
 }
 
@@ -117,6 +278,8 @@ pub struct WarmupConfig {
     pub warmup_embedding: bool,
     pub warmup_parsing: bool,
     pub warmup_search: bool,
+    /// Languages whose Tree-sitter grammars are loaded and exercised
+    pub warmup_languages: Option<Vec<String>>,
 }
 
 /// Warmup result
@@ -135,7 +298,7 @@ pub fn run_warmup(config: WarmupConfig) -> WarmupResult {
     let mut embedding_total = 0u64;
     let mut parsing_total = 0u64;
     let mut search_total = 0u64;
-    
+
     if config.warmup_embedding {
         for _ in 0..config.iterations {
             let op_start = Instant::now();
@@ -144,7 +307,7 @@ pub fn run_warmup(config: WarmupConfig) -> WarmupResult {
             embedding_total += op_start.elapsed().as_micros() as u64;
         }
     }
-    
+
     if config.warmup_parsing {
         for _ in 0..config.iterations {
             let op_start = Instant::now();
@@ -154,7 +317,29 @@ pub fn run_warmup(config: WarmupConfig) -> WarmupResult {
             parsing_total += op_start.elapsed().as_micros() as u64;
         }
     }
-    
+
+    // Unsupported languages are skipped rather than failing the warmup
+    for language in config.warmup_languages.iter().flatten() {
+        if let Ok(result) = warmup_parser(language.clone(), Some(config.iterations)) {
+            parsing_total += (result.parsing_latency_ms * 1000.0 * config.iterations as f64) as u64;
+        }
+    }
+
+    let mut calibration_languages: Vec<&str> = config
+        .warmup_languages
+        .iter()
+        .flatten()
+        .map(String::as_str)
+        .collect();
+    if calibration_languages.is_empty() && config.warmup_parsing {
+        calibration_languages.push("typescript");
+    }
+    for language in calibration_languages {
+        if let Some(bytes_per_second) = measure_indexing_throughput(language) {
+            INDEXING_THROUGHPUT.lock().unwrap().record(bytes_per_second);
+        }
+    }
+
     if config.warmup_search {
         for _ in 0..config.iterations {
             let op_start = Instant::now();
@@ -164,9 +349,9 @@ pub fn run_warmup(config: WarmupConfig) -> WarmupResult {
             search_total += op_start.elapsed().as_micros() as u64;
         }
     }
-    
+
     let iterations = config.iterations.max(1) as f64;
-    
+
     WarmupResult {
         embedding_latency_ms: (embedding_total as f64 / iterations) / 1000.0,
         parsing_latency_ms: (parsing_total as f64 / iterations) / 1000.0,
@@ -175,6 +360,94 @@ pub fn run_warmup(config: WarmupConfig) -> WarmupResult {
     }
 }
 
+/// Load a language's grammar and parse a small snippet `iterations` times
+///
+/// `parsing_latency_ms` is the average per parse, excluding grammar
+/// loading; `total_duration_ms` includes it.
+#[napi]
+pub fn warmup_parser(language: String, iterations: Option<u32>) -> Result<WarmupResult> {
+    let start = Instant::now();
+    let iterations = iterations.unwrap_or(1).max(1);
+
+    let mut parser =
+        crate::parser::get_parser(&language).map_err(|e| Error::from_reason(e.to_string()))?;
+    let snippet = warmup_snippet(&language);
+
+    let parse_start = Instant::now();
+    for _ in 0..iterations {
+        parser.parse(snippet, None).ok_or_else(|| {
+            Error::from_reason(format!("Failed to parse {} warmup snippet", language))
+        })?;
+    }
+    let parsing_total = parse_start.elapsed().as_micros() as f64;
+
+    Ok(WarmupResult {
+        embedding_latency_ms: 0.0,
+        parsing_latency_ms: (parsing_total / iterations as f64) / 1000.0,
+        search_latency_ms: 0.0,
+        total_duration_ms: start.elapsed().as_secs_f64() * 1000.0,
+    })
+}
+
+/// Tiny representative source for each language
+fn warmup_snippet(language: &str) -> &'static str {
+    match language {
+        "python" => "def main():\n    print(\"Hello\")\n",
+        "rust" => "fn main() { println!(\"Hello\"); }\n",
+        "go" => "package main\n\nfunc main() { println(\"Hello\") }\n",
+        _ => "export function main(): void { console.log(\"Hello\"); }\n",
+    }
+}
+
+/// Bytes per second `parse_file` gets through on a file of warmup snippets
+fn measure_indexing_throughput(language: &str) -> Option<f64> {
+    let source = warmup_snippet(language).repeat(200);
+    let start = Instant::now();
+    crate::parser::parse_file("warmup", &source, language).ok()?;
+    Some(source.len() as f64 / start.elapsed().as_secs_f64())
+}
+
+/// Expected cost of indexing a set of files
+#[napi(object)]
+pub struct IndexingEstimate {
+    pub estimated_seconds: f64,
+    pub total_bytes: i64,
+    pub file_count: u32,
+    pub recommended_batch_size: u32,
+}
+
+/// Estimate how long indexing files will take from their sizes on disk
+///
+/// Files are not read; paths that are missing or not files are left out
+/// of the counts. The throughput is the average measured by `run_warmup`
+/// calls so far, or 2 MiB/s before the first. Batches are sized to about
+/// half a second of work each.
+#[napi]
+pub fn estimate_indexing_time(file_paths: Vec<String>) -> IndexingEstimate {
+    let sizes: Vec<u64> = file_paths
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .collect();
+    let bytes_per_second = INDEXING_THROUGHPUT.lock().unwrap().bytes_per_second;
+    indexing_estimate(&sizes, bytes_per_second)
+}
+
+/// Estimate for files of the given sizes at a throughput
+fn indexing_estimate(sizes: &[u64], bytes_per_second: f64) -> IndexingEstimate {
+    let total_bytes: u64 = sizes.iter().sum();
+    let average_size = total_bytes as f64 / sizes.len().max(1) as f64;
+    let batch = (bytes_per_second * TARGET_BATCH_SECONDS / average_size.max(1.0)) as u32;
+
+    IndexingEstimate {
+        estimated_seconds: total_bytes as f64 / bytes_per_second,
+        total_bytes: total_bytes as i64,
+        file_count: sizes.len() as u32,
+        recommended_batch_size: batch.clamp(1, MAX_RECOMMENDED_BATCH),
+    }
+}
+
 /// Quantization format
 #[napi]
 pub enum QuantFormat {
@@ -189,39 +462,237 @@ pub enum QuantFormat {
 /// Model quantization config
 #[napi(object)]
 pub struct QuantConfig {
-    pub format: String, // "f32", "f16", "bf16", "q8_0", "q4_0", "q4_1"
+    /// "f32", "f16", "bf16" or "q8_0"; "q4_0" and "q4_1" report sizes only
+    pub format: String,
     pub use_gpu: bool,
     pub threads: Option<u32>,
 }
 
-/// Quantization result
+/// Quantized weights and their size accounting
 #[napi(object)]
-pub struct QuantResult {
+pub struct QuantizedWeights {
+    pub data: Buffer,
+    /// One scale per block for block formats, empty otherwise
+    pub scale_factors: Vec<f64>,
     pub original_size_mb: f64,
     pub quantized_size_mb: f64,
     pub compression_ratio: f64,
     pub duration_ms: f64,
 }
 
-/// Quantize weights (placeholder for GGUF integration)
+/// Values per block in the Q8_0 format
+const Q8_0_BLOCK_SIZE: usize = 32;
+
+/// Quantize weights
+///
+/// Encodes "f32", "f16", "bf16" (little-endian) and "q8_0", where each block
+/// of 32 values shares the scale `max(abs(block)) / 127` and each value is
+/// stored as an `i8`. The quantized size counts the data plus 8 bytes per
+/// scale factor. "q4_0" and "q4_1" are not encoded yet: their data is empty
+/// and only the sizes are reported, as for 4-bit values with one scale (and
+/// for q4_1 one minimum) per block.
 #[napi]
-pub fn quantize_weights(weights: Vec<f64>, config: QuantConfig) -> QuantResult {
+pub fn quantize_weights(weights: Vec<f64>, config: QuantConfig) -> Result<QuantizedWeights> {
     let start = Instant::now();
     let original_size = weights.len() * 8; // f64 = 8 bytes
-    
-    let quantized_size = match config.format.as_str() {
-        "f32" => weights.len() * 4,
-        "f16" | "bf16" => weights.len() * 2,
-        "q8_0" => weights.len(),
-        "q4_0" | "q4_1" => weights.len() / 2,
-        _ => weights.len() * 4,
+
+    let (data, scale_factors, quantized_size) = match q4_size(&config.format, weights.len()) {
+        Some(size) => (vec![], vec![], size),
+        None => {
+            let (data, scale_factors) =
+                quantize_data(&weights, &config.format).map_err(Error::from_reason)?;
+            let size = data.len() + scale_factors.len() * 8;
+            (data, scale_factors, size)
+        }
     };
-    
-    QuantResult {
+
+    Ok(QuantizedWeights {
+        data: data.into(),
+        scale_factors,
         original_size_mb: original_size as f64 / (1024.0 * 1024.0),
         quantized_size_mb: quantized_size as f64 / (1024.0 * 1024.0),
-        compression_ratio: original_size as f64 / quantized_size as f64,
+        compression_ratio: original_size as f64 / quantized_size.max(1) as f64,
         duration_ms: start.elapsed().as_millis() as f64,
+    })
+}
+
+/// Restore weights produced by `quantize_weights` in the same format
+#[napi]
+pub fn dequantize_weights(qw: QuantizedWeights, format: String) -> Result<Vec<f64>> {
+    dequantize_data(&qw.data, &qw.scale_factors, &format).map_err(Error::from_reason)
+}
+
+/// Size in bytes of `count` values in a 4-bit format, `None` for others
+fn q4_size(format: &str, count: usize) -> Option<usize> {
+    let blocks = count.div_ceil(Q8_0_BLOCK_SIZE);
+    match format {
+        "q4_0" => Some(count.div_ceil(2) + blocks * 8),
+        "q4_1" => Some(count.div_ceil(2) + blocks * 16),
+        _ => None,
+    }
+}
+
+/// Encode weights in a format, returning the data and any block scales
+fn quantize_data(
+    weights: &[f64],
+    format: &str,
+) -> std::result::Result<(Vec<u8>, Vec<f64>), String> {
+    match format {
+        "f32" => Ok((
+            weights
+                .iter()
+                .flat_map(|w| (*w as f32).to_le_bytes())
+                .collect(),
+            vec![],
+        )),
+        "f16" => Ok((
+            weights
+                .iter()
+                .flat_map(|w| f32_to_f16(*w as f32).to_le_bytes())
+                .collect(),
+            vec![],
+        )),
+        "bf16" => Ok((
+            weights
+                .iter()
+                .flat_map(|w| f32_to_bf16(*w as f32).to_le_bytes())
+                .collect(),
+            vec![],
+        )),
+        "q8_0" => Ok(quantize_q8_0(weights)),
+        other => Err(format!("Unsupported quantization format: {}", other)),
+    }
+}
+
+/// Decode data produced by `quantize_data`
+fn dequantize_data(
+    data: &[u8],
+    scale_factors: &[f64],
+    format: &str,
+) -> std::result::Result<Vec<f64>, String> {
+    match format {
+        "f32" => Ok(data
+            .as_chunks::<4>()
+            .0
+            .iter()
+            .map(|b| f32::from_le_bytes(*b) as f64)
+            .collect()),
+        "f16" => Ok(data
+            .as_chunks::<2>()
+            .0
+            .iter()
+            .map(|b| f16_to_f32(u16::from_le_bytes(*b)) as f64)
+            .collect()),
+        "bf16" => Ok(data
+            .as_chunks::<2>()
+            .0
+            .iter()
+            .map(|b| f32::from_bits((u16::from_le_bytes(*b) as u32) << 16) as f64)
+            .collect()),
+        "q8_0" => {
+            if scale_factors.len() != data.len().div_ceil(Q8_0_BLOCK_SIZE) {
+                return Err("Scale factor count does not match q8_0 data".to_string());
+            }
+            Ok(data
+                .chunks(Q8_0_BLOCK_SIZE)
+                .zip(scale_factors)
+                .flat_map(|(block, scale)| block.iter().map(move |q| *q as i8 as f64 * scale))
+                .collect())
+        }
+        other => Err(format!("Unsupported quantization format: {}", other)),
+    }
+}
+
+/// Quantize to Q8_0 blocks, returning the `i8` data and per-block scales
+fn quantize_q8_0(weights: &[f64]) -> (Vec<u8>, Vec<f64>) {
+    let mut data = Vec::with_capacity(weights.len());
+    let mut scales = Vec::with_capacity(weights.len().div_ceil(Q8_0_BLOCK_SIZE));
+
+    for block in weights.chunks(Q8_0_BLOCK_SIZE) {
+        let max = block.iter().fold(0.0f64, |m, w| m.max(w.abs()));
+        let scale = max / 127.0;
+        scales.push(scale);
+
+        for w in block {
+            let q = if scale > 0.0 {
+                (w / scale).round().clamp(-127.0, 127.0) as i8
+            } else {
+                0
+            };
+            data.push(q as u8);
+        }
+    }
+
+    (data, scales)
+}
+
+/// Round an f32 to the nearest bf16, ties to even
+fn f32_to_bf16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    if value.is_nan() {
+        return ((bits >> 16) | 0x40) as u16;
+    }
+    let rounding = 0x7FFF + ((bits >> 16) & 1);
+    (bits.wrapping_add(rounding) >> 16) as u16
+}
+
+/// Round an f32 to the nearest IEEE half, ties to even
+///
+/// Values beyond the half range become infinite and values below its
+/// smallest subnormal become zero.
+fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xFF) as i32;
+    let mantissa = bits & 0x7F_FFFF;
+
+    if exponent == 0xFF {
+        let nan = if mantissa != 0 { 0x200 } else { 0 };
+        return sign | 0x7C00 | nan;
+    }
+
+    let half_exponent = exponent - 127 + 15;
+    if half_exponent >= 0x1F {
+        return sign | 0x7C00;
+    }
+
+    // Normal halves keep 10 mantissa bits; subnormals shift in the
+    // implicit bit and lose more
+    let (kept, source, shift) = if half_exponent > 0 {
+        let kept = ((half_exponent as u32) << 10) | (mantissa >> 13);
+        (kept, mantissa, 13)
+    } else if half_exponent >= -10 {
+        let source = mantissa | 0x80_0000;
+        let shift = (14 - half_exponent) as u32;
+        (source >> shift, source, shift)
+    } else {
+        return sign;
+    };
+
+    // A carry out of the mantissa correctly bumps the exponent
+    let rest = source & ((1 << shift) - 1);
+    let halfway = 1 << (shift - 1);
+    let rounded = if rest > halfway || (rest == halfway && kept & 1 == 1) {
+        kept + 1
+    } else {
+        kept
+    };
+    sign | rounded as u16
+}
+
+/// Widen an IEEE half to an f32
+fn f16_to_f32(half: u16) -> f32 {
+    let sign = ((half & 0x8000) as u32) << 16;
+    let exponent = ((half >> 10) & 0x1F) as u32;
+    let mantissa = (half & 0x3FF) as u32;
+
+    match exponent {
+        0 => {
+            let magnitude = mantissa as f32 / (1 << 24) as f32;
+            f32::from_bits(sign | magnitude.to_bits())
+        }
+        0x1F => f32::from_bits(sign | 0x7F80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13)),
     }
 }
 
@@ -230,39 +701,385 @@ pub fn quantize_weights(weights: Vec<f64>, config: QuantConfig) -> QuantResult {
 pub struct GgufHeader {
     pub magic: String,
     pub version: u32,
-    pub tensor_count: u64,
-    pub metadata_kv_count: u64,
+    pub tensor_count: i64,
+    pub metadata_kv_count: i64,
 }
 
-/// Parse GGUF header (placeholder - real implementation would parse binary)
+/// GGUF header and metadata key-value pairs
+#[napi(object)]
+pub struct GgufMetadata {
+    pub header: GgufHeader,
+    /// Values as strings; arrays are rendered as JSON arrays
+    pub metadata: HashMap<String, String>,
+}
+
+/// GGUF file magic
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+
+/// Deepest nesting of GGUF arrays read before the file is rejected
+const GGUF_MAX_ARRAY_DEPTH: usize = 8;
+
+/// Parse the header of a GGUF file
 #[napi]
 pub fn parse_gguf_header(path: String) -> Result<GgufHeader> {
-    // Placeholder - real implementation would read and parse the GGUF file
-    if !std::path::Path::new(&path).exists() {
-        return Err(Error::new(Status::GenericFailure, "File not found"));
+    let mut reader = open_gguf(&path)?;
+    read_gguf_header(&mut reader).map_err(Error::from_reason)
+}
+
+/// Parse the header and metadata of a GGUF file
+///
+/// Supports GGUF versions 2 and 3, which share a layout.
+#[napi]
+pub fn parse_gguf_metadata(path: String) -> Result<GgufMetadata> {
+    let mut reader = open_gguf(&path)?;
+    read_gguf_metadata(&mut reader).map_err(Error::from_reason)
+}
+
+/// Memory needed to run a model
+#[napi(object)]
+pub struct ModelMemoryEstimate {
+    pub parameters_billions: f64,
+    pub weights_memory_mb: f64,
+    /// f16 key and value cache for the model's full context length
+    pub kv_cache_mb: f64,
+    pub total_mb: f64,
+    /// Whether `total_mb` fits in the system's available memory
+    pub fits_in_memory: bool,
+}
+
+/// Estimate the memory a GGUF model needs when loaded in `quant_format`
+///
+/// The parameter count is summed from the tensor shapes in the file, and
+/// the KV cache is sized from the architecture's block count, context
+/// length and attention dimensions in its metadata (zero when missing).
+#[napi]
+pub fn estimate_model_memory(
+    gguf_path: String,
+    quant_format: String,
+) -> Result<ModelMemoryEstimate> {
+    let mut reader = open_gguf(&gguf_path)?;
+    let parsed = read_gguf_metadata(&mut reader).map_err(Error::from_reason)?;
+    let parameters = read_gguf_parameter_count(&mut reader, parsed.header.tensor_count as u64)
+        .map_err(Error::from_reason)?;
+    let (weights_memory_mb, kv_cache_mb) =
+        estimate_memory_mb(&parsed.metadata, parameters, &quant_format)
+            .map_err(Error::from_reason)?;
+
+    let total_mb = weights_memory_mb + kv_cache_mb;
+    Ok(ModelMemoryEstimate {
+        parameters_billions: parameters as f64 / 1e9,
+        weights_memory_mb,
+        kv_cache_mb,
+        total_mb,
+        fits_in_memory: total_mb <= get_system_memory_info().free_mb,
+    })
+}
+
+/// Storage bytes per parameter of a quantization format, including the
+/// per-block scales of the block formats
+fn bytes_per_parameter(format: &str) -> Option<f64> {
+    match format.to_ascii_lowercase().as_str() {
+        "f32" => Some(4.0),
+        "f16" | "bf16" => Some(2.0),
+        // 32 values per block plus an f16 scale (and an f16 minimum for q4_1)
+        "q8_0" => Some(34.0 / 32.0),
+        "q4_0" => Some(18.0 / 32.0),
+        "q4_1" => Some(20.0 / 32.0),
+        _ => None,
+    }
+}
+
+/// Recommended quantization format for a model
+#[napi(object)]
+pub struct QuantRecommendation {
+    pub recommended_format: String,
+    /// Rough perplexity increase over f32
+    pub expected_quality_loss_percent: f64,
+    /// Memory for the weights in this format
+    pub memory_mb: f64,
+}
+
+/// Formats from best to worst quality, with their rough quality loss and
+/// the lowest `min_quality` that accepts them
+const QUANT_FORMATS: &[(&str, f64, &str)] = &[
+    ("f32", 0.0, "high"),
+    ("f16", 0.01, "high"),
+    ("bf16", 0.02, "high"),
+    ("q8_0", 0.1, "high"),
+    ("q4_1", 2.0, "medium"),
+    ("q4_0", 2.5, "low"),
+];
+
+/// Pick the highest quality format whose weights fit in memory
+///
+/// `min_quality` is `"high"` (8-bit or better), `"medium"` (also `q4_1`)
+/// or `"low"` (any format). Fails when no acceptable format fits.
+#[napi]
+pub fn select_quantization_format(
+    model_parameters_b: f64,
+    available_memory_mb: f64,
+    min_quality: String,
+) -> Result<QuantRecommendation> {
+    select_format(model_parameters_b, available_memory_mb, &min_quality).map_err(Error::from_reason)
+}
+
+/// Implementation of `select_quantization_format`
+fn select_format(
+    parameters_b: f64,
+    available_memory_mb: f64,
+    min_quality: &str,
+) -> std::result::Result<QuantRecommendation, String> {
+    let tiers = ["high", "medium", "low"];
+    let tier = tiers
+        .iter()
+        .position(|t| *t == min_quality)
+        .ok_or_else(|| format!("Unknown quality level: {}", min_quality))?;
+
+    QUANT_FORMATS
+        .iter()
+        .filter(|(_, _, quality)| tiers[..=tier].contains(quality))
+        .map(|&(format, loss, _)| {
+            let memory_mb =
+                parameters_b * 1e9 * bytes_per_parameter(format).unwrap_or(4.0) / (1024.0 * 1024.0);
+            QuantRecommendation {
+                recommended_format: format.to_string(),
+                expected_quality_loss_percent: loss,
+                memory_mb,
+            }
+        })
+        .find(|rec| rec.memory_mb <= available_memory_mb)
+        .ok_or_else(|| {
+            format!(
+                "No {} quality format fits {:.1}B parameters in {:.0} MB",
+                min_quality, parameters_b, available_memory_mb
+            )
+        })
+}
+
+/// Weights and KV cache sizes in MB for a model's metadata
+fn estimate_memory_mb(
+    metadata: &HashMap<String, String>,
+    parameters: u64,
+    format: &str,
+) -> std::result::Result<(f64, f64), String> {
+    let bytes = bytes_per_parameter(format)
+        .ok_or_else(|| format!("Unsupported quantization format: {}", format))?;
+    let to_mb = |bytes: f64| bytes / (1024.0 * 1024.0);
+
+    let arch = metadata
+        .get("general.architecture")
+        .map(String::as_str)
+        .unwrap_or("llama");
+    let number = |key: &str| {
+        metadata
+            .get(&format!("{}.{}", arch, key))
+            .and_then(|value| value.parse::<f64>().ok())
+    };
+    let kv_cache_bytes = match (
+        number("block_count"),
+        number("context_length"),
+        number("embedding_length"),
+    ) {
+        (Some(layers), Some(context), Some(embedding)) => {
+            // Grouped-query attention shares each KV head between query heads
+            let heads = number("attention.head_count").unwrap_or(1.0).max(1.0);
+            let kv_heads = number("attention.head_count_kv").unwrap_or(heads);
+            2.0 * layers * context * embedding * (kv_heads / heads) * 2.0
+        }
+        _ => 0.0,
+    };
+
+    Ok((to_mb(parameters as f64 * bytes), to_mb(kv_cache_bytes)))
+}
+
+/// Sum the element counts of the tensor infos that follow the metadata
+fn read_gguf_parameter_count(
+    reader: &mut impl Read,
+    tensor_count: u64,
+) -> std::result::Result<u64, String> {
+    let mut total = 0u64;
+    for _ in 0..tensor_count {
+        read_gguf_string(reader)?;
+        let dims = u32::from_le_bytes(read_array(reader)?);
+        let mut elements = 1u64;
+        for _ in 0..dims {
+            elements = elements.saturating_mul(read_u64(reader)?);
+        }
+        // Tensor type and data offset
+        read_array::<12>(reader)?;
+        total = total.saturating_add(elements);
+    }
+    Ok(total)
+}
+
+/// Open a GGUF file for buffered reading
+fn open_gguf(path: &str) -> Result<BufReader<File>> {
+    File::open(path)
+        .map(BufReader::new)
+        .map_err(|e| Error::from_reason(format!("Failed to open {}: {}", path, e)))
+}
+
+/// Read the fixed-size header at the start of a GGUF file
+fn read_gguf_header(reader: &mut impl Read) -> std::result::Result<GgufHeader, String> {
+    let magic: [u8; 4] = read_array(reader)?;
+    if &magic != GGUF_MAGIC {
+        return Err("Not a GGUF file".to_string());
+    }
+    let version = u32::from_le_bytes(read_array(reader)?);
+    if !(2..=3).contains(&version) {
+        return Err(format!("Unsupported GGUF version {}", version));
     }
-    
+
     Ok(GgufHeader {
         magic: "GGUF".to_string(),
-        version: 3,
-        tensor_count: 0,
-        metadata_kv_count: 0,
+        version,
+        tensor_count: read_u64(reader)? as i64,
+        metadata_kv_count: read_u64(reader)? as i64,
     })
 }
 
+/// Read the header and every metadata key-value pair
+fn read_gguf_metadata(reader: &mut impl Read) -> std::result::Result<GgufMetadata, String> {
+    let header = read_gguf_header(reader)?;
+    let mut metadata = HashMap::new();
+    for _ in 0..header.metadata_kv_count {
+        let key = read_gguf_string(reader)?;
+        let value_type = u32::from_le_bytes(read_array(reader)?);
+        let value = match read_gguf_value(reader, value_type, 0)? {
+            serde_json::Value::String(text) => text,
+            other => other.to_string(),
+        };
+        metadata.insert(key, value);
+    }
+    Ok(GgufMetadata { header, metadata })
+}
+
+/// Read one metadata value of a GGUF value type, `depth` arrays deep
+fn read_gguf_value(
+    reader: &mut impl Read,
+    value_type: u32,
+    depth: usize,
+) -> std::result::Result<serde_json::Value, String> {
+    use serde_json::Value;
+
+    Ok(match value_type {
+        0 => Value::from(u8::from_le_bytes(read_array(reader)?)),
+        1 => Value::from(i8::from_le_bytes(read_array(reader)?)),
+        2 => Value::from(u16::from_le_bytes(read_array(reader)?)),
+        3 => Value::from(i16::from_le_bytes(read_array(reader)?)),
+        4 => Value::from(u32::from_le_bytes(read_array(reader)?)),
+        5 => Value::from(i32::from_le_bytes(read_array(reader)?)),
+        6 => Value::from(f32::from_le_bytes(read_array(reader)?)),
+        7 => Value::from(u8::from_le_bytes(read_array(reader)?) != 0),
+        8 => Value::from(read_gguf_string(reader)?),
+        9 if depth >= GGUF_MAX_ARRAY_DEPTH => {
+            return Err("GGUF arrays nested too deeply".to_string());
+        }
+        9 => {
+            let item_type = u32::from_le_bytes(read_array(reader)?);
+            let len = read_u64(reader)?;
+            let items = (0..len)
+                .map(|_| read_gguf_value(reader, item_type, depth + 1))
+                .collect::<std::result::Result<Vec<_>, _>>()?;
+            Value::Array(items)
+        }
+        10 => Value::from(read_u64(reader)?),
+        11 => Value::from(i64::from_le_bytes(read_array(reader)?)),
+        12 => Value::from(f64::from_le_bytes(read_array(reader)?)),
+        other => return Err(format!("Unknown GGUF value type {}", other)),
+    })
+}
+
+/// Read a length-prefixed UTF-8 string
+fn read_gguf_string(reader: &mut impl Read) -> std::result::Result<String, String> {
+    let len = read_u64(reader)?;
+    // Read through `take` so a corrupt length cannot force a huge allocation
+    let mut bytes = Vec::new();
+    reader
+        .take(len)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read GGUF file: {}", e))?;
+    if bytes.len() as u64 != len {
+        return Err("Truncated GGUF file".to_string());
+    }
+    String::from_utf8(bytes).map_err(|_| "Invalid UTF-8 in GGUF string".to_string())
+}
+
+/// Read a little-endian u64
+fn read_u64(reader: &mut impl Read) -> std::result::Result<u64, String> {
+    Ok(u64::from_le_bytes(read_array(reader)?))
+}
+
+/// Read exactly `N` bytes
+fn read_array<const N: usize>(reader: &mut impl Read) -> std::result::Result<[u8; N], String> {
+    let mut bytes = [0u8; N];
+    reader.read_exact(&mut bytes).map_err(|e| match e.kind() {
+        std::io::ErrorKind::UnexpectedEof => "Truncated GGUF file".to_string(),
+        _ => format!("Failed to read GGUF file: {}", e),
+    })?;
+    Ok(bytes)
+}
+
 /// Performance metrics
 #[napi(object)]
 pub struct PerformanceMetrics {
-    pub total_operations: u64,
+    pub total_operations: i64,
     pub average_latency_ns: f64,
     pub operations_per_second: f64,
 }
 
 /// Record operation for metrics
 #[napi]
-pub fn record_operation(duration_ns: u64) {
+pub fn record_operation(duration_ns: i64) {
     TOTAL_OPERATIONS.fetch_add(1, Ordering::Relaxed);
-    TOTAL_DURATION_NS.fetch_add(duration_ns, Ordering::Relaxed);
+    TOTAL_DURATION_NS.fetch_add(duration_ns.max(0) as u64, Ordering::Relaxed);
+    if let Ok(mut latencies) = LATENCIES.lock() {
+        latencies.push(duration_ns.max(0) as u64);
+    }
+}
+
+/// Latency percentiles over the most recent operations
+#[napi(object)]
+pub struct LatencyHistogram {
+    pub p50_ns: f64,
+    pub p90_ns: f64,
+    pub p95_ns: f64,
+    pub p99_ns: f64,
+    pub p999_ns: f64,
+    pub min_ns: f64,
+    pub max_ns: f64,
+}
+
+/// Get latency percentiles over the last 10,000 recorded operations
+#[napi]
+pub fn get_performance_histogram() -> LatencyHistogram {
+    let samples = LATENCIES
+        .lock()
+        .map(|latencies| latencies.samples.clone())
+        .unwrap_or_default();
+    latency_histogram(samples)
+}
+
+/// Metrics for one operation label
+#[napi(object)]
+pub struct LabeledMetricEntry {
+    pub label: String,
+    pub total_operations: i64,
+    pub average_latency_ns: f64,
+    pub ops_per_second: f64,
+}
+
+/// Record an operation under a label, also counting it in the global metrics
+#[napi]
+pub fn record_operation_with_label(label: String, duration_ns: i64) {
+    record_operation(duration_ns);
+    LABELED_METRICS.record(&label, duration_ns.max(0) as u64);
+}
+
+/// Get performance metrics for each label
+#[napi]
+pub fn get_metrics_by_label() -> Vec<LabeledMetricEntry> {
+    LABELED_METRICS.snapshot()
 }
 
 /// Get performance metrics
@@ -270,21 +1087,21 @@ pub fn record_operation(duration_ns: u64) {
 pub fn get_performance_metrics() -> PerformanceMetrics {
     let total_ops = TOTAL_OPERATIONS.load(Ordering::Relaxed);
     let total_duration = TOTAL_DURATION_NS.load(Ordering::Relaxed);
-    
+
     let avg_latency = if total_ops > 0 {
         total_duration as f64 / total_ops as f64
     } else {
         0.0
     };
-    
+
     let ops_per_second = if total_duration > 0 {
         (total_ops as f64 * 1_000_000_000.0) / total_duration as f64
     } else {
         0.0
     };
-    
+
     PerformanceMetrics {
-        total_operations: total_ops,
+        total_operations: total_ops as i64,
         average_latency_ns: avg_latency,
         operations_per_second: ops_per_second,
     }
@@ -295,6 +1112,10 @@ pub fn get_performance_metrics() -> PerformanceMetrics {
 pub fn reset_performance_metrics() {
     TOTAL_OPERATIONS.store(0, Ordering::Relaxed);
     TOTAL_DURATION_NS.store(0, Ordering::Relaxed);
+    LABELED_METRICS.entries.clear();
+    if let Ok(mut latencies) = LATENCIES.lock() {
+        latencies.clear();
+    }
 }
 
 /// Memory pool for efficient allocations
@@ -304,17 +1125,51 @@ pub struct MemoryPoolStats {
     pub used_mb: f64,
     pub free_mb: f64,
     pub fragmentation: f64,
+    pub process_rss_mb: f64,
 }
 
 /// Get system memory info
+///
+/// `allocated_mb` is total physical memory and `free_mb` what is still
+/// available to new allocations; `used_mb` is the difference.
 #[napi]
 pub fn get_system_memory_info() -> MemoryPoolStats {
-    // Placeholder - real implementation would use sysinfo crate
+    use sysinfo::{MemoryRefreshKind, ProcessRefreshKind, ProcessesToUpdate, RefreshKind, System};
+
+    let mut system = System::new_with_specifics(
+        RefreshKind::nothing().with_memory(MemoryRefreshKind::nothing().with_ram()),
+    );
+
+    let to_mb = |bytes: u64| bytes as f64 / (1024.0 * 1024.0);
+    let allocated_mb = to_mb(system.total_memory());
+    let free_mb = to_mb(system.available_memory());
+    let used_mb = allocated_mb - free_mb;
+
+    let process_rss_mb = match sysinfo::get_current_pid() {
+        Ok(pid) => {
+            system.refresh_processes_specifics(
+                ProcessesToUpdate::Some(&[pid]),
+                false,
+                ProcessRefreshKind::nothing().with_memory(),
+            );
+            system
+                .process(pid)
+                .map(|p| to_mb(p.memory()))
+                .unwrap_or(0.0)
+        }
+        Err(_) => 0.0,
+    };
+
     MemoryPoolStats {
-        allocated_mb: 0.0,
-        used_mb: 0.0,
-        free_mb: 0.0,
-        fragmentation: 0.0,
+        allocated_mb,
+        used_mb,
+        free_mb,
+        fragmentation: if allocated_mb > 0.0 {
+            1.0 - used_mb / allocated_mb
+        } else {
+            0.0
+        },
+        process_rss_mb,
     }
 }
 
@@ -368,45 +1223,355 @@ pub fn detect_simd_capabilities() -> SimdCapabilities {
         sse: is_x86_feature_detected!("sse"),
         #[cfg(not(target_arch = "x86_64"))]
         sse: false,
-        
+
         #[cfg(target_arch = "x86_64")]
         sse2: is_x86_feature_detected!("sse2"),
         #[cfg(not(target_arch = "x86_64"))]
         sse2: false,
-        
+
         #[cfg(target_arch = "x86_64")]
         sse3: is_x86_feature_detected!("sse3"),
         #[cfg(not(target_arch = "x86_64"))]
         sse3: false,
-        
+
         #[cfg(target_arch = "x86_64")]
         sse4_1: is_x86_feature_detected!("sse4.1"),
         #[cfg(not(target_arch = "x86_64"))]
         sse4_1: false,
-        
+
         #[cfg(target_arch = "x86_64")]
         sse4_2: is_x86_feature_detected!("sse4.2"),
         #[cfg(not(target_arch = "x86_64"))]
         sse4_2: false,
-        
+
         #[cfg(target_arch = "x86_64")]
         avx: is_x86_feature_detected!("avx"),
         #[cfg(not(target_arch = "x86_64"))]
         avx: false,
-        
+
         #[cfg(target_arch = "x86_64")]
         avx2: is_x86_feature_detected!("avx2"),
         #[cfg(not(target_arch = "x86_64"))]
         avx2: false,
-        
+
         #[cfg(target_arch = "x86_64")]
         avx512: false, // AVX-512 detection is complex
         #[cfg(not(target_arch = "x86_64"))]
         avx512: false,
-        
+
         #[cfg(target_arch = "aarch64")]
         neon: true, // NEON is always available on AArch64
         #[cfg(not(target_arch = "aarch64"))]
         neon: false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labeled_metrics() {
+        let metrics = LabeledMetrics::default();
+        metrics.record("parse", 100);
+        metrics.record("parse", 300);
+        metrics.record("embed", 1_000_000_000);
+
+        let entries = metrics.snapshot();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].label, "embed");
+        assert_eq!(entries[0].ops_per_second, 1.0);
+        assert_eq!(entries[1].total_operations, 2);
+        assert_eq!(entries[1].average_latency_ns, 200.0);
+    }
+
+    #[test]
+    fn test_latency_histogram() {
+        let mut ring = LatencyRing::new(1000);
+        for duration in (1..=1500).rev() {
+            ring.push(duration);
+        }
+        // The oldest 500 (1500 down to 1001) were overwritten
+        assert_eq!(ring.samples.len(), 1000);
+        let histogram = latency_histogram(ring.samples.clone());
+        assert_eq!((histogram.min_ns, histogram.max_ns), (1.0, 1000.0));
+        assert_eq!(histogram.p50_ns, 500.0);
+        assert_eq!(histogram.p90_ns, 900.0);
+        assert_eq!(histogram.p95_ns, 950.0);
+        assert_eq!(histogram.p99_ns, 990.0);
+        assert_eq!(histogram.p999_ns, 999.0);
+
+        let single = latency_histogram(vec![42]);
+        assert_eq!(
+            (single.p50_ns, single.p999_ns, single.min_ns),
+            (42.0, 42.0, 42.0)
+        );
+        assert_eq!(latency_histogram(vec![]).max_ns, 0.0);
+
+        ring.clear();
+        assert!(ring.samples.is_empty());
+    }
+
+    #[test]
+    fn test_indexing_estimate() {
+        let mut model = ThroughputModel::default();
+        model.record(1000.0);
+        assert_eq!(model.bytes_per_second, 1000.0);
+        model.record(3000.0);
+        model.record(f64::INFINITY);
+        assert_eq!((model.bytes_per_second, model.samples), (2000.0, 2));
+
+        let estimate = indexing_estimate(&[100, 300], model.bytes_per_second);
+        assert_eq!(estimate.estimated_seconds, 0.2);
+        assert_eq!((estimate.total_bytes, estimate.file_count), (400, 2));
+        assert_eq!(estimate.recommended_batch_size, 5);
+
+        assert_eq!(
+            indexing_estimate(&[1 << 30], 1000.0).recommended_batch_size,
+            1
+        );
+        assert_eq!(
+            indexing_estimate(&[10], 1e9).recommended_batch_size,
+            MAX_RECOMMENDED_BATCH
+        );
+        assert_eq!(indexing_estimate(&[], 1000.0).estimated_seconds, 0.0);
+
+        let path = std::env::temp_dir().join(format!("titan-estimate-{}.ts", std::process::id()));
+        std::fs::write(&path, "x".repeat(1234)).unwrap();
+        let paths = vec![
+            path.to_string_lossy().to_string(),
+            "/no/such/file.ts".to_string(),
+        ];
+        let estimate = estimate_indexing_time(paths);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((estimate.total_bytes, estimate.file_count), (1234, 1));
+        assert!(measure_indexing_throughput("rust").unwrap() > 0.0);
+    }
+
+    #[test]
+    fn test_q8_0_roundtrip() {
+        let weights: Vec<f64> = (0..70)
+            .map(|i| ((i as f64) * 0.37).sin() * (i % 5) as f64)
+            .collect();
+        let (data, scales) = quantize_data(&weights, "q8_0").unwrap();
+        assert_eq!(data.len(), 70);
+        assert_eq!(scales.len(), 3);
+
+        let restored = dequantize_data(&data, &scales, "q8_0").unwrap();
+        assert_eq!(restored.len(), weights.len());
+        for (block, chunk) in weights.chunks(32).zip(restored.chunks(32)) {
+            let max = block.iter().fold(0.0f64, |m, w| m.max(w.abs()));
+            for (w, r) in block.iter().zip(chunk) {
+                assert!((w - r).abs() <= max / 254.0 + 1e-12);
+            }
+        }
+
+        assert!(dequantize_data(&data, &scales[..2], "q8_0").is_err());
+    }
+
+    #[test]
+    fn test_float_formats_roundtrip() {
+        let weights = vec![1.0, -2.5, 0.15625, 3.0e9];
+        let (data, scales) = quantize_data(&weights, "f32").unwrap();
+        assert_eq!(data.len(), 16);
+        assert_eq!(dequantize_data(&data, &scales, "f32").unwrap(), weights);
+
+        // Halfway values round to the even mantissa
+        let (data, _) = quantize_data(&[-2.5, 1.00390625, 1.01171875], "bf16").unwrap();
+        assert_eq!(
+            dequantize_data(&data, &[], "bf16").unwrap(),
+            vec![-2.5, 1.0, 1.015625]
+        );
+
+        // Halves round to even, saturate to infinity and keep subnormals
+        let halves = [
+            1.0,
+            -2.5,
+            65504.0,
+            1.0e5,
+            1.0 + 2f64.powi(-10),
+            1.0 + 2f64.powi(-11),
+            2f64.powi(-24),
+            -3.0 * 2f64.powi(-16),
+        ];
+        let (data, _) = quantize_data(&halves, "f16").unwrap();
+        assert_eq!(data.len(), 16);
+        assert_eq!(
+            dequantize_data(&data, &[], "f16").unwrap(),
+            vec![
+                1.0,
+                -2.5,
+                65504.0,
+                f64::INFINITY,
+                1.0 + 2f64.powi(-10),
+                1.0,
+                2f64.powi(-24),
+                -3.0 * 2f64.powi(-16),
+            ]
+        );
+
+        // 4-bit formats report sizes without encoding
+        assert!(quantize_data(&weights, "q4_0").is_err());
+        assert_eq!(q4_size("q4_0", 64), Some(32 + 16));
+        assert_eq!(q4_size("q4_1", 33), Some(17 + 32));
+        assert_eq!(q4_size("q8_0", 64), None);
+    }
+
+    /// A GGUF v3 file with the given metadata and no tensors
+    fn gguf_fixture(tensor_count: u64, kvs: &[(&str, u32, Vec<u8>)]) -> Vec<u8> {
+        let mut bytes = b"GGUF".to_vec();
+        bytes.extend(3u32.to_le_bytes());
+        bytes.extend(tensor_count.to_le_bytes());
+        bytes.extend((kvs.len() as u64).to_le_bytes());
+        for (key, value_type, value) in kvs {
+            bytes.extend(gguf_string(key));
+            bytes.extend(value_type.to_le_bytes());
+            bytes.extend(value);
+        }
+        bytes
+    }
+
+    /// A GGUF length-prefixed string
+    fn gguf_string(text: &str) -> Vec<u8> {
+        let mut bytes = (text.len() as u64).to_le_bytes().to_vec();
+        bytes.extend(text.as_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_read_gguf_metadata() {
+        let mut array = 4u32.to_le_bytes().to_vec();
+        array.extend(2u64.to_le_bytes());
+        array.extend(7u32.to_le_bytes());
+        array.extend(9u32.to_le_bytes());
+        let bytes = gguf_fixture(
+            0,
+            &[
+                ("general.name", 8, gguf_string("tiny")),
+                ("llama.block_count", 4, 32u32.to_le_bytes().to_vec()),
+                ("llama.rope.freq_base", 6, 0.5f32.to_le_bytes().to_vec()),
+                ("general.quantized", 7, vec![1]),
+                ("general.ids", 9, array),
+            ],
+        );
+
+        let parsed = read_gguf_metadata(&mut bytes.as_slice()).unwrap();
+        assert_eq!(
+            (
+                parsed.header.version,
+                parsed.header.tensor_count,
+                parsed.header.metadata_kv_count
+            ),
+            (3, 0, 5)
+        );
+        assert_eq!(parsed.metadata["general.name"], "tiny");
+        assert_eq!(parsed.metadata["llama.block_count"], "32");
+        assert_eq!(parsed.metadata["llama.rope.freq_base"], "0.5");
+        assert_eq!(parsed.metadata["general.quantized"], "true");
+        assert_eq!(parsed.metadata["general.ids"], "[7,9]");
+
+        assert_eq!(
+            read_gguf_metadata(&mut &bytes[..bytes.len() - 1])
+                .err()
+                .unwrap(),
+            "Truncated GGUF file"
+        );
+
+        // Arrays of arrays, `depth` deep, around a single u32
+        let nested = |depth: usize| {
+            let mut value = 4u32.to_le_bytes().to_vec();
+            value.extend(1u64.to_le_bytes());
+            value.extend(7u32.to_le_bytes());
+            for _ in 1..depth {
+                let mut outer = 9u32.to_le_bytes().to_vec();
+                outer.extend(1u64.to_le_bytes());
+                outer.extend(value);
+                value = outer;
+            }
+            gguf_fixture(0, &[("general.ids", 9, value)])
+        };
+        let parsed = read_gguf_metadata(&mut nested(GGUF_MAX_ARRAY_DEPTH).as_slice()).unwrap();
+        assert_eq!(parsed.metadata["general.ids"], "[[[[[[[[7]]]]]]]]");
+        assert_eq!(
+            read_gguf_metadata(&mut nested(GGUF_MAX_ARRAY_DEPTH + 1).as_slice())
+                .err()
+                .unwrap(),
+            "GGUF arrays nested too deeply"
+        );
+        assert_eq!(
+            read_gguf_header(&mut &b"GGML\x03\0\0\0"[..]).err().unwrap(),
+            "Not a GGUF file"
+        );
+    }
+
+    #[test]
+    fn test_estimate_model_memory() {
+        let mut bytes = gguf_fixture(
+            2,
+            &[
+                ("general.architecture", 8, gguf_string("llama")),
+                ("llama.block_count", 4, 2u32.to_le_bytes().to_vec()),
+                ("llama.context_length", 4, 1024u32.to_le_bytes().to_vec()),
+                ("llama.embedding_length", 4, 512u32.to_le_bytes().to_vec()),
+                ("llama.attention.head_count", 4, 8u32.to_le_bytes().to_vec()),
+                (
+                    "llama.attention.head_count_kv",
+                    4,
+                    2u32.to_le_bytes().to_vec(),
+                ),
+            ],
+        );
+        for (name, dims) in [
+            ("token_embd.weight", vec![512u64, 1024]),
+            ("output_norm.weight", vec![512]),
+        ] {
+            bytes.extend(gguf_string(name));
+            bytes.extend((dims.len() as u32).to_le_bytes());
+            for dim in dims {
+                bytes.extend(dim.to_le_bytes());
+            }
+            bytes.extend(0u32.to_le_bytes());
+            bytes.extend(0u64.to_le_bytes());
+        }
+
+        let mut reader = bytes.as_slice();
+        let parsed = read_gguf_metadata(&mut reader).unwrap();
+        let parameters = read_gguf_parameter_count(&mut reader, 2).unwrap();
+        assert_eq!(parameters, 512 * 1024 + 512);
+
+        let (weights, kv_cache) = estimate_memory_mb(&parsed.metadata, 1 << 20, "f16").unwrap();
+        assert_eq!(weights, 2.0);
+        // K and V, 2 layers, 1024 positions, 512 * 2 / 8 values, 2 bytes each
+        assert_eq!(kv_cache, 1.0);
+        let (weights, _) = estimate_memory_mb(&parsed.metadata, 1 << 20, "Q4_0").unwrap();
+        assert_eq!(weights, 0.5625);
+        assert!(estimate_memory_mb(&parsed.metadata, 1, "q2_k").is_err());
+    }
+
+    #[test]
+    fn test_select_format() {
+        // 7B parameters: f16 needs about 13.0 GB, q8_0 6.9 GB, q4_1 4.1 GB
+        let rec = select_format(7.0, 14_000.0, "high").unwrap();
+        assert_eq!(rec.recommended_format, "f16");
+        assert_eq!(
+            select_format(7.0, 8_000.0, "high")
+                .unwrap()
+                .recommended_format,
+            "q8_0"
+        );
+        assert_eq!(
+            select_format(7.0, 4_000.0, "low")
+                .unwrap()
+                .recommended_format,
+            "q4_0"
+        );
+
+        let rec = select_format(7.0, 5_000.0, "medium").unwrap();
+        assert_eq!(rec.recommended_format, "q4_1");
+        assert!(rec.memory_mb > 4_000.0 && rec.memory_mb < 5_000.0);
+        assert!(rec.expected_quality_loss_percent > 0.0);
+
+        assert!(select_format(7.0, 5_000.0, "high").is_err());
+        assert!(select_format(7.0, 5_000.0, "best").is_err());
+    }
+}
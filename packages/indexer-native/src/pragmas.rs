@@ -0,0 +1,239 @@
+//! `@titan-ignore` pragmas that exclude code from indexing
+
+/// Excludes the declaration on the next code line
+const IGNORE: &str = "@titan-ignore";
+/// Opens an excluded region
+const IGNORE_START: &str = "@titan-ignore-start";
+/// Closes an excluded region
+const IGNORE_END: &str = "@titan-ignore-end";
+
+/// A pragma comment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pragma {
+    Start,
+    End,
+    Next,
+}
+
+/// Check if content contains any pragma
+pub fn has_pragmas(content: &str) -> bool {
+    content.contains(IGNORE)
+}
+
+/// Mark the lines excluded by `@titan-ignore` pragmas
+///
+/// Lines from `@titan-ignore-start` to `@titan-ignore-end` are excluded,
+/// markers included; a start without an end runs to the end of the file.
+/// A lone `@titan-ignore` excludes itself and the declaration on the next
+/// code line: the outermost node starting on that line when a parse tree is
+/// given, otherwise up to where braces balance or, without braces, where
+/// the indentation returns. Each excluded region is logged as a warning.
+pub fn ignored_lines(
+    file_path: &str,
+    content: &str,
+    tree: Option<&tree_sitter::Tree>,
+) -> Vec<bool> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut ignored = vec![false; lines.len()];
+    if !has_pragmas(content) {
+        return ignored;
+    }
+
+    let mut regions = Vec::new();
+    let mut open: Option<usize> = None;
+    for (i, line) in lines.iter().enumerate() {
+        match (pragma(line), open) {
+            (Some(Pragma::Start), None) => open = Some(i),
+            (Some(Pragma::End), Some(start)) => {
+                regions.push((start, i));
+                open = None;
+            }
+            (Some(Pragma::Next), None) => {
+                let end = next_code_line(&lines, i + 1)
+                    .map(|decl| declaration_end(&lines, decl, tree))
+                    .unwrap_or(i);
+                regions.push((i, end));
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = open {
+        regions.push((start, lines.len().saturating_sub(1)));
+    }
+
+    let file = if file_path.is_empty() {
+        "<input>"
+    } else {
+        file_path
+    };
+    for (start, end) in regions {
+        log::warn!(
+            "{}: skipping lines {}-{} marked with @titan-ignore",
+            file,
+            start + 1,
+            end + 1
+        );
+        for flag in &mut ignored[start..=end] {
+            *flag = true;
+        }
+    }
+
+    ignored
+}
+
+/// Check whether a 1-based line range touches an excluded line
+pub fn overlaps(ignored: &[bool], start_line: u32, end_line: u32) -> bool {
+    let start = (start_line as usize).saturating_sub(1);
+    let end = (end_line as usize).min(ignored.len());
+    start < end && ignored[start..end].iter().any(|&flag| flag)
+}
+
+/// Parse a pragma from a comment line
+fn pragma(line: &str) -> Option<Pragma> {
+    let trimmed = line.trim();
+    let text = trimmed
+        .strip_prefix("//")
+        .or_else(|| trimmed.strip_prefix('#'))?
+        .trim();
+
+    if text.starts_with(IGNORE_START) {
+        Some(Pragma::Start)
+    } else if text.starts_with(IGNORE_END) {
+        Some(Pragma::End)
+    } else if text == IGNORE || text.starts_with(&format!("{} ", IGNORE)) {
+        Some(Pragma::Next)
+    } else {
+        None
+    }
+}
+
+/// First line from `from` that is neither blank nor a comment
+fn next_code_line(lines: &[&str], from: usize) -> Option<usize> {
+    (from..lines.len()).find(|&i| {
+        let trimmed = lines[i].trim();
+        !(trimmed.is_empty()
+            || trimmed.starts_with("//")
+            || trimmed.starts_with("/*")
+            || trimmed.starts_with('*')
+            || (trimmed.starts_with('#') && !trimmed.starts_with("#[")))
+    })
+}
+
+/// Last line of the declaration starting on `row`
+fn declaration_end(lines: &[&str], row: usize, tree: Option<&tree_sitter::Tree>) -> usize {
+    if let Some(end) = tree.and_then(|tree| node_end(tree, row)) {
+        return end;
+    }
+
+    // Braces: run until every brace opened from the declaration closes
+    let mut depth = 0i32;
+    let mut opened = false;
+    for (i, line) in lines.iter().enumerate().skip(row) {
+        for c in line.chars() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if opened && depth <= 0 {
+            return i;
+        }
+        if !opened && i > row {
+            break;
+        }
+    }
+
+    // Indentation: run until a line indented no deeper than the declaration
+    let indent = |line: &str| line.len() - line.trim_start().len();
+    let base = indent(lines[row]);
+    (row + 1..lines.len())
+        .take_while(|&i| lines[i].trim().is_empty() || indent(lines[i]) > base)
+        .filter(|&i| !lines[i].trim().is_empty())
+        .last()
+        .unwrap_or(row)
+}
+
+/// Last row of the outermost node starting on `row`
+///
+/// A Rust attribute is a sibling of the item it annotates, so the item
+/// after a run of attributes is included.
+fn node_end(tree: &tree_sitter::Tree, row: usize) -> Option<usize> {
+    let mut node = tree.root_node();
+    loop {
+        let mut cursor = node.walk();
+        let child = node
+            .children(&mut cursor)
+            .find(|child| child.end_position().row >= row && child.start_position().row <= row)?;
+
+        if child.start_position().row == row {
+            let mut end = child;
+            while end.kind() == "attribute_item" {
+                match end.next_named_sibling() {
+                    Some(next) => end = next,
+                    None => break,
+                }
+            }
+            return Some(end.end_position().row);
+        }
+        node = child;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines_of(ignored: &[bool]) -> Vec<usize> {
+        ignored
+            .iter()
+            .enumerate()
+            .filter(|(_, &flag)| flag)
+            .map(|(i, _)| i + 1)
+            .collect()
+    }
+
+    #[test]
+    fn test_ignore_region() {
+        let content =
+            "a\n// @titan-ignore-start\nb\nc\n// @titan-ignore-end\nd\n# @titan-ignore-start\ne";
+        assert_eq!(
+            lines_of(&ignored_lines("f", content, None)),
+            vec![2, 3, 4, 5, 7, 8]
+        );
+        assert!(!has_pragmas("// titan"));
+    }
+
+    #[test]
+    fn test_ignore_next_declaration() {
+        let content = "// @titan-ignore\nfunction a() {\n    return 1;\n}\nfunction b() {}\n";
+        assert_eq!(
+            lines_of(&ignored_lines("f", content, None)),
+            vec![1, 2, 3, 4]
+        );
+
+        let content = "# @titan-ignore\ndef a():\n    return 1\n\ndef b():\n    pass\n";
+        assert_eq!(lines_of(&ignored_lines("f", content, None)), vec![1, 2, 3]);
+
+        // With a tree the node extent is used
+        let content =
+            "// @titan-ignore\n#[derive(Debug)]\nstruct A\n{\n    x: u32,\n}\nfn b() {}\n";
+        let mut parser = crate::parser::get_parser("rust").unwrap();
+        let tree = parser.parse(content, None).unwrap();
+        assert_eq!(
+            lines_of(&ignored_lines("f", content, Some(&tree))),
+            vec![1, 2, 3, 4, 5, 6]
+        );
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let ignored = [false, true, false];
+        assert!(overlaps(&ignored, 1, 2));
+        assert!(!overlaps(&ignored, 3, 3));
+        assert!(!overlaps(&ignored, 4, 9));
+    }
+}
@@ -0,0 +1,294 @@
+//! In-memory symbol search over an inverted index of names
+
+use crate::Symbol;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Inverted index from symbol names to the symbols declaring them
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SymbolIndex {
+    symbols: Vec<Symbol>,
+    /// Lowercased name and name words -> symbol positions
+    terms: HashMap<String, Vec<usize>>,
+    /// Lowercased name trigrams -> symbol positions
+    trigrams: HashMap<String, Vec<usize>>,
+}
+
+impl SymbolIndex {
+    /// Index symbols by name, by lowercased name and by the words of
+    /// camelCase and snake_case names
+    pub fn new(symbols: Vec<Symbol>) -> Self {
+        let mut terms: HashMap<String, Vec<usize>> = HashMap::new();
+        let mut trigrams: HashMap<String, Vec<usize>> = HashMap::new();
+
+        for (i, symbol) in symbols.iter().enumerate() {
+            let lower = symbol.name.to_lowercase();
+            let mut keys = split_words(&symbol.name);
+            keys.push(lower.clone());
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                terms.entry(key).or_default().push(i);
+            }
+
+            let mut grams = name_trigrams(&lower);
+            grams.sort();
+            grams.dedup();
+            for gram in grams {
+                trigrams.entry(gram).or_default().push(i);
+            }
+        }
+
+        Self {
+            symbols,
+            terms,
+            trigrams,
+        }
+    }
+
+    /// Symbols whose name, or a word of it, matches `name` ignoring case
+    ///
+    /// Exact-case matches of the whole name come first, then the rest in
+    /// index order.
+    pub fn find(&self, name: &str) -> Vec<Symbol> {
+        let mut hits: Vec<usize> = self
+            .terms
+            .get(&name.to_lowercase())
+            .cloned()
+            .unwrap_or_default();
+        hits.sort_by_key(|&i| (self.symbols[i].name != name, i));
+        hits.into_iter().map(|i| self.symbols[i].clone()).collect()
+    }
+
+    /// Symbols with names most similar to `query` by trigram overlap
+    ///
+    /// Similarity is the Jaccard index of the lowercased trigram sets, so
+    /// typos and transpositions still match. Ties go to shorter names.
+    pub fn fuzzy_find(&self, query: &str, max_results: usize) -> Vec<Symbol> {
+        let mut query_grams = name_trigrams(&query.to_lowercase());
+        query_grams.sort();
+        query_grams.dedup();
+
+        // Shared trigram counts for every candidate touching the query
+        let mut shared: HashMap<usize, usize> = HashMap::new();
+        for gram in &query_grams {
+            for &i in self.trigrams.get(gram).into_iter().flatten() {
+                *shared.entry(i).or_default() += 1;
+            }
+        }
+
+        let mut scored: Vec<(f64, usize)> = shared
+            .into_iter()
+            .map(|(i, common)| {
+                let mut grams = name_trigrams(&self.symbols[i].name.to_lowercase());
+                grams.sort();
+                grams.dedup();
+                let union = query_grams.len() + grams.len() - common;
+                (common as f64 / union as f64, i)
+            })
+            .collect();
+        scored.sort_by(|a, b| {
+            b.0.total_cmp(&a.0)
+                .then_with(|| {
+                    self.symbols[a.1]
+                        .name
+                        .len()
+                        .cmp(&self.symbols[b.1].name.len())
+                })
+                .then_with(|| a.1.cmp(&b.1))
+        });
+
+        scored
+            .into_iter()
+            .take(max_results)
+            .map(|(_, i)| self.symbols[i].clone())
+            .collect()
+    }
+
+    /// Number of indexed symbols
+    pub fn symbol_count(&self) -> usize {
+        self.symbols.len()
+    }
+}
+
+/// Canonical form of a symbol name for comparing across languages
+///
+/// The words of camelCase, PascalCase and snake_case names are lowercased
+/// and joined by spaces, so `calculateTotal` and `calculate_total` are both
+/// `calculate total`. Sigils such as PHP's `$` are dropped, as is the `r#`
+/// of Rust raw identifiers.
+pub fn normalize_name(name: &str, language: &str) -> String {
+    let name = match language {
+        "rust" => name.strip_prefix("r#").unwrap_or(name),
+        _ => name,
+    };
+    split_words(name).join(" ")
+}
+
+/// Similarity of two names from 0 to 1 once normalised
+///
+/// This is the Jaccard index of the trigrams of the normalised names, as
+/// in `fuzzy_find`, so names made of the same words score 1 whatever their
+/// casing convention.
+pub fn name_similarity(a: &str, b: &str) -> f64 {
+    let grams = |name: &str| {
+        let mut grams = name_trigrams(&normalize_name(name, ""));
+        grams.sort();
+        grams.dedup();
+        grams
+    };
+    let (a, b) = (grams(a), grams(b));
+    let common = a
+        .iter()
+        .filter(|gram| b.binary_search(gram).is_ok())
+        .count();
+    common as f64 / (a.len() + b.len() - common) as f64
+}
+
+/// Lowercased words of a camelCase, PascalCase or snake_case name
+///
+/// Acronyms stay together: `parseHTTPRequest` is `parse`, `http`, `request`.
+fn split_words(name: &str) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut words = Vec::new();
+    let mut current = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        let next = chars.get(i + 1);
+        let boundary = c.is_uppercase()
+            && prev.is_some_and(|p| {
+                p.is_lowercase()
+                    || p.is_ascii_digit()
+                    || (p.is_uppercase() && next.is_some_and(|n| n.is_lowercase()))
+            });
+        if boundary && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+}
+
+/// Trigrams of a name padded with spaces, so short names still have some
+fn name_trigrams(name: &str) -> Vec<String> {
+    let padded: Vec<char> = format!("  {} ", name).chars().collect();
+    padded.windows(3).map(|w| w.iter().collect()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn symbol(name: &str, file_path: &str, line: u32) -> Symbol {
+        Symbol {
+            name: name.to_string(),
+            kind: "function".to_string(),
+            file_path: file_path.to_string(),
+            start_line: line,
+            end_line: line,
+            start_column: 0,
+            end_column: 0,
+            start_column_char: 0,
+            signature: None,
+            parameters: vec![],
+            exported: true,
+            parent_name: None,
+            parent_kind: None,
+            references: None,
+            generic_params: vec![],
+            signature_annotation: None,
+        }
+    }
+
+    fn names(symbols: &[Symbol]) -> Vec<&str> {
+        symbols.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_split_words() {
+        assert_eq!(split_words("MyComponent"), vec!["my", "component"]);
+        assert_eq!(
+            split_words("parseHTTPRequest"),
+            vec!["parse", "http", "request"]
+        );
+        assert_eq!(split_words("get_user_name2"), vec!["get", "user", "name2"]);
+    }
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(
+            normalize_name("calculateTotal", "typescript"),
+            "calculate total"
+        );
+        assert_eq!(
+            normalize_name("calculate_total", "python"),
+            "calculate total"
+        );
+        assert_eq!(normalize_name("CalculateTotal", "go"), "calculate total");
+        assert_eq!(normalize_name("$userName", "php"), "user name");
+        assert_eq!(normalize_name("r#type", "rust"), "type");
+
+        assert_eq!(name_similarity("calculateTotal", "calculate_total"), 1.0);
+        let close = name_similarity("calculateTotal", "calculate_totals");
+        let far = name_similarity("calculateTotal", "parseConfig");
+        assert!(close > 0.7 && close < 1.0, "{}", close);
+        assert!(far < 0.1, "{}", far);
+    }
+
+    #[test]
+    fn test_find_symbol() {
+        let index = SymbolIndex::new(vec![
+            symbol("mycomponent", "b.ts", 1),
+            symbol("MyComponent", "a.tsx", 3),
+            symbol("renderComponent", "c.ts", 7),
+        ]);
+        let found = index.find("MyComponent");
+        assert_eq!(names(&found), vec!["MyComponent", "mycomponent"]);
+        assert_eq!(
+            (found[0].file_path.as_str(), found[0].start_line),
+            ("a.tsx", 3)
+        );
+        assert_eq!(
+            names(&index.find("component")),
+            vec!["MyComponent", "renderComponent"]
+        );
+        assert!(index.find("Comp").is_empty());
+    }
+
+    #[test]
+    fn test_fuzzy_find_symbol() {
+        let index = SymbolIndex::new(vec![
+            symbol("createUser", "a.ts", 1),
+            symbol("deleteUser", "a.ts", 5),
+            symbol("UserService", "b.ts", 1),
+            symbol("parseConfig", "c.ts", 1),
+        ]);
+        assert_eq!(names(&index.fuzzy_find("craeteUser", 2))[0], "createUser");
+        assert_eq!(
+            names(&index.fuzzy_find("userservice", 1)),
+            vec!["UserService"]
+        );
+        assert!(index.fuzzy_find("zzzz", 5).is_empty());
+
+        // The index survives a serialisation round trip
+        let json = serde_json::to_string(&index).unwrap();
+        let restored: SymbolIndex = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.symbol_count(), 4);
+        assert_eq!(
+            names(&restored.fuzzy_find("parse_config", 1)),
+            vec!["parseConfig"]
+        );
+    }
+}
@@ -0,0 +1,281 @@
+//! Index snapshots for persisting chunks and symbols between sessions
+//!
+//! A snapshot is an object with `schema_version`, `created_at` (Unix
+//! seconds), `chunks` and `symbols`. The fields of chunks and symbols use
+//! the Rust (snake_case) names.
+//!
+//! Changing the layout of a snapshot:
+//!
+//! 1. Bump `SCHEMA_VERSION`.
+//! 2. Add an arm to `migrate` that rewrites a value of the previous version
+//!    into the new layout (rename, fill in or drop fields) and sets its
+//!    `schema_version`.
+//! 3. Keep a test that imports a snapshot of the previous version.
+//!
+//! MessagePack snapshots hold the same fields positionally, which makes them
+//! smaller but means `Value` based migration does not apply: keep the
+//! previous version's structs to decode old MessagePack snapshots, then
+//! convert them in `import_msgpack`.
+//!
+//! Snapshots newer than `SCHEMA_VERSION` are rejected rather than guessed at.
+
+use crate::{CodeChunk, IndexSnapshot, Symbol};
+use anyhow::Result;
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Serialize};
+
+/// Version written by `export_json` and `export_msgpack`
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// On-disk snapshot layout
+#[derive(Debug, Serialize, Deserialize)]
+struct SnapshotData {
+    schema_version: u32,
+    created_at: u64,
+    chunks: Vec<CodeChunk>,
+    symbols: Vec<Symbol>,
+}
+
+/// Serialise chunks and symbols as a JSON snapshot
+pub fn export_json(chunks: Vec<CodeChunk>, symbols: Vec<Symbol>) -> Result<String> {
+    let data = SnapshotData {
+        schema_version: SCHEMA_VERSION,
+        created_at: now_secs(),
+        chunks,
+        symbols,
+    };
+    Ok(serde_json::to_string(&data)?)
+}
+
+/// Read a JSON snapshot, migrating it from older schema versions
+pub fn import_json(json: &str) -> Result<IndexSnapshot> {
+    let value: serde_json::Value = serde_json::from_str(json)?;
+    let version = value
+        .get("schema_version")
+        .and_then(serde_json::Value::as_u64)
+        .ok_or_else(|| anyhow::anyhow!("Snapshot has no schema_version"))?;
+    check_version(version)?;
+
+    let data: SnapshotData = serde_json::from_value(migrate(value, version as u32)?)?;
+    Ok(IndexSnapshot {
+        chunks: data.chunks,
+        symbols: data.symbols,
+    })
+}
+
+/// Serialise chunks and symbols as a MessagePack snapshot
+pub fn export_msgpack(chunks: Vec<CodeChunk>, symbols: Vec<Symbol>) -> Result<Vec<u8>> {
+    let data = SnapshotData {
+        schema_version: SCHEMA_VERSION,
+        created_at: now_secs(),
+        chunks,
+        symbols,
+    };
+    Ok(rmp_serde::to_vec(&data)?)
+}
+
+/// Read a MessagePack snapshot
+pub fn import_msgpack(bytes: &[u8]) -> Result<IndexSnapshot> {
+    // The version is the first element, check it before decoding the rest
+    let (version, ..): (u64, IgnoredAny, IgnoredAny, IgnoredAny) = rmp_serde::from_slice(bytes)?;
+    check_version(version)?;
+
+    // Version 1 decodes as is: version 2 only appended defaulted fields
+    let data: SnapshotData = rmp_serde::from_slice(bytes)?;
+    Ok(IndexSnapshot {
+        chunks: data.chunks,
+        symbols: data.symbols,
+    })
+}
+
+/// Reject versions this build cannot read
+fn check_version(version: u64) -> Result<()> {
+    if version == 0 || version > SCHEMA_VERSION as u64 {
+        anyhow::bail!(
+            "Unsupported snapshot schema_version {} (supported: 1 to {})",
+            version,
+            SCHEMA_VERSION
+        );
+    }
+    Ok(())
+}
+
+/// Upgrade a snapshot value one version at a time to `SCHEMA_VERSION`
+fn migrate(value: serde_json::Value, version: u32) -> Result<serde_json::Value> {
+    match version {
+        SCHEMA_VERSION => Ok(value),
+        1 => migrate(migrate_v1_to_v2(value), 2),
+        _ => anyhow::bail!("No migration from snapshot schema_version {}", version),
+    }
+}
+
+/// Version 2 appended `original_start_line` and `language_version` to
+/// chunks and `generic_params` and `signature_annotation` to symbols; they
+/// default when missing, so only `schema_version` changes
+fn migrate_v1_to_v2(mut value: serde_json::Value) -> serde_json::Value {
+    value["schema_version"] = 2.into();
+    value
+}
+
+/// Current Unix time in seconds
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser;
+
+    #[test]
+    fn test_json_round_trip() {
+        let content = "export function add(a: number, b: number) {\n    return a + b;\n}\n";
+        let chunks = parser::parse_file("a.ts", content, "typescript").unwrap();
+        let symbols = parser::extract_symbols("a.ts", content, "typescript", true).unwrap();
+
+        let json = export_json(chunks.clone(), symbols.clone()).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["schema_version"], SCHEMA_VERSION);
+        assert!(value["created_at"].as_u64().unwrap() > 0);
+
+        let snapshot = import_json(&json).unwrap();
+        assert_eq!(
+            serde_json::to_string(&snapshot.chunks).unwrap(),
+            serde_json::to_string(&chunks).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&snapshot.symbols).unwrap(),
+            serde_json::to_string(&symbols).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_msgpack_matches_json() {
+        let chunks: Vec<CodeChunk> = (0..1000)
+            .map(|i| {
+                let content = format!("function f{}(x) {{\n    return x + {};\n}}", i, i);
+                CodeChunk {
+                    id: parser::generate_chunk_id(&content, Some(&format!("f{}", i)), "javascript"),
+                    file_path: format!("src/file{}.js", i / 10),
+                    start_line: i % 10 * 3 + 1,
+                    end_line: i % 10 * 3 + 3,
+                    start_byte: i * 40,
+                    end_byte: i * 40 + content.len() as u32,
+                    complexity: i % 4,
+                    token_count: 9,
+                    header_lines: 0,
+                    original_start_line: None,
+                    language_version: None,
+                    chunk_type: "function".to_string(),
+                    language: "javascript".to_string(),
+                    symbols: vec![format!("f{}", i)],
+                    hash: crate::HashAlgorithm::Sha256.hash(&content),
+                    content,
+                }
+            })
+            .collect();
+        let symbols = parser::extract_symbols(
+            "a.ts",
+            "function f(x: number) {}\nclass A {}\n",
+            "typescript",
+            true,
+        )
+        .unwrap();
+
+        let json = export_json(chunks.clone(), symbols.clone()).unwrap();
+        let msgpack = export_msgpack(chunks.clone(), symbols.clone()).unwrap();
+        assert!(
+            msgpack.len() * 10 < json.len() * 7,
+            "{} vs {}",
+            msgpack.len(),
+            json.len()
+        );
+
+        let from_json = import_json(&json).unwrap();
+        let from_msgpack = import_msgpack(&msgpack).unwrap();
+        assert_eq!(
+            serde_json::to_string(&from_msgpack).unwrap(),
+            serde_json::to_string(&from_json).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_string(&from_msgpack.chunks).unwrap(),
+            serde_json::to_string(&chunks).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_import_v1() {
+        let json = r#"{"schema_version": 1, "created_at": 0, "chunks": [{"id": "c", "file_path": "a.js", "content": "f()", "start_line": 1, "end_line": 1, "start_byte": 0, "end_byte": 3, "complexity": 0, "token_count": 2, "header_lines": 0, "chunk_type": "chunk", "language": "javascript", "symbols": [], "hash": "h"}], "symbols": [{"name": "f", "kind": "function", "file_path": "a.js", "start_line": 1, "end_line": 1, "start_column": 0, "end_column": 3, "start_column_char": 0, "signature": null, "parameters": [], "exported": false, "parent_name": null, "parent_kind": null, "references": null}]}"#;
+        let from_json = import_json(json).unwrap();
+        assert_eq!(from_json.chunks[0].original_start_line, None);
+        assert_eq!(from_json.symbols[0].generic_params, Vec::<String>::new());
+        assert_eq!(from_json.symbols[0].signature_annotation, None);
+
+        // Positional, as version 1 wrote it
+        let chunk = (
+            "c",
+            "a.js",
+            "f()",
+            1,
+            1,
+            0,
+            3,
+            0,
+            2,
+            0,
+            "chunk",
+            "javascript",
+            Vec::<String>::new(),
+            "h",
+        );
+        let symbol = (
+            "f",
+            "function",
+            "a.js",
+            1,
+            1,
+            0,
+            3,
+            0,
+            (),
+            Vec::<()>::new(),
+            false,
+            (),
+            (),
+            (),
+        );
+        let msgpack = rmp_serde::to_vec(&(1, 0, [chunk], [symbol])).unwrap();
+        let from_msgpack = import_msgpack(&msgpack).unwrap();
+        assert_eq!(
+            serde_json::to_string(&from_msgpack).unwrap(),
+            serde_json::to_string(&from_json).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_import_rejects_bad_versions() {
+        assert!(import_json(r#"{"created_at": 0, "chunks": [], "symbols": []}"#).is_err());
+        let newer = format!(
+            r#"{{"schema_version": {}, "created_at": 0, "chunks": [], "symbols": []}}"#,
+            SCHEMA_VERSION + 1
+        );
+        assert!(import_json(&newer)
+            .unwrap_err()
+            .to_string()
+            .contains("Unsupported"));
+        assert!(import_json(
+            r#"{"schema_version": 1, "created_at": 0, "chunks": [], "symbols": []}"#
+        )
+        .is_ok());
+
+        let newer = rmp_serde::to_vec(&(SCHEMA_VERSION + 1, 0, (), ())).unwrap();
+        assert!(import_msgpack(&newer)
+            .unwrap_err()
+            .to_string()
+            .contains("Unsupported"));
+        assert!(import_msgpack(b"not msgpack").is_err());
+    }
+}
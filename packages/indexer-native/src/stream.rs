@@ -0,0 +1,142 @@
+//! Chunks computed on a background thread and read one at a time
+
+use crate::{chunker, ChunkOptions, CodeChunk};
+use anyhow::Result;
+use std::ops::ControlFlow;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+/// Chunks computed ahead of the reader before chunking pauses
+const READ_AHEAD: usize = 16;
+
+/// Chunks of one file, produced as they are read
+///
+/// A worker thread runs the line chunker and hands over each chunk as soon
+/// as it is complete. At most `READ_AHEAD` chunks wait to be read, so a
+/// slow reader pauses the chunking rather than letting chunks pile up.
+pub struct ChunkStream {
+    receiver: Mutex<Option<Receiver<Result<CodeChunk>>>>,
+    cancelled: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl ChunkStream {
+    /// Start chunking `content` on a new thread
+    pub fn start(content: String, language: String, options: ChunkOptions) -> Self {
+        let (tx, rx) = mpsc::sync_channel(READ_AHEAD);
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let stop = cancelled.clone();
+
+        let worker = std::thread::spawn(move || {
+            let result = chunker::chunk_code_each(&content, &language, &options, |chunk| {
+                // Sending fails once the reader is gone
+                if stop.load(Ordering::Relaxed) || tx.send(Ok(chunk)).is_err() {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            });
+            if let Err(e) = result {
+                let _ = tx.send(Err(e));
+            }
+        });
+
+        Self {
+            receiver: Mutex::new(Some(rx)),
+            cancelled,
+            worker: Some(worker),
+        }
+    }
+
+    /// Wait for the next chunk, `None` once all have been read or the
+    /// stream was cancelled
+    ///
+    /// A chunking error is returned once, in place of the chunk that
+    /// failed; the stream ends after it.
+    pub fn next(&self) -> Result<Option<CodeChunk>> {
+        let receiver = self.receiver.lock().unwrap();
+        match receiver.as_ref().map(Receiver::recv) {
+            Some(Ok(item)) => item.map(Some),
+            // The worker finished and every chunk has been read
+            Some(Err(_)) | None => Ok(None),
+        }
+    }
+
+    /// Stop chunking and drop the chunks not yet read
+    ///
+    /// Does not wait for a pending `next`, which then returns `None`.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        // A pending `next` holds the receiver, but then the worker is not
+        // waiting for room: it stops at its next chunk, ending that `next`
+        if let Ok(mut receiver) = self.receiver.try_lock() {
+            receiver.take();
+        }
+    }
+}
+
+impl Drop for ChunkStream {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        // Dropping the receiver wakes a worker waiting for room
+        if let Ok(receiver) = self.receiver.get_mut() {
+            receiver.take();
+        }
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(count: usize) -> String {
+        (0..count)
+            .map(|i| format!("let v{} = {};\n", i, i))
+            .collect()
+    }
+
+    fn options() -> ChunkOptions {
+        ChunkOptions {
+            max_chunk_size: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stream_matches_chunk_code() {
+        let content = lines(100);
+        let expected =
+            chunker::chunk_code_with_options(&content, "javascript", &options()).unwrap();
+
+        let stream = ChunkStream::start(content, "javascript".to_string(), options());
+        let mut streamed = Vec::new();
+        while let Some(chunk) = stream.next().unwrap() {
+            streamed.push(chunk);
+        }
+        assert_eq!(
+            serde_json::to_string(&streamed).unwrap(),
+            serde_json::to_string(&expected).unwrap()
+        );
+        assert!(stream.next().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_stream_cancel_and_errors() {
+        // Far more chunks than are read ahead, so the worker is waiting
+        let stream = ChunkStream::start(lines(10_000), "javascript".to_string(), options());
+        assert!(stream.next().unwrap().is_some());
+        stream.cancel();
+        assert!(stream.next().unwrap().is_none());
+        drop(stream);
+
+        // Nothing to detect a language from
+        let stream = ChunkStream::start("plain words\n".to_string(), "auto".to_string(), options());
+        assert!(stream.next().is_err());
+        assert!(stream.next().unwrap().is_none());
+    }
+}
@@ -0,0 +1,124 @@
+//! Token counts for LLM context budgets
+
+/// Average characters per token for source code
+const CHARS_PER_TOKEN: f64 = 3.5;
+
+/// Count the tokens in text
+///
+/// With `exact`, the text is encoded with the GPT-4 (`cl100k_base`)
+/// tokenizer when the crate is built with the `tiktoken` feature. Without
+/// the feature, or when `exact` is false, `estimate_tokens` is used.
+pub fn count_tokens(text: &str, exact: bool) -> u32 {
+    #[cfg(feature = "tiktoken")]
+    if exact {
+        return tiktoken_rs::cl100k_base_singleton()
+            .encode_ordinary(text)
+            .len() as u32;
+    }
+    #[cfg(not(feature = "tiktoken"))]
+    let _ = exact;
+
+    estimate_tokens(text)
+}
+
+/// Estimate tokens at about 3.5 characters each
+///
+/// Tokenizers merge runs of whitespace such as indentation into a single
+/// token, so each run counts as one character. This keeps deeply indented
+/// code from being overestimated.
+pub fn estimate_tokens(text: &str) -> u32 {
+    (counted_chars(text).count() as f64 / CHARS_PER_TOKEN).ceil() as u32
+}
+
+/// Byte length of the longest prefix of text estimated at `max_tokens` or
+/// fewer, as counted by `estimate_tokens`
+pub fn fit_tokens(text: &str, max_tokens: u32) -> usize {
+    let max_chars = (max_tokens as f64 * CHARS_PER_TOKEN).floor() as usize;
+    match counted_chars(text).nth(max_chars) {
+        Some(i) => i,
+        None => text.len(),
+    }
+}
+
+/// `estimate_tokens` of text appended a piece at a time
+///
+/// Each piece costs only its own length, so growing text line by line
+/// stays linear.
+#[derive(Clone, Copy, Default)]
+pub struct TokenEstimate {
+    chars: usize,
+    in_whitespace: bool,
+}
+
+impl TokenEstimate {
+    /// Append text, counting characters as `estimate_tokens` does across
+    /// the join
+    pub fn push(&mut self, text: &str) {
+        for c in text.chars() {
+            let whitespace = c.is_whitespace();
+            if !(whitespace && self.in_whitespace) {
+                self.chars += 1;
+            }
+            self.in_whitespace = whitespace;
+        }
+    }
+
+    /// Estimated tokens in all text appended so far
+    pub fn tokens(&self) -> u32 {
+        (self.chars as f64 / CHARS_PER_TOKEN).ceil() as u32
+    }
+}
+
+/// Byte offsets of the characters `estimate_tokens` counts, skipping all
+/// but the first character of each whitespace run
+fn counted_chars(text: &str) -> impl Iterator<Item = usize> + '_ {
+    let mut in_whitespace = false;
+    text.char_indices().filter_map(move |(i, c)| {
+        let whitespace = c.is_whitespace();
+        let counted = !(whitespace && in_whitespace);
+        in_whitespace = whitespace;
+        counted.then_some(i)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcdefg"), 2);
+        // Indentation collapses to one character per run
+        assert_eq!(estimate_tokens("a\n        b"), estimate_tokens("a b"));
+        assert_eq!(count_tokens("abcdefg", false), 2);
+    }
+
+    #[test]
+    fn test_fit_tokens() {
+        assert_eq!(fit_tokens("abcdefghij", 2), 7);
+        assert_eq!(estimate_tokens(&"abcdefghij"[..7]), 2);
+        assert_eq!(fit_tokens("ab", 2), 2);
+        // Skipped whitespace does not use up the budget
+        assert_eq!(fit_tokens("a      bcdefghij", 1), 8);
+    }
+
+    #[test]
+    fn test_token_estimate_matches_joined_text() {
+        let lines = ["fn a() {", "    let x = 1;  ", "", "    x", "}"];
+        let mut estimate = TokenEstimate::default();
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                estimate.push("\n");
+            }
+            estimate.push(line);
+            assert_eq!(estimate.tokens(), estimate_tokens(&lines[..=i].join("\n")));
+        }
+    }
+
+    #[cfg(feature = "tiktoken")]
+    #[test]
+    fn test_count_tokens_exact() {
+        assert_eq!(count_tokens("hello world", true), 2);
+    }
+}
@@ -0,0 +1,224 @@
+//! Filesystem watcher reporting debounced incremental diffs
+
+use crate::merkle::MerkleState;
+use crate::SyncDiff;
+use anyhow::Result;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher as _};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Quiet period after the last event before a diff is reported
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// Directories whose contents are never reported
+const IGNORED_DIRS: &[&str] = &[".git", "node_modules", "target"];
+
+/// A running watch on a directory tree
+pub struct Watcher {
+    watcher: RecommendedWatcher,
+    worker: JoinHandle<()>,
+}
+
+impl Watcher {
+    /// Watch `root`, calling `on_diff` once each burst of changes settles
+    ///
+    /// The tree is hashed up front, so the first diff is already relative
+    /// to the files on disk. Paths in diffs are relative to `root` with `/`
+    /// separators and hashes are SHA-256 of the file bytes, as from
+    /// `hash_content`. Bursts that change no file hash are not reported.
+    pub fn start<F>(root: &Path, on_diff: F) -> Result<Self>
+    where
+        F: Fn(SyncDiff) + Send + 'static,
+    {
+        let root = root.canonicalize()?;
+        let mut state = MerkleState::default();
+        scan(&root, &root, &mut state.files)?;
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    let _ = tx.send(event.paths);
+                }
+            })?;
+        watcher.watch(&root, RecursiveMode::Recursive)?;
+
+        let worker = std::thread::spawn(move || run(&root, state, rx, on_diff));
+        Ok(Self { watcher, worker })
+    }
+
+    /// Stop watching, reporting any burst still pending
+    pub fn stop(self) {
+        // Dropping the watcher closes the channel, which ends the worker
+        drop(self.watcher);
+        let _ = self.worker.join();
+    }
+}
+
+/// Collect bursts of events and report the diff for each
+fn run<F: Fn(SyncDiff)>(
+    root: &Path,
+    mut state: MerkleState,
+    events: mpsc::Receiver<Vec<PathBuf>>,
+    on_diff: F,
+) {
+    while let Ok(paths) = events.recv() {
+        // Keep collecting until the channel has been quiet for the window
+        let mut changed: BTreeSet<PathBuf> = paths.into_iter().collect();
+        while let Ok(paths) = events.recv_timeout(DEBOUNCE) {
+            changed.extend(paths);
+        }
+
+        let mut files = state.files.clone();
+        for path in &changed {
+            apply_change(root, path, &mut files);
+        }
+
+        let diff = state.update_files(files);
+        if diff.total_changed > 0 {
+            on_diff(diff);
+        }
+    }
+}
+
+/// Bring the hashes under one changed path up to date
+fn apply_change(root: &Path, path: &Path, files: &mut BTreeMap<String, String>) {
+    let rel = match relative_path(root, path) {
+        Some(rel) => rel,
+        None => return,
+    };
+    if rel.is_empty() || is_ignored(&rel) {
+        return;
+    }
+
+    if path.is_file() {
+        if let Ok(hash) = hash_file(path) {
+            files.insert(rel, hash);
+        }
+    } else if path.is_dir() {
+        let _ = scan(root, path, files);
+    } else {
+        // Removed: drop the file, or everything under a removed directory
+        let prefix = format!("{}/", rel);
+        files.remove(&rel);
+        files.retain(|file, _| !file.starts_with(&prefix));
+    }
+}
+
+/// Hash every file under `dir` into `files`
+fn scan(root: &Path, dir: &Path, files: &mut BTreeMap<String, String>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let path = entry.path();
+
+        if file_type.is_dir() {
+            if !IGNORED_DIRS.iter().any(|d| entry.file_name() == *d) {
+                scan(root, &path, files)?;
+            }
+        } else if file_type.is_file() {
+            if let Some(rel) = relative_path(root, &path) {
+                files.insert(rel, hash_file(&path)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Path relative to the root with `/` separators
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(root).ok()?;
+    let parts: Vec<&str> = rel
+        .components()
+        .map(|c| c.as_os_str().to_str())
+        .collect::<Option<_>>()?;
+    Some(parts.join("/"))
+}
+
+/// Check if a relative path lies in an ignored directory
+fn is_ignored(rel: &str) -> bool {
+    rel.split('/').any(|part| IGNORED_DIRS.contains(&part))
+}
+
+/// SHA-256 of a file's bytes
+fn hash_file(path: &Path) -> Result<String> {
+    let bytes = std::fs::read(path)?;
+    let mut hasher = Sha256::new();
+    hasher.update(&bytes);
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// Receive diffs until `done` holds for the merged result or time runs out
+    fn wait_for(
+        rx: &mpsc::Receiver<SyncDiff>,
+        done: impl Fn(&[SyncDiff]) -> bool,
+    ) -> Vec<SyncDiff> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut diffs = Vec::new();
+        while !done(&diffs) {
+            let left = deadline.saturating_duration_since(Instant::now());
+            match rx.recv_timeout(left) {
+                Ok(diff) => diffs.push(diff),
+                Err(_) => break,
+            }
+        }
+        diffs
+    }
+
+    #[test]
+    fn test_watcher_reports_diffs() {
+        let root = std::env::temp_dir().join(format!("titan-watch-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("node_modules")).unwrap();
+        std::fs::write(root.join("a.ts"), "a").unwrap();
+
+        let (tx, rx) = mpsc::channel();
+        let watcher = Watcher::start(&root, move |diff| {
+            let _ = tx.send(diff);
+        })
+        .unwrap();
+
+        std::fs::write(root.join("node_modules/x.js"), "x").unwrap();
+        std::fs::write(root.join("a.ts"), "changed").unwrap();
+        std::fs::write(root.join("b.ts"), "b").unwrap();
+        let has = |diffs: &[SyncDiff], f: &dyn Fn(&SyncDiff) -> bool| diffs.iter().any(f);
+        let diffs = wait_for(&rx, |d| {
+            has(d, &|s| s.modified.contains(&"a.ts".to_string()))
+                && has(d, &|s| s.added.contains(&"b.ts".to_string()))
+        });
+        assert!(has(&diffs, &|s| s.modified == vec!["a.ts"]));
+        assert!(has(&diffs, &|s| s.added == vec!["b.ts"]));
+        assert!(diffs
+            .iter()
+            .all(|s| s.added.iter().all(|p| !p.contains("node_modules"))));
+
+        std::fs::remove_file(root.join("b.ts")).unwrap();
+        let diffs = wait_for(&rx, |d| has(d, &|s| !s.deleted.is_empty()));
+        assert!(has(&diffs, &|s| s.deleted == vec!["b.ts"]));
+
+        watcher.stop();
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_apply_change_removed_directory() {
+        let mut files = BTreeMap::new();
+        files.insert("src/a.ts".to_string(), "1".to_string());
+        files.insert("src/b/c.ts".to_string(), "2".to_string());
+        files.insert("srcx.ts".to_string(), "3".to_string());
+
+        let root = Path::new("/missing-root");
+        apply_change(root, &root.join("src"), &mut files);
+        assert_eq!(files.keys().collect::<Vec<_>>(), vec!["srcx.ts"]);
+        assert!(is_ignored("web/node_modules/react/index.js"));
+    }
+}
@@ -0,0 +1,15 @@
+import { Readable } from 'stream';
+import { ChunkOptions } from './index';
+
+/**
+ * Chunk code into an object-mode `Readable` of `CodeChunk`s
+ *
+ * Chunks are computed on a background thread as the stream is read, so a
+ * slow consumer pauses chunking instead of buffering every chunk. Chunking
+ * errors destroy the stream, and destroying it stops the chunking.
+ */
+export function chunkCodeStreaming(
+  content: string,
+  language: string,
+  options: ChunkOptions
+): Readable;
@@ -0,0 +1,31 @@
+'use strict';
+
+const { Readable } = require('stream');
+const { ChunkStream } = require('./index.js');
+
+/**
+ * Chunk code into an object-mode `Readable` of `CodeChunk`s
+ *
+ * Chunks are computed on a background thread as the stream is read, so a
+ * slow consumer pauses chunking instead of buffering every chunk. Chunking
+ * errors destroy the stream, and destroying it stops the chunking.
+ */
+function chunkCodeStreaming(content, language, options) {
+  const chunks = new ChunkStream(content, language, options);
+
+  return new Readable({
+    objectMode: true,
+    read() {
+      chunks.next().then(
+        chunk => this.push(chunk),
+        err => this.destroy(err)
+      );
+    },
+    destroy(err, callback) {
+      chunks.cancel();
+      callback(err);
+    },
+  });
+}
+
+module.exports = { chunkCodeStreaming };